@@ -3,7 +3,7 @@ use crate::{
     skinning::VertexSkinningSystemDesc,
     systems::{
         AnimationControlSystemDesc, AnimationProcessor, SamplerInterpolationSystem,
-        SamplerProcessor,
+        SamplerProcessor, TweenSystem,
     },
 };
 use amethyst_core::{
@@ -165,3 +165,52 @@ where
             .build(world, builder)
     }
 }
+
+/// Bundle for playing `Tween`s on a component.
+///
+/// Will add `TweenSystem<T>` with the given name.
+///
+/// ### Type parameters:
+///
+/// - `T`: the component type that tweens should be applied to
+#[derive(Default, Debug)]
+pub struct TweenBundle<'a, T> {
+    name: &'a str,
+    dep: &'a [&'a str],
+    m: marker::PhantomData<T>,
+}
+
+impl<'a, T> TweenBundle<'a, T> {
+    /// Create a new tween bundle
+    ///
+    /// ### Parameters:
+    ///
+    /// - `name`: name of the `TweenSystem`
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            dep: &[],
+            m: marker::PhantomData,
+        }
+    }
+
+    /// Set dependencies for the `TweenSystem`
+    pub fn with_dep(mut self, dep: &'a [&'a str]) -> Self {
+        self.dep = dep;
+        self
+    }
+}
+
+impl<'a, 'b, 'c, T> SystemBundle<'a, 'b> for TweenBundle<'c, T>
+where
+    T: AnimationSampling + Component,
+{
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(TweenSystem::<T>::new(), self.name, self.dep);
+        Ok(())
+    }
+}
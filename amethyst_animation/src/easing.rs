@@ -0,0 +1,167 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Easing curve, mapping the linear progress of a tween to the progress of its value.
+///
+/// Every curve maps `0.0` to `0.0` and `1.0` to `1.0`. `Back` and `Elastic` curves overshoot in
+/// between.
+///
+/// ### Example:
+///
+/// ```
+/// use amethyst_animation::Easing;
+///
+/// assert_eq!(Easing::Linear.apply(0.25), 0.25);
+/// assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+/// assert_eq!(Easing::QuadOut.apply(0.5), 0.75);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Quadratic, accelerating from zero velocity
+    QuadIn,
+    /// Quadratic, decelerating to zero velocity
+    QuadOut,
+    /// Quadratic, accelerating until halfway, then decelerating
+    QuadInOut,
+    /// Cubic, accelerating from zero velocity
+    CubicIn,
+    /// Cubic, decelerating to zero velocity
+    CubicOut,
+    /// Cubic, accelerating until halfway, then decelerating
+    CubicInOut,
+    /// Sinusoidal, accelerating from zero velocity
+    SineIn,
+    /// Sinusoidal, decelerating to zero velocity
+    SineOut,
+    /// Sinusoidal, accelerating until halfway, then decelerating
+    SineInOut,
+    /// Exponential, accelerating from zero velocity
+    ExpoIn,
+    /// Exponential, decelerating to zero velocity
+    ExpoOut,
+    /// Exponential, accelerating until halfway, then decelerating
+    ExpoInOut,
+    /// Pulls back slightly before accelerating
+    BackIn,
+    /// Overshoots slightly before settling
+    BackOut,
+    /// Pulls back at the start and overshoots at the end
+    BackInOut,
+    /// Oscillates with a growing amplitude at the start
+    ElasticIn,
+    /// Oscillates with a shrinking amplitude at the end
+    ElasticOut,
+    /// Oscillates at the start and at the end
+    ElasticInOut,
+    /// Bounces with a growing amplitude at the start
+    BounceIn,
+    /// Bounces with a shrinking amplitude at the end, like a dropped ball
+    BounceOut,
+    /// Bounces at the start and at the end
+    BounceInOut,
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::Linear
+    }
+}
+
+impl Easing {
+    /// Maps the progress `t`, clamped to `0.0..=1.0`, through the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        use self::Easing::*;
+
+        let t = t.max(0.).min(1.);
+        match self {
+            Linear => t,
+            QuadIn => quad(t),
+            QuadOut => ease_out(quad, t),
+            QuadInOut => ease_in_out(quad, t),
+            CubicIn => cubic(t),
+            CubicOut => ease_out(cubic, t),
+            CubicInOut => ease_in_out(cubic, t),
+            SineIn => sine(t),
+            SineOut => ease_out(sine, t),
+            SineInOut => ease_in_out(sine, t),
+            ExpoIn => expo(t),
+            ExpoOut => ease_out(expo, t),
+            ExpoInOut => ease_in_out(expo, t),
+            BackIn => back(t),
+            BackOut => ease_out(back, t),
+            BackInOut => ease_in_out(back, t),
+            ElasticIn => elastic(t),
+            ElasticOut => ease_out(elastic, t),
+            ElasticInOut => ease_in_out(elastic, t),
+            BounceIn => bounce(t),
+            BounceOut => ease_out(bounce, t),
+            BounceInOut => ease_in_out(bounce, t),
+        }
+    }
+}
+
+fn ease_out(curve: fn(f32) -> f32, t: f32) -> f32 {
+    1. - curve(1. - t)
+}
+
+fn ease_in_out(curve: fn(f32) -> f32, t: f32) -> f32 {
+    if t < 0.5 {
+        curve(2. * t) / 2.
+    } else {
+        1. - curve(2. - 2. * t) / 2.
+    }
+}
+
+fn quad(t: f32) -> f32 {
+    t * t
+}
+
+fn cubic(t: f32) -> f32 {
+    t * t * t
+}
+
+fn sine(t: f32) -> f32 {
+    1. - (t * PI / 2.).cos()
+}
+
+fn expo(t: f32) -> f32 {
+    if t <= 0. {
+        0.
+    } else {
+        2f32.powf(10. * t - 10.)
+    }
+}
+
+fn back(t: f32) -> f32 {
+    const OVERSHOOT: f32 = 1.701_58;
+    (OVERSHOOT + 1.) * t * t * t - OVERSHOOT * t * t
+}
+
+fn elastic(t: f32) -> f32 {
+    if t <= 0. || t >= 1. {
+        t
+    } else {
+        -(2f32.powf(10. * t - 10.)) * ((10. * t - 10.75) * 2. * PI / 3.).sin()
+    }
+}
+
+fn bounce(t: f32) -> f32 {
+    // The curve of a bouncing ball, mirrored so it bounces in.
+    let t = 1. - t;
+    let bounced = if t < 1. / 2.75 {
+        7.5625 * t * t
+    } else if t < 2. / 2.75 {
+        let t = t - 1.5 / 2.75;
+        7.5625 * t * t + 0.75
+    } else if t < 2.5 / 2.75 {
+        let t = t - 2.25 / 2.75;
+        7.5625 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / 2.75;
+        7.5625 * t * t + 0.984_375
+    };
+    1. - bounced
+}
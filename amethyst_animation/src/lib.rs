@@ -57,7 +57,8 @@
 pub use minterpolate::{InterpolationFunction, InterpolationPrimitive};
 
 pub use self::{
-    bundle::{AnimationBundle, SamplingBundle, VertexSkinningBundle},
+    bundle::{AnimationBundle, SamplingBundle, TweenBundle, VertexSkinningBundle},
+    easing::Easing,
     material::{MaterialChannel, MaterialPrimitive},
     prefab::{AnimatablePrefab, AnimationHierarchyPrefab, AnimationPrefab, AnimationSetPrefab},
     resources::{
@@ -69,19 +70,27 @@ pub use self::{
     sprite::{SpriteRenderChannel, SpriteRenderPrimitive},
     systems::{
         AnimationControlSystem, AnimationProcessor, SamplerInterpolationSystem, SamplerProcessor,
+        TweenSystem,
     },
+    tint::TintChannel,
     transform::TransformChannel,
+    tween::{Tween, TweenEvent, TweenEventKind, TweenMode, TweenStep},
+    ui_text::UiTextChannel,
     ui_transform::UiTransformChannel,
     util::{get_animation_set, SamplerPrimitive},
 };
 
 mod bundle;
+mod easing;
 mod material;
 mod prefab;
 mod resources;
 mod skinning;
 mod sprite;
 mod systems;
+mod tint;
 mod transform;
+mod tween;
+mod ui_text;
 mod ui_transform;
 mod util;
@@ -5,10 +5,12 @@ use crate::resources::{Animation, Sampler};
 pub use self::{
     control::{AnimationControlSystem, AnimationControlSystemDesc},
     sampling::SamplerInterpolationSystem,
+    tween::TweenSystem,
 };
 
 mod control;
 mod sampling;
+mod tween;
 
 /// Asset storage processor for `Sampler`
 pub type SamplerProcessor<S> = Processor<Sampler<S>>;
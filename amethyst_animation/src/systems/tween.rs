@@ -0,0 +1,80 @@
+use std::marker;
+
+use amethyst_core::{
+    ecs::prelude::{Component, Entities, Entity, Join, Read, System, Write, WriteStorage},
+    shrev::EventChannel,
+    Time,
+};
+
+use crate::{
+    resources::{AnimationSampling, ApplyData},
+    tween::{Tween, TweenEvent},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// System playing the `Tween<T>` components.
+///
+/// Tweens are advanced by the scaled game time, so they stop while the time scale is 0. Tweens
+/// with `TweenMode::Once` are removed once they completed.
+///
+/// ### Type parameters:
+///
+/// - `T`: the component type that the tweens are applied to
+#[derive(Default, Debug)]
+pub struct TweenSystem<T>
+where
+    T: AnimationSampling,
+{
+    m: marker::PhantomData<T>,
+    events: Vec<TweenEvent>,
+    completed: Vec<Entity>,
+}
+
+impl<T> TweenSystem<T>
+where
+    T: AnimationSampling,
+{
+    /// Creates a new `TweenSystem`
+    pub fn new() -> Self {
+        Self {
+            m: marker::PhantomData,
+            events: Vec::default(),
+            completed: Vec::default(),
+        }
+    }
+}
+
+impl<'a, T> System<'a> for TweenSystem<T>
+where
+    T: AnimationSampling + Component,
+{
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        WriteStorage<'a, Tween<T>>,
+        WriteStorage<'a, T>,
+        Write<'a, EventChannel<TweenEvent>>,
+        <T as ApplyData<'a>>::ApplyData,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, mut tweens, mut comps, mut channel, apply_data): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("tween_system");
+
+        let delta = time.delta_seconds();
+        for (entity, tween, comp) in (&entities, &mut tweens, &mut comps).join() {
+            if !tween.advance(entity, comp, delta, &apply_data, &mut self.events) {
+                self.completed.push(entity);
+            }
+        }
+        for entity in self.completed.drain(..) {
+            tweens.remove(entity);
+        }
+        channel.drain_vec_write(&mut self.events);
+    }
+}
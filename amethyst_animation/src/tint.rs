@@ -0,0 +1,54 @@
+use amethyst_rendy::{palette::Srgba, resources::Tint};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    resources::{AnimationSampling, ApplyData, BlendMethod},
+    util::SamplerPrimitive,
+};
+
+/// Channels that can be animated on `Tint`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TintChannel {
+    /// The RGBA color of the tint
+    Color,
+}
+
+impl<'a> ApplyData<'a> for Tint {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for Tint {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = TintChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        use crate::util::SamplerPrimitive::*;
+
+        use self::TintChannel::*;
+
+        match (channel, *data) {
+            (&Color, Vec4(ref d)) => {
+                self.0 = Srgba::new(d[0], d[1], d[2], d[3]);
+            }
+            _ => panic!("Attempt to apply invalid sample to Tint"),
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        use self::TintChannel::*;
+        match channel {
+            Color => SamplerPrimitive::Vec4(self.clone().into()),
+        }
+    }
+    fn default_primitive(channel: &Self::Channel) -> Self::Primitive {
+        use self::TintChannel::*;
+        match channel {
+            Color => SamplerPrimitive::Vec4([1.; 4]),
+        }
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
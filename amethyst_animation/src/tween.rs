@@ -0,0 +1,497 @@
+use std::time::Duration;
+
+use minterpolate::InterpolationPrimitive;
+
+use amethyst_core::{
+    duration_to_secs,
+    ecs::prelude::{Component, DenseVecStorage, Entity},
+};
+
+use crate::{
+    easing::Easing,
+    resources::{AnimationSampling, ApplyData},
+};
+
+/// How a `Tween` continues once its last step completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TweenMode {
+    /// Play the steps once, then remove the `Tween`.
+    Once,
+    /// Restart from the first step, forever.
+    Loop,
+    /// Play the steps backwards, then forwards again, forever.
+    YoYo,
+}
+
+impl Default for TweenMode {
+    fn default() -> Self {
+        TweenMode::Once
+    }
+}
+
+/// A single interpolation of a channel, played by a `Tween`.
+///
+/// ### Type parameters:
+///
+/// - `T`: the component type that the step is applied to
+#[derive(Debug, Clone)]
+pub struct TweenStep<T>
+where
+    T: AnimationSampling,
+{
+    /// The channel to interpolate.
+    pub channel: T::Channel,
+    /// The start value, or `None` to start from the value of the channel when the step starts.
+    pub from: Option<T::Primitive>,
+    /// The end value.
+    pub to: T::Primitive,
+    /// Time to wait before the step starts.
+    pub delay: Duration,
+    /// Time the interpolation takes.
+    pub duration: Duration,
+    /// Easing curve of the interpolation.
+    pub easing: Easing,
+}
+
+impl<T> TweenStep<T>
+where
+    T: AnimationSampling,
+{
+    fn sample(&self, from: &T::Primitive, progress: f32) -> T::Primitive {
+        from.add(&self.to.sub(from).mul(progress))
+    }
+}
+
+/// Event written to the `EventChannel<TweenEvent>` while tweens play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TweenEvent {
+    /// The entity with the tween.
+    pub entity: Entity,
+    /// What happened.
+    pub kind: TweenEventKind,
+}
+
+/// What happened to a tween.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TweenEventKind {
+    /// The step with the given index completed.
+    StepCompleted(usize),
+    /// A looping tween restarted, or a yoyo tween changed direction.
+    Looped,
+    /// A tween with `TweenMode::Once` completed its last step, and was removed.
+    Completed,
+}
+
+/// Component interpolating channels of the component `T` of its entity over time.
+///
+/// A tween plays a sequence of steps, each with its own delay, duration and easing curve. Steps
+/// are played by `TweenSystem<T>`, which writes `TweenEvent`s as they complete.
+///
+/// ### Type parameters:
+///
+/// - `T`: the component type that the tween is applied to
+///
+/// ### Example:
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use amethyst_animation::{Easing, Tween, TweenMode, TransformChannel};
+/// use amethyst_core::Transform;
+///
+/// // Moves up with a bounce, waits half a second, then shrinks, back and forth.
+/// let tween = Tween::<Transform>::new(
+///     TransformChannel::Translation,
+///     [0., 2., 0.].into(),
+///     Duration::from_secs(1),
+/// )
+/// .with_easing(Easing::BounceOut)
+/// .then(TransformChannel::Scale, [0.5; 3].into(), Duration::from_millis(250))
+/// .with_delay(Duration::from_millis(500))
+/// .with_mode(TweenMode::YoYo);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tween<T>
+where
+    T: AnimationSampling,
+{
+    steps: Vec<TweenStep<T>>,
+    /// The start values of the steps, resolved when they first start.
+    starts: Vec<Option<T::Primitive>>,
+    mode: TweenMode,
+    current: usize,
+    reversed: bool,
+    elapsed: f32,
+}
+
+impl<T> Tween<T>
+where
+    T: AnimationSampling,
+{
+    /// Creates a tween with a single step, interpolating `channel` from its current value to `to`
+    /// over `duration`.
+    pub fn new(channel: T::Channel, to: T::Primitive, duration: Duration) -> Self {
+        Tween {
+            steps: Vec::new(),
+            starts: Vec::new(),
+            mode: TweenMode::Once,
+            current: 0,
+            reversed: false,
+            elapsed: 0.,
+        }
+        .then(channel, to, duration)
+    }
+
+    /// Appends a step interpolating `channel` from its value when the step starts to `to` over
+    /// `duration`.
+    ///
+    /// `from`, `with_delay` and `with_easing` configure the last step.
+    pub fn then(mut self, channel: T::Channel, to: T::Primitive, duration: Duration) -> Self {
+        self.steps.push(TweenStep {
+            channel,
+            from: None,
+            to,
+            delay: Duration::from_secs(0),
+            duration,
+            easing: Easing::Linear,
+        });
+        self.starts.push(None);
+        self
+    }
+
+    /// Sets the start value of the last step.
+    pub fn from(mut self, from: T::Primitive) -> Self {
+        self.last_step().from = Some(from);
+        self
+    }
+
+    /// Sets the time to wait before the last step starts.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.last_step().delay = delay;
+        self
+    }
+
+    /// Sets the easing curve of the last step.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.last_step().easing = easing;
+        self
+    }
+
+    /// Sets how the tween continues once its last step completed.
+    pub fn with_mode(mut self, mode: TweenMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the steps of the tween.
+    pub fn steps(&self) -> &[TweenStep<T>] {
+        &self.steps
+    }
+
+    /// Returns the index of the step playing.
+    pub fn current_step(&self) -> usize {
+        self.current
+    }
+
+    /// Returns whether a yoyo tween is playing its steps backwards.
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    fn last_step(&mut self) -> &mut TweenStep<T> {
+        self.steps
+            .last_mut()
+            .expect("A tween always has at least one step")
+    }
+
+    fn cycle_length(&self) -> f32 {
+        self.steps
+            .iter()
+            .map(|step| duration_to_secs(step.delay + step.duration))
+            .sum()
+    }
+
+    /// Advances the tween by `delta` seconds, applying the steps to `comp`.
+    ///
+    /// Returns `false` once a tween with `TweenMode::Once` completed.
+    pub(crate) fn advance<'a>(
+        &mut self,
+        entity: Entity,
+        comp: &mut T,
+        mut delta: f32,
+        apply_data: &<T as ApplyData<'a>>::ApplyData,
+        events: &mut Vec<TweenEvent>,
+    ) -> bool {
+        loop {
+            let reversed = self.reversed;
+            let step = &self.steps[self.current];
+            let from = self.starts[self.current]
+                .get_or_insert_with(|| {
+                    step.from
+                        .clone()
+                        .unwrap_or_else(|| comp.current_sample(&step.channel, apply_data))
+                })
+                .clone();
+            let delay = duration_to_secs(step.delay);
+            let length = delay + duration_to_secs(step.duration);
+
+            self.elapsed += delta;
+            if self.elapsed >= delay {
+                let linear = if self.elapsed >= length {
+                    1.
+                } else {
+                    (self.elapsed - delay) / (length - delay)
+                };
+                let progress = if reversed {
+                    step.easing.apply(1. - linear)
+                } else {
+                    step.easing.apply(linear)
+                };
+                comp.apply_sample(&step.channel, &step.sample(&from, progress), apply_data);
+            }
+            if self.elapsed < length {
+                return true;
+            }
+
+            delta = self.elapsed - length;
+            self.elapsed = 0.;
+            events.push(TweenEvent {
+                entity,
+                kind: TweenEventKind::StepCompleted(self.current),
+            });
+
+            let last = if reversed {
+                self.current == 0
+            } else {
+                self.current + 1 == self.steps.len()
+            };
+            if !last {
+                if reversed {
+                    self.current -= 1;
+                } else {
+                    self.current += 1;
+                }
+                continue;
+            }
+
+            match self.mode {
+                TweenMode::Once => {
+                    events.push(TweenEvent {
+                        entity,
+                        kind: TweenEventKind::Completed,
+                    });
+                    return false;
+                }
+                TweenMode::Loop => self.current = 0,
+                TweenMode::YoYo => self.reversed = !reversed,
+            }
+            events.push(TweenEvent {
+                entity,
+                kind: TweenEventKind::Looped,
+            });
+            // Without time left, or with steps taking no time, wait for the next frame instead
+            // of looping forever.
+            if delta <= 0. || self.cycle_length() <= 0. {
+                return true;
+            }
+        }
+    }
+}
+
+impl<T> Component for Tween<T>
+where
+    T: AnimationSampling,
+{
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use amethyst_core::{
+        ecs::prelude::{Builder, Entity, World, WorldExt},
+        Transform,
+    };
+
+    use super::*;
+    use crate::{SamplerPrimitive, TransformChannel};
+
+    fn entity() -> Entity {
+        World::new().create_entity().build()
+    }
+
+    fn to(x: f32) -> SamplerPrimitive<f32> {
+        [x, 0., 0.].into()
+    }
+
+    fn events(entity: Entity, kinds: &[TweenEventKind]) -> Vec<TweenEvent> {
+        kinds
+            .iter()
+            .map(|&kind| TweenEvent { entity, kind })
+            .collect()
+    }
+
+    #[test]
+    fn leftover_time_carries_over_to_the_next_step() {
+        let entity = entity();
+        let mut transform = Transform::default();
+        let mut tween = Tween::<Transform>::new(
+            TransformChannel::Translation,
+            to(4.),
+            Duration::from_secs(1),
+        )
+        .then(
+            TransformChannel::Translation,
+            to(8.),
+            Duration::from_secs(1),
+        );
+        let mut written = Vec::new();
+
+        assert!(tween.advance(entity, &mut transform, 1.5, &(), &mut written));
+        assert_eq!(tween.current_step(), 1);
+        assert_eq!(transform.translation().x, 6.);
+        assert_eq!(written, events(entity, &[TweenEventKind::StepCompleted(0)]));
+    }
+
+    #[test]
+    fn yoyo_changes_direction_after_the_last_step() {
+        let entity = entity();
+        let mut transform = Transform::default();
+        let mut tween = Tween::<Transform>::new(
+            TransformChannel::Translation,
+            to(4.),
+            Duration::from_secs(1),
+        )
+        .with_mode(TweenMode::YoYo);
+        let mut written = Vec::new();
+
+        assert!(tween.advance(entity, &mut transform, 1.25, &(), &mut written));
+        assert!(tween.is_reversed());
+        assert_eq!(transform.translation().x, 3.);
+        assert_eq!(
+            written,
+            events(
+                entity,
+                &[TweenEventKind::StepCompleted(0), TweenEventKind::Looped]
+            )
+        );
+
+        written.clear();
+        assert!(tween.advance(entity, &mut transform, 1., &(), &mut written));
+        assert!(!tween.is_reversed());
+        assert_eq!(transform.translation().x, 1.);
+        assert_eq!(
+            written,
+            events(
+                entity,
+                &[TweenEventKind::StepCompleted(0), TweenEventKind::Looped]
+            )
+        );
+    }
+
+    #[test]
+    fn loop_restarts_at_the_first_step() {
+        let entity = entity();
+        let mut transform = Transform::default();
+        let mut tween = Tween::<Transform>::new(
+            TransformChannel::Translation,
+            to(2.),
+            Duration::from_millis(500),
+        )
+        .then(
+            TransformChannel::Translation,
+            to(4.),
+            Duration::from_millis(500),
+        )
+        .with_mode(TweenMode::Loop);
+        let mut written = Vec::new();
+
+        assert!(tween.advance(entity, &mut transform, 1.25, &(), &mut written));
+        assert_eq!(tween.current_step(), 0);
+        // The first step starts again from where it first started.
+        assert_eq!(transform.translation().x, 1.);
+        assert_eq!(
+            written,
+            events(
+                entity,
+                &[
+                    TweenEventKind::StepCompleted(0),
+                    TweenEventKind::StepCompleted(1),
+                    TweenEventKind::Looped,
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn once_completes_after_the_last_step() {
+        let entity = entity();
+        let mut transform = Transform::default();
+        let mut tween = Tween::<Transform>::new(
+            TransformChannel::Translation,
+            to(2.),
+            Duration::from_secs(1),
+        )
+        .then(
+            TransformChannel::Translation,
+            to(4.),
+            Duration::from_secs(1),
+        )
+        .with_delay(Duration::from_secs(1));
+        let mut written = Vec::new();
+
+        // The second step waits for its delay.
+        assert!(tween.advance(entity, &mut transform, 1.5, &(), &mut written));
+        assert_eq!(transform.translation().x, 2.);
+        assert!(!tween.advance(entity, &mut transform, 5., &(), &mut written));
+        assert_eq!(transform.translation().x, 4.);
+        assert_eq!(
+            written,
+            events(
+                entity,
+                &[
+                    TweenEventKind::StepCompleted(0),
+                    TweenEventKind::StepCompleted(1),
+                    TweenEventKind::Completed,
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn steps_without_duration_wait_for_the_next_frame() {
+        let entity = entity();
+        let mut transform = Transform::default();
+        let mut tween = Tween::<Transform>::new(
+            TransformChannel::Translation,
+            to(4.),
+            Duration::from_secs(0),
+        )
+        .with_mode(TweenMode::Loop);
+        let mut written = Vec::new();
+
+        assert!(tween.advance(entity, &mut transform, 1., &(), &mut written));
+        assert_eq!(transform.translation().x, 4.);
+        assert_eq!(
+            written,
+            events(
+                entity,
+                &[TweenEventKind::StepCompleted(0), TweenEventKind::Looped]
+            )
+        );
+    }
+
+    #[test]
+    fn playing_keeps_the_steps_unchanged() {
+        let mut transform = Transform::default();
+        let mut tween = Tween::<Transform>::new(
+            TransformChannel::Translation,
+            to(4.),
+            Duration::from_secs(1),
+        );
+
+        assert!(tween.advance(entity(), &mut transform, 0.5, &(), &mut Vec::new()));
+        assert!(tween.steps()[0].from.is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use amethyst_ui::UiText;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    resources::{AnimationSampling, ApplyData, BlendMethod},
+    util::SamplerPrimitive,
+};
+
+/// Channels that can be animated on `UiText`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum UiTextChannel {
+    /// The RGBA color of the text
+    Color,
+}
+
+impl<'a> ApplyData<'a> for UiText {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for UiText {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = UiTextChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        use crate::util::SamplerPrimitive::*;
+
+        use self::UiTextChannel::*;
+
+        match (channel, *data) {
+            (&Color, Vec4(d)) => {
+                self.color = d;
+            }
+            _ => panic!("Attempt to apply invalid sample to UiText"),
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        use self::UiTextChannel::*;
+        match channel {
+            Color => SamplerPrimitive::Vec4(self.color),
+        }
+    }
+    fn default_primitive(channel: &Self::Channel) -> Self::Primitive {
+        use self::UiTextChannel::*;
+        match channel {
+            Color => SamplerPrimitive::Vec4([1.; 4]),
+        }
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
@@ -11,6 +11,8 @@
 
 #[cfg(feature = "json")]
 pub use crate::formats::JsonFormat;
+#[cfg(feature = "saveload")]
+pub use crate::snapshot::SnapshotSchemaExt;
 pub use crate::{
     asset::{Asset, Format, FormatValue, ProcessableAsset, SerializableFormat},
     cache::Cache,
@@ -23,8 +25,8 @@ pub use crate::{
     },
     progress::{Completion, Progress, ProgressCounter, Tracker},
     reload::{HotReloadBundle, HotReloadStrategy, HotReloadSystem, Reload, SingleFile},
-    source::{Directory, Source},
-    storage::{AssetStorage, Handle, ProcessingState, Processor, WeakHandle},
+    source::{Directory, ReadSeek, Source},
+    storage::{AssetPath, AssetStorage, Handle, ProcessingState, Processor, WeakHandle},
 };
 
 pub use rayon::ThreadPool;
@@ -39,6 +41,8 @@ mod loader;
 mod prefab;
 mod progress;
 mod reload;
+#[cfg(feature = "saveload")]
+mod snapshot;
 mod source;
 mod storage;
 
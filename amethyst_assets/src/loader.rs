@@ -10,7 +10,7 @@ use thread_profiler::profile_scope;
 
 use crate::{
     error::Error,
-    storage::{AssetStorage, Handle, Processed},
+    storage::{AssetPath, AssetStorage, Handle, Processed},
     Asset, Directory, Format, FormatValue, Progress, Source,
 };
 
@@ -138,6 +138,13 @@ impl Loader {
         };
 
         let handle = storage.allocate();
+        storage.set_path(
+            &handle,
+            AssetPath {
+                source: source.to_string(),
+                name: name.clone(),
+            },
+        );
 
         debug!(
             "{:?}: Loading asset {:?} with format {:?} from source {:?} (handle id: {:?})",
@@ -0,0 +1,166 @@
+//! Saving asset handles in snapshots.
+
+use amethyst_core::{ecs::prelude::World, snapshot::SnapshotSchema};
+use amethyst_error::{format_err, Error};
+
+use crate::{Asset, AssetPath, AssetStorage, Format, Handle, Loader};
+
+/// Saves the asset `Handle`s of a `SnapshotSchema`.
+///
+/// A handle is saved as the `AssetPath` its asset was loaded from, and its asset is loaded
+/// again when the snapshot is loaded, like any other asset.
+pub trait SnapshotSchemaExt {
+    /// Saves the `Handle<A>` components under the given name, loading their assets again with
+    /// `format`.
+    ///
+    /// Only handles of assets loaded by the `Loader` from a source can be saved. The `World` the
+    /// snapshot is loaded into needs the `Loader` and the `AssetStorage<A>`.
+    fn with_asset_handle<A, F>(self, name: &str, format: F) -> Self
+    where
+        A: Asset,
+        A::HandleStorage: Default,
+        F: Format<A::Data> + Clone;
+}
+
+impl SnapshotSchemaExt for SnapshotSchema {
+    fn with_asset_handle<A, F>(self, name: &str, format: F) -> Self
+    where
+        A: Asset,
+        A::HandleStorage: Default,
+        F: Format<A::Data> + Clone,
+    {
+        self.with_component_as(
+            name,
+            |handle: &Handle<A>, world: &World| {
+                world
+                    .try_fetch::<AssetStorage<A>>()
+                    .and_then(|storage| storage.path(handle))
+                    .ok_or_else(|| {
+                        format_err!(
+                            "{} handle {} wasn't loaded from a source",
+                            A::NAME,
+                            handle.id()
+                        )
+                    })
+            },
+            move |path: AssetPath, world: &World| {
+                let loader = world
+                    .try_fetch::<Loader>()
+                    .ok_or_else(|| format_err!("The `Loader` is missing"))?;
+                let storage = world
+                    .try_fetch::<AssetStorage<A>>()
+                    .ok_or_else(|| format_err!("The storage of {} is missing", A::NAME))?;
+                Ok(loader.load_from(
+                    path.name,
+                    format.clone(),
+                    path.source.as_str(),
+                    (),
+                    &storage,
+                ))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use rayon::{ThreadPool, ThreadPoolBuilder};
+    use serde::Deserialize;
+
+    use amethyst_core::{
+        ecs::{prelude::*, saveload::MarkedBuilder},
+        snapshot::SnapshotMarker,
+    };
+
+    use super::*;
+    use crate::{ProcessableAsset, RonFormat, Source};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Level {
+        name: String,
+    }
+
+    impl Asset for Level {
+        const NAME: &'static str = "Level";
+        type Data = Self;
+        type HandleStorage = VecStorage<Handle<Self>>;
+    }
+
+    struct Levels;
+
+    impl Source for Levels {
+        fn modified(&self, _: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            Ok(format!("(name: {:?})", path).into_bytes())
+        }
+    }
+
+    fn setup(schema: &SnapshotSchema) -> World {
+        let mut world = World::new();
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        let mut loader = Loader::new(".", pool.clone());
+        loader.add_source("levels", Levels);
+        world.insert(pool);
+        world.insert(loader);
+        world.insert(AssetStorage::<Level>::new());
+        schema.setup(&mut world);
+        world
+    }
+
+    #[test]
+    fn handles_are_loaded_again_from_their_path() {
+        let schema = SnapshotSchema::new(1).with_asset_handle::<Level, _>("level", RonFormat);
+        let mut world = setup(&schema);
+        let handle = world.read_resource::<Loader>().load_from(
+            "forest",
+            RonFormat,
+            "levels",
+            (),
+            &world.read_resource::<AssetStorage<Level>>(),
+        );
+        world
+            .create_entity()
+            .with(handle)
+            .marked::<SnapshotMarker>()
+            .build();
+
+        let snapshot = schema.save(&world).unwrap();
+        let path = AssetPath {
+            source: "levels".to_string(),
+            name: "forest".to_string(),
+        };
+        assert_eq!(
+            snapshot.entities[0].components["level"],
+            serde_json::to_value(&path).unwrap()
+        );
+
+        let mut loaded = setup(&schema);
+        schema.load(&mut loaded, snapshot).unwrap();
+        let handle = (&loaded.read_storage::<Handle<Level>>())
+            .join()
+            .next()
+            .cloned()
+            .expect("Expected the handle to be loaded");
+        let pool = loaded.read_resource::<Arc<ThreadPool>>().clone();
+        let mut storage = loaded.write_resource::<AssetStorage<Level>>();
+        for _ in 0..1000 {
+            if storage.contains(&handle) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+            storage.process(ProcessableAsset::process, 0, &pool, None);
+        }
+        assert_eq!(storage.path(&handle), Some(path));
+        assert_eq!(
+            storage.get(&handle),
+            Some(&Level {
+                name: "forest".to_string()
+            })
+        );
+    }
+}
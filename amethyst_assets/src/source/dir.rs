@@ -1,5 +1,6 @@
 use std::{
     fs::File,
+    io::BufReader,
     path::{Path, PathBuf},
     time::UNIX_EPOCH,
 };
@@ -9,7 +10,10 @@ use thread_profiler::profile_scope;
 
 use amethyst_error::{format_err, Error, ResultExt};
 
-use crate::{error, source::Source};
+use crate::{
+    error,
+    source::{ReadSeek, Source},
+};
 
 /// Directory source.
 ///
@@ -75,6 +79,16 @@ impl Source for Directory {
 
         Ok(v)
     }
+
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>, Error> {
+        let path = self.path(path);
+
+        let file = File::open(&path)
+            .with_context(|_| format_err!("Failed to open file {:?}", path))
+            .with_context(|_| error::Error::Source)?;
+
+        Ok(Box::new(BufReader::new(file)))
+    }
 }
 
 #[cfg(test)]
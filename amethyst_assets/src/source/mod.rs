@@ -1,3 +1,5 @@
+use std::io::{Cursor, Read, Seek};
+
 use amethyst_error::Error;
 
 pub use self::dir::Directory;
@@ -32,4 +34,17 @@ pub trait Source: Send + Sync + 'static {
 
         Ok((b, m))
     }
+
+    /// Opens a reader over the bytes of the given path, used to stream large assets instead of
+    /// loading them at once.
+    ///
+    /// The default implementation reads the bytes using `load`.
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>, Error> {
+        Ok(Box::new(Cursor::new(self.load(path)?)))
+    }
 }
+
+/// A reader over the bytes of an asset, see `Source::open`.
+pub trait ReadSeek: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> ReadSeek for T {}
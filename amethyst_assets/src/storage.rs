@@ -8,8 +8,11 @@ use std::{
 
 use crossbeam_queue::SegQueue;
 use derivative::Derivative;
+use fnv::FnvHashMap;
 use log::{debug, error, trace, warn};
+use parking_lot::RwLock;
 use rayon::ThreadPool;
+use serde::{Deserialize, Serialize};
 
 use amethyst_core::{
     ecs::{
@@ -44,6 +47,15 @@ impl Allocator {
     }
 }
 
+/// Where the `Loader` loaded an asset from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AssetPath {
+    /// The id of the source, empty for the default source.
+    pub source: String,
+    /// The name of the asset in its source, e.g. `"meshes/a.obj"`.
+    pub name: String,
+}
+
 /// An asset storage, storing the actual assets and allocating
 /// handles to them.
 pub struct AssetStorage<A: Asset> {
@@ -54,6 +66,8 @@ pub struct AssetStorage<A: Asset> {
     pub(crate) processed: Arc<SegQueue<Processed<A>>>,
     reloads: Vec<(WeakHandle<A>, Box<dyn Reload<A::Data>>)>,
     unused_handles: SegQueue<Handle<A>>,
+    /// Where the assets loaded by the `Loader` come from, by handle id.
+    paths: RwLock<FnvHashMap<u32, AssetPath>>,
 }
 
 /// Returned by processor systems, describes the loading state of the asset.
@@ -88,6 +102,17 @@ impl<A: Asset> AssetStorage<A> {
         }
     }
 
+    /// Records where the `Loader` loads the asset of a handle from.
+    pub(crate) fn set_path(&self, handle: &Handle<A>, path: AssetPath) {
+        self.paths.write().insert(handle.id(), path);
+    }
+
+    /// Returns where the asset of a handle was loaded from, `None` if it wasn't loaded by the
+    /// `Loader` from a source, e.g. when it was inserted or loaded from data.
+    pub fn path(&self, handle: &Handle<A>) -> Option<AssetPath> {
+        self.paths.read().get(&handle.id()).cloned()
+    }
+
     /// Remove all data from asset storages, invalidating all associated handles.
     /// Trying to retreive any data using old handle will return `None`.
     pub fn unload_all(&mut self) {
@@ -413,6 +438,7 @@ impl<A: Asset> AssetStorage<A> {
                 drop_fn(asset);
             }
             self.bitset.remove(id);
+            self.paths.get_mut().remove(&id);
 
             // Can't reuse old handle here, because otherwise weak handles would still be valid.
             // TODO: maybe just store u32?
@@ -484,6 +510,7 @@ impl<A: Asset> Default for AssetStorage<A> {
             processed: Arc::new(SegQueue::new()),
             reloads: Default::default(),
             unused_handles: SegQueue::new(),
+            paths: Default::default(),
         }
     }
 }
@@ -1,6 +1,9 @@
 //! ECS audio bundles
 
+use std::path::Path;
+
 use amethyst_assets::Processor;
+use amethyst_config::{Config, ConfigError};
 use amethyst_core::{
     bundle::SystemBundle,
     ecs::prelude::{DispatcherBuilder, World},
@@ -8,17 +11,48 @@ use amethyst_core::{
 };
 use amethyst_error::Error;
 
-use crate::{output::Output, source::*, systems::AudioSystemDesc};
+use crate::{
+    mixer::{Mixer, MixerConfig},
+    output::Output,
+    source::*,
+    systems::AudioSystemDesc,
+};
 
 /// Audio bundle
 ///
-/// This will only add the audio system and the asset processor for `Source`.
+/// This will only add the audio system and the asset processor for `Source`,
+/// and insert the `Mixer` resource.
 ///
-/// `DjSystem` must be added separately if you want to use our background music system.
+/// `DjSystem` or `MusicSystem` must be added separately if you want to use our background music
+/// systems.
 ///
 /// The generic N type should be the same as the one in `Transform`.
 #[derive(Default, Debug)]
-pub struct AudioBundle(Output);
+pub struct AudioBundle {
+    output: Output,
+    mixer: MixerConfig,
+}
+
+impl AudioBundle {
+    /// Creates a bundle playing to the given output, e.g. `Output::null()` on servers.
+    pub fn new(output: Output) -> Self {
+        AudioBundle {
+            output,
+            mixer: MixerConfig::default(),
+        }
+    }
+
+    /// Configures the `Mixer` with the given configuration.
+    pub fn with_mixer_config(mut self, mixer: MixerConfig) -> Self {
+        self.mixer = mixer;
+        self
+    }
+
+    /// Configures the `Mixer` from a RON file, see `MixerConfig`.
+    pub fn with_mixer_config_from_file<P: AsRef<Path>>(self, file: P) -> Result<Self, ConfigError> {
+        Ok(self.with_mixer_config(MixerConfig::load_no_fallback(file)?))
+    }
+}
 
 impl<'a, 'b> SystemBundle<'a, 'b> for AudioBundle {
     fn build(
@@ -26,8 +60,9 @@ impl<'a, 'b> SystemBundle<'a, 'b> for AudioBundle {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        world.insert(Mixer::from_config(&self.mixer));
         builder.add(
-            AudioSystemDesc::new(self.0).build(world),
+            AudioSystemDesc::new(self.output).build(world),
             "audio_system",
             &[],
         );
@@ -1,23 +1,36 @@
-use std::{
-    io::Cursor,
-    sync::{atomic::AtomicBool, Arc},
-};
+use std::sync::{atomic::AtomicBool, Arc};
 
-use rodio::{Decoder, SpatialSink};
+use rodio::Sink;
 use smallvec::SmallVec;
 
-use amethyst_core::ecs::{prelude::Component, storage::BTreeStorage};
+use amethyst_core::{
+    ecs::{prelude::Component, storage::BTreeStorage},
+    math::Point3,
+};
 
-use crate::{source::Source, DecoderError};
+use crate::{
+    effects::EffectChain,
+    mixer::AudioBus,
+    source::{Source, SourceDecoder},
+    spatial::{Attenuation, Cone, Doppler, SpatialControl},
+    DecoderError,
+};
 
 /// An audio source, add this component to anything that emits sound.
 /// TODO: This should get a proper Debug impl parsing the sinks and sound queue
 #[allow(missing_debug_implementations)]
 #[derive(Default)]
 pub struct AudioEmitter {
-    pub(crate) sinks: SmallVec<[(SpatialSink, Arc<AtomicBool>); 4]>,
-    pub(crate) sound_queue: SmallVec<[Decoder<Cursor<Source>>; 4]>,
+    pub(crate) sinks: SmallVec<[(Sink, SpatialControl, Arc<AtomicBool>); 4]>,
+    pub(crate) sound_queue: SmallVec<[(Source, SourceDecoder); 4]>,
+    pub(crate) cue_queue: SmallVec<[String; 2]>,
     pub(crate) picker: Option<Box<dyn FnMut(&mut AudioEmitter) -> bool + Send + Sync>>,
+    pub(crate) bus: AudioBus,
+    pub(crate) effects: EffectChain,
+    pub(crate) attenuation: Attenuation,
+    pub(crate) cone: Option<Cone>,
+    pub(crate) doppler: Doppler,
+    pub(crate) last_position: Option<Point3<f32>>,
 }
 
 impl AudioEmitter {
@@ -28,13 +41,84 @@ impl AudioEmitter {
         Default::default()
     }
 
+    /// Creates a new AudioEmitter which plays its sounds on the given bus.
+    pub fn with_bus(bus: AudioBus) -> AudioEmitter {
+        AudioEmitter {
+            bus,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the bus this emitter plays its sounds on, `AudioBus::Sfx` by default.
+    pub fn bus(&self) -> AudioBus {
+        self.bus
+    }
+
+    /// Sets the bus this emitter plays its sounds on.
+    ///
+    /// Sounds which are already playing stay on their previous bus.
+    pub fn set_bus(&mut self, bus: AudioBus) {
+        self.bus = bus;
+    }
+
+    /// Returns the effects applied to the sounds of this emitter, before those of its bus.
+    ///
+    /// Changes to the chain are picked up by sounds which are already playing.
+    pub fn effects(&self) -> &EffectChain {
+        &self.effects
+    }
+
+    /// Replaces the effect chain of this emitter. The chain may be shared with other emitters.
+    ///
+    /// Sounds which are already playing keep using the previous chain.
+    pub fn set_effects(&mut self, effects: EffectChain) {
+        self.effects = effects;
+    }
+
+    /// Returns how the volume of this emitter decreases with the distance to the listener.
+    pub fn attenuation(&self) -> &Attenuation {
+        &self.attenuation
+    }
+
+    /// Sets how the volume of this emitter decreases with the distance to the listener.
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.attenuation = attenuation;
+    }
+
+    /// Returns the cone of this emitter, if it is directional.
+    pub fn cone(&self) -> Option<&Cone> {
+        self.cone.as_ref()
+    }
+
+    /// Makes this emitter directional, or omnidirectional when `None` is passed.
+    pub fn set_cone(&mut self, cone: Option<Cone>) {
+        self.cone = cone;
+    }
+
+    /// Returns the doppler settings of this emitter.
+    pub fn doppler(&self) -> &Doppler {
+        &self.doppler
+    }
+
+    /// Sets the doppler settings of this emitter.
+    pub fn set_doppler(&mut self, doppler: Doppler) {
+        self.doppler = doppler;
+    }
+
     /// Plays an audio source from this emitter.
     pub fn play(&mut self, source: &Source) -> Result<(), DecoderError> {
-        self.sound_queue
-            .push(Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?);
+        self.sound_queue.push((source.clone(), source.decoder()?));
         Ok(())
     }
 
+    /// Plays the `SoundCue` with the given name from this emitter.
+    ///
+    /// The cue is looked up in the `SoundCues` resource by the `AudioSystem`, which may skip it
+    /// because of its cooldown or voice limits.
+    pub fn play_cue<N: Into<String>>(&mut self, name: N) {
+        self.cue_queue.push(name.into());
+    }
+
     /// An emitter's picker will be called by the AudioSystem whenever the emitter runs out of
     /// sounds to play.
     ///
@@ -76,7 +160,7 @@ mod tests {
         f.read_to_end(&mut buffer).unwrap();
 
         // Create a Source and AudioEmitter from those bytes
-        let src = Source { bytes: buffer };
+        let src = Source::from_bytes(buffer);
         let mut emitter = AudioEmitter::default();
 
         // Call play
@@ -118,6 +202,13 @@ mod tests {
         test_play("tests/sound_test.fake", false);
     }
 
+    // A default emitter sounds like it did through rodio's `SpatialSink`.
+    #[test]
+    fn test_default_attenuation() {
+        let emitter = AudioEmitter::default();
+        assert_eq!(emitter.attenuation().gain(2.0), 0.25);
+    }
+
     // test_picker tests the set and clear picker functions
     #[test]
     fn test_picker() {
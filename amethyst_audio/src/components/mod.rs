@@ -11,7 +11,12 @@ use amethyst_error::Error;
 
 use serde::{Deserialize, Serialize};
 
-use crate::output::Output;
+use crate::{
+    effects::{Effect, EffectChain},
+    mixer::AudioBus,
+    output::Output,
+    spatial::{Attenuation, Cone},
+};
 
 mod audio_emitter;
 mod audio_listener;
@@ -22,6 +27,18 @@ mod audio_listener;
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct AudioPrefab {
     emitter: bool,
+    /// The bus the emitter plays its sounds on.
+    #[serde(default)]
+    bus: AudioBus,
+    /// The effects applied to the sounds of the emitter.
+    #[serde(default)]
+    effects: Vec<Effect>,
+    /// The distance attenuation of the emitter.
+    #[serde(default)]
+    attenuation: Attenuation,
+    /// Makes the emitter directional.
+    #[serde(default)]
+    cone: Option<Cone>,
     /// Left, Right
     listener: Option<(Point3<f32>, Point3<f32>)>,
 }
@@ -42,7 +59,13 @@ impl<'a> PrefabData<'a> for AudioPrefab {
         _: &[Entity],
     ) -> Result<(), Error> {
         if self.emitter {
-            system_data.0.insert(entity, AudioEmitter::default())?;
+            let mut emitter = AudioEmitter::with_bus(self.bus);
+            if !self.effects.is_empty() {
+                emitter.set_effects(EffectChain::from_effects(self.effects.clone()));
+            }
+            emitter.set_attenuation(self.attenuation);
+            emitter.set_cone(self.cone);
+            system_data.0.insert(entity, emitter)?;
         }
         if let Some((left_ear, right_ear)) = self.listener {
             system_data.1.insert(
@@ -56,3 +79,76 @@ impl<'a> PrefabData<'a> for AudioPrefab {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::ecs::{Builder, SystemData, World, WorldExt};
+
+    use crate::spatial::DistanceModel;
+
+    use super::*;
+
+    fn add_to_new_entity(prefab: &AudioPrefab) -> (World, Entity) {
+        let mut world = World::new();
+        world.register::<AudioEmitter>();
+        world.register::<AudioListener>();
+        let entity = world.create_entity().build();
+        prefab
+            .add_to_entity(
+                entity,
+                &mut <AudioPrefab as PrefabData<'_>>::SystemData::fetch(&world),
+                &[],
+                &[],
+            )
+            .unwrap();
+        (world, entity)
+    }
+
+    #[test]
+    fn prefab_emitter_gets_effect_chain() {
+        let effects = vec![
+            Effect::LowPass { cutoff: 800.0 },
+            Effect::Echo {
+                delay: 0.25,
+                feedback: 0.5,
+                mix: 0.5,
+            },
+        ];
+        let prefab = AudioPrefab {
+            emitter: true,
+            effects: effects.clone(),
+            ..Default::default()
+        };
+
+        let (world, entity) = add_to_new_entity(&prefab);
+        let emitters = world.read_storage::<AudioEmitter>();
+        assert_eq!(emitters.get(entity).unwrap().effects().effects(), effects);
+    }
+
+    #[test]
+    fn prefab_emitter_gets_attenuation_and_cone() {
+        let attenuation = Attenuation {
+            model: DistanceModel::Inverse,
+            min_distance: 2.0,
+            max_distance: 50.0,
+            rolloff: 1.5,
+        };
+        let cone = Cone {
+            inner_angle: 60.0,
+            outer_angle: 120.0,
+            outer_gain: 0.25,
+        };
+        let prefab = AudioPrefab {
+            emitter: true,
+            attenuation,
+            cone: Some(cone),
+            ..Default::default()
+        };
+
+        let (world, entity) = add_to_new_entity(&prefab);
+        let emitters = world.read_storage::<AudioEmitter>();
+        let emitter = emitters.get(entity).unwrap();
+        assert_eq!(emitter.attenuation(), &attenuation);
+        assert_eq!(emitter.cone(), Some(&cone));
+    }
+}
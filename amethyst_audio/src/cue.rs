@@ -0,0 +1,509 @@
+//! Provides sound cues: named sounds with randomized variations and a limited amount of voices.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use log::warn;
+use rand::{thread_rng, Rng};
+use rodio::{Sample, Source as RSource};
+use serde::{Deserialize, Serialize};
+
+use amethyst_assets::{AssetStorage, Loader};
+
+use crate::{
+    formats::{DecodedFormat, FlacFormat, Mp3Format, OggFormat, WavFormat},
+    source::{Source, SourceHandle},
+};
+
+/// What happens when a cue is triggered while all of its voices are playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoiceStealing {
+    /// The new sound is not played.
+    Reject,
+    /// The oldest voice is stopped to play the new sound.
+    Oldest,
+}
+
+impl Default for VoiceStealing {
+    fn default() -> Self {
+        VoiceStealing::Oldest
+    }
+}
+
+/// A named sound, played with `AudioEmitter::play_cue`.
+///
+/// Every time the cue is played one of its variations is picked at random, with a random pitch
+/// and volume.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoundCue {
+    /// The sources picked from.
+    pub variations: Vec<SourceHandle>,
+    /// The range the pitch is picked in, 1.0 is unchanged.
+    pub pitch: (f32, f32),
+    /// The range the volume is picked in, 1.0 is unchanged.
+    pub volume: (f32, f32),
+    /// The minimum time between two plays of the cue, in game time.
+    pub cooldown: Duration,
+    /// How many sounds of this cue can play at once.
+    pub max_voices: usize,
+    /// When the total amount of voices is reached, voices of cues with a lower priority are
+    /// stolen.
+    pub priority: i32,
+    /// What happens when all the voices of this cue are playing.
+    pub stealing: VoiceStealing,
+}
+
+impl SoundCue {
+    /// Creates a cue playing the given variations unchanged, without cooldown and with up to
+    /// eight voices.
+    pub fn new(variations: Vec<SourceHandle>) -> Self {
+        SoundCue {
+            variations,
+            pitch: (1.0, 1.0),
+            volume: (1.0, 1.0),
+            cooldown: Duration::from_secs(0),
+            max_voices: 8,
+            priority: 0,
+            stealing: VoiceStealing::default(),
+        }
+    }
+}
+
+/// The configuration of a `SoundCue`, with the paths of its variations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundCueConfig {
+    /// The paths of the variations, their format is chosen by extension.
+    pub variations: Vec<String>,
+    /// The range the pitch is picked in.
+    pub pitch: (f32, f32),
+    /// The range the volume is picked in.
+    pub volume: (f32, f32),
+    /// The cooldown, in seconds.
+    pub cooldown: f32,
+    /// How many sounds of this cue can play at once.
+    pub max_voices: usize,
+    /// The priority of the cue's voices.
+    pub priority: i32,
+    /// What happens when all the voices of this cue are playing.
+    pub stealing: VoiceStealing,
+}
+
+impl Default for SoundCueConfig {
+    fn default() -> Self {
+        SoundCueConfig {
+            variations: Vec::new(),
+            pitch: (1.0, 1.0),
+            volume: (1.0, 1.0),
+            cooldown: 0.0,
+            max_voices: 8,
+            priority: 0,
+            stealing: VoiceStealing::default(),
+        }
+    }
+}
+
+/// The configuration of the `SoundCues` resource, usually loaded from a RON file:
+///
+/// ```ron
+/// (
+///     max_voices: 32,
+///     cues: {
+///         "jump": (
+///             variations: ["audio/jump_1.wav", "audio/jump_2.wav"],
+///             pitch: (0.9, 1.1),
+///             volume: (0.8, 1.0),
+///             cooldown: 0.1,
+///             max_voices: 2,
+///         ),
+///         "explosion": (variations: ["audio/explosion.ogg"], priority: 10),
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundCuesConfig {
+    /// How many sounds of all cues can play at once.
+    pub max_voices: usize,
+    /// The cues by name.
+    pub cues: HashMap<String, SoundCueConfig>,
+}
+
+impl Default for SoundCuesConfig {
+    fn default() -> Self {
+        SoundCuesConfig {
+            max_voices: 32,
+            cues: HashMap::new(),
+        }
+    }
+}
+
+/// Resource holding the `SoundCue`s by name.
+#[derive(Clone, Debug)]
+pub struct SoundCues {
+    cues: HashMap<String, SoundCue>,
+    max_voices: usize,
+}
+
+impl Default for SoundCues {
+    fn default() -> Self {
+        SoundCues {
+            cues: HashMap::new(),
+            max_voices: 32,
+        }
+    }
+}
+
+impl SoundCues {
+    /// Creates the cues of the given configuration, loading their variations.
+    ///
+    /// Variations are decoded when loaded, as cues are meant for short sounds played often.
+    pub fn load(config: &SoundCuesConfig, loader: &Loader, storage: &AssetStorage<Source>) -> Self {
+        let cues = config
+            .cues
+            .iter()
+            .map(|(name, cue)| {
+                let variations = cue
+                    .variations
+                    .iter()
+                    .filter_map(|path| load_variation(path, loader, storage))
+                    .collect();
+                let cue = SoundCue {
+                    variations,
+                    pitch: cue.pitch,
+                    volume: cue.volume,
+                    cooldown: Duration::from_secs_f32(cue.cooldown.max(0.0)),
+                    max_voices: cue.max_voices,
+                    priority: cue.priority,
+                    stealing: cue.stealing,
+                };
+                (name.clone(), cue)
+            })
+            .collect();
+        SoundCues {
+            cues,
+            max_voices: config.max_voices,
+        }
+    }
+
+    /// Returns the cue with the given name.
+    pub fn get(&self, name: &str) -> Option<&SoundCue> {
+        self.cues.get(name)
+    }
+
+    /// Adds a cue, replacing the one with the same name.
+    pub fn insert<N: Into<String>>(&mut self, name: N, cue: SoundCue) -> Option<SoundCue> {
+        self.cues.insert(name.into(), cue)
+    }
+
+    /// Removes a cue.
+    pub fn remove(&mut self, name: &str) -> Option<SoundCue> {
+        self.cues.remove(name)
+    }
+
+    /// Returns how many sounds of all cues can play at once, 32 by default.
+    pub fn max_voices(&self) -> usize {
+        self.max_voices
+    }
+
+    /// Sets how many sounds of all cues can play at once.
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        self.max_voices = max_voices;
+    }
+}
+
+fn load_variation(
+    path: &str,
+    loader: &Loader,
+    storage: &AssetStorage<Source>,
+) -> Option<SourceHandle> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("wav") => Some(loader.load(path, DecodedFormat(WavFormat), (), storage)),
+        Some("ogg") => Some(loader.load(path, DecodedFormat(OggFormat), (), storage)),
+        Some("flac") => Some(loader.load(path, DecodedFormat(FlacFormat), (), storage)),
+        Some("mp3") => Some(loader.load(path, DecodedFormat(Mp3Format), (), storage)),
+        _ => {
+            warn!("Unknown audio format of sound cue variation {:?}", path);
+            None
+        }
+    }
+}
+
+fn random_in((min, max): (f32, f32)) -> f32 {
+    if min < max {
+        thread_rng().gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+/// How a triggered cue is played.
+#[derive(Clone, Debug)]
+pub(crate) struct CueSound {
+    pub source: SourceHandle,
+    pub pitch: f32,
+    pub volume: f32,
+    pub voice: Arc<VoiceState>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct VoiceState {
+    stopped: AtomicBool,
+    ended: AtomicBool,
+}
+
+impl VoiceState {
+    /// Frees the voice of a sound which could not be played.
+    pub fn end(&self) {
+        self.ended.store(true, Ordering::Relaxed);
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    fn is_ended(&self) -> bool {
+        self.ended.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct Voice {
+    cue: String,
+    priority: i32,
+    started: Duration,
+    state: Arc<VoiceState>,
+}
+
+/// Tracks the voices of all cues, owned by the `AudioSystem`.
+#[derive(Debug, Default)]
+pub(crate) struct CueVoices {
+    voices: Vec<Voice>,
+    last_played: HashMap<String, Duration>,
+    last_variation: HashMap<String, usize>,
+}
+
+impl CueVoices {
+    /// Decides whether the cue can be played at the given time, stealing a voice if needed.
+    pub fn trigger(&mut self, name: &str, cues: &SoundCues, now: Duration) -> Option<CueSound> {
+        let cue = match cues.get(name) {
+            Some(cue) => cue,
+            None => {
+                warn!("Unknown sound cue {:?}", name);
+                return None;
+            }
+        };
+        if cue.variations.is_empty() {
+            return None;
+        }
+        self.voices.retain(|voice| !voice.state.is_ended());
+        if let Some(&last) = self.last_played.get(name) {
+            if now < last + cue.cooldown {
+                return None;
+            }
+        }
+
+        let cue_voices = self.voices.iter().filter(|voice| voice.cue == name).count();
+        if cue_voices >= cue.max_voices {
+            match cue.stealing {
+                VoiceStealing::Reject => return None,
+                VoiceStealing::Oldest => self.steal(|voice| voice.cue == name)?,
+            }
+        }
+        if self.voices.len() >= cues.max_voices() {
+            let stealing = cue.stealing;
+            let priority = cue.priority;
+            self.steal(|voice| {
+                voice.priority < priority
+                    || (voice.priority == priority && stealing == VoiceStealing::Oldest)
+            })?;
+        }
+
+        let variation = self.pick_variation(name, cue.variations.len());
+        let voice = Arc::new(VoiceState::default());
+        self.voices.push(Voice {
+            cue: name.to_string(),
+            priority: cue.priority,
+            started: now,
+            state: voice.clone(),
+        });
+        self.last_played.insert(name.to_string(), now);
+        Some(CueSound {
+            source: cue.variations[variation].clone(),
+            pitch: random_in(cue.pitch),
+            volume: random_in(cue.volume),
+            voice,
+        })
+    }
+
+    /// Stops the voice with the lowest priority, then the oldest, among those matching.
+    fn steal<F: Fn(&Voice) -> bool>(&mut self, can_steal: F) -> Option<()> {
+        let (index, _) = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter(|(_, voice)| can_steal(voice))
+            .min_by_key(|(_, voice)| (voice.priority, voice.started))?;
+        self.voices.remove(index).state.stop();
+        Some(())
+    }
+
+    /// Picks a random variation, avoiding the previous one.
+    fn pick_variation(&mut self, name: &str, count: usize) -> usize {
+        let last = self.last_variation.get(name).cloned();
+        let variation = match last {
+            Some(last) if count > 1 => (last + thread_rng().gen_range(1, count)) % count,
+            _ => thread_rng().gen_range(0, count),
+        };
+        self.last_variation.insert(name.to_string(), variation);
+        variation
+    }
+}
+
+/// Ends when the voice is stolen, and marks the voice as ended with the sound.
+pub(crate) struct VoiceSource<I> {
+    input: I,
+    voice: Arc<VoiceState>,
+}
+
+impl<I> VoiceSource<I> {
+    pub fn new(input: I, voice: Arc<VoiceState>) -> Self {
+        VoiceSource { input, voice }
+    }
+}
+
+impl<I> Iterator for VoiceSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let sample = if self.voice.stopped.load(Ordering::Relaxed) {
+            None
+        } else {
+            self.input.next()
+        };
+        if sample.is_none() {
+            self.voice.ended.store(true, Ordering::Relaxed);
+        }
+        sample
+    }
+}
+
+impl<I> RSource for VoiceSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+impl<I> Drop for VoiceSource<I> {
+    fn drop(&mut self) {
+        self.voice.ended.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cues(cue: SoundCue, max_voices: usize) -> SoundCues {
+        let mut cues = SoundCues::default();
+        cues.set_max_voices(max_voices);
+        cues.insert("cue", cue);
+        cues
+    }
+
+    fn cue(storage: &mut AssetStorage<Source>) -> SoundCue {
+        let variations = (0..3)
+            .map(|i| storage.insert(Source::from_bytes(vec![i])))
+            .collect();
+        SoundCue::new(variations)
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn cooldown_and_ranges() {
+        let mut storage = AssetStorage::new();
+        let mut cue = cue(&mut storage);
+        cue.cooldown = secs(1);
+        cue.pitch = (0.5, 0.6);
+        let cues = cues(cue, 32);
+        let mut voices = CueVoices::default();
+
+        let first = voices.trigger("cue", &cues, secs(0)).unwrap();
+        assert!(first.pitch >= 0.5 && first.pitch < 0.6);
+        assert_eq!(first.volume, 1.0);
+        assert!(voices.trigger("cue", &cues, secs(0)).is_none());
+        let second = voices.trigger("cue", &cues, secs(1)).unwrap();
+        assert_ne!(first.source, second.source);
+        assert!(voices.trigger("other", &cues, secs(2)).is_none());
+    }
+
+    #[test]
+    fn cue_voices_are_stolen_or_rejected() {
+        let mut storage = AssetStorage::new();
+        let mut cue = cue(&mut storage);
+        cue.max_voices = 1;
+        let mut voices = CueVoices::default();
+
+        let first = voices
+            .trigger("cue", &cues(cue.clone(), 32), secs(0))
+            .unwrap();
+        voices
+            .trigger("cue", &cues(cue.clone(), 32), secs(1))
+            .unwrap();
+        assert!(first.voice.stopped.load(Ordering::Relaxed));
+
+        cue.stealing = VoiceStealing::Reject;
+        assert!(voices.trigger("cue", &cues(cue, 32), secs(2)).is_none());
+    }
+
+    #[test]
+    fn lower_priority_voices_are_stolen() {
+        let mut storage = AssetStorage::new();
+        let mut cues = cues(cue(&mut storage), 1);
+        let mut important = cue(&mut storage);
+        important.priority = 1;
+        important.stealing = VoiceStealing::Reject;
+        cues.insert("important", important);
+        let mut voices = CueVoices::default();
+
+        let low = voices.trigger("cue", &cues, secs(0)).unwrap();
+        let high = voices.trigger("important", &cues, secs(1)).unwrap();
+        assert!(low.voice.stopped.load(Ordering::Relaxed));
+        assert!(voices.trigger("cue", &cues, secs(2)).is_none());
+        assert!(!high.voice.stopped.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,715 @@
+//! Provides DSP effects which can be applied to sounds, either per `AudioEmitter` or per bus.
+
+use std::{
+    f32::consts::PI,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source as RSource};
+use serde::{Deserialize, Serialize};
+
+/// A DSP effect.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    /// Attenuates frequencies above the cutoff frequency, in Hz.
+    LowPass {
+        /// The cutoff frequency in Hz.
+        cutoff: f32,
+    },
+    /// Attenuates frequencies below the cutoff frequency, in Hz.
+    HighPass {
+        /// The cutoff frequency in Hz.
+        cutoff: f32,
+    },
+    /// Repeats the sound after a delay, each repetition being attenuated by `feedback`.
+    Echo {
+        /// The delay between repetitions, in seconds.
+        delay: f32,
+        /// How much of a repetition is fed back into the next one, between 0.0 and 1.0.
+        feedback: f32,
+        /// How much of the echo is mixed into the output, between 0.0 and 1.0.
+        mix: f32,
+    },
+    /// A simple reverb, made of parallel comb filters followed by all-pass filters.
+    Reverb {
+        /// The size of the simulated room, between 0.0 and 1.0.
+        room_size: f32,
+        /// How much high frequencies are absorbed, between 0.0 and 1.0.
+        damping: f32,
+        /// How much of the reverb is mixed into the output, between 0.0 and 1.0.
+        mix: f32,
+    },
+    /// Reduces the dynamic range of the sound.
+    Compressor {
+        /// The level above which the sound is compressed, in dB.
+        threshold: f32,
+        /// The compression ratio, e.g. 4.0 for 4:1.
+        ratio: f32,
+        /// How fast the compressor reacts to a rising level, in seconds.
+        attack: f32,
+        /// How fast the compressor recovers from a falling level, in seconds.
+        release: f32,
+        /// Gain applied after compression, in dB.
+        makeup_gain: f32,
+    },
+}
+
+struct ChainState {
+    effects: Mutex<Vec<Effect>>,
+    version: AtomicUsize,
+}
+
+/// An ordered list of effects, shared between the owner of the chain and every sound it is
+/// applied to.
+///
+/// Cloning an `EffectChain` returns a handle to the same chain, so changes made at runtime are
+/// picked up by sounds which are already playing.
+///
+/// Effects which produce a tail, like `Echo` and `Reverb`, are cut when the sound ends.
+#[derive(Clone)]
+pub struct EffectChain {
+    state: Arc<ChainState>,
+}
+
+impl EffectChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        EffectChain::from_effects(Vec::new())
+    }
+
+    /// Creates a chain with the given effects, applied in order.
+    pub fn from_effects(effects: Vec<Effect>) -> Self {
+        EffectChain {
+            state: Arc::new(ChainState {
+                effects: Mutex::new(effects),
+                version: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns a copy of the effects in the chain.
+    pub fn effects(&self) -> Vec<Effect> {
+        self.state.effects.lock().unwrap().clone()
+    }
+
+    /// Returns true if the chain has no effects.
+    pub fn is_empty(&self) -> bool {
+        self.state.effects.lock().unwrap().is_empty()
+    }
+
+    /// Replaces all effects in the chain.
+    pub fn set_effects(&self, effects: Vec<Effect>) {
+        self.modify(|current| *current = effects);
+    }
+
+    /// Appends an effect to the chain.
+    pub fn push(&self, effect: Effect) {
+        self.modify(|current| current.push(effect));
+    }
+
+    /// Replaces the effect at the given index, keeping the state of the effect if it is of the
+    /// same kind. This is the way to tweak effect parameters without clicks.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn set(&self, index: usize, effect: Effect) {
+        self.modify(|current| current[index] = effect);
+    }
+
+    /// Removes the effect at the given index.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn remove(&self, index: usize) -> Effect {
+        let mut removed = None;
+        self.modify(|current| removed = Some(current.remove(index)));
+        removed.unwrap()
+    }
+
+    /// Removes all effects from the chain.
+    pub fn clear(&self) {
+        self.modify(Vec::clear);
+    }
+
+    /// Applies this chain to the given source.
+    pub fn apply<I>(&self, input: I) -> EffectSource<I>
+    where
+        I: RSource,
+        I::Item: Sample,
+    {
+        EffectSource {
+            processor: EffectProcessor::new(self.clone()),
+            input,
+            channel: 0,
+        }
+    }
+
+    fn modify<F: FnOnce(&mut Vec<Effect>)>(&self, f: F) {
+        f(&mut self.state.effects.lock().unwrap());
+        self.state.version.fetch_add(1, Ordering::Release);
+    }
+
+    fn version(&self) -> usize {
+        self.state.version.load(Ordering::Acquire)
+    }
+}
+
+impl Default for EffectChain {
+    fn default() -> Self {
+        EffectChain::new()
+    }
+}
+
+impl Debug for EffectChain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("EffectChain")
+            .field("effects", &self.effects())
+            .finish()
+    }
+}
+
+/// A source with an `EffectChain` applied, see `EffectChain::apply`.
+#[derive(Debug)]
+pub struct EffectSource<I> {
+    input: I,
+    processor: EffectProcessor,
+    channel: u16,
+}
+
+impl<I> Iterator for EffectSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            self.processor
+                .sync(self.input.channels(), self.input.sample_rate());
+        }
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.input.channels().max(1);
+
+        self.input
+            .next()
+            .map(|sample| self.processor.process(channel, sample.to_f32()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> RSource for EffectSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Runs the DSP state of an `EffectChain` for a single sound.
+///
+/// `sync` must be called on frame boundaries, it picks up changes made to the chain.
+pub(crate) struct EffectProcessor {
+    chain: EffectChain,
+    version: Option<usize>,
+    channels: u16,
+    sample_rate: u32,
+    stages: Vec<(Effect, Vec<Dsp>)>,
+}
+
+impl EffectProcessor {
+    pub(crate) fn new(chain: EffectChain) -> Self {
+        EffectProcessor {
+            chain,
+            version: None,
+            channels: 0,
+            sample_rate: 0,
+            stages: Vec::new(),
+        }
+    }
+
+    pub(crate) fn sync(&mut self, channels: u16, sample_rate: u32) {
+        let version = self.chain.version();
+        let format_changed = channels != self.channels || sample_rate != self.sample_rate;
+        if !format_changed && self.version == Some(version) {
+            return;
+        }
+        // Never block the audio thread, try again on the next frame instead.
+        let effects = match self.chain.state.effects.try_lock() {
+            Ok(effects) => effects.clone(),
+            Err(_) => return,
+        };
+        if format_changed {
+            self.stages.clear();
+            self.channels = channels;
+            self.sample_rate = sample_rate;
+        }
+        self.version = Some(version);
+
+        self.stages.truncate(effects.len());
+        for (index, effect) in effects.into_iter().enumerate() {
+            let keep_state = self
+                .stages
+                .get(index)
+                .map_or(false, |(current, _)| Dsp::same_kind(current, &effect));
+            if keep_state {
+                let (current, dsps) = &mut self.stages[index];
+                for dsp in dsps.iter_mut() {
+                    dsp.configure(&effect, sample_rate);
+                }
+                *current = effect;
+            } else {
+                let dsps = (0..channels)
+                    .map(|_| Dsp::new(&effect, sample_rate))
+                    .collect();
+                if index < self.stages.len() {
+                    self.stages[index] = (effect, dsps);
+                } else {
+                    self.stages.push((effect, dsps));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn process(&mut self, channel: u16, mut sample: f32) -> f32 {
+        for (_, dsps) in &mut self.stages {
+            if let Some(dsp) = dsps.get_mut(channel as usize) {
+                sample = dsp.process(sample);
+            }
+        }
+        sample
+    }
+}
+
+impl Debug for EffectProcessor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("EffectProcessor")
+            .field("chain", &self.chain)
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate)
+            .finish()
+    }
+}
+
+// Freeverb tunings, in samples at 44.1kHz.
+const COMB_TUNINGS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASS_TUNINGS: [usize; 2] = [556, 441];
+
+/// The state of a single effect for a single channel.
+enum Dsp {
+    Biquad(Biquad),
+    Echo {
+        buffer: Vec<f32>,
+        position: usize,
+        feedback: f32,
+        mix: f32,
+    },
+    Reverb {
+        combs: Vec<Comb>,
+        allpasses: Vec<AllPass>,
+        mix: f32,
+    },
+    Compressor {
+        envelope: f32,
+        threshold: f32,
+        ratio: f32,
+        attack: f32,
+        release: f32,
+        makeup: f32,
+    },
+}
+
+impl Dsp {
+    fn new(effect: &Effect, sample_rate: u32) -> Self {
+        let scale = sample_rate as f32 / 44100.0;
+        let mut dsp = match effect {
+            Effect::LowPass { .. } | Effect::HighPass { .. } => Dsp::Biquad(Biquad::default()),
+            Effect::Echo { delay, .. } => Dsp::Echo {
+                buffer: vec![0.0; ((delay * sample_rate as f32) as usize).max(1)],
+                position: 0,
+                feedback: 0.0,
+                mix: 0.0,
+            },
+            Effect::Reverb { .. } => Dsp::Reverb {
+                combs: COMB_TUNINGS
+                    .iter()
+                    .map(|t| Comb::new((*t as f32 * scale) as usize))
+                    .collect(),
+                allpasses: ALLPASS_TUNINGS
+                    .iter()
+                    .map(|t| AllPass::new((*t as f32 * scale) as usize))
+                    .collect(),
+                mix: 0.0,
+            },
+            Effect::Compressor { .. } => Dsp::Compressor {
+                envelope: 0.0,
+                threshold: 0.0,
+                ratio: 1.0,
+                attack: 0.0,
+                release: 0.0,
+                makeup: 1.0,
+            },
+        };
+        dsp.configure(effect, sample_rate);
+        dsp
+    }
+
+    fn same_kind(a: &Effect, b: &Effect) -> bool {
+        match (a, b) {
+            (Effect::LowPass { .. }, Effect::LowPass { .. })
+            | (Effect::HighPass { .. }, Effect::HighPass { .. })
+            | (Effect::Reverb { .. }, Effect::Reverb { .. })
+            | (Effect::Compressor { .. }, Effect::Compressor { .. }) => true,
+            // The delay line length depends on the delay.
+            (Effect::Echo { delay: a, .. }, Effect::Echo { delay: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+
+    fn configure(&mut self, effect: &Effect, sample_rate: u32) {
+        let sample_rate = sample_rate as f32;
+        match (self, effect) {
+            (Dsp::Biquad(biquad), Effect::LowPass { cutoff }) => {
+                biquad.set_low_pass(*cutoff, sample_rate)
+            }
+            (Dsp::Biquad(biquad), Effect::HighPass { cutoff }) => {
+                biquad.set_high_pass(*cutoff, sample_rate)
+            }
+            (
+                Dsp::Echo {
+                    feedback: f,
+                    mix: m,
+                    ..
+                },
+                Effect::Echo { feedback, mix, .. },
+            ) => {
+                *f = feedback.clamp(0.0, 0.99);
+                *m = *mix;
+            }
+            (
+                Dsp::Reverb { combs, mix: m, .. },
+                Effect::Reverb {
+                    room_size,
+                    damping,
+                    mix,
+                },
+            ) => {
+                let feedback = 0.7 + 0.28 * room_size.clamp(0.0, 1.0);
+                for comb in combs {
+                    comb.feedback = feedback;
+                    comb.damping = damping.clamp(0.0, 1.0);
+                }
+                *m = *mix;
+            }
+            (
+                Dsp::Compressor {
+                    threshold: t,
+                    ratio: r,
+                    attack: a,
+                    release: rel,
+                    makeup: mk,
+                    ..
+                },
+                Effect::Compressor {
+                    threshold,
+                    ratio,
+                    attack,
+                    release,
+                    makeup_gain,
+                },
+            ) => {
+                *t = *threshold;
+                *r = ratio.max(1.0);
+                *a = time_coefficient(*attack, sample_rate);
+                *rel = time_coefficient(*release, sample_rate);
+                *mk = db_to_gain(*makeup_gain);
+            }
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        match self {
+            Dsp::Biquad(biquad) => biquad.process(input),
+            Dsp::Echo {
+                buffer,
+                position,
+                feedback,
+                mix,
+            } => {
+                let delayed = buffer[*position];
+                buffer[*position] = input + delayed * *feedback;
+                *position = (*position + 1) % buffer.len();
+                input + delayed * *mix
+            }
+            Dsp::Reverb {
+                combs,
+                allpasses,
+                mix,
+            } => {
+                let mut wet =
+                    combs.iter_mut().map(|c| c.process(input)).sum::<f32>() / combs.len() as f32;
+                for allpass in allpasses {
+                    wet = allpass.process(wet);
+                }
+                input * (1.0 - *mix) + wet * *mix
+            }
+            Dsp::Compressor {
+                envelope,
+                threshold,
+                ratio,
+                attack,
+                release,
+                makeup,
+            } => {
+                let level = input.abs();
+                let coefficient = if level > *envelope { *attack } else { *release };
+                *envelope = level + coefficient * (*envelope - level);
+
+                let level_db = gain_to_db(*envelope);
+                let gain = if level_db > *threshold {
+                    let compressed = *threshold + (level_db - *threshold) / *ratio;
+                    db_to_gain(compressed - level_db)
+                } else {
+                    1.0
+                };
+                input * gain * *makeup
+            }
+        }
+    }
+}
+
+fn time_coefficient(seconds: f32, sample_rate: f32) -> f32 {
+    if seconds <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (seconds * sample_rate)).exp()
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-6).log10()
+}
+
+/// A second order filter, see the "Cookbook formulae for audio EQ biquad filter coefficients".
+#[derive(Default)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    fn set_low_pass(&mut self, cutoff: f32, sample_rate: f32) {
+        let (cos, alpha) = Biquad::omega(cutoff, sample_rate);
+        let b1 = 1.0 - cos;
+        self.set(b1 / 2.0, b1, b1 / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+    }
+
+    fn set_high_pass(&mut self, cutoff: f32, sample_rate: f32) {
+        let (cos, alpha) = Biquad::omega(cutoff, sample_rate);
+        let b1 = 1.0 + cos;
+        self.set(
+            b1 / 2.0,
+            -b1,
+            b1 / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        );
+    }
+
+    fn omega(cutoff: f32, sample_rate: f32) -> (f32, f32) {
+        let cutoff = cutoff.clamp(1.0, sample_rate * 0.49);
+        let omega = 2.0 * PI * cutoff / sample_rate;
+        // Q of 1/sqrt(2), the flattest response without resonance.
+        (
+            omega.cos(),
+            omega.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2),
+        )
+    }
+
+    fn set(&mut self, b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) {
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+struct Comb {
+    buffer: Vec<f32>,
+    position: usize,
+    feedback: f32,
+    damping: f32,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Comb {
+            buffer: vec![0.0; len.max(1)],
+            position: 0,
+            feedback: 0.0,
+            damping: 0.0,
+            filter_store: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.filter_store = output * (1.0 - self.damping) + self.filter_store * self.damping;
+        self.buffer[self.position] = input + self.filter_store * self.feedback;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct AllPass {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl AllPass {
+    fn new(len: usize) -> Self {
+        AllPass {
+            buffer: vec![0.0; len.max(1)],
+            position: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.position];
+        self.buffer[self.position] = input + buffered * 0.5;
+        self.position = (self.position + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::{buffer::SamplesBuffer, Source};
+
+    use super::*;
+
+    fn process(chain: &EffectChain, samples: Vec<f32>) -> Vec<f32> {
+        chain.apply(SamplesBuffer::new(1, 44100, samples)).collect()
+    }
+
+    #[test]
+    fn empty_chain_is_transparent() {
+        let chain = EffectChain::new();
+
+        assert_eq!(
+            process(&chain, vec![0.5, -0.25, 1.0]),
+            vec![0.5, -0.25, 1.0]
+        );
+    }
+
+    #[test]
+    fn low_pass_removes_alternating_signal() {
+        let chain = EffectChain::from_effects(vec![Effect::LowPass { cutoff: 200.0 }]);
+        let nyquist = (0..4410)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+
+        let output = process(&chain, nyquist);
+
+        assert!(output[4000..].iter().all(|s| s.abs() < 0.01));
+    }
+
+    #[test]
+    fn high_pass_removes_constant_signal() {
+        let chain = EffectChain::from_effects(vec![Effect::HighPass { cutoff: 200.0 }]);
+
+        let output = process(&chain, vec![1.0; 4410]);
+
+        assert!(output[4000..].iter().all(|s| s.abs() < 0.01));
+    }
+
+    #[test]
+    fn echo_repeats_after_delay() {
+        let chain = EffectChain::from_effects(vec![Effect::Echo {
+            delay: 0.001,
+            feedback: 0.0,
+            mix: 0.5,
+        }]);
+        let mut impulse = vec![0.0; 100];
+        impulse[0] = 1.0;
+
+        let output = process(&chain, impulse);
+
+        assert_eq!(output[0], 1.0);
+        assert_eq!(output[44], 0.5);
+    }
+
+    #[test]
+    fn compressor_reduces_loud_signal() {
+        let chain = EffectChain::from_effects(vec![Effect::Compressor {
+            threshold: -20.0,
+            ratio: 4.0,
+            attack: 0.0,
+            release: 0.1,
+            makeup_gain: 0.0,
+        }]);
+
+        let output = process(&chain, vec![1.0; 10]);
+
+        // 0dB in, 20dB over the threshold compressed to 5dB over it.
+        assert!((gain_to_db(output[9]) + 15.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn runtime_changes_reach_playing_sources() {
+        let chain = EffectChain::new();
+        let mut source = chain.apply(SamplesBuffer::new(1, 44100, vec![1.0f32; 4410]));
+        assert_eq!(source.next(), Some(1.0));
+
+        chain.push(Effect::HighPass { cutoff: 200.0 });
+
+        assert!(source.by_ref().skip(4000).all(|s| s.abs() < 0.01));
+        assert_eq!(source.channels(), 1);
+    }
+}
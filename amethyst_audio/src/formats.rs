@@ -1,10 +1,14 @@
+use std::sync::Arc;
+
 use amethyst_assets::*;
 use amethyst_error::Error;
 
 use serde::{Deserialize, Serialize};
 
+use crate::source::Source as AudioSource;
+
 #[derive(Clone, Debug)]
-pub struct AudioData(pub Vec<u8>);
+pub struct AudioData(pub AudioSource);
 amethyst_assets::register_format_type!(AudioData);
 
 /// Loads audio from wav files.
@@ -18,7 +22,7 @@ impl Format<AudioData> for WavFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        Ok(AudioData(AudioSource::from_bytes(bytes)))
     }
 }
 
@@ -33,7 +37,7 @@ impl Format<AudioData> for OggFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        Ok(AudioData(AudioSource::from_bytes(bytes)))
     }
 }
 
@@ -48,7 +52,7 @@ impl Format<AudioData> for FlacFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        Ok(AudioData(AudioSource::from_bytes(bytes)))
     }
 }
 
@@ -63,6 +67,49 @@ impl Format<AudioData> for Mp3Format {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        Ok(AudioData(AudioSource::from_bytes(bytes)))
+    }
+}
+
+/// Loads audio with the wrapped format and decodes it at once, see `Source::Decoded`.
+///
+/// Decoded sources use more memory, but are cheap to play. Use this for short sounds played
+/// often, e.g. `DecodedFormat(WavFormat)`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct DecodedFormat<F>(pub F);
+
+impl<F: Format<AudioData>> Format<AudioData> for DecodedFormat<F> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
+        let AudioData(source) = self.0.import_simple(bytes)?;
+        Ok(AudioData(source.decoded()?))
+    }
+}
+
+/// Streams audio from the asset source while it plays, see `Source::Streamed`.
+///
+/// Streamed sources are not held in memory, which suits long music tracks. They are not hot
+/// reloaded. All supported formats are detected while decoding, so the wrapped format is only
+/// used for its name, e.g. `StreamedFormat(OggFormat)`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct StreamedFormat<F>(pub F);
+
+impl<F: Format<AudioData>> Format<AudioData> for StreamedFormat<F> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        _create_reload: Option<Box<dyn Format<AudioData>>>,
+    ) -> Result<FormatValue<AudioData>, Error> {
+        Ok(FormatValue::data(AudioData(AudioSource::stream(
+            source, name,
+        )?)))
     }
 }
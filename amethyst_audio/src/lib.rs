@@ -11,9 +11,14 @@
 pub use self::{
     bundle::AudioBundle,
     components::*,
-    formats::{FlacFormat, Mp3Format, OggFormat, WavFormat},
+    cue::{SoundCue, SoundCueConfig, SoundCues, SoundCuesConfig, VoiceStealing},
+    effects::{Effect, EffectChain, EffectSource},
+    formats::{DecodedFormat, FlacFormat, Mp3Format, OggFormat, StreamedFormat, WavFormat},
+    mixer::{AudioBus, BusConfig, BusControl, BusSource, Mixer, MixerConfig},
+    music::{LoopPoints, MusicEvent, MusicPlayer, Playlist, RepeatMode, Track},
     sink::AudioSink,
-    source::{Source, SourceHandle},
+    source::{AudioStream, DecodedAudio, Source, SourceHandle},
+    spatial::{Attenuation, Cone, DistanceModel, Doppler, SPEED_OF_SOUND},
     systems::*,
 };
 
@@ -26,10 +31,16 @@ pub mod output;
 
 mod bundle;
 mod components;
+mod cue;
+mod effects;
 mod end_signal;
 mod formats;
+mod mixer;
+mod music;
+mod null_output;
 mod sink;
 mod source;
+mod spatial;
 mod systems;
 
 /// An error occurred while decoding the source.
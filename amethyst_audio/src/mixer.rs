@@ -0,0 +1,423 @@
+//! Provides a mixer which groups sounds into buses with their own volume, mute and pause state,
+//! and their own effects.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source as RSource};
+use serde::{Deserialize, Serialize};
+
+use crate::effects::{Effect, EffectChain, EffectProcessor};
+
+/// A category of sounds which can be controlled as a whole.
+///
+/// Every bus is routed through `AudioBus::Master`, so changing the master bus affects all sounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AudioBus {
+    /// The bus every other bus is routed through.
+    Master,
+    /// Background music, used by the `DjSystem` by default.
+    Music,
+    /// Sound effects, used by `AudioEmitter`s by default.
+    Sfx,
+    /// Dialogue and voice overs.
+    Voice,
+    /// User interface sounds.
+    Ui,
+}
+
+impl AudioBus {
+    /// All available buses.
+    pub const ALL: [AudioBus; 5] = [
+        AudioBus::Master,
+        AudioBus::Music,
+        AudioBus::Sfx,
+        AudioBus::Voice,
+        AudioBus::Ui,
+    ];
+}
+
+impl Default for AudioBus {
+    fn default() -> Self {
+        AudioBus::Sfx
+    }
+}
+
+/// The configuration of a single bus.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BusConfig {
+    /// Volume of the bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    pub volume: f32,
+    /// Whether the bus is muted.
+    pub muted: bool,
+    /// Whether the bus is paused.
+    pub paused: bool,
+    /// The effects applied to every sound on the bus.
+    pub effects: Vec<Effect>,
+}
+
+impl Default for BusConfig {
+    fn default() -> Self {
+        BusConfig {
+            volume: 1.0,
+            muted: false,
+            paused: false,
+            effects: Vec::new(),
+        }
+    }
+}
+
+/// The configuration of the `Mixer`, which can be loaded from and written to a RON file,
+/// for example from an options menu.
+///
+/// Buses which are not listed use the default `BusConfig`.
+///
+/// ```ron
+/// (
+///     buses: {
+///         Master: (volume: 0.8),
+///         Music: (volume: 0.5, muted: true),
+///         Sfx: (effects: [LowPass(cutoff: 800.0)]),
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixerConfig {
+    /// Configuration per bus.
+    pub buses: HashMap<AudioBus, BusConfig>,
+}
+
+#[derive(Debug)]
+struct BusState {
+    volume: AtomicU32,
+    muted: AtomicBool,
+    paused: AtomicBool,
+    effects: EffectChain,
+}
+
+impl BusState {
+    fn new(config: BusConfig) -> Self {
+        let state = BusState {
+            volume: AtomicU32::new(0),
+            muted: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            effects: EffectChain::new(),
+        };
+        state.apply(config);
+        state
+    }
+
+    fn apply(&self, config: BusConfig) {
+        self.volume
+            .store(config.volume.max(0.0).to_bits(), Ordering::Relaxed);
+        self.muted.store(config.muted, Ordering::Relaxed);
+        self.paused.store(config.paused, Ordering::Relaxed);
+        self.effects.set_effects(config.effects);
+    }
+
+    fn config(&self) -> BusConfig {
+        BusConfig {
+            volume: self.volume(),
+            muted: self.muted.load(Ordering::Relaxed),
+            paused: self.paused.load(Ordering::Relaxed),
+            effects: self.effects.effects(),
+        }
+    }
+
+    fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    fn gain(&self) -> f32 {
+        if self.muted.load(Ordering::Relaxed) {
+            0.0
+        } else {
+            self.volume()
+        }
+    }
+}
+
+/// A handle to the state of a bus, used to route sounds through it.
+///
+/// Changes made on the `Mixer` are picked up by sounds which are already playing.
+#[derive(Clone, Debug)]
+pub struct BusControl {
+    bus: Arc<BusState>,
+    master: Arc<BusState>,
+}
+
+impl BusControl {
+    /// Returns the effective gain of the bus, including the master bus.
+    pub fn gain(&self) -> f32 {
+        if Arc::ptr_eq(&self.bus, &self.master) {
+            self.bus.gain()
+        } else {
+            self.bus.gain() * self.master.gain()
+        }
+    }
+
+    /// Returns whether the bus, or the master bus, is paused.
+    pub fn is_paused(&self) -> bool {
+        self.bus.paused.load(Ordering::Relaxed) || self.master.paused.load(Ordering::Relaxed)
+    }
+
+    /// Routes the given source through this bus.
+    pub fn apply<I>(&self, input: I) -> BusSource<I>
+    where
+        I: RSource,
+        I::Item: Sample,
+    {
+        let master_effects = if Arc::ptr_eq(&self.bus, &self.master) {
+            None
+        } else {
+            Some(EffectProcessor::new(self.master.effects.clone()))
+        };
+        BusSource {
+            input,
+            control: self.clone(),
+            bus_effects: EffectProcessor::new(self.bus.effects.clone()),
+            master_effects,
+            channel: 0,
+            paused: false,
+        }
+    }
+}
+
+/// Resource holding the state of every `AudioBus`.
+#[derive(Debug)]
+pub struct Mixer {
+    buses: HashMap<AudioBus, Arc<BusState>>,
+}
+
+impl Mixer {
+    /// Creates a mixer with the given configuration.
+    pub fn from_config(config: &MixerConfig) -> Self {
+        let buses = AudioBus::ALL
+            .iter()
+            .map(|bus| {
+                let bus_config = config.buses.get(bus).cloned().unwrap_or_default();
+                (*bus, Arc::new(BusState::new(bus_config)))
+            })
+            .collect();
+        Mixer { buses }
+    }
+
+    /// Applies the given configuration to all buses, including sounds which are already playing.
+    pub fn apply_config(&mut self, config: &MixerConfig) {
+        for (bus, state) in &self.buses {
+            state.apply(config.buses.get(bus).cloned().unwrap_or_default());
+        }
+    }
+
+    /// Returns the current configuration, so it can be saved.
+    pub fn config(&self) -> MixerConfig {
+        MixerConfig {
+            buses: self
+                .buses
+                .iter()
+                .map(|(bus, state)| (*bus, state.config()))
+                .collect(),
+        }
+    }
+
+    /// Returns a handle used to route sounds through the given bus.
+    pub fn control(&self, bus: AudioBus) -> BusControl {
+        BusControl {
+            bus: self.state(bus).clone(),
+            master: self.state(AudioBus::Master).clone(),
+        }
+    }
+
+    /// Retrieves the volume of the bus.
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        self.state(bus).volume()
+    }
+
+    /// Sets the volume of the bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    pub fn set_volume(&mut self, bus: AudioBus, volume: f32) {
+        self.state(bus)
+            .volume
+            .store(volume.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the effective volume of the bus, taking mute and the master bus into account.
+    pub fn effective_volume(&self, bus: AudioBus) -> f32 {
+        self.control(bus).gain()
+    }
+
+    /// Returns true if the bus is muted.
+    pub fn is_muted(&self, bus: AudioBus) -> bool {
+        self.state(bus).muted.load(Ordering::Relaxed)
+    }
+
+    /// Mutes or unmutes the bus.
+    pub fn set_muted(&mut self, bus: AudioBus, muted: bool) {
+        self.state(bus).muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Returns true if the bus is paused.
+    pub fn is_paused(&self, bus: AudioBus) -> bool {
+        self.state(bus).paused.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes every sound playing on the bus.
+    pub fn set_paused(&mut self, bus: AudioBus, paused: bool) {
+        self.state(bus).paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Returns the effects applied to every sound on the bus.
+    ///
+    /// Effects are applied to each sound separately, before the bus volume. Changes to the
+    /// chain are picked up by sounds which are already playing.
+    pub fn effects(&self, bus: AudioBus) -> &EffectChain {
+        &self.state(bus).effects
+    }
+
+    fn state(&self, bus: AudioBus) -> &Arc<BusState> {
+        &self.buses[&bus]
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer::from_config(&MixerConfig::default())
+    }
+}
+
+/// A source routed through a bus, see `BusControl::apply`.
+///
+/// The effects of the bus, then those of the master bus, are applied before the volume.
+/// While the bus is paused silence is produced and the wrapped source is not advanced.
+#[derive(Debug)]
+pub struct BusSource<I> {
+    input: I,
+    control: BusControl,
+    bus_effects: EffectProcessor,
+    master_effects: Option<EffectProcessor>,
+    // Position inside the current frame, the pause state only changes on frame boundaries so
+    // channels do not get swapped.
+    channel: u16,
+    paused: bool,
+}
+
+impl<I> Iterator for BusSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channels = self.input.channels();
+        if self.channel == 0 {
+            self.paused = self.control.is_paused();
+            let sample_rate = self.input.sample_rate();
+            self.bus_effects.sync(channels, sample_rate);
+            if let Some(master_effects) = &mut self.master_effects {
+                master_effects.sync(channels, sample_rate);
+            }
+        }
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % channels.max(1);
+
+        if self.paused {
+            return Some(0.0);
+        }
+        let mut sample = self
+            .bus_effects
+            .process(channel, self.input.next()?.to_f32());
+        if let Some(master_effects) = &mut self.master_effects {
+            sample = master_effects.process(channel, sample);
+        }
+        Some(sample * self.control.gain())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> RSource for BusSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn effective_volume_includes_master() {
+        let mut mixer = Mixer::default();
+        mixer.set_volume(AudioBus::Master, 0.5);
+        mixer.set_volume(AudioBus::Music, 0.5);
+
+        assert_eq!(mixer.effective_volume(AudioBus::Master), 0.5);
+        assert_eq!(mixer.effective_volume(AudioBus::Music), 0.25);
+        assert_eq!(mixer.effective_volume(AudioBus::Sfx), 0.5);
+
+        mixer.set_muted(AudioBus::Master, true);
+        assert_eq!(mixer.effective_volume(AudioBus::Sfx), 0.0);
+    }
+
+    #[test]
+    fn config_roundtrip() {
+        let mut mixer = Mixer::default();
+        mixer.set_volume(AudioBus::Voice, 0.3);
+        mixer.set_muted(AudioBus::Ui, true);
+
+        let config = mixer.config();
+        let other = Mixer::from_config(&config);
+
+        assert_eq!(other.volume(AudioBus::Voice), 0.3);
+        assert!(other.is_muted(AudioBus::Ui));
+        assert!(!other.is_muted(AudioBus::Voice));
+    }
+
+    #[test]
+    fn bus_source_follows_the_mixer() {
+        let mut mixer = Mixer::default();
+        let mut source =
+            mixer
+                .control(AudioBus::Sfx)
+                .apply(SamplesBuffer::new(1, 44100, vec![1.0f32; 4]));
+
+        assert_eq!(source.next(), Some(1.0));
+        mixer.set_volume(AudioBus::Sfx, 0.5);
+        assert_eq!(source.next(), Some(0.5));
+
+        mixer.set_paused(AudioBus::Master, true);
+        assert_eq!(source.next(), Some(0.0));
+        assert_eq!(source.next(), Some(0.0));
+
+        mixer.set_paused(AudioBus::Master, false);
+        assert_eq!(source.by_ref().count(), 2);
+    }
+}
@@ -0,0 +1,585 @@
+//! Provides playlists and the `MusicPlayer` resource, played by the `MusicSystem`.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rand::{seq::SliceRandom, thread_rng};
+use rodio::{Sample, Source as RSource};
+use serde::{Deserialize, Serialize};
+
+use crate::source::SourceHandle;
+
+/// What a `Playlist` plays once a track ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepeatMode {
+    /// Plays every track once, then stops.
+    Off,
+    /// Repeats the current track until `MusicPlayer::next` is called.
+    One,
+    /// Starts over once the last track ended. Shuffled playlists are shuffled again.
+    All,
+}
+
+impl Default for RepeatMode {
+    fn default() -> Self {
+        RepeatMode::Off
+    }
+}
+
+/// The part of a track which is looped seamlessly, for music with an intro.
+///
+/// A track with loop points never ends by itself, `MusicPlayer::next` has to be called to leave
+/// it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoopPoints {
+    /// Where playback jumps back to.
+    pub start: Duration,
+    /// Where playback jumps back from, the end of the track if `None`.
+    pub end: Option<Duration>,
+}
+
+/// A track of a `Playlist`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    /// The music to play.
+    pub source: SourceHandle,
+    /// The volume of the track, 1.0 is unchanged.
+    pub volume: f32,
+    /// The part of the track to loop, if any.
+    pub loop_points: Option<LoopPoints>,
+}
+
+impl Track {
+    /// Creates a track playing the given source at full volume.
+    pub fn new(source: SourceHandle) -> Self {
+        Track {
+            source,
+            volume: 1.0,
+            loop_points: None,
+        }
+    }
+
+    /// Sets the volume of the track.
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Loops the given part of the track.
+    pub fn with_loop_points(mut self, loop_points: LoopPoints) -> Self {
+        self.loop_points = Some(loop_points);
+        self
+    }
+}
+
+impl From<SourceHandle> for Track {
+    fn from(source: SourceHandle) -> Self {
+        Track::new(source)
+    }
+}
+
+/// An ordered list of tracks, played by the `MusicPlayer`.
+#[derive(Clone, Debug, Default)]
+pub struct Playlist {
+    tracks: Vec<Track>,
+    repeat: RepeatMode,
+    shuffle: bool,
+}
+
+impl Playlist {
+    /// Creates an empty playlist.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a track to the playlist.
+    pub fn with_track<T: Into<Track>>(mut self, track: T) -> Self {
+        self.tracks.push(track.into());
+        self
+    }
+
+    /// Sets what is played once a track ended.
+    pub fn with_repeat(mut self, repeat: RepeatMode) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Plays the tracks in a random order.
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Returns the tracks of the playlist.
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Returns the repeat mode of the playlist.
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    /// Returns whether the tracks are played in a random order.
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle
+    }
+
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.tracks.len()).collect();
+        if self.shuffle {
+            order.shuffle(&mut thread_rng());
+        }
+        order
+    }
+}
+
+/// Events written by the `MusicSystem`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MusicEvent {
+    /// A track started playing, at the beginning of its fade in.
+    TrackStarted(SourceHandle),
+    /// A track ended or was stopped, at the end of its fade out.
+    TrackEnded(SourceHandle),
+    /// The last track of a playlist which does not repeat ended.
+    PlaylistEnded,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum MusicCommand {
+    Play(Playlist),
+    Next,
+    Stop(Duration),
+    FadeOut(Duration),
+    FadeIn(Duration),
+    Stinger(SourceHandle, f32),
+}
+
+/// Resource controlling the music played by the `MusicSystem`.
+///
+/// Commands are executed the next time the system runs. The `fade_out` and `fade_in` methods
+/// are meant to be called on state transitions, e.g. from `State::on_pause` and
+/// `State::on_resume`.
+#[derive(Debug)]
+pub struct MusicPlayer {
+    pub(crate) commands: Vec<MusicCommand>,
+    pub(crate) current: Option<SourceHandle>,
+    pub(crate) playing: bool,
+    crossfade: Duration,
+    stinger_ducking: f32,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        MusicPlayer {
+            commands: Vec::new(),
+            current: None,
+            playing: false,
+            crossfade: Duration::from_secs(2),
+            stinger_ducking: 0.5,
+        }
+    }
+}
+
+impl MusicPlayer {
+    /// Plays the given playlist from its first track, crossfading from the current track.
+    pub fn play(&mut self, playlist: Playlist) {
+        self.commands.push(MusicCommand::Play(playlist));
+    }
+
+    /// Plays a single track, crossfading from the current track.
+    pub fn play_track<T: Into<Track>>(&mut self, track: T) {
+        self.play(Playlist::new().with_track(track));
+    }
+
+    /// Crossfades to the next track of the playlist, even if the current one repeats.
+    pub fn next(&mut self) {
+        self.commands.push(MusicCommand::Next);
+    }
+
+    /// Fades the music out over the given duration, then forgets the playlist.
+    pub fn stop(&mut self, fade_out: Duration) {
+        self.commands.push(MusicCommand::Stop(fade_out));
+    }
+
+    /// Fades the music out over the given duration, then pauses it.
+    pub fn fade_out(&mut self, duration: Duration) {
+        self.commands.push(MusicCommand::FadeOut(duration));
+    }
+
+    /// Resumes the music paused by `fade_out`, fading it in over the given duration.
+    pub fn fade_in(&mut self, duration: Duration) {
+        self.commands.push(MusicCommand::FadeIn(duration));
+    }
+
+    /// Plays a short piece of music over the current track, which is ducked while it plays.
+    pub fn play_stinger(&mut self, source: SourceHandle, volume: f32) {
+        self.commands.push(MusicCommand::Stinger(source, volume));
+    }
+
+    /// Returns the duration of the crossfade between two tracks, two seconds by default.
+    pub fn crossfade(&self) -> Duration {
+        self.crossfade
+    }
+
+    /// Sets the duration of the crossfade between two tracks, zero to cut between them.
+    ///
+    /// When the length of a track is known the crossfade ends with the track, otherwise it
+    /// starts once the track ended.
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.crossfade = crossfade;
+    }
+
+    /// Returns the volume of the music while a stinger plays, 0.5 by default.
+    pub fn stinger_ducking(&self) -> f32 {
+        self.stinger_ducking
+    }
+
+    /// Sets the volume of the music while a stinger plays, 1.0 disables ducking.
+    pub fn set_stinger_ducking(&mut self, volume: f32) {
+        self.stinger_ducking = volume;
+    }
+
+    /// Returns the track currently playing, not including a track fading out.
+    pub fn current_track(&self) -> Option<&SourceHandle> {
+        self.current.as_ref()
+    }
+
+    /// Returns whether a track is playing and the music is not paused by `fade_out`.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+}
+
+/// The tracks left to play of a `Playlist`.
+#[derive(Debug)]
+pub(crate) struct PlaylistQueue {
+    playlist: Playlist,
+    order: Vec<usize>,
+    position: Option<usize>,
+}
+
+impl PlaylistQueue {
+    pub fn new(playlist: Playlist) -> Self {
+        PlaylistQueue {
+            order: playlist.order(),
+            playlist,
+            position: None,
+        }
+    }
+
+    /// Returns the next track to play, `ended` telling whether the current track ended by
+    /// itself rather than being skipped.
+    pub fn advance(&mut self, ended: bool) -> Option<Track> {
+        let len = self.order.len();
+        let next = match self.position {
+            _ if len == 0 => return None,
+            None => 0,
+            Some(position) if ended && self.playlist.repeat == RepeatMode::One => position,
+            Some(position) if position + 1 < len => position + 1,
+            Some(_) if self.playlist.repeat == RepeatMode::Off => return None,
+            Some(_) => {
+                self.order = self.playlist.order();
+                0
+            }
+        };
+        self.position = Some(next);
+        Some(self.playlist.tracks[self.order[next]].clone())
+    }
+}
+
+/// A linear ramp between two volumes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Fade {
+    from: f32,
+    to: f32,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Fade {
+    pub fn new(from: f32, to: f32, duration: Duration) -> Self {
+        Fade {
+            from,
+            to,
+            duration: duration.as_secs_f32(),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Fade::new(value, value, Duration::from_secs(0))
+    }
+
+    pub fn to(self, to: f32, duration: Duration) -> Self {
+        Fade::new(self.value(), to, duration)
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) {
+        self.elapsed += delta_seconds;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn value(&self) -> f32 {
+        if self.is_done() {
+            self.to
+        } else {
+            self.from + (self.to - self.from) * self.elapsed / self.duration
+        }
+    }
+}
+
+/// Plays a track, looping it between its loop points and counting the samples played.
+///
+/// When the loop ends before the track, the samples between the loop points are kept in memory
+/// while they are first played, so looping replays them without decoding the track again. A
+/// loop reaching the end of the track is streamed instead: the track is decoded again from its
+/// beginning, skipping to the loop start.
+///
+/// Looping sources report a single frame since jumping back may happen anywhere.
+pub(crate) struct TrackSource<S: Iterator> {
+    input: S,
+    // Decodes the track again from its beginning.
+    reopen: Box<dyn FnMut() -> Option<S> + Send>,
+    // Loop start and end, in samples.
+    loop_range: Option<(usize, Option<usize>)>,
+    // The samples between the loop points, when the loop ends before the track.
+    loop_samples: Vec<S::Item>,
+    // Whether playback jumped back, the samples then being read from `loop_samples`.
+    looping: bool,
+    position: Arc<AtomicUsize>,
+    offset: usize,
+}
+
+impl<S> TrackSource<S>
+where
+    S: RSource,
+    S::Item: Sample,
+{
+    /// Creates the source, `input` decoding the track from its beginning and `reopen` decoding
+    /// it again for loops reaching the end of the track.
+    pub fn new<F>(input: S, loop_points: Option<LoopPoints>, reopen: F) -> Self
+    where
+        F: FnMut() -> Option<S> + Send + 'static,
+    {
+        let to_samples = |time: Duration| {
+            let frames = (time.as_secs_f32() * input.sample_rate() as f32) as usize;
+            frames * usize::from(input.channels())
+        };
+        let loop_range = loop_points.map(|points| {
+            let start = to_samples(points.start);
+            let end = points.end.map(to_samples).filter(|&end| end > start);
+            (start, end)
+        });
+        TrackSource {
+            input,
+            reopen: Box::new(reopen),
+            loop_range,
+            loop_samples: Vec::new(),
+            looping: false,
+            position: Arc::new(AtomicUsize::new(0)),
+            offset: 0,
+        }
+    }
+
+    /// Returns the position of playback in the track, in samples, which jumps back to the loop
+    /// start every time the track loops.
+    pub fn position(&self) -> Arc<AtomicUsize> {
+        self.position.clone()
+    }
+
+    fn next_looped(&mut self, start: usize) -> Option<S::Item> {
+        let index = match self.offset.checked_sub(start) {
+            Some(index) if index < self.loop_samples.len() => index,
+            _ => {
+                self.offset = start;
+                0
+            }
+        };
+        self.loop_samples.get(index).cloned()
+    }
+
+    /// Decodes the track again from the loop start, for loops reaching the end of the track.
+    fn restart(&mut self, start: usize) -> Option<S::Item> {
+        self.input = (self.reopen)()?;
+        if start > 0 {
+            self.input.nth(start - 1)?;
+        }
+        self.offset = start;
+        self.input.next()
+    }
+}
+
+impl<S> Iterator for TrackSource<S>
+where
+    S: RSource,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let sample = match self.loop_range {
+            Some((start, Some(_))) if self.looping => self.next_looped(start)?,
+            Some((start, Some(end))) => {
+                let sample = if self.offset >= end {
+                    None
+                } else {
+                    self.input.next()
+                };
+                match sample {
+                    Some(sample) => {
+                        if self.offset >= start {
+                            self.loop_samples.push(sample);
+                        }
+                        sample
+                    }
+                    None => {
+                        self.looping = true;
+                        self.next_looped(start)?
+                    }
+                }
+            }
+            Some((start, None)) => match self.input.next() {
+                Some(sample) => sample,
+                None => self.restart(start)?,
+            },
+            None => self.input.next()?,
+        };
+        self.offset += 1;
+        self.position.store(self.offset, Ordering::Relaxed);
+        Some(sample)
+    }
+}
+
+impl<S> RSource for TrackSource<S>
+where
+    S: RSource,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.loop_range.is_some() {
+            None
+        } else {
+            self.input.current_frame_len()
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.loop_range.is_some() {
+            None
+        } else {
+            self.input.total_duration()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use amethyst_assets::AssetStorage;
+
+    use super::*;
+    use crate::source::Source;
+
+    fn queue(repeat: RepeatMode) -> PlaylistQueue {
+        // Tracks are told apart by their volume.
+        let mut storage = AssetStorage::<Source>::new();
+        let playlist = (0..3).fold(Playlist::new().with_repeat(repeat), |playlist, i| {
+            let source = storage.insert(Source::from_bytes(Vec::new()));
+            playlist.with_track(Track::new(source).with_volume(i as f32))
+        });
+        PlaylistQueue::new(playlist)
+    }
+
+    fn volumes(queue: &mut PlaylistQueue, ended: bool, n: usize) -> Vec<Option<f32>> {
+        (0..n)
+            .map(|_| queue.advance(ended).map(|track| track.volume))
+            .collect()
+    }
+
+    #[test]
+    fn repeat_modes() {
+        assert_eq!(
+            volumes(&mut queue(RepeatMode::Off), true, 4),
+            vec![Some(0.0), Some(1.0), Some(2.0), None]
+        );
+        assert_eq!(
+            volumes(&mut queue(RepeatMode::All), true, 4),
+            vec![Some(0.0), Some(1.0), Some(2.0), Some(0.0)]
+        );
+        let mut one = queue(RepeatMode::One);
+        assert_eq!(volumes(&mut one, true, 2), vec![Some(0.0), Some(0.0)]);
+        assert_eq!(volumes(&mut one, false, 1), vec![Some(1.0)]);
+    }
+
+    #[test]
+    fn fade_interpolates() {
+        let mut fade = Fade::new(1.0, 0.0, Duration::from_secs(2));
+        fade.advance(0.5);
+        assert_eq!(fade.value(), 0.75);
+        fade.advance(2.0);
+        assert!(fade.is_done());
+        assert_eq!(fade.value(), 0.0);
+        assert_eq!(Fade::constant(0.5).value(), 0.5);
+    }
+
+    #[test]
+    fn track_source_loops_between_points() {
+        let input = SamplesBuffer::new(1, 4, vec![0i16, 1, 2, 3]);
+        let loop_points = LoopPoints {
+            start: Duration::from_millis(250),
+            end: Some(Duration::from_millis(750)),
+        };
+        let source = TrackSource::new(input, Some(loop_points), || None);
+
+        assert_eq!(
+            source.take(7).collect::<Vec<_>>(),
+            vec![0, 1, 2, 1, 2, 1, 2]
+        );
+    }
+
+    #[test]
+    fn track_source_streams_loops_to_the_end_of_the_track() {
+        let track = || SamplesBuffer::new(1, 4, vec![0i16, 1, 2, 3]);
+        let loop_points = LoopPoints {
+            start: Duration::from_millis(500),
+            end: None,
+        };
+        let mut source = TrackSource::new(track(), Some(loop_points), move || Some(track()));
+        let position = source.position();
+
+        assert_eq!(
+            source.by_ref().take(8).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 2, 3, 2, 3]
+        );
+        assert_eq!(position.load(Ordering::Relaxed), 4);
+        assert!(source.loop_samples.is_empty());
+    }
+
+    #[test]
+    fn track_source_counts_samples() {
+        let mut source = TrackSource::new(SamplesBuffer::new(2, 4, vec![0i16; 8]), None, || None);
+        let position = source.position();
+
+        source.nth(2);
+        assert_eq!(position.load(Ordering::Relaxed), 3);
+        assert_eq!(source.count(), 5);
+    }
+}
@@ -0,0 +1,392 @@
+//! An audio backend which plays to nowhere, for servers and tests.
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use log::error;
+use rodio::{
+    queue::SourcesQueueOutput, source::UniformSourceIterator, Sample, Sink, Source as RSource,
+};
+
+use amethyst_core::math::Point3;
+
+use crate::source::Source;
+
+const SAMPLE_RATE: u32 = 44100;
+const CHANNELS: u16 = 2;
+/// How often the background thread consumes samples.
+const TICK: Duration = Duration::from_millis(10);
+
+/// A sound played through a `NullOutput`.
+///
+/// Times are measured on the clock of the output, which starts at zero when it is created and
+/// advances as samples are consumed.
+#[derive(Clone, Debug)]
+pub struct PlayRecord {
+    /// The played source.
+    pub source: Source,
+    /// The volume the sound was started with.
+    ///
+    /// For sounds played by an `AudioEmitter` this is the gain from attenuation and cone, the
+    /// volume of the mixer buses is not included.
+    pub volume: f32,
+    /// The position of the `AudioEmitter` which played the sound, if any.
+    pub position: Option<Point3<f32>>,
+    /// When the sound was handed to the output.
+    pub queued: Duration,
+    /// When the first sample of the sound was consumed.
+    pub started: Option<Duration>,
+    /// When the sound ended or was stopped.
+    pub stopped: Option<Duration>,
+}
+
+impl PlayRecord {
+    /// Returns whether the sound started and was not stopped yet.
+    pub fn is_playing(&self) -> bool {
+        self.started.is_some() && self.stopped.is_none()
+    }
+}
+
+struct NullState {
+    queues: Mutex<Vec<UniformSourceIterator<SourcesQueueOutput<f32>, f32>>>,
+    // Records with the id of their sound.
+    records: Mutex<Vec<(usize, PlayRecord)>>,
+    next_id: AtomicUsize,
+    frames: AtomicU64,
+}
+
+impl NullState {
+    fn time(&self) -> Duration {
+        let frames = self.frames.load(Ordering::Relaxed);
+        Duration::from_secs_f64(frames as f64 / f64::from(SAMPLE_RATE))
+    }
+
+    fn advance(&self, duration: Duration) {
+        let frames = (duration.as_secs_f64() * f64::from(SAMPLE_RATE)) as u64;
+        let mut queues = self.queues.lock().unwrap();
+        // Sinks are played frame by frame, so records see the clock advance.
+        for _ in 0..frames {
+            let mut i = 0;
+            while i < queues.len() {
+                if (0..CHANNELS).all(|_| queues[i].next().is_some()) {
+                    i += 1;
+                } else {
+                    queues.swap_remove(i);
+                }
+            }
+            self.frames.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn update_record<F: FnOnce(&mut PlayRecord, Duration)>(&self, id: usize, f: F) {
+        let time = self.time();
+        let mut records = self.records.lock().unwrap();
+        if let Some((_, record)) = records.iter_mut().rev().find(|(i, _)| *i == id) {
+            f(record, time);
+        }
+    }
+}
+
+/// An audio backend which consumes samples without playing them, and records what was played.
+///
+/// Use it through `Output::from` on servers without an audio device, or in tests to assert
+/// which sounds were played, e.g. with `AmethystApplication::audio_base` of `amethyst_test`.
+#[derive(Clone)]
+pub struct NullOutput {
+    state: Arc<NullState>,
+}
+
+impl NullOutput {
+    /// The fastest an output started with `with_speed` consumes samples, relative to real time.
+    pub const MAX_SPEED: f32 = 1000.0;
+
+    /// Creates an output consuming samples in real time, on a background thread.
+    pub fn new() -> Self {
+        NullOutput::with_speed(1.0)
+    }
+
+    /// Creates an output consuming samples `speed` times faster than real time, on a background
+    /// thread.
+    ///
+    /// The speed is clamped between 0.0, which never consumes samples, and `MAX_SPEED`. A speed
+    /// which is not a number is treated as 0.0.
+    pub fn with_speed(speed: f32) -> Self {
+        let output = NullOutput::manual();
+        let state = Arc::downgrade(&output.state);
+        let tick = TICK.mul_f32(clamp_speed(speed));
+        let spawned = thread::Builder::new()
+            .name("null_audio_output".into())
+            .spawn(move || consume(state, tick));
+        if let Err(e) = spawned {
+            error!("Failed to start the null audio output thread: {}", e);
+        }
+        output
+    }
+
+    /// Creates an output which only consumes samples when `advance` is called, for deterministic
+    /// tests.
+    pub fn manual() -> Self {
+        NullOutput {
+            state: Arc::new(NullState {
+                queues: Mutex::new(Vec::new()),
+                records: Mutex::new(Vec::new()),
+                next_id: AtomicUsize::new(0),
+                frames: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Consumes the samples of the given duration, from every playing sound.
+    pub fn advance(&self, duration: Duration) {
+        self.state.advance(duration);
+    }
+
+    /// Returns the time of the output clock.
+    pub fn time(&self) -> Duration {
+        self.state.time()
+    }
+
+    /// Returns every sound played so far.
+    pub fn records(&self) -> Vec<PlayRecord> {
+        let records = self.state.records.lock().unwrap();
+        records.iter().map(|(_, record)| record.clone()).collect()
+    }
+
+    /// Returns every time the given source was played.
+    pub fn records_of(&self, source: &Source) -> Vec<PlayRecord> {
+        self.state
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, record)| record.source == *source)
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
+    /// Forgets the sounds played so far.
+    pub fn clear_records(&self) {
+        self.state.records.lock().unwrap().clear();
+    }
+
+    pub(crate) fn sink(&self) -> Sink {
+        let (sink, queue) = Sink::new_idle();
+        self.state
+            .queues
+            .lock()
+            .unwrap()
+            .push(UniformSourceIterator::new(queue, CHANNELS, SAMPLE_RATE));
+        sink
+    }
+
+    pub(crate) fn record<I>(
+        &self,
+        input: I,
+        source: &Source,
+        volume: f32,
+        position: Option<Point3<f32>>,
+    ) -> Recorded<I> {
+        let id = self.state.next_id.fetch_add(1, Ordering::Relaxed);
+        let record = PlayRecord {
+            source: source.clone(),
+            volume,
+            position,
+            queued: self.state.time(),
+            started: None,
+            stopped: None,
+        };
+        self.state.records.lock().unwrap().push((id, record));
+        Recorded {
+            input,
+            record: Some((self.state.clone(), id)),
+            started: false,
+            stopped: false,
+        }
+    }
+}
+
+impl Default for NullOutput {
+    fn default() -> Self {
+        NullOutput::new()
+    }
+}
+
+impl PartialEq for NullOutput {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for NullOutput {}
+
+impl Debug for NullOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("NullOutput")
+            .field("time", &self.time())
+            .field("records", &self.state.records.lock().unwrap().len())
+            .finish()
+    }
+}
+
+fn clamp_speed(speed: f32) -> f32 {
+    if speed.is_nan() {
+        0.0
+    } else {
+        speed.max(0.0).min(NullOutput::MAX_SPEED)
+    }
+}
+
+fn consume(state: Weak<NullState>, tick: Duration) {
+    loop {
+        thread::sleep(TICK);
+        match state.upgrade() {
+            Some(state) => state.advance(tick),
+            None => return,
+        }
+    }
+}
+
+/// Updates the `PlayRecord` of a sound as it is played, does nothing for real outputs.
+pub(crate) struct Recorded<I> {
+    input: I,
+    record: Option<(Arc<NullState>, usize)>,
+    started: bool,
+    stopped: bool,
+}
+
+impl<I> Recorded<I> {
+    /// Wraps a sound which is not recorded.
+    pub fn passthrough(input: I) -> Self {
+        Recorded {
+            input,
+            record: None,
+            started: false,
+            stopped: false,
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some((state, id)) = &self.record {
+            if !self.stopped {
+                state.update_record(*id, |record, time| record.stopped = Some(time));
+            }
+        }
+        self.stopped = true;
+    }
+}
+
+impl<I> Iterator for Recorded<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if !self.started {
+            self.started = true;
+            if let Some((state, id)) = &self.record {
+                state.update_record(*id, |record, time| record.started = Some(time));
+            }
+        }
+        let sample = self.input.next();
+        if sample.is_none() {
+            self.stop();
+        }
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> RSource for Recorded<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+impl<I> Drop for Recorded<I> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn records_start_and_stop() {
+        let output = NullOutput::manual();
+        let source = Source::from_bytes(vec![1, 2, 3]);
+        let sink = output.sink();
+        // Half a second of stereo audio.
+        let samples = SamplesBuffer::new(2, SAMPLE_RATE, vec![0.0f32; SAMPLE_RATE as usize]);
+        sink.append(output.record(samples, &source, 0.5, None));
+        sink.detach();
+
+        output.advance(Duration::from_millis(100));
+        let record = &output.records_of(&source)[0];
+        assert_eq!(record.volume, 0.5);
+        assert_eq!(record.started, Some(Duration::from_secs(0)));
+        assert!(record.is_playing());
+
+        output.advance(Duration::from_secs(1));
+        let stopped = output.records()[0].stopped.unwrap();
+        assert!((stopped.as_secs_f32() - 0.5).abs() < 0.01);
+        assert!(!output.records()[0].is_playing());
+    }
+
+    #[test]
+    fn dropped_sounds_are_stopped() {
+        let output = NullOutput::manual();
+        let source = Source::from_bytes(vec![1]);
+        drop(output.record(SamplesBuffer::new(1, 1, vec![0i16]), &source, 1.0, None));
+
+        let record = &output.records()[0];
+        assert_eq!(record.started, None);
+        assert_eq!(record.stopped, Some(Duration::from_secs(0)));
+
+        output.clear_records();
+        assert!(output.records().is_empty());
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        assert_eq!(clamp_speed(2.0), 2.0);
+        assert_eq!(clamp_speed(-1.0), 0.0);
+        assert_eq!(clamp_speed(std::f32::NAN), 0.0);
+        assert_eq!(clamp_speed(std::f32::INFINITY), NullOutput::MAX_SPEED);
+        assert_eq!(clamp_speed(std::f32::MAX), NullOutput::MAX_SPEED);
+        // Does not panic.
+        NullOutput::with_speed(std::f32::INFINITY);
+        NullOutput::with_speed(std::f32::NAN);
+    }
+}
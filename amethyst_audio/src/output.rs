@@ -1,25 +1,32 @@
 //! Provides structures and functions used to get audio outputs.
 
 // We have to use types from this to provide an output iterator type.
-use std::{
-    fmt::{Debug, Formatter, Result as FmtResult},
-    io::Cursor,
-};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
 
 use cpal::OutputDevices;
 use log::error;
-use rodio::{default_output_device, output_devices, Decoder, Device, Sink, Source as RSource};
+use rodio::{default_output_device, output_devices, Device, Sink, Source as RSource};
+
+use amethyst_core::{ecs::World, math::Point3};
 
-use amethyst_core::ecs::World;
+use crate::{
+    mixer::BusControl, null_output::Recorded, sink::AudioSink, source::Source, DecoderError,
+};
 
-use crate::{sink::AudioSink, source::Source, DecoderError};
+pub use crate::null_output::{NullOutput, PlayRecord};
 
 /// A speaker(s) through which audio can be played.
 ///
 /// By convention, the default output is stored as a resource in the `World`.
 #[derive(Clone, Eq, PartialEq)]
 pub struct Output {
-    pub(crate) device: Device,
+    backend: Backend,
+}
+
+#[derive(Clone, Eq, PartialEq)]
+enum Backend {
+    Device(Device),
+    Null(NullOutput),
 }
 
 /// Convenience method for opening the default output device.
@@ -30,15 +37,67 @@ pub struct Output {
 impl Default for Output {
     fn default() -> Self {
         default_output_device()
-            .map(|re| Output { device: re })
+            .map(Output::from_device)
             .expect("No default output device")
     }
 }
 
+impl From<NullOutput> for Output {
+    fn from(null: NullOutput) -> Self {
+        Output {
+            backend: Backend::Null(null),
+        }
+    }
+}
+
 impl Output {
+    fn from_device(device: Device) -> Self {
+        Output {
+            backend: Backend::Device(device),
+        }
+    }
+
+    /// Creates an output which plays to nowhere in real time, see `NullOutput`.
+    pub fn null() -> Self {
+        NullOutput::new().into()
+    }
+
     /// Gets the name of the output
     pub fn name(&self) -> String {
-        self.device.name()
+        match &self.backend {
+            Backend::Device(device) => device.name(),
+            Backend::Null(_) => "null".to_string(),
+        }
+    }
+
+    /// Returns the null backend of this output, to inspect what was played.
+    pub fn null_output(&self) -> Option<&NullOutput> {
+        match &self.backend {
+            Backend::Device(_) => None,
+            Backend::Null(null) => Some(null),
+        }
+    }
+
+    /// Creates a sink playing to this output.
+    pub(crate) fn sink(&self) -> Sink {
+        match &self.backend {
+            Backend::Device(device) => Sink::new(device),
+            Backend::Null(null) => null.sink(),
+        }
+    }
+
+    /// Wraps a sound about to be appended to a sink, so null outputs record it.
+    pub(crate) fn record<I>(
+        &self,
+        input: I,
+        source: &Source,
+        volume: f32,
+        position: Option<Point3<f32>>,
+    ) -> Recorded<I> {
+        match &self.backend {
+            Backend::Device(_) => Recorded::passthrough(input),
+            Backend::Null(null) => null.record(input, source, volume, position),
+        }
     }
 
     /// Play a sound once.  A volume of 1.0 is unchanged, while 0.0 is silent.
@@ -73,13 +132,64 @@ impl Output {
         volume: f32,
         n: u16,
     ) -> Result<(), DecoderError> {
-        let sink = Sink::new(&self.device);
+        self.play_sink(source, volume, n, None)
+    }
+
+    /// Play a sound once on the given bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// This may silently fail, in order to get error information use `try_play_once_on`.
+    pub fn play_once_on(&self, source: &Source, volume: f32, bus: &BusControl) {
+        self.play_n_times_on(source, volume, 1, bus);
+    }
+
+    /// Play a sound once on the given bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// This will return an Error if the loaded audio file in source could not be decoded.
+    pub fn try_play_once_on(
+        &self,
+        source: &Source,
+        volume: f32,
+        bus: &BusControl,
+    ) -> Result<(), DecoderError> {
+        self.try_play_n_times_on(source, volume, 1, bus)
+    }
+
+    /// Play a sound n times on the given bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// This may silently fail, in order to get error information use `try_play_n_times_on`.
+    pub fn play_n_times_on(&self, source: &Source, volume: f32, n: u16, bus: &BusControl) {
+        if let Err(err) = self.try_play_n_times_on(source, volume, n, bus) {
+            error!("An error occurred while trying to play a sound: {:?}", err);
+        }
+    }
+
+    /// Play a sound n times on the given bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// This will return an Error if the loaded audio file in source could not be decoded.
+    pub fn try_play_n_times_on(
+        &self,
+        source: &Source,
+        volume: f32,
+        n: u16,
+        bus: &BusControl,
+    ) -> Result<(), DecoderError> {
+        self.play_sink(source, volume, n, Some(bus))
+    }
+
+    fn play_sink(
+        &self,
+        source: &Source,
+        volume: f32,
+        n: u16,
+        bus: Option<&BusControl>,
+    ) -> Result<(), DecoderError> {
+        let sink = self.sink();
         for _ in 0..n {
-            sink.append(
-                Decoder::new(Cursor::new(source.clone()))
-                    .map_err(|_| DecoderError)?
-                    .amplify(volume),
-            );
+            let decoder = source.decoder()?.amplify(volume);
+            match bus {
+                Some(bus) => sink.append(self.record(bus.apply(decoder), source, volume, None)),
+                None => sink.append(self.record(decoder, source, volume, None)),
+            }
         }
         sink.detach();
         Ok(())
@@ -104,13 +214,13 @@ impl Iterator for OutputIterator {
     type Item = Output;
 
     fn next(&mut self) -> Option<Output> {
-        self.input.next().map(|re| Output { device: re })
+        self.input.next().map(Output::from_device)
     }
 }
 
 /// Get the default output, returns none if no outputs are available.
 pub fn default_output() -> Option<Output> {
-    default_output_device().map(|re| Output { device: re })
+    default_output_device().map(Output::from_device)
 }
 
 /// Get a list of outputs available to the system.
@@ -121,15 +231,24 @@ pub fn outputs() -> OutputIterator {
 }
 
 /// Initialize default output
+///
+/// An `Output` already in the world is kept. Without an audio device a null output is used, so
+/// nothing is heard but the audio systems keep working.
 pub fn init_output(world: &mut World) {
-    if let Some(o) = default_output() {
-        world
-            .entry::<AudioSink>()
-            .or_insert_with(|| AudioSink::new(&o));
-        world.entry::<Output>().or_insert_with(|| o);
-    } else {
-        error!("Failed finding a default audio output to hook AudioSink to, audio will not work!")
-    }
+    let output = match world.try_fetch::<Output>() {
+        Some(output) => Some(output.clone()),
+        None => default_output(),
+    };
+    let output = output.unwrap_or_else(|| {
+        error!(
+            "Failed finding a default audio output to hook AudioSink to, audio will not be heard!"
+        );
+        Output::null()
+    });
+    world
+        .entry::<AudioSink>()
+        .or_insert_with(|| AudioSink::new(&output));
+    world.entry::<Output>().or_insert_with(|| output);
 }
 
 #[cfg(test)]
@@ -184,7 +303,7 @@ mod tests {
         f.read_to_end(&mut buffer).unwrap();
 
         // Create a Source from those bytes
-        let src = Source { bytes: buffer };
+        let src = Source::from_bytes(buffer);
 
         // Set volume and number of times to play
         let vol: f32 = 4.0;
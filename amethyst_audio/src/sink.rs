@@ -1,8 +1,6 @@
-use std::io::Cursor;
+use rodio::Sink;
 
-use rodio::{Decoder, Sink};
-
-use crate::{output::Output, source::Source, DecoderError};
+use crate::{mixer::BusControl, output::Output, source::Source, DecoderError};
 
 /// This structure provides a way to programmatically pick and play music.
 // TODO: This needs a proper debug implementeation. This should probably propigate up to a TODO
@@ -10,20 +8,31 @@ use crate::{output::Output, source::Source, DecoderError};
 #[allow(missing_debug_implementations)]
 pub struct AudioSink {
     sink: Sink,
+    output: Output,
 }
 
 impl AudioSink {
     /// Creates a new `AudioSink` using the given audio output.
     pub fn new(output: &Output) -> AudioSink {
         AudioSink {
-            sink: Sink::new(&output.device),
+            sink: output.sink(),
+            output: output.clone(),
         }
     }
 
     /// Adds a source to the sink's queue of music to play.
     pub fn append(&self, source: &Source) -> Result<(), DecoderError> {
         self.sink
-            .append(Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?);
+            .append(self.output.record(source.decoder()?, source, 1.0, None));
+        Ok(())
+    }
+
+    /// Adds a source to the sink's queue of music to play, routed through the given bus.
+    pub fn append_on(&self, source: &Source, bus: &BusControl) -> Result<(), DecoderError> {
+        self.sink.append(
+            self.output
+                .record(bus.apply(source.decoder()?), source, 1.0, None),
+        );
         Ok(())
     }
 
@@ -85,7 +94,7 @@ mod tests {
         f.read_to_end(&mut buffer).unwrap();
 
         // Create a Source from those bytes
-        let src = Source { bytes: buffer };
+        let src = Source::from_bytes(buffer);
 
         // Create a Output and AudioSink
         let output = Output::default();
@@ -1,26 +1,126 @@
 //! Provides structures used to load audio files.
 //!
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io::Cursor,
+    sync::Arc,
+    time::Duration,
+};
+
+use rodio::{source::UniformSourceIterator, Decoder, Source as RSource};
+
 use amethyst_assets::{
-    Asset, AssetStorage, Handle, Loader, PrefabData, ProcessableAsset, ProcessingState,
+    Asset, AssetStorage, Handle, Loader, PrefabData, ProcessableAsset, ProcessingState, ReadSeek,
+    Source as AssetSource,
 };
 use amethyst_core::ecs::prelude::{Entity, Read, ReadExpect, VecStorage};
 use amethyst_error::Error;
 
-use crate::formats::AudioData;
+use crate::{formats::AudioData, DecoderError};
 
 /// A handle to a source asset.
 pub type SourceHandle = Handle<Source>;
 
-/// A loaded audio file
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Source {
-    /// The bytes of this audio source.
-    pub bytes: Vec<u8>,
+/// A loaded audio file.
+///
+/// How a source is kept in memory is chosen per asset by the format it is loaded with, see
+/// `DecodedFormat` and `StreamedFormat`.
+#[derive(Clone, Debug)]
+pub enum Source {
+    /// The compressed bytes of an audio file, decoded every time the source is played.
+    Encoded(Arc<[u8]>),
+    /// Samples decoded once, when the source was loaded. Cheap to play, meant for short sounds
+    /// played often.
+    Decoded(DecodedAudio),
+    /// An audio file read from its asset source while it plays. Meant for long music tracks.
+    Streamed(AudioStream),
+}
+
+impl Source {
+    /// Creates a source from the compressed bytes of an audio file.
+    pub fn from_bytes<B: Into<Arc<[u8]>>>(bytes: B) -> Self {
+        Source::Encoded(bytes.into())
+    }
+
+    /// Decodes the compressed bytes of an audio file at once.
+    pub fn decode<B: Into<Arc<[u8]>>>(bytes: B) -> Result<Self, DecoderError> {
+        Source::from_bytes(bytes).decoded()
+    }
+
+    /// Returns this source decoded in memory, decoding it if needed.
+    pub fn decoded(&self) -> Result<Self, DecoderError> {
+        match self {
+            Source::Decoded(_) => Ok(self.clone()),
+            _ => {
+                let decoder = self.decoder()?;
+                let channels = decoder.channels();
+                let sample_rate = decoder.sample_rate();
+                // Some formats can change their layout between frames.
+                let samples = UniformSourceIterator::<_, i16>::new(decoder, channels, sample_rate)
+                    .collect::<Vec<_>>();
+                Ok(Source::Decoded(DecodedAudio::new(
+                    channels,
+                    sample_rate,
+                    samples,
+                )))
+            }
+        }
+    }
+
+    /// Creates a source streaming the asset `name` from the given asset source.
+    ///
+    /// This fails if the start of the asset can not be decoded.
+    pub fn stream(source: Arc<dyn AssetSource>, name: String) -> Result<Self, DecoderError> {
+        let stream = Source::Streamed(AudioStream { source, name });
+        stream.decoder()?;
+        Ok(stream)
+    }
+
+    /// Returns whether the source was decoded when loaded.
+    pub fn is_decoded(&self) -> bool {
+        match self {
+            Source::Decoded(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the source is streamed while playing.
+    pub fn is_streamed(&self) -> bool {
+        match self {
+            Source::Streamed(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns a sample iterator playing the source from its beginning.
+    pub(crate) fn decoder(&self) -> Result<SourceDecoder, DecoderError> {
+        Ok(match self {
+            Source::Encoded(bytes) => SourceDecoder::Encoded(
+                Decoder::new(Cursor::new(bytes.clone())).map_err(|_| DecoderError)?,
+            ),
+            Source::Decoded(audio) => SourceDecoder::Decoded(DecodedSamples {
+                audio: audio.clone(),
+                position: 0,
+            }),
+            Source::Streamed(stream) => {
+                let reader = stream.source.open(&stream.name).map_err(|_| DecoderError)?;
+                SourceDecoder::Streamed(Decoder::new(reader).map_err(|_| DecoderError)?)
+            }
+        })
+    }
 }
 
-impl AsRef<[u8]> for Source {
-    fn as_ref(&self) -> &[u8] {
-        &self.bytes
+/// Sources are equal if they play the same audio, streamed sources if they read the same asset.
+impl PartialEq for Source {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Source::Encoded(a), Source::Encoded(b)) => Arc::ptr_eq(a, b) || a == b,
+            (Source::Decoded(a), Source::Decoded(b)) => a == b,
+            (Source::Streamed(a), Source::Streamed(b)) => {
+                a.name == b.name && Arc::ptr_eq(&a.source, &b.source)
+            }
+            _ => false,
+        }
     }
 }
 
@@ -32,7 +132,7 @@ impl Asset for Source {
 
 impl ProcessableAsset for Source {
     fn process(data: AudioData) -> Result<ProcessingState<Source>, Error> {
-        Ok(ProcessingState::Loaded(Source { bytes: data.0 }))
+        Ok(ProcessingState::Loaded(data.0))
     }
 }
 
@@ -52,3 +152,195 @@ impl<'a> PrefabData<'a> for AudioData {
             .load_from_data(self.clone(), (), &system_data.1))
     }
 }
+
+/// Samples of a decoded `Source`, interleaved by channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedAudio {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[i16]>,
+}
+
+impl DecodedAudio {
+    pub(crate) fn new(channels: u16, sample_rate: u32, samples: Vec<i16>) -> Self {
+        DecodedAudio {
+            channels,
+            sample_rate,
+            samples: samples.into(),
+        }
+    }
+
+    /// Returns the amount of channels.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Returns the amount of frames per second.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns the interleaved samples.
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Returns the duration of the audio.
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() as f64 / f64::from(self.channels.max(1));
+        Duration::from_secs_f64(frames / f64::from(self.sample_rate.max(1)))
+    }
+}
+
+/// An audio file streamed from an asset source.
+#[derive(Clone)]
+pub struct AudioStream {
+    source: Arc<dyn AssetSource>,
+    name: String,
+}
+
+impl AudioStream {
+    /// Returns the name of the streamed asset.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for AudioStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("AudioStream")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// Plays decoded samples without copying them.
+pub(crate) struct DecodedSamples {
+    audio: DecodedAudio,
+    position: usize,
+}
+
+impl Iterator for DecodedSamples {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.audio.samples.get(self.position).cloned();
+        self.position += 1;
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.audio.samples.len().saturating_sub(self.position);
+        (remaining, Some(remaining))
+    }
+}
+
+/// The samples of a playing `Source`.
+pub(crate) enum SourceDecoder {
+    Encoded(Decoder<Cursor<Arc<[u8]>>>),
+    Decoded(DecodedSamples),
+    Streamed(Decoder<Box<dyn ReadSeek>>),
+}
+
+impl Iterator for SourceDecoder {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        match self {
+            SourceDecoder::Encoded(decoder) => decoder.next(),
+            SourceDecoder::Decoded(samples) => samples.next(),
+            SourceDecoder::Streamed(decoder) => decoder.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SourceDecoder::Encoded(decoder) => decoder.size_hint(),
+            SourceDecoder::Decoded(samples) => samples.size_hint(),
+            SourceDecoder::Streamed(decoder) => decoder.size_hint(),
+        }
+    }
+}
+
+impl RSource for SourceDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self {
+            SourceDecoder::Encoded(decoder) => decoder.current_frame_len(),
+            SourceDecoder::Decoded(samples) => samples.size_hint().1,
+            SourceDecoder::Streamed(decoder) => decoder.current_frame_len(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self {
+            SourceDecoder::Encoded(decoder) => decoder.channels(),
+            SourceDecoder::Decoded(samples) => samples.audio.channels,
+            SourceDecoder::Streamed(decoder) => decoder.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            SourceDecoder::Encoded(decoder) => decoder.sample_rate(),
+            SourceDecoder::Decoded(samples) => samples.audio.sample_rate,
+            SourceDecoder::Streamed(decoder) => decoder.sample_rate(),
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self {
+            SourceDecoder::Encoded(decoder) => decoder.total_duration(),
+            SourceDecoder::Decoded(samples) => Some(samples.audio.duration()),
+            SourceDecoder::Streamed(decoder) => decoder.total_duration(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read};
+
+    use amethyst_assets::Directory;
+    use amethyst_utils::app_root_dir::application_root_dir;
+
+    use super::*;
+
+    fn bytes(file_name: &str) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        File::open(application_root_dir().unwrap().join(file_name))
+            .unwrap()
+            .read_to_end(&mut buffer)
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn decoded_source_plays_the_same_samples() {
+        let encoded = Source::from_bytes(bytes("tests/sound_test.wav"));
+        let decoded = encoded.decoded().unwrap();
+
+        assert!(decoded.is_decoded());
+        assert_eq!(
+            decoded.decoder().unwrap().collect::<Vec<_>>(),
+            encoded.decoder().unwrap().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn streamed_source_plays_the_same_samples() {
+        let directory = Directory::new(application_root_dir().unwrap().join("tests"));
+        let streamed = Source::stream(Arc::new(directory), "sound_test.wav".into()).unwrap();
+        let encoded = Source::from_bytes(bytes("tests/sound_test.wav"));
+
+        assert!(streamed.is_streamed());
+        assert_eq!(
+            streamed.decoder().unwrap().collect::<Vec<_>>(),
+            encoded.decoder().unwrap().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn invalid_sources_fail_to_decode() {
+        assert!(Source::decode(bytes("tests/sound_test.fake")).is_err());
+    }
+}
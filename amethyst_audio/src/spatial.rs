@@ -0,0 +1,402 @@
+//! Provides distance attenuation, directional cones and doppler for `AudioEmitter`s.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rodio::{Sample, Source as RSource};
+use serde::{Deserialize, Serialize};
+
+use amethyst_core::math::{Point3, Vector3};
+
+/// The speed of sound in air, in units per second, assuming one unit is one meter.
+pub const SPEED_OF_SOUND: f32 = 343.3;
+
+/// The amount of frames after which a playing sound picks up new spatial parameters.
+const UPDATE_FRAMES: usize = 256;
+
+/// How the volume of an emitter decreases with the distance to the listener.
+///
+/// These are the clamped distance models of OpenAL: below `min_distance` the volume is not
+/// changed, above `max_distance` it is not attenuated any further.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DistanceModel {
+    /// The volume is not attenuated with distance.
+    None,
+    /// The volume falls off linearly, reaching silence at `max_distance` with a rolloff of 1.0.
+    Linear,
+    /// The volume falls off with the inverse of the distance.
+    Inverse,
+    /// The volume falls off with the distance to the power of `-rolloff`.
+    Exponential,
+}
+
+/// The distance attenuation of an emitter.
+///
+/// By default, the volume falls off with the square of the distance and is not changed closer
+/// than one unit, like rodio's `SpatialSink`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attenuation {
+    /// The distance model.
+    pub model: DistanceModel,
+    /// Distance under which the volume is not attenuated.
+    pub min_distance: f32,
+    /// Distance above which the volume is not attenuated any further.
+    pub max_distance: f32,
+    /// How fast the volume falls off.
+    pub rolloff: f32,
+}
+
+impl Attenuation {
+    /// Returns the gain for an emitter at the given distance of the listener.
+    pub fn gain(&self, distance: f32) -> f32 {
+        let min = self.min_distance.max(std::f32::EPSILON);
+        let max = self.max_distance.max(min);
+        let distance = distance.clamp(min, max);
+        let gain = match self.model {
+            DistanceModel::None => 1.0,
+            DistanceModel::Linear => {
+                if max > min {
+                    1.0 - self.rolloff * (distance - min) / (max - min)
+                } else {
+                    1.0
+                }
+            }
+            DistanceModel::Inverse => min / (min + self.rolloff * (distance - min)),
+            DistanceModel::Exponential => (distance / min).powf(-self.rolloff),
+        };
+        gain.clamp(0.0, 1.0)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation {
+            model: DistanceModel::Exponential,
+            min_distance: 1.0,
+            max_distance: std::f32::MAX,
+            rolloff: 2.0,
+        }
+    }
+}
+
+/// Makes an emitter directional: it is loudest in front of it, along the negative z axis of its
+/// `Transform`, like `Transform::move_forward`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cone {
+    /// The full angle, in degrees, inside of which the volume is not attenuated.
+    pub inner_angle: f32,
+    /// The full angle, in degrees, outside of which the volume is `outer_gain`.
+    pub outer_angle: f32,
+    /// The gain outside of the outer angle, between 0.0 and 1.0.
+    pub outer_gain: f32,
+}
+
+impl Cone {
+    /// Returns the gain for a listener at the given angle, in degrees, of the emitter's forward
+    /// direction.
+    pub fn gain(&self, angle: f32) -> f32 {
+        let inner = self.inner_angle / 2.0;
+        let outer = (self.outer_angle / 2.0).max(inner);
+        if angle <= inner {
+            1.0
+        } else if angle >= outer {
+            self.outer_gain
+        } else {
+            let t = (angle - inner) / (outer - inner);
+            1.0 + (self.outer_gain - 1.0) * t
+        }
+    }
+}
+
+/// The doppler effect of an emitter.
+///
+/// Disabled by default, set a `factor` of 1.0 for a physically accurate effect.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Doppler {
+    /// Exaggerates or reduces the effect, 0.0 disables it.
+    pub factor: f32,
+    /// The speed of sound, in units per second.
+    pub speed_of_sound: f32,
+}
+
+impl Doppler {
+    /// Returns the pitch for the given positions and velocities, following the OpenAL model.
+    pub fn pitch(
+        &self,
+        emitter: Point3<f32>,
+        emitter_velocity: Vector3<f32>,
+        listener: Point3<f32>,
+        listener_velocity: Vector3<f32>,
+    ) -> f32 {
+        let to_listener = listener - emitter;
+        let distance = to_listener.norm();
+        if self.factor <= 0.0 || self.speed_of_sound <= 0.0 || distance <= std::f32::EPSILON {
+            return 1.0;
+        }
+        let direction = to_listener / distance;
+        let limit = self.speed_of_sound / self.factor;
+        let listener_speed = direction.dot(&listener_velocity).min(limit);
+        let emitter_speed = direction.dot(&emitter_velocity).min(limit);
+
+        let pitch = (self.speed_of_sound - self.factor * listener_speed)
+            / (self.speed_of_sound - self.factor * emitter_speed);
+        if pitch.is_finite() {
+            pitch.clamp(0.1, 10.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for Doppler {
+    fn default() -> Self {
+        Doppler {
+            factor: 0.0,
+            speed_of_sound: SPEED_OF_SOUND,
+        }
+    }
+}
+
+/// Parameters computed by the `AudioSystem` every frame and picked up by the playing sounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SpatialParams {
+    pub left_gain: f32,
+    pub right_gain: f32,
+    pub pitch: f32,
+}
+
+impl SpatialParams {
+    /// Computes the parameters of a sound at `emitter` heard through the given ears.
+    ///
+    /// The gain of the emitter, from attenuation and cone, is passed in `gain`.
+    pub fn new(
+        emitter: Point3<f32>,
+        left_ear: Point3<f32>,
+        right_ear: Point3<f32>,
+        gain: f32,
+        pitch: f32,
+    ) -> Self {
+        // Same panning as rodio's `Spatial` source.
+        let left_distance = (left_ear - emitter).norm();
+        let right_distance = (right_ear - emitter).norm();
+        let ears_distance = (left_ear - right_ear).norm().max(std::f32::EPSILON);
+        let left_pan = ((left_distance - right_distance) / ears_distance + 1.0) / 4.0 + 0.5;
+        let right_pan = ((right_distance - left_distance) / ears_distance + 1.0) / 4.0 + 0.5;
+
+        SpatialParams {
+            left_gain: left_pan * gain,
+            right_gain: right_pan * gain,
+            pitch,
+        }
+    }
+}
+
+/// A handle shared between the `AudioSystem` and a playing `SpatialSource`.
+#[derive(Clone, Debug)]
+pub(crate) struct SpatialControl(Arc<Mutex<SpatialParams>>);
+
+impl SpatialControl {
+    pub fn new(params: SpatialParams) -> Self {
+        SpatialControl(Arc::new(Mutex::new(params)))
+    }
+
+    pub fn set(&self, params: SpatialParams) {
+        *self.0.lock().unwrap() = params;
+    }
+
+    pub fn apply<I>(&self, input: I) -> SpatialSource<I>
+    where
+        I: RSource,
+        I::Item: Sample,
+    {
+        let params = *self.0.lock().unwrap();
+        SpatialSource {
+            input,
+            control: self.clone(),
+            params,
+            remaining: 0,
+            right: None,
+        }
+    }
+}
+
+/// Mixes its input down to mono, then plays it in stereo with the gain and pitch given through
+/// its `SpatialControl`.
+///
+/// The pitch is applied by changing the reported sample rate, which is why the source is split
+/// into short frames.
+pub(crate) struct SpatialSource<I> {
+    input: I,
+    control: SpatialControl,
+    params: SpatialParams,
+    // Samples left in the current frame.
+    remaining: usize,
+    // The right sample of the current stereo frame, once the left one was returned.
+    right: Option<f32>,
+}
+
+impl<I> Iterator for SpatialSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.right.take() {
+            self.remaining = self.remaining.saturating_sub(1);
+            return Some(right);
+        }
+        if self.remaining == 0 {
+            if let Ok(params) = self.control.0.try_lock() {
+                self.params = *params;
+            }
+            self.remaining = UPDATE_FRAMES * 2;
+        }
+
+        let channels = self.input.channels().max(1);
+        let mut mono = 0.0;
+        for _ in 0..channels {
+            mono += self.input.next()?.to_f32();
+        }
+        mono /= f32::from(channels);
+
+        self.remaining -= 1;
+        self.right = Some(mono * self.params.right_gain);
+        Some(mono * self.params.left_gain)
+    }
+}
+
+impl<I> RSource for SpatialSource<I>
+where
+    I: RSource,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.remaining == 0 {
+            Some(UPDATE_FRAMES * 2)
+        } else {
+            Some(self.remaining)
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        let pitch = if self.remaining == 0 {
+            self.control
+                .0
+                .try_lock()
+                .map(|params| params.pitch)
+                .unwrap_or(self.params.pitch)
+        } else {
+            self.params.pitch
+        };
+        ((self.input.sample_rate() as f32 * pitch) as u32).max(1)
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::math::{Point3, Vector3};
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    fn attenuation(model: DistanceModel) -> Attenuation {
+        Attenuation {
+            model,
+            min_distance: 1.0,
+            max_distance: 11.0,
+            rolloff: 1.0,
+        }
+    }
+
+    #[test]
+    fn distance_models() {
+        assert_eq!(attenuation(DistanceModel::None).gain(5.0), 1.0);
+        assert_eq!(attenuation(DistanceModel::Linear).gain(6.0), 0.5);
+        assert_eq!(attenuation(DistanceModel::Inverse).gain(2.0), 0.5);
+        assert_eq!(attenuation(DistanceModel::Exponential).gain(4.0), 0.25);
+        // Clamped to min and max distance.
+        assert_eq!(attenuation(DistanceModel::Inverse).gain(0.1), 1.0);
+        assert_eq!(attenuation(DistanceModel::Linear).gain(100.0), 0.0);
+        // Emitters which don't opt in fall off with the square of the distance.
+        assert_eq!(Attenuation::default().gain(0.5), 1.0);
+        assert_eq!(Attenuation::default().gain(4.0), 0.0625);
+    }
+
+    #[test]
+    fn cone_interpolates_between_angles() {
+        let cone = Cone {
+            inner_angle: 90.0,
+            outer_angle: 180.0,
+            outer_gain: 0.0,
+        };
+
+        assert_eq!(cone.gain(10.0), 1.0);
+        assert_eq!(cone.gain(67.5), 0.5);
+        assert_eq!(cone.gain(120.0), 0.0);
+    }
+
+    #[test]
+    fn doppler_raises_pitch_of_approaching_emitter() {
+        let doppler = Doppler {
+            factor: 1.0,
+            ..Default::default()
+        };
+        let emitter = Point3::new(0.0, 0.0, -10.0);
+        let listener = Point3::origin();
+
+        let approaching = doppler.pitch(
+            emitter,
+            Vector3::new(0.0, 0.0, 34.33),
+            listener,
+            Vector3::zeros(),
+        );
+        let leaving = doppler.pitch(
+            emitter,
+            Vector3::new(0.0, 0.0, -34.33),
+            listener,
+            Vector3::zeros(),
+        );
+        let still = doppler.pitch(emitter, Vector3::zeros(), listener, Vector3::zeros());
+
+        assert!((approaching - 1.0 / 0.9).abs() < 1e-4);
+        assert!((leaving - 1.0 / 1.1).abs() < 1e-4);
+        assert_eq!(still, 1.0);
+        assert_eq!(
+            Doppler::default().pitch(
+                emitter,
+                Vector3::new(0.0, 0.0, 34.33),
+                listener,
+                Vector3::zeros()
+            ),
+            1.0
+        );
+    }
+
+    #[test]
+    fn spatial_source_pans_and_pitches() {
+        let control = SpatialControl::new(SpatialParams {
+            left_gain: 1.0,
+            right_gain: 0.5,
+            pitch: 2.0,
+        });
+        let source = control.apply(SamplesBuffer::new(2, 44100, vec![1.0f32, 0.0, 1.0, 0.0]));
+
+        assert_eq!(source.channels(), 2);
+        assert_eq!(source.sample_rate(), 88200);
+        assert_eq!(source.collect::<Vec<_>>(), vec![0.5, 0.25, 0.5, 0.25]);
+    }
+}
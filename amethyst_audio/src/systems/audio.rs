@@ -8,24 +8,31 @@ use std::{
 };
 
 use derive_new::new;
-use rodio::SpatialSink;
+use log::{error, warn};
+use rodio::{Sample, Source as RSource};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+use amethyst_assets::AssetStorage;
 use amethyst_core::{
     ecs::prelude::{
         Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, WriteStorage,
     },
-    math::convert,
+    math::{Point3, Vector3},
+    timing::Time,
     transform::Transform,
     SystemDesc,
 };
 
 use crate::{
     components::{AudioEmitter, AudioListener},
+    cue::{CueVoices, SoundCues, VoiceSource},
     end_signal::EndSignalSource,
+    mixer::Mixer,
     output::Output,
+    source::Source,
+    spatial::{SpatialControl, SpatialParams},
 };
 
 /// Builds an `AudioSystem`.
@@ -46,8 +53,18 @@ impl<'a, 'b> SystemDesc<'a, 'b, AudioSystem> for AudioSystemDesc {
 }
 
 /// Syncs 3D transform data with the audio engine to provide 3D audio.
+///
+/// The volume of every emitter is attenuated with the distance to the listener and, for
+/// directional emitters, with the angle to the listener. The pitch is shifted by the doppler
+/// effect, computed from how far emitter and listener moved since the previous frame.
 #[derive(Debug, Default, new)]
-pub struct AudioSystem(Output);
+pub struct AudioSystem {
+    output: Output,
+    #[new(default)]
+    listener_position: Option<Point3<f32>>,
+    #[new(default)]
+    cue_voices: CueVoices,
+}
 
 /// Add this structure to world as a resource with ID 0 to select an entity whose AudioListener
 /// component will be used.  If this resource isn't found then the system will arbitrarily select
@@ -55,9 +72,64 @@ pub struct AudioSystem(Output);
 #[derive(Debug)]
 pub struct SelectedListener(pub Entity);
 
+/// Where and how loud an emitter plays its sounds this frame.
+#[derive(Clone, Copy)]
+struct Placement {
+    params: SpatialParams,
+    gain: f32,
+    position: Point3<f32>,
+}
+
+/// Plays a sound from the emitter, through its effects and bus, then spatialized.
+fn play<I>(
+    output: &Output,
+    mixer: &Mixer,
+    emitter: &mut AudioEmitter,
+    placement: Placement,
+    source: &Source,
+    input: I,
+    volume: f32,
+) where
+    I: RSource + Send + 'static,
+    I::Item: Sample + Send,
+{
+    let sink = output.sink();
+    let spatial = SpatialControl::new(placement.params);
+    let atomic_bool = Arc::new(AtomicBool::new(false));
+    let clone = atomic_bool.clone();
+    let input = mixer
+        .control(emitter.bus)
+        .apply(emitter.effects.apply(input.amplify(volume)));
+    let input = spatial.apply(EndSignalSource::new(input, move || {
+        clone.store(true, Ordering::Relaxed);
+    }));
+    sink.append(output.record(
+        input,
+        source,
+        placement.gain * volume,
+        Some(placement.position),
+    ));
+    emitter.sinks.push((sink, spatial, atomic_bool));
+}
+
+fn velocity(
+    previous: Option<Point3<f32>>,
+    current: Point3<f32>,
+    delta_seconds: f32,
+) -> Vector3<f32> {
+    match previous {
+        Some(previous) if delta_seconds > 0.0 => (current - previous) / delta_seconds,
+        _ => Vector3::zeros(),
+    }
+}
+
 impl<'a> System<'a> for AudioSystem {
     type SystemData = (
         Option<Read<'a, Output>>,
+        Read<'a, Mixer>,
+        Read<'a, SoundCues>,
+        Read<'a, AssetStorage<Source>>,
+        Read<'a, Time>,
         Option<Read<'a, SelectedListener>>,
         Entities<'a>,
         ReadStorage<'a, Transform>,
@@ -67,7 +139,18 @@ impl<'a> System<'a> for AudioSystem {
 
     fn run(
         &mut self,
-        (output, select_listener, entities, transform, listener, mut audio_emitter): Self::SystemData,
+        (
+            output,
+            mixer,
+            cues,
+            storage,
+            time,
+            select_listener,
+            entities,
+            transform,
+            listener,
+            mut audio_emitter,
+        ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("audio_system");
@@ -82,34 +165,41 @@ impl<'a> System<'a> for AudioSystem {
                 .and_then(|sl| transform.get(sl.0))
                 .or_else(|| transform.get(entity))
             {
+                let delta_seconds = time.delta_seconds();
                 let listener_transform = listener_transform.global_matrix();
-                let left_ear_position: [f32; 3] = {
-                    let pos = listener_transform
-                        .transform_point(&listener.left_ear)
-                        .to_homogeneous()
-                        .xyz();
-                    [convert(pos.x), convert(pos.y), convert(pos.z)]
-                };
-                let right_ear_position: [f32; 3] = {
-                    let pos = listener_transform
-                        .transform_point(&listener.right_ear)
-                        .to_homogeneous()
-                        .xyz();
-                    [convert(pos.x), convert(pos.y), convert(pos.z)]
-                };
+                let left_ear = listener_transform.transform_point(&listener.left_ear);
+                let right_ear = listener_transform.transform_point(&listener.right_ear);
+                let listener_position = listener_transform.transform_point(&Point3::origin());
+                let listener_velocity =
+                    velocity(self.listener_position, listener_position, delta_seconds);
+                self.listener_position = Some(listener_position);
+
                 for (transform, mut audio_emitter) in (&transform, &mut audio_emitter).join() {
-                    let emitter_position: [f32; 3] = {
-                        let x = transform.global_matrix()[(0, 3)];
-                        let y = transform.global_matrix()[(1, 3)];
-                        let z = transform.global_matrix()[(2, 3)];
-                        [convert(x), convert(y), convert(z)]
-                    };
+                    let emitter_matrix = transform.global_matrix();
+                    let emitter_position = emitter_matrix.transform_point(&Point3::origin());
+                    let emitter_velocity =
+                        velocity(audio_emitter.last_position, emitter_position, delta_seconds);
+                    audio_emitter.last_position = Some(emitter_position);
+
+                    let to_listener = listener_position - emitter_position;
+                    let mut gain = audio_emitter.attenuation.gain(to_listener.norm());
+                    if let Some(cone) = audio_emitter.cone {
+                        let forward = -emitter_matrix.transform_vector(&Vector3::z());
+                        gain *= cone.gain(forward.angle(&to_listener).to_degrees());
+                    }
+                    let pitch = audio_emitter.doppler.pitch(
+                        emitter_position,
+                        emitter_velocity,
+                        listener_position,
+                        listener_velocity,
+                    );
+                    let params =
+                        SpatialParams::new(emitter_position, left_ear, right_ear, gain, pitch);
+
                     // Remove all sinks whose sounds have ended.
-                    audio_emitter.sinks.retain(|s| !s.1.load(Ordering::Relaxed));
-                    for &mut (ref mut sink, _) in &mut audio_emitter.sinks {
-                        sink.set_emitter_position(emitter_position);
-                        sink.set_left_ear_position(left_ear_position);
-                        sink.set_right_ear_position(right_ear_position);
+                    audio_emitter.sinks.retain(|s| !s.2.load(Ordering::Relaxed));
+                    for (_, spatial, _) in &audio_emitter.sinks {
+                        spatial.set(params);
                     }
                     if audio_emitter.sinks.is_empty() {
                         if let Some(mut picker) = replace(&mut audio_emitter.picker, None) {
@@ -118,20 +208,60 @@ impl<'a> System<'a> for AudioSystem {
                             }
                         }
                     }
-                    while let Some(source) = audio_emitter.sound_queue.pop() {
+                    let placement = Placement {
+                        params,
+                        gain,
+                        position: emitter_position,
+                    };
+                    let triggered = audio_emitter.cue_queue.drain().collect::<Vec<_>>();
+                    for name in triggered {
+                        let sound =
+                            match self.cue_voices.trigger(&name, &cues, time.absolute_time()) {
+                                Some(sound) => sound,
+                                None => continue,
+                            };
+                        let decoder = storage
+                            .get(&sound.source)
+                            .map(|source| (source, source.decoder()));
+                        match (&output, decoder) {
+                            (Some(output), Some((source, Ok(decoder)))) => {
+                                let decoder =
+                                    VoiceSource::new(decoder.speed(sound.pitch), sound.voice);
+                                play(
+                                    output,
+                                    &mixer,
+                                    &mut audio_emitter,
+                                    placement,
+                                    source,
+                                    decoder,
+                                    sound.volume,
+                                );
+                            }
+                            (_, Some((_, Err(_)))) => {
+                                error!(
+                                    "Failed to decode sound cue {:?} from {:?}",
+                                    name, sound.source
+                                );
+                                sound.voice.end();
+                            }
+                            (_, None) => {
+                                warn!("Sound cue {:?} is not loaded", name);
+                                sound.voice.end();
+                            }
+                            (None, Some(_)) => sound.voice.end(),
+                        }
+                    }
+                    while let Some((source, decoder)) = audio_emitter.sound_queue.pop() {
                         if let Some(output) = &output {
-                            let sink = SpatialSink::new(
-                                &output.device,
-                                emitter_position,
-                                left_ear_position,
-                                right_ear_position,
+                            play(
+                                output,
+                                &mixer,
+                                &mut audio_emitter,
+                                placement,
+                                &source,
+                                decoder,
+                                1.0,
                             );
-                            let atomic_bool = Arc::new(AtomicBool::new(false));
-                            let clone = atomic_bool.clone();
-                            sink.append(EndSignalSource::new(source, move || {
-                                clone.store(true, Ordering::Relaxed);
-                            }));
-                            audio_emitter.sinks.push((sink, atomic_bool));
                         }
                     }
                 }
@@ -13,6 +13,7 @@ use amethyst_core::{
 };
 
 use crate::{
+    mixer::{AudioBus, Mixer},
     output::init_output,
     sink::AudioSink,
     source::{Source, SourceHandle},
@@ -25,9 +26,19 @@ use crate::{
 #[derive(Debug, new)]
 pub struct DjSystemDesc<F, R> {
     f: F,
+    #[new(value = "AudioBus::Music")]
+    bus: AudioBus,
     marker: PhantomData<R>,
 }
 
+impl<F, R> DjSystemDesc<F, R> {
+    /// Plays the picked music on the given bus instead of `AudioBus::Music`.
+    pub fn with_bus(mut self, bus: AudioBus) -> Self {
+        self.bus = bus;
+        self
+    }
+}
+
 impl<'a, 'b, F, R> SystemDesc<'a, 'b, DjSystem<F, R>> for DjSystemDesc<F, R>
 where
     F: FnMut(&mut R) -> Option<SourceHandle>,
@@ -38,17 +49,29 @@ where
 
         init_output(world);
 
-        DjSystem::new(self.f)
+        DjSystem::new(self.f).with_bus(self.bus)
     }
 }
 
 /// Calls a closure if the `AudioSink` is empty.
+///
+/// The picked music is played on the bus the system was created with.
 #[derive(Debug, new)]
 pub struct DjSystem<F, R> {
     f: F,
+    #[new(value = "AudioBus::Music")]
+    bus: AudioBus,
     marker: PhantomData<R>,
 }
 
+impl<F, R> DjSystem<F, R> {
+    /// Plays the picked music on the given bus instead of `AudioBus::Music`.
+    pub fn with_bus(mut self, bus: AudioBus) -> Self {
+        self.bus = bus;
+        self
+    }
+}
+
 impl<'a, F, R> System<'a> for DjSystem<F, R>
 where
     F: FnMut(&mut R) -> Option<SourceHandle>,
@@ -57,17 +80,18 @@ where
     type SystemData = (
         Read<'a, AssetStorage<Source>>,
         Option<Read<'a, AudioSink>>,
+        Read<'a, Mixer>,
         WriteExpect<'a, R>,
     );
 
-    fn run(&mut self, (storage, sink, mut res): Self::SystemData) {
+    fn run(&mut self, (storage, sink, mixer, mut res): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("dj_system");
 
         if let Some(ref sink) = sink {
             if sink.empty() {
                 if let Some(source) = (&mut self.f)(&mut res).and_then(|h| storage.get(&h)) {
-                    if let Err(e) = sink.append(source) {
+                    if let Err(e) = sink.append_on(source, &mixer.control(self.bus)) {
                         error!("DJ Cannot append source to sink. {}", e);
                     }
                 }
@@ -3,7 +3,9 @@
 pub use self::{
     audio::{AudioSystem, AudioSystemDesc},
     dj::{DjSystem, DjSystemDesc},
+    music::{MusicSystem, MusicSystemDesc},
 };
 
 mod audio;
 mod dj;
+mod music;
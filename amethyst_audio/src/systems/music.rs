@@ -0,0 +1,508 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use derive_new::new;
+use log::error;
+use rodio::{Sink, Source as RSource};
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_assets::AssetStorage;
+use amethyst_core::{
+    ecs::prelude::{Read, System, SystemData, World, Write},
+    shrev::EventChannel,
+    timing::Time,
+    SystemDesc,
+};
+
+use crate::{
+    end_signal::EndSignalSource,
+    mixer::{AudioBus, BusControl, Mixer},
+    music::{Fade, MusicCommand, MusicEvent, MusicPlayer, PlaylistQueue, Track, TrackSource},
+    output::{init_output, Output},
+    source::Source,
+};
+
+/// How long it takes to duck the music when a stinger starts, and to restore it afterwards.
+const DUCKING_DURATION: Duration = Duration::from_millis(250);
+
+/// Builds a `MusicSystem`.
+#[derive(Debug, new)]
+pub struct MusicSystemDesc {
+    #[new(value = "AudioBus::Music")]
+    bus: AudioBus,
+}
+
+impl MusicSystemDesc {
+    /// Plays the music on the given bus instead of `AudioBus::Music`.
+    pub fn with_bus(mut self, bus: AudioBus) -> Self {
+        self.bus = bus;
+        self
+    }
+}
+
+impl Default for MusicSystemDesc {
+    fn default() -> Self {
+        MusicSystemDesc::new()
+    }
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, MusicSystem> for MusicSystemDesc {
+    fn build(self, world: &mut World) -> MusicSystem {
+        <MusicSystem as System<'_>>::SystemData::setup(world);
+
+        init_output(world);
+
+        MusicSystem::new(self.bus)
+    }
+}
+
+/// A track being played.
+struct Deck {
+    sink: Sink,
+    track: Track,
+    fade: Fade,
+    // Whether the deck is fading out, to be dropped once silent.
+    leaving: bool,
+    ended: Arc<AtomicBool>,
+    position: Arc<AtomicUsize>,
+    samples_per_second: f32,
+    duration: Option<Duration>,
+}
+
+impl Deck {
+    fn remaining(&self) -> Option<Duration> {
+        let played = self.position.load(Ordering::Relaxed) as f32 / self.samples_per_second;
+        self.duration
+            .map(|duration| duration - Duration::from_secs_f32(played).min(duration))
+    }
+}
+
+/// Plays the music requested through the `MusicPlayer` resource and writes `MusicEvent`s.
+///
+/// Fades use real time, so they are not affected by the time scale of the game.
+// TODO: rodio's `Sink` does not implement `Debug`, see `AudioSink`.
+#[allow(missing_debug_implementations)]
+pub struct MusicSystem {
+    bus: AudioBus,
+    queue: Option<PlaylistQueue>,
+    pending: Option<Track>,
+    decks: Vec<Deck>,
+    stingers: Vec<(Sink, Arc<AtomicBool>)>,
+    master: Fade,
+    fading_out: bool,
+    ducking: Fade,
+    ducked: bool,
+    paused: bool,
+}
+
+impl MusicSystem {
+    /// Creates a music system playing on the given bus.
+    ///
+    /// Use `MusicSystemDesc` to also initialize the audio output.
+    pub fn new(bus: AudioBus) -> Self {
+        MusicSystem {
+            bus,
+            queue: None,
+            pending: None,
+            decks: Vec::new(),
+            stingers: Vec::new(),
+            master: Fade::constant(1.0),
+            fading_out: false,
+            ducking: Fade::constant(1.0),
+            ducked: false,
+            paused: false,
+        }
+    }
+
+    fn current(&mut self) -> Option<&mut Deck> {
+        self.decks.last_mut().filter(|deck| !deck.leaving)
+    }
+
+    /// Fades the current deck out and queues the next track, if any.
+    fn advance(&mut self, ended: bool, crossfade: Duration, events: &mut EventChannel<MusicEvent>) {
+        if let Some(deck) = self.current() {
+            deck.leaving = true;
+            let fade_out = deck
+                .remaining()
+                .map_or(crossfade, |remaining| remaining.min(crossfade));
+            deck.fade = deck.fade.to(0.0, fade_out);
+        }
+        self.pending = self.queue.as_mut().and_then(|queue| queue.advance(ended));
+        if self.pending.is_none() && self.queue.take().is_some() {
+            events.single_write(MusicEvent::PlaylistEnded);
+        }
+    }
+
+    fn start(
+        &mut self,
+        track: Track,
+        source: &Source,
+        output: &Output,
+        bus: &BusControl,
+        crossfade: Duration,
+    ) -> bool {
+        let track_source = match source.decoder() {
+            Ok(decoder) => {
+                let source = source.clone();
+                TrackSource::new(decoder, track.loop_points, move || source.decoder().ok())
+            }
+            Err(_) => {
+                error!("Failed to decode music track {:?}", track.source);
+                return false;
+            }
+        };
+        let position = track_source.position();
+        let samples_per_second =
+            track_source.sample_rate() as f32 * f32::from(track_source.channels().max(1));
+        let duration = track_source.total_duration();
+
+        let ended = Arc::new(AtomicBool::new(false));
+        let ended_signal = ended.clone();
+        let sink = output.sink();
+        let track_source = EndSignalSource::new(bus.apply(track_source), move || {
+            ended_signal.store(true, Ordering::Relaxed)
+        });
+        sink.append(output.record(track_source, source, track.volume, None));
+        if self.paused {
+            sink.pause();
+        }
+
+        // The first track starts at full volume, the following ones are crossfaded.
+        let fade = if self.decks.is_empty() {
+            Fade::constant(1.0)
+        } else {
+            Fade::new(0.0, 1.0, crossfade)
+        };
+        self.decks.push(Deck {
+            sink,
+            track,
+            fade,
+            leaving: false,
+            ended,
+            position,
+            samples_per_second,
+            duration,
+        });
+        true
+    }
+
+    fn execute(
+        &mut self,
+        command: MusicCommand,
+        crossfade: Duration,
+        storage: &AssetStorage<Source>,
+        output: &Output,
+        bus: &BusControl,
+        events: &mut EventChannel<MusicEvent>,
+    ) {
+        match command {
+            MusicCommand::Play(playlist) => {
+                self.queue = Some(PlaylistQueue::new(playlist));
+                self.advance(false, crossfade, events);
+            }
+            MusicCommand::Next => self.advance(false, crossfade, events),
+            MusicCommand::Stop(fade_out) => {
+                self.queue = None;
+                self.pending = None;
+                for deck in &mut self.decks {
+                    deck.leaving = true;
+                    deck.fade = deck.fade.to(0.0, fade_out);
+                }
+            }
+            MusicCommand::FadeOut(duration) => {
+                self.master = self.master.to(0.0, duration);
+                self.fading_out = true;
+            }
+            MusicCommand::FadeIn(duration) => {
+                self.master = self.master.to(1.0, duration);
+                self.fading_out = false;
+                self.paused = false;
+                for deck in &self.decks {
+                    deck.sink.play();
+                }
+            }
+            MusicCommand::Stinger(handle, volume) => match storage.get(&handle) {
+                Some(source) => match source.decoder() {
+                    Ok(decoder) => {
+                        let ended = Arc::new(AtomicBool::new(false));
+                        let ended_signal = ended.clone();
+                        let sink = output.sink();
+                        sink.set_volume(volume);
+                        let decoder = EndSignalSource::new(bus.apply(decoder), move || {
+                            ended_signal.store(true, Ordering::Relaxed)
+                        });
+                        sink.append(output.record(decoder, source, volume, None));
+                        self.stingers.push((sink, ended));
+                    }
+                    Err(_) => error!("Failed to decode stinger {:?}", handle),
+                },
+                None => error!("Stinger {:?} is not loaded", handle),
+            },
+        }
+    }
+}
+
+impl<'a> System<'a> for MusicSystem {
+    type SystemData = (
+        Read<'a, AssetStorage<Source>>,
+        Option<Read<'a, Output>>,
+        Read<'a, Mixer>,
+        Read<'a, Time>,
+        Write<'a, MusicPlayer>,
+        Write<'a, EventChannel<MusicEvent>>,
+    );
+
+    fn run(&mut self, (storage, output, mixer, time, mut player, mut events): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("music_system");
+
+        let commands = player.commands.drain(..).collect::<Vec<_>>();
+        let output = match output {
+            Some(output) => output,
+            None => return,
+        };
+        let bus = mixer.control(self.bus);
+        let crossfade = player.crossfade();
+        for command in commands {
+            self.execute(command, crossfade, &storage, &output, &bus, &mut events);
+        }
+
+        let delta_seconds = time.delta_real_seconds();
+        self.master.advance(delta_seconds);
+        if self.fading_out && !self.paused && self.master.is_done() {
+            self.paused = true;
+            for deck in &self.decks {
+                deck.sink.pause();
+            }
+        }
+
+        self.stingers
+            .retain(|(_, ended)| !ended.load(Ordering::Relaxed));
+        let ducked = !self.stingers.is_empty();
+        if ducked != self.ducked {
+            let target = if ducked {
+                player.stinger_ducking()
+            } else {
+                1.0
+            };
+            self.ducking = self.ducking.to(target, DUCKING_DURATION);
+            self.ducked = ducked;
+        }
+        self.ducking.advance(delta_seconds);
+
+        // Hand over to the next track once the current one ended, or when it is about to end.
+        let handover = self.current().map(|deck| {
+            deck.ended.load(Ordering::Relaxed)
+                || deck
+                    .remaining()
+                    .map_or(false, |remaining| remaining <= crossfade)
+        });
+        if handover == Some(true) {
+            self.advance(true, crossfade, &mut events);
+        }
+
+        let gain = self.master.value() * self.ducking.value();
+        let decks = self.decks.drain(..).collect::<Vec<_>>();
+        for mut deck in decks {
+            deck.fade.advance(delta_seconds);
+            let done = deck.ended.load(Ordering::Relaxed) || (deck.leaving && deck.fade.is_done());
+            if done {
+                deck.sink.stop();
+                events.single_write(MusicEvent::TrackEnded(deck.track.source));
+            } else {
+                deck.sink
+                    .set_volume(deck.track.volume * deck.fade.value() * gain);
+                self.decks.push(deck);
+            }
+        }
+
+        if let Some(track) = self.pending.take() {
+            match storage.get(&track.source) {
+                Some(source) => {
+                    let handle = track.source.clone();
+                    if self.start(track, source, &output, &bus, crossfade) {
+                        events.single_write(MusicEvent::TrackStarted(handle));
+                    }
+                }
+                // Waits for the track to be loaded.
+                None => self.pending = Some(track),
+            }
+        }
+
+        player.current = self.current().map(|deck| deck.track.source.clone());
+        player.playing = player.current.is_some() && !self.paused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::{
+        ecs::{RunNow, WorldExt},
+        shrev::ReaderId,
+    };
+
+    use super::*;
+    use crate::{
+        music::{Playlist, RepeatMode},
+        output::NullOutput,
+        source::{DecodedAudio, SourceHandle},
+    };
+
+    struct Harness {
+        world: World,
+        system: MusicSystem,
+        output: NullOutput,
+        reader: ReaderId<MusicEvent>,
+    }
+
+    impl Harness {
+        fn new(crossfade: Duration) -> Self {
+            let mut world = World::new();
+            let output = NullOutput::manual();
+            world.insert(Output::from(output.clone()));
+            world.insert(AssetStorage::<Source>::new());
+            world.insert(Mixer::default());
+            world.insert(Time::default());
+            let mut player = MusicPlayer::default();
+            player.set_crossfade(crossfade);
+            world.insert(player);
+            let mut events = EventChannel::<MusicEvent>::new();
+            let reader = events.register_reader();
+            world.insert(events);
+            Harness {
+                world,
+                system: MusicSystem::new(AudioBus::Music),
+                output,
+                reader,
+            }
+        }
+
+        /// Inserts a silent track of the given length.
+        fn track(&mut self, seconds: usize) -> SourceHandle {
+            let audio = DecodedAudio::new(1, 1000, vec![0; seconds * 1000]);
+            self.world
+                .write_resource::<AssetStorage<Source>>()
+                .insert(Source::Decoded(audio))
+        }
+
+        /// Plays `seconds` of audio, then runs the system.
+        fn run(&mut self, seconds: f32) -> Vec<MusicEvent> {
+            self.output.advance(Duration::from_secs_f32(seconds));
+            self.world
+                .write_resource::<Time>()
+                .set_delta_seconds(seconds);
+            self.system.run_now(&self.world);
+            self.world
+                .read_resource::<EventChannel<MusicEvent>>()
+                .read(&mut self.reader)
+                .cloned()
+                .collect()
+        }
+
+        fn playing(&self, handle: &SourceHandle) -> bool {
+            let storage = self.world.read_resource::<AssetStorage<Source>>();
+            self.output
+                .records_of(storage.get(handle).unwrap())
+                .iter()
+                .any(|record| record.is_playing())
+        }
+    }
+
+    #[test]
+    fn playlist_plays_tracks_in_order() {
+        let mut harness = Harness::new(Duration::from_secs(0));
+        let first = harness.track(1);
+        let second = harness.track(1);
+        harness.world.write_resource::<MusicPlayer>().play(
+            Playlist::new()
+                .with_track(first.clone())
+                .with_track(second.clone()),
+        );
+
+        assert_eq!(
+            harness.run(0.0),
+            vec![MusicEvent::TrackStarted(first.clone())]
+        );
+        assert_eq!(
+            harness.world.read_resource::<MusicPlayer>().current_track(),
+            Some(&first)
+        );
+        assert_eq!(
+            harness.run(1.1),
+            vec![
+                MusicEvent::TrackEnded(first),
+                MusicEvent::TrackStarted(second.clone()),
+            ]
+        );
+        assert_eq!(
+            harness.run(1.1),
+            vec![MusicEvent::PlaylistEnded, MusicEvent::TrackEnded(second)]
+        );
+        assert!(!harness.world.read_resource::<MusicPlayer>().is_playing());
+    }
+
+    #[test]
+    fn crossfade_starts_the_next_track_before_the_current_one_ends() {
+        let mut harness = Harness::new(Duration::from_secs(1));
+        let first = harness.track(3);
+        let second = harness.track(3);
+        harness.world.write_resource::<MusicPlayer>().play(
+            Playlist::new()
+                .with_track(first.clone())
+                .with_track(second.clone()),
+        );
+        harness.run(0.0);
+
+        // The crossfade starts one second before the end of the first track.
+        assert_eq!(harness.run(1.5), vec![]);
+        assert_eq!(
+            harness.run(0.6),
+            vec![MusicEvent::TrackStarted(second.clone())]
+        );
+        harness.output.advance(Duration::from_millis(100));
+        assert!(harness.playing(&first));
+        assert!(harness.playing(&second));
+        assert_eq!(
+            harness.world.read_resource::<MusicPlayer>().current_track(),
+            Some(&second)
+        );
+
+        assert_eq!(harness.run(1.0), vec![MusicEvent::TrackEnded(first)]);
+    }
+
+    #[test]
+    fn next_skips_a_repeating_track() {
+        let mut harness = Harness::new(Duration::from_secs(0));
+        let first = harness.track(1);
+        let second = harness.track(1);
+        harness.world.write_resource::<MusicPlayer>().play(
+            Playlist::new()
+                .with_track(first.clone())
+                .with_track(second.clone())
+                .with_repeat(RepeatMode::One),
+        );
+        harness.run(0.0);
+
+        assert_eq!(
+            harness.run(1.1),
+            vec![
+                MusicEvent::TrackEnded(first.clone()),
+                MusicEvent::TrackStarted(first.clone()),
+            ]
+        );
+        harness.world.write_resource::<MusicPlayer>().next();
+        assert_eq!(
+            harness.run(0.1),
+            vec![
+                MusicEvent::TrackEnded(first),
+                MusicEvent::TrackStarted(second),
+            ]
+        );
+    }
+}
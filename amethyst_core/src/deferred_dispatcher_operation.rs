@@ -1,4 +1,6 @@
 //! Provides the ability to store `Systems`, `Bundles`, `Barriers`, in a normal vector for deferred dispatcher construction.
+//!
+//! Systems and thread local systems added this way are profiled by the `FrameProfiler`.
 
 use std::marker::PhantomData;
 
@@ -8,6 +10,7 @@ use amethyst_error::Error;
 
 use crate::{
     ecs::prelude::{DispatcherBuilder, RunNow, System, World},
+    frame_profiler::{profiled, profiled_thread_local},
     RunNowDesc, SystemBundle, SystemDesc,
 };
 
@@ -55,7 +58,7 @@ where
 {
     fn exec(
         self: Box<Self>,
-        _world: &mut World,
+        world: &mut World,
         dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
         let dependencies = self
@@ -63,7 +66,8 @@ where
             .iter()
             .map(String::as_str)
             .collect::<Vec<&str>>();
-        dispatcher_builder.add(self.system, &self.name, &dependencies);
+        let system = profiled(world, self.system, &self.name);
+        dispatcher_builder.add(system, &self.name, &dependencies);
         Ok(())
     }
 }
@@ -99,6 +103,7 @@ where
             .iter()
             .map(String::as_str)
             .collect::<Vec<&str>>();
+        let system = profiled(world, system, &self.name);
         dispatcher_builder.add(system, &self.name, &dependencies);
         Ok(())
     }
@@ -119,10 +124,11 @@ where
 {
     fn exec(
         self: Box<Self>,
-        _world: &mut World,
+        world: &mut World,
         dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
-        dispatcher_builder.add_thread_local(self.system);
+        let system = profiled_thread_local(world, self.system);
+        dispatcher_builder.add_thread_local(system);
         Ok(())
     }
 }
@@ -149,6 +155,7 @@ where
         dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
         let system = self.system_desc.build(world);
+        let system = profiled_thread_local(world, system);
         dispatcher_builder.add_thread_local(system);
         Ok(())
     }
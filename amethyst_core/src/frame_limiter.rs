@@ -39,7 +39,7 @@
 //!
 //! # Frame Rate Limiting Strategies
 //!
-//! The four possible strategies described by [`FrameRateLimitStrategy`] are as follows:
+//! The five possible strategies described by [`FrameRateLimitStrategy`] are as follows:
 //!
 //! * `Unlimited` will not try to limit the frame rate to the specified maximum. Amethyst
 //!   will call [`thread::yield_now`] once and then continue to the next frame.
@@ -54,6 +54,8 @@
 //!   and then will yield until the next frame starts. This approach attempts to get the
 //!   consistent frame timings of yielding, while reducing CPU usage compared to the yield-only
 //!   approach.
+//! * `Adaptive` works like `SleepAndYield`, but measures how late the operating system wakes the
+//!   game after sleeping, and adapts the time left for yielding to it.
 //!
 //! By default amethyst will use the `Yield` strategy, which is fine for desktop and console
 //! games that aren't as affected by extra CPU usage. For mobile devices, the `Sleep` strategy
@@ -63,12 +65,30 @@
 //! will have to test different grace period timings to determine how much time needs to be left
 //! to ensure that the main thread doesn't sleep too long and miss the start of the next frame.
 //!
+//! `Adaptive` finds that grace period by itself, at the cost of yielding a bit longer after a
+//! late wake up.
+//!
+//! # Background Throttling
+//!
+//! With a background frame rate, set with [`FrameLimiter::set_background_fps`] or the
+//! `background_fps` of the config, the game sleeps down to that frame rate while its window is
+//! unfocused, whatever the strategy.
+//!
+//! # Frame Pacing Statistics
+//!
+//! The limiter records the duration of the last frames in a [`FrameTimeHistogram`], which
+//! reports their minimum, mean, 99th percentile and maximum. `FpsCounter` of `amethyst_utils`
+//! copies these statistics every frame.
+//!
 //! [`Application`]: ../../amethyst/struct.Application.html
 //! [`FrameRateLimitStrategy`]: ./enum.FrameRateLimitStrategy.html
+//! [`FrameLimiter::set_background_fps`]: ./struct.FrameLimiter.html#method.set_background_fps
+//! [`FrameTimeHistogram`]: ./struct.FrameTimeHistogram.html
 //! [`thread::yield_now`]: https://doc.rust-lang.org/std/thread/fn.yield_now.html
 //! [`thread::sleep`]: https://doc.rust-lang.org/stable/std/thread/fn.sleep.html
 
 use std::{
+    collections::VecDeque,
     thread::{sleep, yield_now},
     time::{Duration, Instant},
 };
@@ -78,6 +98,9 @@ use serde::{Deserialize, Serialize};
 
 const ZERO: Duration = Duration::from_millis(0);
 
+/// The least time the `Adaptive` strategy leaves for yielding.
+const MIN_YIELD_THRESHOLD: Duration = Duration::from_micros(250);
+
 /// Frame rate limiting strategy.
 ///
 /// See the [module documentation] on the difference between sleeping and yielding, and when
@@ -100,6 +123,10 @@ pub enum FrameRateLimitStrategy {
     /// Will sleep repeatedly until the given duration remains, and then will yield repeatedly
     /// for the remaining frame time.
     SleepAndYield(Duration),
+
+    /// Use sleep and yield combined, adapting the time left for yielding to how late sleeps
+    /// wake up.
+    Adaptive,
 }
 
 impl Default for FrameRateLimitStrategy {
@@ -130,6 +157,10 @@ pub struct FrameRateLimitConfig {
     pub strategy: FrameRateLimitStrategy,
     /// The FPS to limit the game loop execution.
     pub fps: u32,
+    /// The FPS to limit the game loop execution to while the window is unfocused, if any.
+    #[new(default)]
+    #[serde(default)]
+    pub background_fps: Option<u32>,
 }
 
 impl Default for FrameRateLimitConfig {
@@ -137,7 +168,117 @@ impl Default for FrameRateLimitConfig {
         FrameRateLimitConfig {
             fps: 144,
             strategy: Default::default(),
+            background_fps: None,
+        }
+    }
+}
+
+/// Statistics of the frame durations in a `FrameTimeHistogram`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTimeStats {
+    /// The shortest frame.
+    pub min: Duration,
+    /// The mean frame duration.
+    pub mean: Duration,
+    /// The 99th percentile of the frame durations.
+    pub p99: Duration,
+    /// The longest frame.
+    pub max: Duration,
+}
+
+/// The durations of the last frames, see `FrameLimiter::frame_times`.
+///
+/// The durations are also kept sorted, and the statistics are updated as they are pushed, so
+/// reading them every frame neither sorts nor allocates.
+#[derive(Debug, Clone)]
+pub struct FrameTimeHistogram {
+    samples: VecDeque<Duration>,
+    sorted: Vec<Duration>,
+    total: Duration,
+    stats: Option<FrameTimeStats>,
+    capacity: usize,
+}
+
+impl FrameTimeHistogram {
+    /// Creates a histogram keeping the last `capacity` frame durations.
+    pub fn new(capacity: usize) -> Self {
+        FrameTimeHistogram {
+            samples: VecDeque::with_capacity(capacity),
+            sorted: Vec::with_capacity(capacity),
+            total: ZERO,
+            stats: None,
+            capacity,
+        }
+    }
+
+    /// Adds a frame duration, dropping the oldest one once `capacity` durations are kept.
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            if let Some(oldest) = self.samples.pop_front() {
+                if let Ok(index) = self.sorted.binary_search(&oldest) {
+                    self.sorted.remove(index);
+                }
+                self.total -= oldest;
+            }
+        }
+        self.samples.push_back(frame_time);
+        let index = match self.sorted.binary_search(&frame_time) {
+            Ok(index) | Err(index) => index,
+        };
+        self.sorted.insert(index, frame_time);
+        self.total += frame_time;
+        self.stats = self.compute_stats();
+    }
+
+    /// Returns the kept frame durations, oldest first.
+    pub fn samples(&self) -> &VecDeque<Duration> {
+        &self.samples
+    }
+
+    /// Forgets the kept frame durations.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.sorted.clear();
+        self.total = ZERO;
+        self.stats = None;
+    }
+
+    /// Returns the frame duration below which `percent` percent of the frames are, using the
+    /// nearest rank.
+    pub fn percentile(&self, percent: f32) -> Option<Duration> {
+        let rank = (percent.max(0.).min(100.) * self.sorted.len() as f32 / 100.).ceil() as usize;
+        self.sorted.get(rank.max(1) - 1).cloned()
+    }
+
+    /// Returns the statistics of the kept frame durations, or `None` if there are none.
+    pub fn stats(&self) -> Option<FrameTimeStats> {
+        self.stats
+    }
+
+    fn compute_stats(&self) -> Option<FrameTimeStats> {
+        Some(FrameTimeStats {
+            min: *self.sorted.first()?,
+            mean: self.total / self.sorted.len() as u32,
+            p99: self.percentile(99.)?,
+            max: *self.sorted.last()?,
+        })
+    }
+
+    /// Counts the frames in buckets of `width`, the first bucket starting at zero.
+    pub fn buckets(&self, width: Duration) -> Vec<usize> {
+        let width = width.as_nanos().max(1);
+        let mut buckets = Vec::new();
+        for sample in &self.samples {
+            let index = (sample.as_nanos() / width) as usize;
+            if index >= buckets.len() {
+                buckets.resize(index + 1, 0);
+            }
+            buckets[index] += 1;
         }
+        buckets
     }
 }
 
@@ -145,11 +286,15 @@ impl Default for FrameRateLimitConfig {
 ///
 /// `FrameLimiter` is used internally by amethyst to limit the frame rate to the
 /// rate specified by the user. It is added as a resource to the world so that user code may
-/// change the frame rate limit at runtime if necessary.
+/// change the frame rate limit at runtime if necessary, or read the frame pacing statistics.
 #[derive(Debug)]
 pub struct FrameLimiter {
     frame_duration: Duration,
     strategy: FrameRateLimitStrategy,
+    background_frame_duration: Option<Duration>,
+    focused: bool,
+    yield_threshold: Duration,
+    frame_times: FrameTimeHistogram,
     last_call: Instant,
 }
 
@@ -165,6 +310,10 @@ impl FrameLimiter {
         let mut s = Self {
             frame_duration: Duration::from_secs(0),
             strategy: Default::default(),
+            background_frame_duration: None,
+            focused: true,
+            yield_threshold: Duration::from_millis(2),
+            frame_times: FrameTimeHistogram::new(240),
             last_call: Instant::now(),
         };
         s.set_rate(strategy, fps);
@@ -181,9 +330,39 @@ impl FrameLimiter {
         self.frame_duration = Duration::from_secs(1) / fps;
     }
 
+    /// Sets the maximum fps while the window is unfocused, or `None` to keep the frame rate.
+    pub fn set_background_fps(&mut self, fps: Option<u32>) {
+        self.background_frame_duration = fps
+            .filter(|fps| *fps > 0)
+            .map(|fps| Duration::from_secs(1) / fps);
+    }
+
+    /// Tells the limiter whether the window is focused. Called by the application when the
+    /// focus changes.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Returns whether the frame rate is lowered to the background frame rate.
+    pub fn is_throttled(&self) -> bool {
+        !self.focused && self.background_frame_duration.is_some()
+    }
+
+    /// Returns the time the `Adaptive` strategy currently leaves for yielding.
+    pub fn yield_threshold(&self) -> Duration {
+        self.yield_threshold
+    }
+
+    /// Returns the durations of the last frames, including the time spent waiting.
+    pub fn frame_times(&self) -> &FrameTimeHistogram {
+        &self.frame_times
+    }
+
     /// Creates a new frame limiter with the given config.
     pub fn from_config(config: FrameRateLimitConfig) -> Self {
-        Self::new(config.strategy, config.fps)
+        let mut limiter = Self::new(config.strategy, config.fps);
+        limiter.set_background_fps(config.background_fps);
+        limiter
     }
 
     /// Resets the frame start time to the current instant.
@@ -203,19 +382,36 @@ impl FrameLimiter {
     /// [`Application`]: ../../amethyst/struct.Application.html
     pub fn wait(&mut self) {
         use self::FrameRateLimitStrategy::*;
-        match self.strategy {
-            Unlimited => yield_now(),
+        match (self.throttled_frame_duration(), self.strategy.clone()) {
+            (Some(frame_duration), _) => self.do_sleep(frame_duration, ZERO),
+
+            (None, Unlimited) => yield_now(),
+
+            (None, Yield) => self.do_yield(),
 
-            Yield => self.do_yield(),
+            (None, Sleep) => self.do_sleep(self.frame_duration, ZERO),
 
-            Sleep => self.do_sleep(ZERO),
+            (None, SleepAndYield(dur)) => {
+                self.do_sleep(self.frame_duration, dur);
+                self.do_yield();
+            }
 
-            SleepAndYield(dur) => {
-                self.do_sleep(dur);
+            (None, Adaptive) => {
+                self.do_adaptive_sleep();
                 self.do_yield();
             }
         }
-        self.last_call = Instant::now();
+        let now = Instant::now();
+        self.frame_times.push(now - self.last_call);
+        self.last_call = now;
+    }
+
+    fn throttled_frame_duration(&self) -> Option<Duration> {
+        if self.focused {
+            None
+        } else {
+            self.background_frame_duration
+        }
     }
 
     fn do_yield(&self) {
@@ -224,8 +420,35 @@ impl FrameLimiter {
         }
     }
 
-    fn do_sleep(&self, stop_on_remaining: Duration) {
-        let frame_duration = self.frame_duration - stop_on_remaining;
+    fn do_adaptive_sleep(&mut self) {
+        let wake_up = self
+            .frame_duration
+            .checked_sub(self.yield_threshold)
+            .unwrap_or(ZERO);
+        let elapsed = Instant::now() - self.last_call;
+        if elapsed >= wake_up {
+            return;
+        }
+        let requested = wake_up - elapsed;
+        let before = Instant::now();
+        sleep(requested);
+        let overshoot = (Instant::now() - before)
+            .checked_sub(requested)
+            .unwrap_or(ZERO);
+
+        // Leave twice the overshoot for yielding, growing at once after a late wake up and
+        // shrinking slowly while the sleeps are accurate.
+        let wanted = (overshoot * 2).max(MIN_YIELD_THRESHOLD);
+        self.yield_threshold = if wanted > self.yield_threshold {
+            wanted
+        } else {
+            (self.yield_threshold * 15 + wanted) / 16
+        }
+        .min(self.frame_duration);
+    }
+
+    fn do_sleep(&self, frame_duration: Duration, stop_on_remaining: Duration) {
+        let frame_duration = frame_duration - stop_on_remaining;
         loop {
             let elapsed = Instant::now() - self.last_call;
             if elapsed >= frame_duration {
@@ -236,3 +459,58 @@ impl FrameLimiter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_reports_frame_time_stats() {
+        let mut histogram = FrameTimeHistogram::new(100);
+        for millis in 1..=101 {
+            histogram.push(Duration::from_millis(millis));
+        }
+
+        assert_eq!(histogram.samples().len(), 100);
+        assert_eq!(
+            histogram.stats(),
+            Some(FrameTimeStats {
+                min: Duration::from_millis(2),
+                mean: Duration::from_micros(51_500),
+                p99: Duration::from_millis(100),
+                max: Duration::from_millis(101),
+            })
+        );
+        assert_eq!(
+            histogram.buckets(Duration::from_millis(50)),
+            vec![48, 50, 2]
+        );
+    }
+
+    #[test]
+    fn unfocused_window_throttles_to_the_background_fps() {
+        let mut limiter = FrameLimiter::from_config(FrameRateLimitConfig {
+            strategy: FrameRateLimitStrategy::Unlimited,
+            fps: 144,
+            background_fps: Some(20),
+        });
+        assert!(!limiter.is_throttled());
+        assert_eq!(limiter.throttled_frame_duration(), None);
+
+        limiter.set_focused(false);
+        assert!(limiter.is_throttled());
+        assert_eq!(
+            limiter.throttled_frame_duration(),
+            Some(Duration::from_millis(50))
+        );
+
+        limiter.start();
+        limiter.wait();
+        let frame_time = *limiter.frame_times().samples().back().unwrap();
+        assert!(frame_time >= Duration::from_millis(50));
+
+        limiter.set_focused(true);
+        assert!(!limiter.is_throttled());
+        assert_eq!(limiter.throttled_frame_duration(), None);
+    }
+}
@@ -0,0 +1,587 @@
+//! Per system timings of the last frames, exported to the Chrome tracing format.
+
+use std::{
+    any::type_name,
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    iter,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use amethyst_error::Error;
+
+use crate::{
+    ecs::prelude::{RunNow, System, World},
+    shred::{AccessorCow, RunningTime},
+};
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static THREAD: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// How long a system ran during a frame.
+#[derive(Clone, Debug)]
+pub struct SystemTiming {
+    name: Arc<str>,
+    thread: u64,
+    start: Instant,
+    duration: Duration,
+}
+
+impl SystemTiming {
+    /// Returns the name of the system.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the index of the thread which ran the system.
+    pub fn thread(&self) -> u64 {
+        self.thread
+    }
+
+    /// Returns when the system started running.
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// Returns how long the system ran.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// The system timings of a frame.
+#[derive(Clone, Debug)]
+pub struct FrameProfile {
+    frame: u64,
+    start: Instant,
+    duration: Duration,
+    systems: Vec<SystemTiming>,
+}
+
+impl FrameProfile {
+    /// Returns the frame number.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns when the frame started.
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// Returns how long the frame took, without the time spent waiting for the next frame.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the timings of the systems which ran during the frame, in the order they finished.
+    pub fn systems(&self) -> &[SystemTiming] {
+        &self.systems
+    }
+}
+
+/// Statistics of a system over the recorded frames, see `FrameProfiler::slowest_systems`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemStats {
+    /// The name of the system.
+    pub name: String,
+    /// How many times the system ran.
+    pub runs: usize,
+    /// The mean time a run took.
+    pub mean: Duration,
+    /// The longest time a run took.
+    pub max: Duration,
+}
+
+/// The timings recorded by profiled systems, shared with the `FrameProfiler`.
+#[derive(Debug)]
+struct Recorder {
+    enabled: AtomicBool,
+    timings: Mutex<Vec<SystemTiming>>,
+}
+
+impl Recorder {
+    fn record(&self, name: &Arc<str>, start: Instant) {
+        let timing = SystemTiming {
+            name: name.clone(),
+            thread: THREAD.with(|thread| *thread),
+            start,
+            duration: start.elapsed(),
+        };
+        self.timings
+            .lock()
+            .expect("Profiler mutex poisoned")
+            .push(timing);
+    }
+}
+
+/// Resource recording how long every system ran during the last frames.
+///
+/// Systems added to the `GameDataBuilder` with `with`, `with_system_desc`, `with_thread_local`
+/// and their fixed and per state variants are profiled individually. Systems added by bundles
+/// are not, unless the bundle wraps them with `FrameProfiler::profile`, but `GameData` records
+/// every stage of its dispatchers, which includes them. The application marks the start and the
+/// end of every frame.
+///
+/// Profiled systems record to the profiler which was in the `World` when they were built, so
+/// configure it with `set_enabled` and `set_capacity` instead of inserting a new one.
+///
+/// The profiler is disabled by default, when profiled systems only check an atomic flag. Once
+/// enabled, every run of a system costs two clock reads and a short mutex lock, so it can stay
+/// enabled in release builds, e.g. to feed a debug overlay.
+///
+/// # Examples
+///
+/// ```rust
+/// use amethyst_core::{ecs::prelude::*, FrameProfiler};
+///
+/// fn slowest_systems(world: &World) {
+///     let profiler = world.read_resource::<FrameProfiler>();
+///     for stats in profiler.slowest_systems(10) {
+///         println!("{}: {:?} (max {:?})", stats.name, stats.mean, stats.max);
+///     }
+/// }
+/// ```
+pub struct FrameProfiler {
+    recorder: Arc<Recorder>,
+    capacity: usize,
+    frames: VecDeque<FrameProfile>,
+    epoch: Instant,
+    frame_start: Option<Instant>,
+}
+
+impl fmt::Debug for FrameProfiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameProfiler")
+            .field("enabled", &self.is_enabled())
+            .field("capacity", &self.capacity)
+            .field("frames", &self.frames.len())
+            .finish()
+    }
+}
+
+impl Default for FrameProfiler {
+    fn default() -> Self {
+        FrameProfiler::new(120)
+    }
+}
+
+impl FrameProfiler {
+    /// Creates a disabled profiler keeping the last `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        FrameProfiler {
+            recorder: Arc::new(Recorder {
+                enabled: AtomicBool::new(false),
+                timings: Mutex::new(Vec::new()),
+            }),
+            capacity,
+            frames: VecDeque::with_capacity(capacity),
+            epoch: Instant::now(),
+            frame_start: None,
+        }
+    }
+
+    /// Enables or disables the profiler. Disabling it keeps the recorded frames.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.recorder.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.frame_start = None;
+        }
+    }
+
+    /// Returns whether the profiler is recording.
+    pub fn is_enabled(&self) -> bool {
+        self.recorder.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many frames are kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sets how many frames are kept, dropping the oldest frames beyond `capacity`.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.frames.len() > capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Wraps a system so its runs are recorded under `name`.
+    pub fn profile<S, N>(&self, system: S, name: N) -> Profiled<S>
+    where
+        N: Into<String>,
+    {
+        Profiled {
+            system,
+            name: Arc::from(name.into()),
+            recorder: self.recorder.clone(),
+        }
+    }
+
+    /// Wraps a thread local system so its runs are recorded under its type name.
+    pub fn profile_thread_local<S>(&self, system: S) -> ProfiledThreadLocal<S> {
+        ProfiledThreadLocal {
+            system,
+            name: Arc::from(type_name::<S>()),
+            recorder: self.recorder.clone(),
+        }
+    }
+
+    /// Records that the stage `name`, like a whole dispatcher, ran from `start` until now.
+    ///
+    /// Called by `GameData` for the stages of its dispatchers.
+    pub fn record_stage(&self, name: &str, start: Instant) {
+        if self.is_enabled() && self.frame_start.is_some() {
+            self.recorder.record(&Arc::from(name), start);
+        }
+    }
+
+    /// Starts recording a frame. Called by the application at the start of every frame.
+    pub fn begin_frame(&mut self) {
+        if self.is_enabled() {
+            self.frame_start = Some(Instant::now());
+            self.lock().clear();
+        }
+    }
+
+    /// Finishes recording the frame `frame`, dropping the oldest frame once `capacity` frames
+    /// are kept. Called by the application at the end of every frame.
+    pub fn end_frame(&mut self, frame: u64) {
+        let start = match self.frame_start.take() {
+            Some(start) => start,
+            None => return,
+        };
+        let systems = self.lock().drain(..).collect();
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        if self.capacity > 0 {
+            self.frames.push_back(FrameProfile {
+                frame,
+                start,
+                duration: start.elapsed(),
+                systems,
+            });
+        }
+    }
+
+    /// Forgets the recorded frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Returns the recorded frames, oldest first.
+    pub fn frames(&self) -> &VecDeque<FrameProfile> {
+        &self.frames
+    }
+
+    /// Returns the last recorded frame.
+    pub fn last_frame(&self) -> Option<&FrameProfile> {
+        self.frames.back()
+    }
+
+    /// Returns the `count` systems with the longest mean run time over the recorded frames,
+    /// slowest first.
+    pub fn slowest_systems(&self, count: usize) -> Vec<SystemStats> {
+        let mut totals = HashMap::<&str, (usize, Duration, Duration)>::new();
+        for timing in self.frames.iter().flat_map(|frame| frame.systems.iter()) {
+            let entry = totals.entry(timing.name()).or_insert((
+                0,
+                Duration::from_secs(0),
+                Duration::from_secs(0),
+            ));
+            entry.0 += 1;
+            entry.1 += timing.duration;
+            entry.2 = entry.2.max(timing.duration);
+        }
+        let mut stats = totals
+            .into_iter()
+            .map(|(name, (runs, total, max))| SystemStats {
+                name: name.to_owned(),
+                runs,
+                mean: total / runs as u32,
+                max,
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| b.mean.cmp(&a.mean).then_with(|| a.name.cmp(&b.name)));
+        stats.truncate(count);
+        stats
+    }
+
+    /// Writes the recorded frames in the Chrome tracing JSON format, which can be opened in
+    /// `chrome://tracing` or speedscope.
+    pub fn write_chrome_trace<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "{{\"traceEvents\":[")?;
+        let mut first = true;
+        for frame in &self.frames {
+            let events = iter::once(("frame".to_owned(), "frame", 0, frame.start, frame.duration))
+                .chain(frame.systems.iter().map(|timing| {
+                    (
+                        escape_json(timing.name()),
+                        "system",
+                        timing.thread + 1,
+                        timing.start,
+                        timing.duration,
+                    )
+                }));
+            for (name, category, thread, start, duration) in events {
+                if !first {
+                    write!(writer, ",")?;
+                }
+                first = false;
+                write!(
+                    writer,
+                    "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\
+                     \"ts\":{},\"dur\":{},\"args\":{{\"frame\":{}}}}}",
+                    name,
+                    category,
+                    thread,
+                    start.duration_since(self.epoch).as_micros(),
+                    duration.as_micros(),
+                    frame.frame,
+                )?;
+            }
+        }
+        write!(writer, "]}}")?;
+        writer.flush()
+    }
+
+    /// Saves the recorded frames to a file in the Chrome tracing JSON format, see
+    /// `write_chrome_trace`.
+    pub fn save_chrome_trace<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path)?;
+        self.write_chrome_trace(BufWriter::new(file))?;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<SystemTiming>> {
+        self.recorder
+            .timings
+            .lock()
+            .expect("Profiler mutex poisoned")
+    }
+}
+
+fn escape_json(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A system recording its runs in the `FrameProfiler`.
+///
+/// This is created using `FrameProfiler::profile`.
+pub struct Profiled<S> {
+    system: S,
+    name: Arc<str>,
+    recorder: Arc<Recorder>,
+}
+
+impl<S> fmt::Debug for Profiled<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Profiled")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<'s, S> System<'s> for Profiled<S>
+where
+    S: System<'s>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        if self.recorder.enabled.load(Ordering::Relaxed) {
+            let start = Instant::now();
+            self.system.run(data);
+            self.recorder.record(&self.name, start);
+        } else {
+            self.system.run(data);
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn accessor<'b>(&'b self) -> AccessorCow<'s, 'b, Self> {
+        match self.system.accessor() {
+            AccessorCow::Ref(accessor) => AccessorCow::Ref(accessor),
+            AccessorCow::Owned(accessor) => AccessorCow::Owned(accessor),
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+
+    fn dispose(self, world: &mut World) {
+        self.system.dispose(world);
+    }
+}
+
+/// A thread local system recording its runs in the `FrameProfiler`.
+///
+/// This is created using `FrameProfiler::profile_thread_local`.
+pub struct ProfiledThreadLocal<S> {
+    system: S,
+    name: Arc<str>,
+    recorder: Arc<Recorder>,
+}
+
+impl<S> fmt::Debug for ProfiledThreadLocal<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProfiledThreadLocal")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<'s, S> RunNow<'s> for ProfiledThreadLocal<S>
+where
+    S: RunNow<'s>,
+{
+    fn run_now(&mut self, world: &'s World) {
+        if self.recorder.enabled.load(Ordering::Relaxed) {
+            let start = Instant::now();
+            self.system.run_now(world);
+            self.recorder.record(&self.name, start);
+        } else {
+            self.system.run_now(world);
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+
+    fn dispose(self: Box<Self>, world: &mut World) {
+        Box::new(self.system).dispose(world);
+    }
+}
+
+/// Wraps a system added by a deferred dispatcher operation, named after its type if it has no
+/// name, adding the `FrameProfiler` to the `World` if needed.
+pub(crate) fn profiled<S>(world: &mut World, system: S, name: &str) -> Profiled<S> {
+    let name = if name.is_empty() {
+        type_name::<S>()
+    } else {
+        name
+    };
+    world
+        .entry::<FrameProfiler>()
+        .or_insert_with(FrameProfiler::default)
+        .profile(system, name)
+}
+
+/// Wraps a thread local system added by a deferred dispatcher operation.
+pub(crate) fn profiled_thread_local<S>(world: &mut World, system: S) -> ProfiledThreadLocal<S> {
+    world
+        .entry::<FrameProfiler>()
+        .or_insert_with(FrameProfiler::default)
+        .profile_thread_local(system)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ecs::prelude::{WorldExt, Write};
+
+    struct Count;
+
+    impl<'a> System<'a> for Count {
+        type SystemData = Write<'a, u32>;
+
+        fn run(&mut self, mut count: Self::SystemData) {
+            *count += 1;
+        }
+    }
+
+    #[test]
+    fn records_enabled_frames_in_a_ring_buffer() {
+        let mut world = World::new();
+        world.insert(FrameProfiler::new(2));
+        let mut system = profiled(&mut world, Count, "count");
+        System::setup(&mut system, &mut world);
+
+        for frame in 0..3 {
+            world.write_resource::<FrameProfiler>().begin_frame();
+            system.run_now(&world);
+            world.write_resource::<FrameProfiler>().end_frame(frame);
+        }
+        assert!(world.read_resource::<FrameProfiler>().frames().is_empty());
+        assert_eq!(*world.read_resource::<u32>(), 3);
+
+        world.write_resource::<FrameProfiler>().set_enabled(true);
+        for frame in 3..6 {
+            world.write_resource::<FrameProfiler>().begin_frame();
+            system.run_now(&world);
+            world.write_resource::<FrameProfiler>().end_frame(frame);
+        }
+
+        let profiler = world.read_resource::<FrameProfiler>();
+        let frames = profiler.frames().iter().map(FrameProfile::frame);
+        assert_eq!(frames.collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(profiler.last_frame().unwrap().systems()[0].name(), "count");
+        let stats = profiler.slowest_systems(10);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].runs, 2);
+
+        let mut trace = Vec::new();
+        profiler.write_chrome_trace(&mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        assert!(trace.starts_with("{\"traceEvents\":[{\"name\":\"frame\""));
+        assert_eq!(trace.matches("\"name\":\"count\"").count(), 2);
+    }
+
+    #[test]
+    fn set_capacity_keeps_the_systems_recording() {
+        let mut world = World::new();
+        world.insert(FrameProfiler::new(4));
+        let mut system = profiled(&mut world, Count, "count");
+        System::setup(&mut system, &mut world);
+        world.write_resource::<FrameProfiler>().set_enabled(true);
+
+        for frame in 0..3 {
+            world.write_resource::<FrameProfiler>().begin_frame();
+            system.run_now(&world);
+            world.write_resource::<FrameProfiler>().end_frame(frame);
+        }
+        world.write_resource::<FrameProfiler>().set_capacity(1);
+        assert_eq!(world.read_resource::<FrameProfiler>().frames().len(), 1);
+
+        world.write_resource::<FrameProfiler>().begin_frame();
+        system.run_now(&world);
+        world
+            .read_resource::<FrameProfiler>()
+            .record_stage("dispatcher", Instant::now());
+        world.write_resource::<FrameProfiler>().end_frame(3);
+
+        let profiler = world.read_resource::<FrameProfiler>();
+        let frame = profiler.last_frame().unwrap();
+        assert_eq!(profiler.frames().len(), 1);
+        assert_eq!(frame.frame(), 3);
+        let names = frame.systems().iter().map(SystemTiming::name);
+        assert_eq!(names.collect::<Vec<_>>(), vec!["count", "dispatcher"]);
+    }
+}
@@ -37,26 +37,36 @@ pub use crate::{
 
 pub use self::{
     axis::{Axis2, Axis3},
+    frame_profiler::{
+        FrameProfile, FrameProfiler, Profiled, ProfiledThreadLocal, SystemStats, SystemTiming,
+    },
     hidden::{Hidden, HiddenPropagate},
     hide_system::{HideHierarchySystem, HideHierarchySystemDesc},
     named::{Named, WithNamed},
     system_desc::{RunNowDesc, SystemDesc},
+    timers::{Timer, TimerCallback, TimerClock, TimerId, Timers},
+    wakeup::{EventPump, Idle, Waker, Wakeup},
 };
 
 pub mod bundle;
 pub mod deferred_dispatcher_operation;
 pub mod frame_limiter;
 pub mod geometry;
+#[cfg(feature = "saveload")]
+pub mod snapshot;
 pub mod timing;
 pub mod transform;
 
 mod axis;
 mod event;
+mod frame_profiler;
 mod hidden;
 mod hide_system;
 mod named;
 mod system_desc;
 mod system_ext;
+mod timers;
+mod wakeup;
 
 /// A rayon thread pool wrapped in an `Arc`. This should be used as resource in `World`.
 pub type ArcThreadPool = Arc<rayon::ThreadPool>;
@@ -0,0 +1,591 @@
+//! Snapshots of selected components and resources of the `World`, for save games.
+//!
+//! A `SnapshotSchema` lists the components and resources to save, under stable names. It saves
+//! the entities marked with a `SnapshotMarker` into a `Snapshot`, which is written to a JSON
+//! file, and loads a snapshot back into a fresh `World`.
+//!
+//! Components are converted with specs' `ConvertSaveload`, so entity references like `Parent`
+//! are saved as markers and point to the loaded entities. Components without entity references
+//! only have to implement `Clone`, `Serialize` and `Deserialize`. Other components are converted
+//! with functions given to `SnapshotSchema::with_component_as`, e.g. asset handles, which
+//! `amethyst_assets::SnapshotSchemaExt` saves as the path of their asset.
+//!
+//! Snapshots record the version of their schema. When the layout of a component changes, bump
+//! the version and add a migration, which updates the saved values of older snapshots.
+//!
+//! ```rust
+//! use amethyst::{
+//!     core::{
+//!         snapshot::{SnapshotMarker, SnapshotSchema},
+//!         Named, Parent, Transform,
+//!     },
+//!     ecs::{prelude::*, saveload::MarkedBuilder},
+//!     error::Error,
+//! };
+//!
+//! # fn main() -> Result<(), Error> {
+//! let schema = SnapshotSchema::new(1)
+//!     .with_component::<Named>("name")
+//!     .with_component::<Parent>("parent")
+//!     .with_component::<Transform>("transform")
+//!     // Version 0 called the names "label".
+//!     .with_migration(0, |snapshot| {
+//!         snapshot.rename_component("label", "name");
+//!         Ok(())
+//!     });
+//!
+//! let mut world = World::new();
+//! schema.setup(&mut world);
+//! let player = world
+//!     .create_entity()
+//!     .with(Named::new("player"))
+//!     .marked::<SnapshotMarker>()
+//!     .build();
+//! world
+//!     .create_entity()
+//!     .with(Parent::new(player))
+//!     .marked::<SnapshotMarker>()
+//!     .build();
+//!
+//! let snapshot = schema.save(&world)?;
+//! let mut loaded = World::new();
+//! schema.load(&mut loaded, snapshot)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter},
+    marker::PhantomData,
+    path::Path,
+};
+
+use amethyst_error::{format_err, Error};
+use derivative::Derivative;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::ecs::{
+    prelude::{
+        Component, Entities, Entity, Join, ReadStorage, World, WorldExt, Write, WriteStorage,
+    },
+    saveload::{ConvertSaveload, MarkerAllocator, SimpleMarker, SimpleMarkerAllocator},
+    shred::Resource,
+};
+
+pub use serde_json::Value;
+
+/// Tag of the `SnapshotMarker`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshotted;
+
+/// Marks the entities saved in snapshots, add it with `MarkedBuilder::marked`.
+pub type SnapshotMarker = SimpleMarker<Snapshotted>;
+
+/// Allocates the ids of `SnapshotMarker`s, added to the `World` by `SnapshotSchema::setup`.
+pub type SnapshotMarkerAllocator = SimpleMarkerAllocator<Snapshotted>;
+
+/// The saved components and resources of a `World`, see `SnapshotSchema`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The version of the schema which saved the snapshot.
+    pub version: u32,
+    /// The saved entities.
+    pub entities: Vec<EntitySnapshot>,
+    /// The saved resources, by name.
+    pub resources: BTreeMap<String, Value>,
+}
+
+/// A saved entity.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    /// The `SnapshotMarker` of the entity, used by other components to refer to it.
+    pub marker: Value,
+    /// The saved components, by name.
+    pub components: BTreeMap<String, Value>,
+}
+
+impl Snapshot {
+    /// Writes the snapshot to a JSON file.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot from a JSON file.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Renames a component of every entity, for migrations.
+    pub fn rename_component(&mut self, from: &str, to: &str) {
+        for entity in &mut self.entities {
+            if let Some(value) = entity.components.remove(from) {
+                entity.components.insert(to.to_string(), value);
+            }
+        }
+    }
+
+    /// Returns the saved values of a component, for migrations.
+    pub fn components_mut<'s>(&'s mut self, name: &'s str) -> impl Iterator<Item = &'s mut Value> {
+        self.entities
+            .iter_mut()
+            .filter_map(move |entity| entity.components.get_mut(name))
+    }
+}
+
+type Migration = Box<dyn Fn(&mut Snapshot) -> Result<(), Error> + Send + Sync>;
+
+/// Selects the components and resources saved in snapshots, and migrates older snapshots.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SnapshotSchema {
+    version: u32,
+    #[derivative(Debug = "ignore")]
+    components: Vec<Box<dyn ComponentEntry>>,
+    #[derivative(Debug = "ignore")]
+    resources: Vec<Box<dyn ResourceEntry>>,
+    #[derivative(Debug = "ignore")]
+    migrations: BTreeMap<u32, Migration>,
+}
+
+impl SnapshotSchema {
+    /// Creates a schema saving snapshots of the given version.
+    pub fn new(version: u32) -> Self {
+        SnapshotSchema {
+            version,
+            components: Vec::new(),
+            resources: Vec::new(),
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Saves a component under the given name.
+    ///
+    /// The entities referenced by the component must be marked too.
+    pub fn with_component<C>(mut self, name: &str) -> Self
+    where
+        C: Component + ConvertSaveload<SnapshotMarker> + Send + Sync,
+        C::Storage: Default,
+        C::Error: Display,
+    {
+        self.components.push(Box::new(ComponentSaver::<C> {
+            name: name.to_string(),
+            marker: PhantomData,
+        }));
+        self
+    }
+
+    /// Saves a component under the given name, converted to `D` by `save` and back by `load`.
+    ///
+    /// This saves components which can't be serialized, like asset handles, from data they can
+    /// be recreated from. Both functions get the `World`, without the storage of the component.
+    pub fn with_component_as<C, D, S, L>(mut self, name: &str, save: S, load: L) -> Self
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+        D: Serialize + DeserializeOwned + 'static,
+        S: Fn(&C, &World) -> Result<D, Error> + Send + Sync + 'static,
+        L: Fn(D, &World) -> Result<C, Error> + Send + Sync + 'static,
+    {
+        self.components.push(Box::new(ComponentConverter {
+            name: name.to_string(),
+            save,
+            load,
+            marker: PhantomData::<(C, fn() -> D)>,
+        }));
+        self
+    }
+
+    /// Saves a resource under the given name.
+    pub fn with_resource<R>(mut self, name: &str) -> Self
+    where
+        R: Resource + Serialize + DeserializeOwned,
+    {
+        self.resources.push(Box::new(ResourceSaver::<R> {
+            name: name.to_string(),
+            marker: PhantomData,
+        }));
+        self
+    }
+
+    /// Adds a migration from snapshots of version `from_version` to the next version.
+    ///
+    /// Loading a snapshot runs the migrations from its version up to the version of the schema.
+    pub fn with_migration<F>(mut self, from_version: u32, migration: F) -> Self
+    where
+        F: Fn(&mut Snapshot) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.migrations.insert(from_version, Box::new(migration));
+        self
+    }
+
+    /// Returns the version of the snapshots saved by this schema.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Registers the `SnapshotMarker` and the saved components, and adds the
+    /// `SnapshotMarkerAllocator`.
+    pub fn setup(&self, world: &mut World) {
+        world.register::<SnapshotMarker>();
+        world
+            .entry::<SnapshotMarkerAllocator>()
+            .or_insert_with(SnapshotMarkerAllocator::default);
+        for component in &self.components {
+            component.register(world);
+        }
+    }
+
+    /// Saves the marked entities and the resources of the `World`.
+    ///
+    /// Resources missing from the `World` aren't saved.
+    pub fn save(&self, world: &World) -> Result<Snapshot, Error> {
+        let mut snapshot = Snapshot {
+            version: self.version,
+            ..Default::default()
+        };
+        let mut indices = HashMap::new();
+        {
+            let (entities, markers) =
+                world.system_data::<(Entities<'_>, ReadStorage<'_, SnapshotMarker>)>();
+            for (entity, marker) in (&entities, &markers).join() {
+                indices.insert(entity, snapshot.entities.len());
+                snapshot.entities.push(EntitySnapshot {
+                    marker: serde_json::to_value(marker)?,
+                    components: BTreeMap::new(),
+                });
+            }
+        }
+        for component in &self.components {
+            component.save(world, &indices, &mut snapshot.entities)?;
+        }
+        for resource in &self.resources {
+            if let Some(value) = resource.save(world)? {
+                snapshot
+                    .resources
+                    .insert(resource.name().to_string(), value);
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Migrates the snapshot, then creates its entities and inserts its resources into the
+    /// `World`.
+    pub fn load(&self, world: &mut World, mut snapshot: Snapshot) -> Result<(), Error> {
+        self.migrate(&mut snapshot)?;
+        self.setup(world);
+        let loaded = {
+            let (entities, mut markers, mut allocator) = world.system_data::<(
+                Entities<'_>,
+                WriteStorage<'_, SnapshotMarker>,
+                Write<'_, SnapshotMarkerAllocator>,
+            )>();
+            snapshot
+                .entities
+                .iter()
+                .map(|entity| {
+                    let marker = serde_json::from_value(entity.marker.clone())?;
+                    Ok(allocator.retrieve_entity(marker, &mut markers, &entities))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+        for component in &self.components {
+            component.load(world, &snapshot.entities, &loaded)?;
+        }
+        for resource in &self.resources {
+            if let Some(value) = snapshot.resources.get(resource.name()) {
+                resource.load(world, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the migrations from the version of the snapshot up to the version of the schema.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the snapshot is newer than the schema, if a migration is missing, or if a
+    /// migration fails.
+    pub fn migrate(&self, snapshot: &mut Snapshot) -> Result<(), Error> {
+        if snapshot.version > self.version {
+            return Err(format_err!(
+                "The snapshot version {} is newer than the schema version {}",
+                snapshot.version,
+                self.version
+            ));
+        }
+        while snapshot.version < self.version {
+            let migration = self.migrations.get(&snapshot.version).ok_or_else(|| {
+                format_err!("No migration from snapshot version {}", snapshot.version)
+            })?;
+            migration(snapshot)?;
+            snapshot.version += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Saves and loads a component of the `SnapshotSchema`.
+trait ComponentEntry: Send + Sync {
+    fn register(&self, world: &mut World);
+
+    fn save(
+        &self,
+        world: &World,
+        indices: &HashMap<Entity, usize>,
+        snapshots: &mut [EntitySnapshot],
+    ) -> Result<(), Error>;
+
+    fn load(
+        &self,
+        world: &mut World,
+        snapshots: &[EntitySnapshot],
+        loaded: &[Entity],
+    ) -> Result<(), Error>;
+}
+
+struct ComponentSaver<C> {
+    name: String,
+    marker: PhantomData<C>,
+}
+
+impl<C> ComponentEntry for ComponentSaver<C>
+where
+    C: Component + ConvertSaveload<SnapshotMarker> + Send + Sync,
+    C::Storage: Default,
+    C::Error: Display,
+{
+    fn register(&self, world: &mut World) {
+        world.register::<C>();
+    }
+
+    fn save(
+        &self,
+        world: &World,
+        indices: &HashMap<Entity, usize>,
+        snapshots: &mut [EntitySnapshot],
+    ) -> Result<(), Error> {
+        let (entities, markers, components) = world.system_data::<(
+            Entities<'_>,
+            ReadStorage<'_, SnapshotMarker>,
+            ReadStorage<'_, C>,
+        )>();
+        for (entity, component) in (&entities, &components).join() {
+            if let Some(&index) = indices.get(&entity) {
+                let data = component
+                    .convert_into(|entity| markers.get(entity).cloned())
+                    .map_err(|e| format_err!("Failed to save component {}: {}", self.name, e))?;
+                snapshots[index]
+                    .components
+                    .insert(self.name.clone(), serde_json::to_value(data)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn load(
+        &self,
+        world: &mut World,
+        snapshots: &[EntitySnapshot],
+        loaded: &[Entity],
+    ) -> Result<(), Error> {
+        let (entities, mut markers, mut allocator, mut components) = world.system_data::<(
+            Entities<'_>,
+            WriteStorage<'_, SnapshotMarker>,
+            Write<'_, SnapshotMarkerAllocator>,
+            WriteStorage<'_, C>,
+        )>();
+        for (snapshot, &entity) in snapshots.iter().zip(loaded) {
+            if let Some(value) = snapshot.components.get(&self.name) {
+                let data = serde_json::from_value(value.clone())?;
+                let component = C::convert_from(data, |marker| {
+                    Some(allocator.retrieve_entity(marker, &mut markers, &entities))
+                })
+                .map_err(|e| format_err!("Failed to load component {}: {}", self.name, e))?;
+                components.insert(entity, component)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ComponentConverter<C, D, S, L> {
+    name: String,
+    save: S,
+    load: L,
+    marker: PhantomData<(C, fn() -> D)>,
+}
+
+impl<C, D, S, L> ComponentEntry for ComponentConverter<C, D, S, L>
+where
+    C: Component + Send + Sync,
+    C::Storage: Default,
+    D: Serialize + DeserializeOwned,
+    S: Fn(&C, &World) -> Result<D, Error> + Send + Sync,
+    L: Fn(D, &World) -> Result<C, Error> + Send + Sync,
+{
+    fn register(&self, world: &mut World) {
+        world.register::<C>();
+    }
+
+    fn save(
+        &self,
+        world: &World,
+        indices: &HashMap<Entity, usize>,
+        snapshots: &mut [EntitySnapshot],
+    ) -> Result<(), Error> {
+        let (entities, components) = world.system_data::<(Entities<'_>, ReadStorage<'_, C>)>();
+        for (entity, component) in (&entities, &components).join() {
+            if let Some(&index) = indices.get(&entity) {
+                let data = (self.save)(component, world)
+                    .map_err(|e| format_err!("Failed to save component {}: {}", self.name, e))?;
+                snapshots[index]
+                    .components
+                    .insert(self.name.clone(), serde_json::to_value(data)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn load(
+        &self,
+        world: &mut World,
+        snapshots: &[EntitySnapshot],
+        loaded: &[Entity],
+    ) -> Result<(), Error> {
+        let world = &*world;
+        let mut components = world.write_storage::<C>();
+        for (snapshot, &entity) in snapshots.iter().zip(loaded) {
+            if let Some(value) = snapshot.components.get(&self.name) {
+                let data = serde_json::from_value(value.clone())?;
+                let component = (self.load)(data, world)
+                    .map_err(|e| format_err!("Failed to load component {}: {}", self.name, e))?;
+                components.insert(entity, component)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Saves and loads a resource of the `SnapshotSchema`.
+trait ResourceEntry: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn save(&self, world: &World) -> Result<Option<Value>, Error>;
+
+    fn load(&self, world: &mut World, value: &Value) -> Result<(), Error>;
+}
+
+struct ResourceSaver<R> {
+    name: String,
+    marker: PhantomData<R>,
+}
+
+impl<R> ResourceEntry for ResourceSaver<R>
+where
+    R: Resource + Serialize + DeserializeOwned,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn save(&self, world: &World) -> Result<Option<Value>, Error> {
+        match world.try_fetch::<R>() {
+            Some(resource) => Ok(Some(serde_json::to_value(&*resource)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn load(&self, world: &mut World, value: &Value) -> Result<(), Error> {
+        world.insert(serde_json::from_value::<R>(value.clone())?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ecs::saveload::MarkedBuilder, Named, Parent};
+
+    fn schema() -> SnapshotSchema {
+        SnapshotSchema::new(1)
+            .with_component::<Named>("name")
+            .with_component::<Parent>("parent")
+            .with_resource::<u32>("score")
+            .with_migration(0, |snapshot| {
+                snapshot.rename_component("label", "name");
+                Ok(())
+            })
+    }
+
+    #[test]
+    fn load_remaps_entity_references() {
+        let schema = schema();
+        let mut world = World::new();
+        schema.setup(&mut world);
+        world.insert(42u32);
+        // Unmarked entities aren't saved, and shift the ids of the loaded entities.
+        world.create_entity().with(Named::new("ignored")).build();
+        let parent = world
+            .create_entity()
+            .with(Named::new("parent"))
+            .marked::<SnapshotMarker>()
+            .build();
+        world
+            .create_entity()
+            .with(Named::new("child"))
+            .with(Parent::new(parent))
+            .marked::<SnapshotMarker>()
+            .build();
+
+        let snapshot = schema.save(&world).unwrap();
+        assert_eq!(snapshot.entities.len(), 2);
+
+        let mut loaded = World::new();
+        schema.load(&mut loaded, snapshot).unwrap();
+        assert_eq!(*loaded.read_resource::<u32>(), 42);
+
+        let names = loaded.read_storage::<Named>();
+        let parents = loaded.read_storage::<Parent>();
+        let (child, parent) = (&*loaded.entities(), &parents)
+            .join()
+            .next()
+            .expect("Expected the child to be loaded");
+        assert_eq!(names.get(child).unwrap().name, "child");
+        assert_eq!(names.get(parent.entity).unwrap().name, "parent");
+    }
+
+    #[test]
+    fn old_snapshots_are_migrated() {
+        let schema = schema();
+        let mut world = World::new();
+        schema.setup(&mut world);
+        world
+            .create_entity()
+            .with(Named::new("old"))
+            .marked::<SnapshotMarker>()
+            .build();
+        // Makes the snapshot look like it was saved by version 0.
+        let mut snapshot = schema.save(&world).unwrap();
+        snapshot.version = 0;
+        snapshot.rename_component("name", "label");
+
+        let mut loaded = World::new();
+        schema.load(&mut loaded, snapshot).unwrap();
+        let names = loaded.read_storage::<Named>();
+        assert_eq!((&names).join().next().unwrap().name, "old");
+
+        let newer = Snapshot {
+            version: 2,
+            ..Default::default()
+        };
+        assert!(schema.migrate(&mut newer.clone()).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+//! One-shot and repeating timers, firing callbacks or events.
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::{
+    ecs::{prelude::*, world::EntitiesRes},
+    shrev::{Event, EventChannel},
+    timing::{duration_to_secs, secs_to_duration, Time},
+};
+
+/// A function run with the `World` when a timer fires.
+pub type TimerCallback = Arc<dyn Fn(&mut World) + Send + Sync>;
+
+/// Identifies a timer started with `Timers::start`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerId(u64);
+
+/// The time which a timer counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerClock {
+    /// Game time, scaled by `Time::time_scale`, so the timer stops while the time scale is 0.
+    Game,
+    /// Real time, ignoring `Time::time_scale`.
+    Real,
+}
+
+/// A timer, started with `Timers::start`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use amethyst::core::{Timer, Timers};
+///
+/// #[derive(Clone, Debug)]
+/// enum GameEvent {
+///     SpawnWave,
+/// }
+///
+/// let mut timers = Timers::default();
+/// // Writes `GameEvent::SpawnWave` to the `EventChannel<GameEvent>` every 30 seconds.
+/// timers.start(Timer::repeating(Duration::from_secs(30)).firing(GameEvent::SpawnWave));
+/// // A cooldown, ready once the timer isn't running anymore.
+/// let cooldown = timers.start(Timer::once(Duration::from_millis(500)));
+/// assert!(timers.is_running(cooldown));
+/// ```
+#[derive(Clone)]
+pub struct Timer {
+    duration: Duration,
+    repeat: bool,
+    clock: TimerClock,
+    entity: Option<Entity>,
+    callback: Option<TimerCallback>,
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("duration", &self.duration)
+            .field("repeat", &self.repeat)
+            .field("clock", &self.clock)
+            .field("entity", &self.entity)
+            .finish()
+    }
+}
+
+impl Timer {
+    /// A timer firing once, after `duration` of game time.
+    pub fn once(duration: Duration) -> Self {
+        Timer {
+            duration,
+            repeat: false,
+            clock: TimerClock::Game,
+            entity: None,
+            callback: None,
+        }
+    }
+
+    /// A timer firing every `interval` of game time, until it is cancelled.
+    pub fn repeating(interval: Duration) -> Self {
+        Timer {
+            repeat: true,
+            ..Timer::once(interval)
+        }
+    }
+
+    /// Counts real time instead of game time.
+    pub fn real_time(mut self) -> Self {
+        self.clock = TimerClock::Real;
+        self
+    }
+
+    /// Attaches the timer to an entity, cancelling it when the entity is deleted.
+    pub fn attached_to(mut self, entity: Entity) -> Self {
+        self.entity = Some(entity);
+        self
+    }
+
+    /// Runs the callback with the `World` when the timer fires.
+    pub fn calling<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&mut World) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Writes the event to its `EventChannel` when the timer fires, adding the channel to the
+    /// `World` if needed.
+    pub fn firing<E>(self, event: E) -> Self
+    where
+        E: Event + Clone,
+    {
+        self.calling(move |world| {
+            world
+                .entry::<EventChannel<E>>()
+                .or_insert_with(EventChannel::default)
+                .single_write(event.clone());
+        })
+    }
+
+    /// Returns how long the timer runs before firing.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+#[derive(Debug)]
+struct RunningTimer {
+    id: TimerId,
+    timer: Timer,
+    elapsed: Duration,
+    paused: bool,
+    finished: bool,
+}
+
+/// Resource of the running timers.
+///
+/// The application ticks the timers at the start of every frame with `Timers::run`, and runs the
+/// callbacks of the timers which fired, before the states and systems.
+#[derive(Debug, Default)]
+pub struct Timers {
+    next_id: u64,
+    timers: Vec<RunningTimer>,
+    idle: Duration,
+}
+
+impl Timers {
+    /// Starts a timer, returning its id.
+    pub fn start(&mut self, timer: Timer) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(RunningTimer {
+            id,
+            timer,
+            elapsed: Duration::from_secs(0),
+            paused: false,
+            finished: false,
+        });
+        id
+    }
+
+    /// Cancels a timer, returning whether it was running.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let before = self.timers.len();
+        self.timers.retain(|running| running.id != id);
+        self.timers.len() != before
+    }
+
+    /// Cancels the timers attached to an entity.
+    pub fn cancel_attached(&mut self, entity: Entity) {
+        self.timers
+            .retain(|running| running.timer.entity != Some(entity));
+    }
+
+    /// Pauses a timer, which keeps its elapsed time until it is resumed.
+    pub fn pause(&mut self, id: TimerId) {
+        if let Some(running) = self.get_mut(id) {
+            running.paused = true;
+        }
+    }
+
+    /// Resumes a paused timer.
+    pub fn resume(&mut self, id: TimerId) {
+        if let Some(running) = self.get_mut(id) {
+            running.paused = false;
+        }
+    }
+
+    /// Returns whether a timer is running or paused, which is `false` once a one-shot timer
+    /// fired or a timer was cancelled.
+    pub fn is_running(&self, id: TimerId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns whether a timer is paused.
+    pub fn is_paused(&self, id: TimerId) -> bool {
+        self.get(id).map_or(false, |running| running.paused)
+    }
+
+    /// Returns the time left before a timer fires next.
+    pub fn remaining(&self, id: TimerId) -> Option<Duration> {
+        self.get(id).map(|running| {
+            running
+                .timer
+                .duration
+                .checked_sub(running.elapsed)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the real time left before the next timer fires, or `None` if no timer will fire,
+    /// e.g. because game time is stopped.
+    ///
+    /// The last frame of `time` and the idle time, which the timers count on the next `tick`,
+    /// are taken into account.
+    pub fn next_deadline(&self, time: &Time) -> Option<Duration> {
+        let time_scale = time.time_scale();
+        let real_pending = time.delta_real_time() + self.idle;
+        let game_pending =
+            time.delta_time() + secs_to_duration(duration_to_secs(self.idle) * time_scale);
+        self.timers
+            .iter()
+            .filter(|running| !running.paused)
+            .filter_map(|running| {
+                let remaining = |pending| {
+                    running
+                        .timer
+                        .duration
+                        .checked_sub(running.elapsed + pending)
+                        .unwrap_or_default()
+                };
+                match running.timer.clock {
+                    TimerClock::Real => Some(remaining(real_pending)),
+                    TimerClock::Game if time_scale > 0. => Some(secs_to_duration(
+                        duration_to_secs(remaining(game_pending)) / time_scale,
+                    )),
+                    TimerClock::Game => None,
+                }
+            })
+            .min()
+    }
+
+    /// Adds real time the application slept between frames, which isn't part of the frames of
+    /// `Time`. Timers count it on the next `tick`, game timers scaled by the time scale.
+    ///
+    /// Called by an event driven application after it slept.
+    pub fn add_idle_time(&mut self, idle: Duration) {
+        self.idle += idle;
+    }
+
+    /// Advances the timers by the last frame of `time`, returning the callbacks of the timers
+    /// which fired, in order. A repeating timer fires as many times as its interval elapsed.
+    ///
+    /// Timers attached to deleted entities are cancelled.
+    pub fn tick(&mut self, time: &Time, entities: &EntitiesRes) -> Vec<TimerCallback> {
+        self.timers.retain(|running| {
+            running
+                .timer
+                .entity
+                .map_or(true, |entity| entities.is_alive(entity))
+        });
+
+        let idle = std::mem::replace(&mut self.idle, Duration::from_secs(0));
+        let game_idle = secs_to_duration(duration_to_secs(idle) * time.time_scale());
+        let mut fired = Vec::new();
+        for running in self.timers.iter_mut().filter(|running| !running.paused) {
+            running.elapsed += match running.timer.clock {
+                TimerClock::Game => time.delta_time() + game_idle,
+                TimerClock::Real => time.delta_real_time() + idle,
+            };
+            while running.elapsed >= running.timer.duration {
+                fired.extend(running.timer.callback.clone());
+                if !running.timer.repeat {
+                    running.finished = true;
+                    break;
+                }
+                if running.timer.duration == Duration::from_secs(0) {
+                    running.elapsed = Duration::from_secs(0);
+                    break;
+                }
+                running.elapsed -= running.timer.duration;
+            }
+        }
+        self.timers.retain(|running| !running.finished);
+        fired
+    }
+
+    /// Ticks the `Timers` of the `World` and runs the callbacks of the timers which fired.
+    ///
+    /// Called by the application at the start of every frame.
+    pub fn run(world: &mut World) {
+        let fired = match world.try_fetch_mut::<Timers>() {
+            Some(mut timers) => timers.tick(&world.read_resource::<Time>(), &world.entities()),
+            None => return,
+        };
+        for callback in fired {
+            callback(world);
+        }
+    }
+
+    fn get(&self, id: TimerId) -> Option<&RunningTimer> {
+        self.timers.iter().find(|running| running.id == id)
+    }
+
+    fn get_mut(&mut self, id: TimerId) -> Option<&mut RunningTimer> {
+        self.timers.iter_mut().find(|running| running.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_after(delta: Duration, time_scale: f32) -> World {
+        let mut world = World::new();
+        let mut time = Time::default();
+        time.set_time_scale(time_scale);
+        time.set_delta_time(delta);
+        world.insert(time);
+        world.insert(Timers::default());
+        world.insert(0u32);
+        world
+    }
+
+    fn count(world: &mut World) {
+        *world.write_resource::<u32>() += 1;
+    }
+
+    #[test]
+    fn repeating_timers_fire_every_interval() {
+        let mut world = world_after(Duration::from_millis(250), 1.0);
+        let id = world
+            .write_resource::<Timers>()
+            .start(Timer::repeating(Duration::from_millis(100)).calling(count));
+
+        Timers::run(&mut world);
+        assert_eq!(*world.read_resource::<u32>(), 2);
+        assert_eq!(
+            world.read_resource::<Timers>().remaining(id),
+            Some(Duration::from_millis(50))
+        );
+
+        world.write_resource::<Timers>().pause(id);
+        Timers::run(&mut world);
+        assert_eq!(*world.read_resource::<u32>(), 2);
+    }
+
+    #[test]
+    fn game_timers_respect_the_time_scale() {
+        let mut world = world_after(Duration::from_millis(100), 0.0);
+        let (game, real) = {
+            let mut timers = world.write_resource::<Timers>();
+            let game = timers.start(Timer::once(Duration::from_millis(50)).calling(count));
+            let real = timers.start(
+                Timer::once(Duration::from_millis(50))
+                    .real_time()
+                    .calling(count),
+            );
+            (game, real)
+        };
+
+        Timers::run(&mut world);
+        assert_eq!(*world.read_resource::<u32>(), 1);
+        let timers = world.read_resource::<Timers>();
+        assert!(timers.is_running(game));
+        assert!(!timers.is_running(real));
+        assert_eq!(timers.next_deadline(&world.read_resource::<Time>()), None);
+    }
+
+    #[test]
+    fn idle_time_only_counts_for_the_next_tick() {
+        let mut world = world_after(Duration::from_millis(0), 0.5);
+        let (game, real) = {
+            let mut timers = world.write_resource::<Timers>();
+            timers.start(Timer::once(Duration::from_millis(40)).calling(count));
+            let game = timers.start(Timer::once(Duration::from_millis(60)).calling(count));
+            let real = timers.start(
+                Timer::once(Duration::from_millis(150))
+                    .real_time()
+                    .calling(count),
+            );
+            timers.add_idle_time(Duration::from_millis(100));
+            (game, real)
+        };
+
+        Timers::run(&mut world);
+        Timers::run(&mut world);
+        assert_eq!(*world.read_resource::<u32>(), 1);
+        let timers = world.read_resource::<Timers>();
+        assert!(timers.is_running(game));
+        assert_eq!(timers.remaining(real), Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn next_deadline_counts_the_time_not_ticked_yet() {
+        let world = world_after(Duration::from_millis(100), 0.5);
+        let mut timers = world.write_resource::<Timers>();
+        let game = timers.start(Timer::once(Duration::from_millis(100)));
+        timers.start(Timer::once(Duration::from_millis(300)).real_time());
+        timers.add_idle_time(Duration::from_millis(60));
+        let time = world.read_resource::<Time>();
+
+        // 50ms of game time passed during the frame and 30ms while idle, 20ms are left.
+        let deadline = timers.next_deadline(&time).unwrap();
+        assert!(deadline > Duration::from_millis(39) && deadline < Duration::from_millis(41));
+        timers.cancel(game);
+        assert_eq!(
+            timers.next_deadline(&time),
+            Some(Duration::from_millis(140))
+        );
+    }
+
+    #[test]
+    fn attached_timers_are_cancelled_with_their_entity() {
+        let mut world = world_after(Duration::from_millis(100), 1.0);
+        let entity = world.create_entity().build();
+        let id = world.write_resource::<Timers>().start(
+            Timer::once(Duration::from_millis(50))
+                .attached_to(entity)
+                .calling(count),
+        );
+        world.delete_entity(entity).unwrap();
+
+        Timers::run(&mut world);
+        assert_eq!(*world.read_resource::<u32>(), 0);
+        assert!(!world.read_resource::<Timers>().is_running(id));
+    }
+}
@@ -1,4 +1,8 @@
 use crate::ecs::prelude::{Component, DenseVecStorage, Entity, FlaggedStorage};
+#[cfg(feature = "saveload")]
+use crate::ecs::{error::NoError, saveload::ConvertSaveload};
+#[cfg(feature = "saveload")]
+use serde::{de::DeserializeOwned, Serialize};
 
 pub use specs_hierarchy::HierarchyEvent;
 use specs_hierarchy::{Hierarchy, Parent as HParent};
@@ -32,3 +36,27 @@ impl HParent for Parent {
         self.entity
     }
 }
+
+/// Saves the parent entity as its marker, see `snapshot::SnapshotSchema`.
+#[cfg(feature = "saveload")]
+impl<M> ConvertSaveload<M> for Parent
+where
+    M: Serialize + DeserializeOwned,
+{
+    type Data = M;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        self.entity.convert_into(ids)
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Entity::convert_from(data, ids).map(Parent::new)
+    }
+}
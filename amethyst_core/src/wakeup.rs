@@ -0,0 +1,282 @@
+//! Waking an event driven application which sleeps until something happens.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+    time::Instant,
+};
+
+use crate::ecs::World;
+
+type WakeHook = Box<dyn Fn() + Send>;
+
+thread_local! {
+    /// The event pump of the application running on this thread, with the signal of its
+    /// `Wakeup`. It can't be a resource, as event pumps like window event loops aren't `Send`.
+    static EVENT_PUMP: RefCell<Option<(Weak<Signal>, Box<dyn EventPump>)>> = RefCell::new(None);
+}
+
+#[derive(Default)]
+struct Signal {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+    hook: Mutex<Option<WakeHook>>,
+}
+
+impl Signal {
+    fn woken(&self) -> MutexGuard<'_, bool> {
+        self.woken.lock().expect("Wakeup mutex poisoned")
+    }
+}
+
+/// A handle waking an event driven application from any thread, see `Wakeup`.
+#[derive(Clone)]
+pub struct Waker {
+    signal: Arc<Signal>,
+}
+
+impl fmt::Debug for Waker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Waker").finish()
+    }
+}
+
+impl Waker {
+    /// Wakes the application, which runs its next frame as soon as possible.
+    pub fn wake(&self) {
+        *self.signal.woken() = true;
+        self.signal.condvar.notify_all();
+        if let Some(hook) = &*self.signal.hook.lock().expect("Wakeup mutex poisoned") {
+            hook();
+        }
+    }
+}
+
+/// How long an idle application may block before the next frame, see `Wakeup::idle`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Idle {
+    /// Block until woken.
+    UntilWoken,
+    /// Block until woken, or until the instant is reached.
+    Until(Instant),
+}
+
+/// Blocks an idle event driven application until an event arrives, like a window event loop.
+///
+/// Registered with `Wakeup::set_event_pump`.
+pub trait EventPump {
+    /// Blocks until an event arrives, the pump is woken or the deadline of `idle` is reached,
+    /// then writes the events which arrived to the `World`.
+    fn wait(&mut self, idle: Idle, world: &World);
+}
+
+/// Resource controlling when an event driven application runs its next frame.
+///
+/// An event driven application, built with `ApplicationBuilder::event_driven`, sleeps after a
+/// frame until it is woken by a window event, a `TransEvent`, a `CallbackQueue` callback, a
+/// timer, a `Waker` or a redraw request. Other applications run frames continuously, and
+/// ignore this resource.
+///
+/// With a window, the events loop of the `EventsLoopSystem` is the event pump: the application
+/// blocks on the window events instead of the `Wakeup`, and wakes turn into window wake ups.
+///
+/// # Examples
+///
+/// ```rust
+/// use amethyst_core::{ecs::prelude::*, Wakeup};
+///
+/// // Runs one more frame after this one, e.g. while an animation plays.
+/// fn keep_animating(world: &World) {
+///     world.write_resource::<Wakeup>().request_redraw();
+/// }
+/// ```
+pub struct Wakeup {
+    signal: Arc<Signal>,
+    event_driven: bool,
+    redraw_requested: bool,
+}
+
+impl fmt::Debug for Wakeup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wakeup")
+            .field("event_driven", &self.event_driven)
+            .field("redraw_requested", &self.redraw_requested)
+            .finish()
+    }
+}
+
+impl Default for Wakeup {
+    fn default() -> Self {
+        Wakeup {
+            signal: Arc::new(Signal::default()),
+            event_driven: false,
+            redraw_requested: false,
+        }
+    }
+}
+
+impl Wakeup {
+    /// Sets whether the application is event driven. Called by the application builder.
+    pub fn set_event_driven(&mut self, event_driven: bool) {
+        self.event_driven = event_driven;
+    }
+
+    /// Returns whether the application sleeps between frames until it is woken.
+    pub fn is_event_driven(&self) -> bool {
+        self.event_driven
+    }
+
+    /// Returns a handle waking the application from any thread.
+    pub fn waker(&self) -> Waker {
+        Waker {
+            signal: self.signal.clone(),
+        }
+    }
+
+    /// Requests another frame after the current one.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    /// Returns whether another frame was requested, clearing the request.
+    pub fn take_redraw_request(&mut self) -> bool {
+        let requested = self.redraw_requested;
+        self.redraw_requested = false;
+        requested
+    }
+
+    /// Registers the event pump, which blocks instead of the `Wakeup` while the application is
+    /// idle, and is woken with `wake` by every `Waker`.
+    ///
+    /// The pump is kept by the current thread, which has to be the thread running the
+    /// application.
+    pub fn set_event_pump<P, F>(&mut self, pump: P, wake: F)
+    where
+        P: EventPump + 'static,
+        F: Fn() + Send + 'static,
+    {
+        let signal = Arc::downgrade(&self.signal);
+        EVENT_PUMP.with(|slot| *slot.borrow_mut() = Some((signal, Box::new(pump))));
+        *self.signal.hook.lock().expect("Wakeup mutex poisoned") = Some(Box::new(wake));
+    }
+
+    /// Sleeps until the application is woken or `idle` elapsed, blocking in the event pump if
+    /// there is one, unless the application was woken since the last call.
+    ///
+    /// Called by an event driven application after a frame, when nothing is pending.
+    pub fn idle(&mut self, idle: Idle, world: &World) {
+        if std::mem::replace(&mut *self.signal.woken(), false) {
+            return;
+        }
+        let signal = &self.signal;
+        let pumped = EVENT_PUMP.with(|slot| match &mut *slot.borrow_mut() {
+            Some((owner, pump)) if owns(owner, signal) => {
+                pump.wait(idle, world);
+                true
+            }
+            _ => false,
+        });
+        if pumped {
+            *self.signal.woken() = false;
+            return;
+        }
+
+        let mut woken = self.signal.woken();
+        while !*woken {
+            woken = match idle {
+                Idle::UntilWoken => self
+                    .signal
+                    .condvar
+                    .wait(woken)
+                    .expect("Wakeup mutex poisoned"),
+                Idle::Until(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    self.signal
+                        .condvar
+                        .wait_timeout(woken, deadline - now)
+                        .expect("Wakeup mutex poisoned")
+                        .0
+                }
+            };
+        }
+        *woken = false;
+    }
+}
+
+impl Drop for Wakeup {
+    fn drop(&mut self) {
+        // The thread may be exiting, when its event pump is already gone.
+        let _ = EVENT_PUMP.try_with(|slot| {
+            let mut slot = slot.borrow_mut();
+            if slot
+                .as_ref()
+                .map_or(false, |(owner, _)| owns(owner, &self.signal))
+            {
+                *slot = None;
+            }
+        });
+    }
+}
+
+/// Returns whether the event pump registered with `owner` belongs to `signal`.
+fn owns(owner: &Weak<Signal>, signal: &Arc<Signal>) -> bool {
+    owner
+        .upgrade()
+        .map_or(false, |owner| Arc::ptr_eq(&owner, signal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn wakers_wake_an_idle_application() {
+        let world = World::new();
+        let mut wakeup = Wakeup::default();
+        let waker = wakeup.waker();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            waker.wake();
+        });
+        wakeup.idle(Idle::UntilWoken, &world);
+        handle.join().unwrap();
+
+        let start = Instant::now();
+        wakeup.idle(Idle::Until(start + Duration::from_millis(10)), &world);
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    /// Records how often it waited.
+    struct CountWaits(Arc<Mutex<usize>>);
+
+    impl EventPump for CountWaits {
+        fn wait(&mut self, _: Idle, _: &World) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn event_pumps_block_instead_of_the_application() {
+        let world = World::new();
+        let waits = Arc::new(Mutex::new(0));
+        let mut wakeup = Wakeup::default();
+        wakeup.set_event_pump(CountWaits(waits.clone()), || {});
+        wakeup.idle(Idle::UntilWoken, &world);
+        assert_eq!(*waits.lock().unwrap(), 1);
+
+        // A wake between frames skips the next wait.
+        wakeup.waker().wake();
+        wakeup.idle(Idle::UntilWoken, &world);
+        assert_eq!(*waits.lock().unwrap(), 1);
+
+        // Dropping the `Wakeup` drops its event pump.
+        drop(wakeup);
+        EVENT_PUMP.with(|slot| assert!(slot.borrow().is_none()));
+    }
+}
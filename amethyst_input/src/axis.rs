@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use super::{Button, ControllerAxis};
+use super::{
+    response::{radial_dead_zone, AxisResponse},
+    Button, ControllerAxis,
+};
 
 /// Represents any input represented by a float value from -1 to 1.
 /// Retrieve the value of this with [axis_value](struct.InputHandler.html#method.axis_value).
@@ -33,4 +36,53 @@ pub enum Axis {
         /// You almost always want this false.
         horizontal: bool,
     },
+    /// Represents one direction of an on-screen touch stick.
+    /// See `InputHandler::insert_touch_stick`.
+    TouchStick {
+        /// The id the stick was inserted with in the `InputHandler`.
+        stick: u32,
+        /// If this value is true then this axis is for the vertical direction of the stick,
+        /// which is positive downward.
+        vertical: bool,
+    },
+}
+
+/// Represents a pair of axes read together as a 2D vector, like a controller stick.
+///
+/// The value of a 2D axis has a radial dead zone, which unlike the dead zones of each `Axis`
+/// doesn't snap diagonal movements to the axes. The dead zones of the `x` and `y` axes are
+/// ignored. Retrieve the value of this with
+/// [axis_2d_value](struct.InputHandler.html#method.axis_2d_value).
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Axis2D {
+    /// The horizontal axis.
+    pub x: Axis,
+    /// The vertical axis.
+    pub y: Axis,
+    /// Treat inputs with a length up to `dead_zone` as 0, linearly interpolate the remaining
+    /// lengths.
+    #[serde(default)]
+    pub dead_zone: f32,
+    /// The response applied to the length of the input, after the dead zone.
+    #[serde(default)]
+    pub response: AxisResponse,
+}
+
+impl Axis2D {
+    /// Creates a 2D axis with no dead zone and a linear response.
+    pub fn new(x: Axis, y: Axis) -> Self {
+        Axis2D {
+            x,
+            y,
+            dead_zone: 0.0,
+            response: AxisResponse::default(),
+        }
+    }
+
+    /// Applies the radial dead zone and the response to a 2D input.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y, magnitude) = radial_dead_zone(x, y, self.dead_zone);
+        let magnitude = self.response.apply(magnitude);
+        (x * magnitude, y * magnitude)
+    }
 }
@@ -12,7 +12,7 @@ use fnv::FnvHashMap as HashMap;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
-use super::{Axis, Button};
+use super::{ActionTrigger, Axis, Axis2D, AxisResponse, Button};
 
 /// Define a set of types used for bindings configuration.
 /// Usually defaulted to `StringBindings`, which uses `String`s.
@@ -103,7 +103,20 @@ impl BindingTypes for StringBindings {
 ///     actions: {
 ///         "fire": [ [Mouse(Left)], [Key(X)] ], // Multiple bindings for one action
 ///         "reload": [ [Key(LControl), Key(R)] ] // Combinations of multiple bindings possible
-///     }
+///     },
+///     triggers: {
+///         "quick_reload": DoubleTap(action: "reload", ms: 300), // Optional, see `ActionTrigger`
+///     },
+///     axes_2d: { // Optional, see `Axis2D`
+///         "move": (
+///             x: Controller(controller_id: 0, axis: LeftX, invert: false, dead_zone: 0.0),
+///             y: Controller(controller_id: 0, axis: LeftY, invert: true, dead_zone: 0.0),
+///             dead_zone: 0.2,
+///         ),
+///     },
+///     responses: {
+///         "updown": (ramp_ms: 150), // Optional, see `AxisResponse`
+///     },
 /// )
 /// ```
 #[derive(Derivative, Serialize, Deserialize)]
@@ -119,6 +132,15 @@ pub struct Bindings<T: BindingTypes> {
     /// So for example if you want to quit by either "Esc" or "Ctrl+q" you would have
     /// `[[Esc], [Ctrl, Q]]`.
     pub(super) actions: HashMap<T::Action, SmallVec<[SmallVec<[Button; 2]>; 4]>>,
+    /// Triggered actions, fired on timed patterns of other actions. See `ActionTrigger`.
+    #[serde(default)]
+    pub(super) triggers: HashMap<T::Action, ActionTrigger<T::Action>>,
+    /// Pairs of axes read as 2D vectors. See `Axis2D`.
+    #[serde(default)]
+    pub(super) axes_2d: HashMap<T::Axis, Axis2D>,
+    /// Responses of axes to their input. See `AxisResponse`.
+    #[serde(default)]
+    pub(super) responses: HashMap<T::Axis, AxisResponse>,
 }
 
 /// An enum of possible errors that can occur when binding an action or axis.
@@ -140,6 +162,8 @@ pub enum BindingError<T: BindingTypes> {
     /// That specific axis on that specific controller is already in use for an
     /// axis binding.
     ControllerAxisAlreadyBound(T::Axis),
+    /// That direction of that touch stick is already in use for an axis binding.
+    TouchStickAxisAlreadyBound(T::Axis),
 }
 
 impl<T: BindingTypes> PartialEq for BindingError<T> {
@@ -169,6 +193,10 @@ impl<T: BindingTypes> PartialEq for BindingError<T> {
                 BindingError::MouseWheelAxisAlreadyBound(a),
                 BindingError::MouseWheelAxisAlreadyBound(x),
             ) => a == x,
+            (
+                BindingError::TouchStickAxisAlreadyBound(a),
+                BindingError::TouchStickAxisAlreadyBound(x),
+            ) => a == x,
             (_, _) => false,
         }
     }
@@ -208,6 +236,9 @@ where
             BindingError::MouseWheelAxisAlreadyBound(ref id) => {
                 write!(f, "Mouse wheel axis provided is already in use by {}", id)
             }
+            BindingError::TouchStickAxisAlreadyBound(ref id) => {
+                write!(f, "Touch stick axis provided is already in use by {}", id)
+            }
         }
     }
 }
@@ -377,6 +408,100 @@ impl<T: BindingTypes> Bindings<T> {
         self.actions.keys()
     }
 
+    /// Assign a trigger to a triggered action.
+    ///
+    /// This will replace the trigger of the action, if any, and return it.
+    pub fn insert_trigger(
+        &mut self,
+        id: T::Action,
+        trigger: ActionTrigger<T::Action>,
+    ) -> Option<ActionTrigger<T::Action>> {
+        self.triggers.insert(id, trigger)
+    }
+
+    /// Removes the trigger of a triggered action, returning it if successful.
+    pub fn remove_trigger<A>(&mut self, id: &A) -> Option<ActionTrigger<T::Action>>
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.triggers.remove(id)
+    }
+
+    /// Returns the trigger of a triggered action.
+    pub fn trigger<A>(&self, id: &A) -> Option<&ActionTrigger<T::Action>>
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.triggers.get(id)
+    }
+
+    /// Gets a list of all triggered actions
+    pub fn triggers(&self) -> impl Iterator<Item = &T::Action> {
+        self.triggers.keys()
+    }
+
+    /// Assign a 2D axis to an ID value.
+    ///
+    /// 2D axes have their own IDs, apart from the IDs of axes. This will replace the 2D axis at
+    /// that id, if any, and return it.
+    pub fn insert_axis_2d<A: Into<T::Axis>>(&mut self, id: A, axis: Axis2D) -> Option<Axis2D> {
+        self.axes_2d.insert(id.into(), axis)
+    }
+
+    /// Removes a 2D axis, this will return the removed 2D axis if successful.
+    pub fn remove_axis_2d<A>(&mut self, id: &A) -> Option<Axis2D>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.axes_2d.remove(id)
+    }
+
+    /// Returns a reference to a 2D axis.
+    pub fn axis_2d<A>(&self, id: &A) -> Option<&Axis2D>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.axes_2d.get(id)
+    }
+
+    /// Gets a list of all 2D axes
+    pub fn axes_2d(&self) -> impl Iterator<Item = &T::Axis> {
+        self.axes_2d.keys()
+    }
+
+    /// Sets how an axis responds to its input.
+    ///
+    /// This will replace the response of the axis, if any, and return it.
+    pub fn insert_response<A: Into<T::Axis>>(
+        &mut self,
+        id: A,
+        response: AxisResponse,
+    ) -> Option<AxisResponse> {
+        self.responses.insert(id.into(), response)
+    }
+
+    /// Removes the response of an axis, making it linear again.
+    pub fn remove_response<A>(&mut self, id: &A) -> Option<AxisResponse>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.responses.remove(id)
+    }
+
+    /// Returns the response of an axis.
+    pub fn response<A>(&self, id: &A) -> Option<&AxisResponse>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.responses.get(id)
+    }
+
     /// Check that this structure upholds its guarantees. Should only be necessary when serializing or deserializing the bindings.
     pub fn check_invariants(&mut self) -> Result<(), BindingError<T>> {
         // The easiest way to do this is to use the existing code that checks for invariants when adding bindings.
@@ -406,39 +531,147 @@ impl<T: BindingTypes> Bindings<T> {
         Ok(())
     }
 
-    fn check_action_invariants(
-        &self,
-        id: &T::Action,
-        bind: &[Button],
-    ) -> Result<(), BindingError<T>> {
-        // Guarantee each button is unique.
-        for i in 0..bind.len() {
-            for j in (i + 1)..bind.len() {
-                if bind[i] == bind[j] {
-                    return Err(BindingError::ComboContainsDuplicates(id.clone()));
-                }
+    /// Rebinds an action, replacing its `old` combination with the `new` one.
+    ///
+    /// If the new combination conflicts with existing bindings, the old one is kept and every
+    /// conflict is returned, to be reported to the player. If `old` isn't bound to the action,
+    /// the new combination is simply added.
+    pub fn rebind_action<B: IntoIterator<Item = Button>>(
+        &mut self,
+        id: T::Action,
+        old: &[Button],
+        new: B,
+    ) -> Result<(), Vec<BindingError<T>>> {
+        let new: SmallVec<[Button; 2]> = new.into_iter().collect();
+        let removed = self.remove_action_binding(&id, old).is_ok();
+        let conflicts = self.action_conflicts(&id, &new);
+        if conflicts.is_empty() {
+            self.actions.entry(id).or_default().push(new);
+            Ok(())
+        } else {
+            if removed {
+                self.actions
+                    .entry(id)
+                    .or_default()
+                    .push(old.iter().cloned().collect());
             }
+            Err(conflicts)
+        }
+    }
+
+    /// Rebinds an axis, returning the replaced axis if any.
+    ///
+    /// If the new axis conflicts with existing bindings, the old one is kept and every conflict
+    /// is returned, to be reported to the player.
+    pub fn rebind_axis<A: Into<T::Axis>>(
+        &mut self,
+        id: A,
+        axis: Axis,
+    ) -> Result<Option<Axis>, Vec<BindingError<T>>> {
+        let id = id.into();
+        let conflicts = self.axis_conflicts(&id, &axis);
+        if conflicts.is_empty() {
+            Ok(self.axes.insert(id, axis))
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    /// Layers overrides, e.g. the player's rebinds, over these bindings.
+    ///
+    /// Each axis, action, trigger and response of the overrides replaces the one with the same
+    /// id. An action overridden with no combinations is removed.
+    pub fn overlay(&mut self, overrides: Bindings<T>) {
+        self.axes.extend(overrides.axes);
+        self.axes_2d.extend(overrides.axes_2d);
+        self.responses.extend(overrides.responses);
+        for (id, combinations) in overrides.actions {
+            if combinations.is_empty() {
+                self.actions.remove(&id);
+            } else {
+                self.actions.insert(id, combinations);
+            }
+        }
+        self.triggers.extend(overrides.triggers);
+    }
+
+    /// Returns the overrides turning `defaults` into these bindings, to be saved as the player's
+    /// rebinds and layered over the defaults with `overlay` on the next run.
+    ///
+    /// Removed actions are overridden with no combinations. Removed axes, triggers and responses
+    /// can't be expressed as overrides and are ignored.
+    pub fn overrides(&self, defaults: &Bindings<T>) -> Bindings<T> {
+        let mut overrides = Bindings::new();
+        for (id, axis) in &self.axes {
+            if defaults.axes.get(id) != Some(axis) {
+                overrides.axes.insert(id.clone(), axis.clone());
+            }
+        }
+        for (id, combinations) in &self.actions {
+            let unchanged = defaults.actions.get(id).map_or(false, |default| {
+                default.len() == combinations.len()
+                    && default
+                        .iter()
+                        .all(|d| combinations.iter().any(|c| same_combination(c, d)))
+            });
+            if !unchanged {
+                overrides.actions.insert(id.clone(), combinations.clone());
+            }
+        }
+        for id in defaults.actions.keys() {
+            if !self.actions.contains_key(id) {
+                overrides.actions.insert(id.clone(), SmallVec::new());
+            }
+        }
+        for (id, trigger) in &self.triggers {
+            if defaults.triggers.get(id) != Some(trigger) {
+                overrides.triggers.insert(id.clone(), trigger.clone());
+            }
+        }
+        for (id, axis) in &self.axes_2d {
+            if defaults.axes_2d.get(id) != Some(axis) {
+                overrides.axes_2d.insert(id.clone(), axis.clone());
+            }
+        }
+        for (id, response) in &self.responses {
+            if defaults.responses.get(id) != Some(response) {
+                overrides.responses.insert(id.clone(), response.clone());
+            }
+        }
+        overrides
+    }
+
+    /// Returns every conflict binding the combination to the action would cause.
+    ///
+    /// An empty list means `insert_action_binding` would succeed.
+    pub fn action_conflicts(&self, id: &T::Action, bind: &[Button]) -> Vec<BindingError<T>> {
+        let mut conflicts = Vec::new();
+        // Guarantee each button is unique.
+        if (0..bind.len()).any(|i| bind[i + 1..].contains(&bind[i])) {
+            conflicts.push(BindingError::ComboContainsDuplicates(id.clone()));
         }
         if bind.len() == 1 {
             for (k, a) in self.axes.iter() {
                 if let Axis::Emulated { pos, neg } = a {
                     if bind[0] == *pos || bind[0] == *neg {
-                        return Err(BindingError::ButtonBoundToAxis(k.clone(), a.clone()));
+                        conflicts.push(BindingError::ButtonBoundToAxis(k.clone(), a.clone()));
                     }
                 }
             }
         }
         for (k, a) in self.actions.iter() {
-            for c in a {
-                if c.len() == bind.len() && bind.iter().all(|bind| c.iter().any(|c| c == bind)) {
-                    return Err(BindingError::ComboAlreadyBound(k.clone()));
-                }
+            if a.iter().any(|c| same_combination(c, bind)) {
+                conflicts.push(BindingError::ComboAlreadyBound(k.clone()));
             }
         }
-        Ok(())
+        conflicts
     }
 
-    fn check_axis_invariants(&self, id: &T::Axis, axis: &Axis) -> Result<(), BindingError<T>> {
+    /// Returns every conflict binding the axis to the id would cause.
+    ///
+    /// An empty list means `insert_axis` would succeed.
+    pub fn axis_conflicts(&self, id: &T::Axis, axis: &Axis) -> Vec<BindingError<T>> {
+        let mut conflicts = Vec::new();
         match axis {
             Axis::Emulated {
                 pos: ref axis_pos,
@@ -448,7 +681,7 @@ impl<T: BindingTypes> Bindings<T> {
                     if let Axis::Emulated { pos, neg } = a {
                         if axis_pos == pos || axis_pos == neg || axis_neg == pos || axis_neg == neg
                         {
-                            return Err(BindingError::AxisButtonAlreadyBoundToAxis(
+                            conflicts.push(BindingError::AxisButtonAlreadyBoundToAxis(
                                 k.clone(),
                                 a.clone(),
                             ));
@@ -459,7 +692,7 @@ impl<T: BindingTypes> Bindings<T> {
                     for c in a {
                         // Since you can't bind combos to an axis we only need to check combos with length 1.
                         if c.len() == 1 && (c[0] == *axis_pos || c[0] == *axis_neg) {
-                            return Err(BindingError::AxisButtonAlreadyBoundToAction(
+                            conflicts.push(BindingError::AxisButtonAlreadyBoundToAction(
                                 k.clone(),
                                 c[0],
                             ));
@@ -480,7 +713,7 @@ impl<T: BindingTypes> Bindings<T> {
                     } = a
                     {
                         if controller_id == input_controller_id && axis == input_axis {
-                            return Err(BindingError::ControllerAxisAlreadyBound(k.clone()));
+                            conflicts.push(BindingError::ControllerAxisAlreadyBound(k.clone()));
                         }
                     }
                 }
@@ -491,16 +724,51 @@ impl<T: BindingTypes> Bindings<T> {
                 for (k, a) in self.axes.iter().filter(|(k, _a)| *k != id) {
                     if let Axis::MouseWheel { horizontal } = a {
                         if input_horizontal == horizontal {
-                            return Err(BindingError::MouseWheelAxisAlreadyBound(k.clone()));
+                            conflicts.push(BindingError::MouseWheelAxisAlreadyBound(k.clone()));
+                        }
+                    }
+                }
+            }
+            Axis::TouchStick {
+                stick: ref input_stick,
+                vertical: ref input_vertical,
+            } => {
+                for (k, a) in self.axes.iter().filter(|(k, _a)| *k != id) {
+                    if let Axis::TouchStick { stick, vertical } = a {
+                        if input_stick == stick && input_vertical == vertical {
+                            conflicts.push(BindingError::TouchStickAxisAlreadyBound(k.clone()));
                         }
                     }
                 }
             }
         }
-        Ok(())
+        conflicts
+    }
+
+    fn check_action_invariants(
+        &self,
+        id: &T::Action,
+        bind: &[Button],
+    ) -> Result<(), BindingError<T>> {
+        match self.action_conflicts(id, bind).into_iter().next() {
+            Some(conflict) => Err(conflict),
+            None => Ok(()),
+        }
+    }
+
+    fn check_axis_invariants(&self, id: &T::Axis, axis: &Axis) -> Result<(), BindingError<T>> {
+        match self.axis_conflicts(id, axis).into_iter().next() {
+            Some(conflict) => Err(conflict),
+            None => Ok(()),
+        }
     }
 }
 
+/// Returns whether both combinations have the same buttons, in any order.
+fn same_combination(a: &[Button], b: &[Button]) -> bool {
+    a.len() == b.len() && b.iter().all(|b| a.contains(b))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -875,4 +1143,129 @@ mod tests {
             Some(Axis::MouseWheel { horizontal: false })
         );
     }
+
+    #[test]
+    fn rebind_reports_every_conflict() {
+        let mut bindings = Bindings::<StringBindings>::new();
+        bindings
+            .insert_axis(
+                String::from("updown"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::W),
+                    neg: Button::Key(VirtualKeyCode::S),
+                },
+            )
+            .unwrap();
+        bindings
+            .insert_action_binding(
+                String::from("fire"),
+                [Button::Mouse(MouseButton::Left)].iter().cloned(),
+            )
+            .unwrap();
+        bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+
+        let conflicts = bindings
+            .rebind_action(
+                String::from("jump"),
+                &[Button::Key(VirtualKeyCode::Space)],
+                [Button::Key(VirtualKeyCode::W)].iter().cloned(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            conflicts,
+            vec![BindingError::ButtonBoundToAxis(
+                String::from("updown"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::W),
+                    neg: Button::Key(VirtualKeyCode::S),
+                },
+            )]
+        );
+        assert_eq!(
+            bindings.action_bindings("jump").collect::<Vec<_>>(),
+            vec![[Button::Key(VirtualKeyCode::Space)]]
+        );
+
+        let conflicts = bindings
+            .rebind_axis(
+                String::from("updown"),
+                Axis::Emulated {
+                    pos: Button::Mouse(MouseButton::Left),
+                    neg: Button::Key(VirtualKeyCode::Space),
+                },
+            )
+            .unwrap_err();
+        assert_eq!(conflicts.len(), 2);
+
+        bindings
+            .rebind_action(
+                String::from("jump"),
+                &[Button::Key(VirtualKeyCode::Space)],
+                [Button::Key(VirtualKeyCode::J)].iter().cloned(),
+            )
+            .unwrap();
+        assert_eq!(
+            bindings.action_bindings("jump").collect::<Vec<_>>(),
+            vec![[Button::Key(VirtualKeyCode::J)]]
+        );
+    }
+
+    #[test]
+    fn overrides_layer_over_defaults() {
+        let mut defaults = Bindings::<StringBindings>::new();
+        defaults
+            .insert_action_binding(
+                String::from("fire"),
+                [Button::Mouse(MouseButton::Left)].iter().cloned(),
+            )
+            .unwrap();
+        defaults
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+        defaults
+            .insert_action_binding(
+                String::from("crouch"),
+                [Button::Key(VirtualKeyCode::C)].iter().cloned(),
+            )
+            .unwrap();
+
+        let mut bindings = defaults.clone();
+        bindings
+            .rebind_action(
+                String::from("jump"),
+                &[Button::Key(VirtualKeyCode::Space)],
+                [Button::Key(VirtualKeyCode::J)].iter().cloned(),
+            )
+            .unwrap();
+        bindings
+            .remove_action_binding("crouch", &[Button::Key(VirtualKeyCode::C)])
+            .unwrap();
+
+        let overrides = bindings.overrides(&defaults);
+        let mut actions = overrides.actions().cloned().collect::<Vec<_>>();
+        actions.sort();
+        assert_eq!(actions, vec![String::from("crouch"), String::from("jump")]);
+        assert_eq!(overrides.action_bindings("crouch").next(), None);
+
+        let mut loaded = defaults.clone();
+        loaded.overlay(overrides);
+        assert_eq!(loaded.actions().count(), 2);
+        assert_eq!(
+            loaded.action_bindings("jump").collect::<Vec<_>>(),
+            vec![[Button::Key(VirtualKeyCode::J)]]
+        );
+        assert_eq!(
+            loaded.action_bindings("fire").collect::<Vec<_>>(),
+            vec![[Button::Mouse(MouseButton::Left)]]
+        );
+        assert_eq!(loaded.overrides(&bindings).actions().next(), None);
+    }
 }
@@ -1,6 +1,6 @@
 //! ECS input bundle
 
-use crate::{BindingError, BindingTypes, Bindings, InputSystemDesc};
+use crate::{BindingContext, BindingError, BindingTypes, Bindings, InputSystemDesc};
 use amethyst_config::{Config, ConfigError};
 use amethyst_core::{
     ecs::prelude::{DispatcherBuilder, World},
@@ -32,6 +32,7 @@ use crate::sdl_events_system::ControllerMappings;
 #[derivative(Default(bound = ""))]
 pub struct InputBundle<T: BindingTypes> {
     bindings: Option<Bindings<T>>,
+    contexts: Vec<(String, BindingContext<T>)>,
     #[cfg(feature = "sdl_controller")]
     controller_mappings: Option<ControllerMappings>,
 }
@@ -61,6 +62,48 @@ impl<T: BindingTypes> InputBundle<T> {
         Ok(self.with_bindings(bindings))
     }
 
+    /// Load the player's binding overrides from file, layered over the bindings loaded so far.
+    ///
+    /// Does nothing if the file doesn't exist yet. Overrides are saved by writing
+    /// `Bindings::overrides` to the file, see `Bindings::overlay`.
+    pub fn with_bindings_override_from_file<P: AsRef<Path>>(
+        mut self,
+        file: P,
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        Bindings<T>: Config,
+    {
+        if !file.as_ref().exists() {
+            return Ok(self);
+        }
+        let overrides = Bindings::load_no_fallback(file)?;
+        let mut bindings = self.bindings.take().unwrap_or_default();
+        bindings.overlay(overrides);
+        bindings.check_invariants()?;
+        Ok(self.with_bindings(bindings))
+    }
+
+    /// Registers a binding context with the `InputHandler`, to be pushed on its context stack
+    /// later on, e.g. by a menu state.
+    pub fn with_context<N: Into<String>>(mut self, name: N, context: BindingContext<T>) -> Self {
+        self.contexts.push((name.into(), context));
+        self
+    }
+
+    /// Load a binding context from file, see `with_context`.
+    pub fn with_context_from_file<N: Into<String>, P: AsRef<Path>>(
+        self,
+        name: N,
+        file: P,
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        BindingContext<T>: Config,
+    {
+        let mut context = BindingContext::load_no_fallback(file)?;
+        context.bindings.check_invariants()?;
+        Ok(self.with_context(name, context))
+    }
+
     /// Load SDL controller mappings from file
     #[cfg(feature = "sdl_controller")]
     pub fn with_sdl_controller_mappings(mut self, mappings: String) -> Self {
@@ -93,11 +136,11 @@ impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for InputBundle<T> {
                 SdlEventsSystem::<T>::new(world, self.controller_mappings).unwrap(),
             );
         }
-        builder.add(
-            InputSystemDesc::<T>::new(self.bindings).build(world),
-            "input_system",
-            &[],
+        let system_desc = self.contexts.into_iter().fold(
+            InputSystemDesc::<T>::new(self.bindings),
+            |desc, (name, context)| desc.with_context(name, context),
         );
+        builder.add(system_desc.build(world), "input_system", &[]);
         Ok(())
     }
 }
@@ -24,6 +24,10 @@ pub enum Button {
     /// A tuple of sequential controller_id in order of connection
     /// and specific type of used controller button.
     Controller(u32, ControllerButton),
+
+    /// On-screen touch button, by the id it was inserted with in the `InputHandler`.
+    /// See `InputHandler::insert_touch_button`.
+    Touch(u32),
 }
 
 impl From<VirtualKeyCode> for Button {
@@ -0,0 +1,78 @@
+//! Capture of the next input, to rebind actions and axes at runtime.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Axis, Button, ControllerAxis, ScrollDirection};
+
+/// How far a controller axis has to be moved to be captured.
+pub(crate) const AXIS_CAPTURE_THRESHOLD: f32 = 0.5;
+/// The dead zone of captured controller axes.
+pub(crate) const CAPTURED_DEAD_ZONE: f64 = 0.1;
+
+/// The kind of input an `InputHandler` listens for when capturing, see
+/// `InputHandler::start_capture`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureKind {
+    /// The next pressed button, including mouse wheel directions.
+    Button,
+    /// The next moved controller axis or mouse wheel.
+    Axis,
+    /// The next pressed button or moved axis, mouse wheel scrolls are captured as an axis.
+    Any,
+}
+
+impl CaptureKind {
+    pub(crate) fn accepts_buttons(self) -> bool {
+        self != CaptureKind::Axis
+    }
+
+    pub(crate) fn accepts_axes(self) -> bool {
+        self != CaptureKind::Button
+    }
+}
+
+/// An input captured by an `InputHandler`, ready to be bound.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CapturedInput {
+    /// A pressed button, to bind to an action or as half of an emulated axis.
+    Button(Button),
+    /// A moved axis, to bind to an axis.
+    Axis(Axis),
+}
+
+impl CapturedInput {
+    /// Captures a controller axis moved past the capture threshold, the axis is inverted if it was
+    /// moved in the negative direction.
+    pub(crate) fn controller_axis(
+        controller_id: u32,
+        axis: ControllerAxis,
+        value: f32,
+    ) -> Option<Self> {
+        if value.abs() < AXIS_CAPTURE_THRESHOLD {
+            return None;
+        }
+        Some(CapturedInput::Axis(Axis::Controller {
+            controller_id,
+            axis,
+            invert: value < 0.0,
+            dead_zone: CAPTURED_DEAD_ZONE,
+        }))
+    }
+
+    /// Captures a mouse wheel scroll, as a button or an axis.
+    pub(crate) fn mouse_wheel(kind: CaptureKind, delta_x: f32, delta_y: f32) -> Option<Self> {
+        let horizontal = delta_y == 0.0;
+        let direction = match (delta_x, delta_y) {
+            (_, dy) if dy > 0.0 => ScrollDirection::ScrollUp,
+            (_, dy) if dy < 0.0 => ScrollDirection::ScrollDown,
+            (dx, _) if dx > 0.0 => ScrollDirection::ScrollRight,
+            (dx, _) if dx < 0.0 => ScrollDirection::ScrollLeft,
+            _ => return None,
+        };
+        if kind.accepts_axes() {
+            Some(CapturedInput::Axis(Axis::MouseWheel { horizontal }))
+        } else {
+            Some(CapturedInput::Button(Button::MouseWheel(direction)))
+        }
+    }
+}
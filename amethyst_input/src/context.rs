@@ -0,0 +1,129 @@
+//! Binding contexts layered over the bindings of an `InputHandler`.
+
+use std::iter;
+
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+
+use super::{Axis, BindingTypes, Bindings, Button};
+
+/// How a `BindingContext` treats the inputs of the contexts below it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContextMode {
+    /// Buttons and axes bound in this context are hidden from the contexts below it, other inputs
+    /// still reach them.
+    ///
+    /// Actions and axes of this context also replace the ones with the same id below it.
+    Shadow,
+    /// Every input is consumed by this context, the contexts below it are inactive.
+    ///
+    /// Use this for menus or text entry, where gameplay should not react at all.
+    Consume,
+}
+
+impl Default for ContextMode {
+    fn default() -> Self {
+        ContextMode::Shadow
+    }
+}
+
+/// A named layer of bindings, pushed on the context stack of an `InputHandler`.
+///
+/// Example Ron config file of a text entry context:
+/// ```ron
+/// (
+///     mode: Consume,
+///     bindings: (
+///         axes: {},
+///         actions: {
+///             "confirm": [ [Key(Return)] ],
+///             "cancel": [ [Key(Escape)] ],
+///         },
+///     ),
+/// )
+/// ```
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct BindingContext<T: BindingTypes> {
+    /// How inputs of the contexts below are treated.
+    #[serde(default)]
+    pub mode: ContextMode,
+    /// The bindings active while the context is on the stack.
+    pub bindings: Bindings<T>,
+}
+
+impl<T: BindingTypes> BindingContext<T> {
+    /// Creates a context with the given mode and bindings.
+    pub fn new(mode: ContextMode, bindings: Bindings<T>) -> Self {
+        BindingContext { mode, bindings }
+    }
+
+    /// Creates a context hiding the inputs it binds from the contexts below it.
+    pub fn shadowing(bindings: Bindings<T>) -> Self {
+        BindingContext::new(ContextMode::Shadow, bindings)
+    }
+
+    /// Creates a context consuming every input.
+    pub fn consuming(bindings: Bindings<T>) -> Self {
+        BindingContext::new(ContextMode::Consume, bindings)
+    }
+}
+
+impl<T: BindingTypes> Bindings<T> {
+    /// Returns whether the button is part of an action combination or an emulated axis.
+    pub(crate) fn uses_button(&self, button: Button) -> bool {
+        self.actions
+            .values()
+            .flat_map(|combinations| combinations.iter())
+            .any(|combination| combination.contains(&button))
+            || self.bound_axes().any(|axis| match axis {
+                Axis::Emulated { pos, neg } => *pos == button || *neg == button,
+                _ => false,
+            })
+    }
+
+    /// Returns the axes, including the ones making up 2D axes.
+    fn bound_axes(&self) -> impl Iterator<Item = &Axis> {
+        self.axes.values().chain(
+            self.axes_2d
+                .values()
+                .flat_map(|axis| iter::once(&axis.x).chain(iter::once(&axis.y))),
+        )
+    }
+
+    /// Returns whether the analogue input of the axis is bound to an axis.
+    pub(crate) fn uses_axis_input(&self, input: &Axis) -> bool {
+        self.bound_axes().any(|axis| match (axis, input) {
+            (
+                Axis::Controller {
+                    controller_id,
+                    axis,
+                    ..
+                },
+                Axis::Controller {
+                    controller_id: input_controller_id,
+                    axis: input_axis,
+                    ..
+                },
+            ) => controller_id == input_controller_id && axis == input_axis,
+            (
+                Axis::MouseWheel { horizontal },
+                Axis::MouseWheel {
+                    horizontal: input_horizontal,
+                },
+            ) => horizontal == input_horizontal,
+            (
+                Axis::TouchStick { stick, vertical },
+                Axis::TouchStick {
+                    stick: input_stick,
+                    vertical: input_vertical,
+                },
+            ) => stick == input_stick && vertical == input_vertical,
+            _ => false,
+        })
+    }
+}
@@ -0,0 +1,219 @@
+//! Controller metadata, button names for prompts and rumble requests.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::controller::{ControllerAxis, ControllerButton};
+
+/// The family of a controller, which decides the names of its buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControllerKind {
+    /// An Xbox controller, or a controller following its layout.
+    Xbox,
+    /// A PlayStation controller.
+    PlayStation,
+    /// A Nintendo Switch controller.
+    Switch,
+    /// Any other controller, named like an Xbox controller, which the SDL controller model
+    /// follows.
+    Unknown,
+}
+
+impl Default for ControllerKind {
+    fn default() -> Self {
+        ControllerKind::Unknown
+    }
+}
+
+impl ControllerKind {
+    /// Guesses the kind of a controller from the name reported by its driver.
+    pub fn from_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        let any = |words: &[&str]| words.iter().any(|word| name.contains(word));
+        if any(&["xbox", "x-box", "xinput"]) {
+            ControllerKind::Xbox
+        } else if any(&["playstation", "ps3", "ps4", "ps5", "dualshock", "dualsense"]) {
+            ControllerKind::PlayStation
+        } else if any(&["switch", "nintendo", "joy-con"]) {
+            ControllerKind::Switch
+        } else {
+            ControllerKind::Unknown
+        }
+    }
+
+    /// Returns the name printed on a button of this kind of controller, for prompts.
+    ///
+    /// Buttons follow the SDL controller model, which is positional: `ControllerButton::A` is
+    /// the bottom face button, named "Cross" on PlayStation and "B" on Switch controllers.
+    pub fn button_name(self, button: ControllerButton) -> &'static str {
+        use self::{ControllerButton::*, ControllerKind::*};
+
+        match (self, button) {
+            (_, DPadUp) => "D-Pad Up",
+            (_, DPadDown) => "D-Pad Down",
+            (_, DPadLeft) => "D-Pad Left",
+            (_, DPadRight) => "D-Pad Right",
+            (PlayStation, A) => "Cross",
+            (PlayStation, B) => "Circle",
+            (PlayStation, X) => "Square",
+            (PlayStation, Y) => "Triangle",
+            (PlayStation, LeftShoulder) => "L1",
+            (PlayStation, RightShoulder) => "R1",
+            (PlayStation, LeftStick) => "L3",
+            (PlayStation, RightStick) => "R3",
+            (PlayStation, Back) => "Share",
+            (PlayStation, Start) => "Options",
+            (PlayStation, Guide) => "PS",
+            (Switch, A) => "B",
+            (Switch, B) => "A",
+            (Switch, X) => "Y",
+            (Switch, Y) => "X",
+            (Switch, LeftShoulder) => "L",
+            (Switch, RightShoulder) => "R",
+            (Switch, LeftStick) => "L Stick",
+            (Switch, RightStick) => "R Stick",
+            (Switch, Back) => "-",
+            (Switch, Start) => "+",
+            (Switch, Guide) => "Home",
+            (Xbox, A) | (Unknown, A) => "A",
+            (Xbox, B) | (Unknown, B) => "B",
+            (Xbox, X) | (Unknown, X) => "X",
+            (Xbox, Y) | (Unknown, Y) => "Y",
+            (Xbox, LeftShoulder) | (Unknown, LeftShoulder) => "LB",
+            (Xbox, RightShoulder) | (Unknown, RightShoulder) => "RB",
+            (Xbox, LeftStick) | (Unknown, LeftStick) => "LS",
+            (Xbox, RightStick) | (Unknown, RightStick) => "RS",
+            (Xbox, Back) | (Unknown, Back) => "View",
+            (Xbox, Start) | (Unknown, Start) => "Menu",
+            (Xbox, Guide) | (Unknown, Guide) => "Xbox",
+        }
+    }
+
+    /// Returns the name of an axis of this kind of controller, for prompts.
+    pub fn axis_name(self, axis: ControllerAxis) -> &'static str {
+        use self::{ControllerAxis::*, ControllerKind::*};
+
+        match (self, axis) {
+            (_, LeftX) | (_, LeftY) => "Left Stick",
+            (_, RightX) | (_, RightY) => "Right Stick",
+            (PlayStation, LeftTrigger) => "L2",
+            (PlayStation, RightTrigger) => "R2",
+            (Switch, LeftTrigger) => "ZL",
+            (Switch, RightTrigger) => "ZR",
+            (Xbox, LeftTrigger) | (Unknown, LeftTrigger) => "LT",
+            (Xbox, RightTrigger) | (Unknown, RightTrigger) => "RT",
+        }
+    }
+}
+
+/// The charge of a controller battery.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatteryLevel {
+    /// The driver doesn't report the battery level.
+    Unknown,
+    /// The battery is almost empty.
+    Empty,
+    /// The battery is low.
+    Low,
+    /// The battery is half charged.
+    Medium,
+    /// The battery is fully charged.
+    Full,
+    /// The controller is wired, and has no battery or is charging.
+    Wired,
+}
+
+impl Default for BatteryLevel {
+    fn default() -> Self {
+        BatteryLevel::Unknown
+    }
+}
+
+/// Information about a connected controller, sent by the controller backend with
+/// `InputHandler::send_controller_info`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ControllerInfo {
+    /// The name reported by the driver.
+    pub name: String,
+    /// The family of the controller.
+    pub kind: ControllerKind,
+    /// The charge of the battery.
+    pub battery: BatteryLevel,
+}
+
+impl ControllerInfo {
+    /// Creates the information of a controller, guessing its kind from its name.
+    pub fn new<N: Into<String>>(name: N, battery: BatteryLevel) -> Self {
+        let name = name.into();
+        ControllerInfo {
+            kind: ControllerKind::from_name(&name),
+            name,
+            battery,
+        }
+    }
+}
+
+/// A request to rumble a controller, see `InputHandler::rumble`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rumble {
+    /// Intensity of the low frequency, usually left, motor, from 0 to 1.
+    pub low_frequency: f32,
+    /// Intensity of the high frequency, usually right, motor, from 0 to 1.
+    pub high_frequency: f32,
+    /// How long the controller rumbles, replacing any previous rumble.
+    pub duration: Duration,
+}
+
+impl Rumble {
+    /// Rumbles both motors with the same intensity, from 0 to 1.
+    pub fn new(intensity: f32, duration: Duration) -> Self {
+        Rumble {
+            low_frequency: intensity,
+            high_frequency: intensity,
+            duration,
+        }
+    }
+
+    /// Stops the rumble of a controller.
+    pub fn stop() -> Self {
+        Rumble::new(0.0, Duration::from_millis(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_from_name() {
+        assert_eq!(
+            ControllerKind::from_name("Xbox One Wireless Controller"),
+            ControllerKind::Xbox
+        );
+        assert_eq!(
+            ControllerKind::from_name("PS4 Controller"),
+            ControllerKind::PlayStation
+        );
+        assert_eq!(
+            ControllerKind::from_name("Nintendo Switch Pro Controller"),
+            ControllerKind::Switch
+        );
+        assert_eq!(
+            ControllerKind::from_name("Generic USB Joystick"),
+            ControllerKind::Unknown
+        );
+    }
+
+    #[test]
+    fn bottom_button_names() {
+        let bottom = ControllerButton::A;
+        assert_eq!(ControllerKind::Xbox.button_name(bottom), "A");
+        assert_eq!(ControllerKind::PlayStation.button_name(bottom), "Cross");
+        assert_eq!(ControllerKind::Switch.button_name(bottom), "B");
+        assert_eq!(
+            ControllerKind::Switch.axis_name(ControllerAxis::RightTrigger),
+            "ZR"
+        );
+    }
+}
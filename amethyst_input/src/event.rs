@@ -5,16 +5,18 @@ use winit::{MouseButton, VirtualKeyCode};
 use super::{
     bindings::BindingTypes,
     button::Button,
+    capture::CapturedInput,
     controller::{ControllerAxis, ControllerButton},
     scroll_direction::ScrollDirection,
+    touch::Gesture,
 };
 
 /// Events generated by the input system
 ///
 /// Type parameter T is the type assigned to your Actions for your
 /// InputBundle or InputHandler.
-#[derive(PartialEq, Serialize, Deserialize, Debug, Derivative)]
-#[derivative(Clone(bound = ""))]
+#[derive(Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Clone(bound = ""), PartialEq(bound = ""))]
 pub enum InputEvent<T>
 where
     T: BindingTypes,
@@ -116,4 +118,31 @@ where
     ActionReleased(T::Action),
     /// The associated action has its mouse wheel moved.
     ActionWheelMoved(T::Action),
+    /// The `ActionTrigger` of the associated triggered action fired.
+    ActionTriggered(T::Action),
+    /// An input was captured, see `InputHandler::start_capture`.
+    InputCaptured(CapturedInput),
+    /// A finger touched the screen.
+    TouchStarted {
+        /// Identifies the touch until it ends.
+        id: u64,
+        /// Where the touch started, in physical pixels.
+        position: (f32, f32),
+    },
+    /// A touch moved.
+    TouchMoved {
+        /// Identifies the touch until it ends.
+        id: u64,
+        /// The new position of the touch, in physical pixels.
+        position: (f32, f32),
+    },
+    /// A touch ended, either released or cancelled.
+    TouchEnded {
+        /// Identifies the touch until it ends.
+        id: u64,
+        /// Where the touch ended, in physical pixels.
+        position: (f32, f32),
+    },
+    /// A gesture was recognized from the touches which don't hold an on-screen control.
+    Gesture(Gesture),
 }
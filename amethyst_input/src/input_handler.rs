@@ -1,18 +1,23 @@
 //! World resource that handles all user input.
 
 use super::{
+    capture::{CaptureKind, CapturedInput},
     controller::{ControllerButton, ControllerEvent},
+    controller_info::{ControllerInfo, ControllerKind, Rumble},
     event::InputEvent::{self, *},
     scroll_direction::ScrollDirection,
+    touch::{TouchControl, TouchTracker},
+    trigger::TriggerState,
     *,
 };
 use amethyst_core::shrev::EventChannel;
 use derivative::Derivative;
+use fnv::FnvHashMap as HashMap;
 use smallvec::SmallVec;
-use std::{borrow::Borrow, hash::Hash};
+use std::{borrow::Borrow, hash::Hash, time::Duration};
 use winit::{
     dpi::LogicalPosition, DeviceEvent, ElementState, Event, KeyboardInput, MouseButton,
-    MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    MouseScrollDelta, Touch, TouchPhase, VirtualKeyCode, WindowEvent,
 };
 
 /// This struct holds state information about input devices.
@@ -26,7 +31,13 @@ where
     T: BindingTypes,
 {
     /// Maps inputs to actions and axes.
+    ///
+    /// These bindings are at the bottom of the context stack, see `push_context`.
     pub bindings: Bindings<T>,
+    /// Binding contexts which can be pushed on the context stack.
+    contexts: HashMap<String, BindingContext<T>>,
+    /// Names of the pushed contexts, from the bottom to the top of the stack.
+    context_stack: SmallVec<[String; 4]>,
     /// Encodes the VirtualKeyCode and corresponding scancode.
     pressed_keys: SmallVec<[(VirtualKeyCode, u32); 12]>,
     pressed_mouse_buttons: SmallVec<[MouseButton; 12]>,
@@ -40,6 +51,30 @@ where
     mouse_position: Option<(f32, f32)>,
     mouse_wheel_vertical: f32,
     mouse_wheel_horizontal: f32,
+    /// The last time sent with `send_time`.
+    time: Duration,
+    /// When the actions which are down were pressed.
+    actions_down_since: HashMap<T::Action, Duration>,
+    just_pressed_actions: SmallVec<[T::Action; 4]>,
+    just_released_actions: SmallVec<[T::Action; 4]>,
+    triggered_actions: SmallVec<[T::Action; 4]>,
+    trigger_states: HashMap<T::Action, TriggerState>,
+    /// The kind of input being captured, if any.
+    capture: Option<CaptureKind>,
+    captured: Option<CapturedInput>,
+    /// Values of the axes with a ramping or smoothing `AxisResponse`.
+    filtered_axes: HashMap<T::Axis, f32>,
+    filtered_axes_2d: HashMap<T::Axis, (f32, f32)>,
+    touches: TouchTracker,
+    gesture_settings: GestureSettings,
+    touch_buttons: HashMap<u32, TouchButton>,
+    touch_sticks: HashMap<u32, TouchStick>,
+    /// Information about the connected controllers, by controller id.
+    controller_infos: HashMap<u32, ControllerInfo>,
+    /// Rumbles requested since the controller backend last drained them, by controller index.
+    rumbles: Vec<(u32, Rumble)>,
+    /// The events sent while the `InputSystem` records, in the order they arrived.
+    recorded_events: Option<Vec<RecordedEvent>>,
 }
 
 impl<T> InputHandler<T>
@@ -60,6 +95,22 @@ where
         event: &Event,
         event_handler: &mut EventChannel<InputEvent<T>>,
         hidpi: f32,
+    ) {
+        if let Some(events) = self.recorded_events.as_mut() {
+            events.extend(RecordedEvent::from_event(event));
+        }
+        let down = self.actions_that_are_down();
+        let buttons = self.buttons_before_capture();
+        self.process_event(event, event_handler, hidpi);
+        self.capture_event(event, buttons, event_handler);
+        self.send_action_edges(down, event_handler);
+    }
+
+    fn process_event(
+        &mut self,
+        event: &Event,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+        hidpi: f32,
     ) {
         match *event {
             Event::WindowEvent { ref event, .. } => match *event {
@@ -88,17 +139,14 @@ where
                             .cloned(),
                         );
                         self.send_axis_moved_events_key(event_handler, key_code, scancode);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations.iter().filter(|c| {
-                                c.contains(&Button::Key(key_code))
-                                    || c.contains(&Button::ScanCode(scancode))
-                            }) {
-                                if combination
+                        for (action, combination) in self.active_combinations() {
+                            if (combination.contains(&Button::Key(key_code))
+                                || combination.contains(&Button::ScanCode(scancode)))
+                                && combination
                                     .iter()
                                     .all(|button| self.button_is_down(*button))
-                                {
-                                    event_handler.single_write(ActionPressed(action.clone()));
-                                }
+                            {
+                                event_handler.single_write(ActionPressed(action.clone()));
                             }
                         }
                     }
@@ -126,24 +174,22 @@ where
                             .cloned(),
                         );
                         self.send_axis_moved_events_key(event_handler, key_code, scancode);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations {
-                                if combination.contains(&Button::Key(key_code))
-                                    && combination
-                                        .iter()
-                                        .filter(|b| b != &&Button::Key(key_code))
-                                        .all(|b| self.button_is_down(*b))
-                                {
-                                    event_handler.single_write(ActionReleased(action.clone()));
-                                }
-                                if combination.contains(&Button::ScanCode(scancode))
-                                    && combination
-                                        .iter()
-                                        .filter(|b| b != &&Button::ScanCode(scancode))
-                                        .all(|b| self.button_is_down(*b))
-                                {
-                                    event_handler.single_write(ActionReleased(action.clone()));
-                                }
+                        for (action, combination) in self.active_combinations() {
+                            if combination.contains(&Button::Key(key_code))
+                                && combination
+                                    .iter()
+                                    .filter(|b| b != &&Button::Key(key_code))
+                                    .all(|b| self.button_is_down(*b))
+                            {
+                                event_handler.single_write(ActionReleased(action.clone()));
+                            }
+                            if combination.contains(&Button::ScanCode(scancode))
+                                && combination
+                                    .iter()
+                                    .filter(|b| b != &&Button::ScanCode(scancode))
+                                    .all(|b| self.button_is_down(*b))
+                            {
+                                event_handler.single_write(ActionReleased(action.clone()));
                             }
                         }
                     }
@@ -169,17 +215,13 @@ where
                             .cloned(),
                         );
                         self.send_axis_moved_events_mouse(event_handler, mouse_button);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations
-                                .iter()
-                                .filter(|c| c.contains(&Button::Mouse(mouse_button)))
-                            {
-                                if combination
+                        for (action, combination) in self.active_combinations() {
+                            if combination.contains(&Button::Mouse(mouse_button))
+                                && combination
                                     .iter()
                                     .all(|button| self.button_is_down(*button))
-                                {
-                                    event_handler.single_write(ActionPressed(action.clone()));
-                                }
+                            {
+                                event_handler.single_write(ActionPressed(action.clone()));
                             }
                         }
                     }
@@ -205,16 +247,14 @@ where
                             .cloned(),
                         );
                         self.send_axis_moved_events_mouse(event_handler, mouse_button);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations {
-                                if combination.contains(&Button::Mouse(mouse_button))
-                                    && combination
-                                        .iter()
-                                        .filter(|b| b != &&Button::Mouse(mouse_button))
-                                        .all(|b| self.button_is_down(*b))
-                                {
-                                    event_handler.single_write(ActionReleased(action.clone()));
-                                }
+                        for (action, combination) in self.active_combinations() {
+                            if combination.contains(&Button::Mouse(mouse_button))
+                                && combination
+                                    .iter()
+                                    .filter(|b| b != &&Button::Mouse(mouse_button))
+                                    .all(|b| self.button_is_down(*b))
+                            {
+                                event_handler.single_write(ActionReleased(action.clone()));
                             }
                         }
                     }
@@ -231,6 +271,15 @@ where
                     }
                     self.mouse_position = Some(((x as f32) * hidpi, (y as f32) * hidpi));
                 }
+                WindowEvent::Touch(Touch {
+                    phase,
+                    location: LogicalPosition { x, y },
+                    id,
+                    ..
+                }) => {
+                    let position = ((x as f32) * hidpi, (y as f32) * hidpi);
+                    self.process_touch(id, phase, position, event_handler);
+                }
                 WindowEvent::Focused(false) => {
                     self.pressed_keys.clear();
                     self.pressed_mouse_buttons.clear();
@@ -284,6 +333,21 @@ where
         &mut self,
         event: &ControllerEvent,
         event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if let Some(events) = self.recorded_events.as_mut() {
+            events.push(RecordedEvent::Controller(*event));
+        }
+        let down = self.actions_that_are_down();
+        let buttons = self.buttons_before_capture();
+        self.process_controller_event(event, event_handler);
+        self.capture_controller_event(event, buttons, event_handler);
+        self.send_action_edges(down, event_handler);
+    }
+
+    fn process_controller_event(
+        &mut self,
+        event: &ControllerEvent,
+        event_handler: &mut EventChannel<InputEvent<T>>,
     ) {
         use self::ControllerEvent::*;
 
@@ -317,17 +381,13 @@ where
                             .iter()
                             .cloned(),
                         );
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations
-                                .iter()
-                                .filter(|c| c.contains(&Button::Controller(controller_id, button)))
-                            {
-                                if combination
+                        for (action, combination) in self.active_combinations() {
+                            if combination.contains(&Button::Controller(controller_id, button))
+                                && combination
                                     .iter()
                                     .all(|button| self.button_is_down(*button))
-                                {
-                                    event_handler.single_write(ActionPressed(action.clone()));
-                                }
+                            {
+                                event_handler.single_write(ActionPressed(action.clone()));
                             }
                         }
                     }
@@ -349,19 +409,14 @@ where
                             .iter()
                             .cloned(),
                         );
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations {
-                                if combination.contains(&Button::Controller(controller_id, button))
-                                {
-                                    let down = combination
-                                        .iter()
-                                        .filter(|b| {
-                                            b != &&Button::Controller(controller_id, button)
-                                        })
-                                        .all(|b| self.button_is_down(*b));
-                                    if down {
-                                        event_handler.single_write(ActionReleased(action.clone()));
-                                    }
+                        for (action, combination) in self.active_combinations() {
+                            if combination.contains(&Button::Controller(controller_id, button)) {
+                                let down = combination
+                                    .iter()
+                                    .filter(|b| b != &&Button::Controller(controller_id, button))
+                                    .all(|b| self.button_is_down(*b));
+                                if down {
+                                    event_handler.single_write(ActionReleased(action.clone()));
                                 }
                             }
                         }
@@ -377,6 +432,7 @@ where
                         .all(|&ids| ids.0 != controller_id)
                     {
                         self.connected_controllers.push((controller_id, which));
+                        event_handler.single_write(event.into());
                     }
                 }
             }
@@ -391,6 +447,9 @@ where
                         self.controller_axes.retain(|a| a.0 != controller_id);
                         self.pressed_controller_buttons
                             .retain(|b| b.0 != controller_id);
+                        self.controller_infos.remove(&controller_id);
+                        self.rumbles.retain(|r| r.0 != which);
+                        event_handler.single_write(event.into());
                     }
                 }
             }
@@ -404,6 +463,112 @@ where
     pub fn send_frame_begin(&mut self) {
         self.mouse_wheel_vertical = 0.0;
         self.mouse_wheel_horizontal = 0.0;
+        self.just_pressed_actions.clear();
+        self.just_released_actions.clear();
+        self.triggered_actions.clear();
+    }
+
+    /// Starts or stops keeping the events sent to the handler, for the `InputRecorder`.
+    pub(crate) fn record_events(&mut self, record: bool) {
+        if !record {
+            self.recorded_events = None;
+        } else if self.recorded_events.is_none() {
+            self.recorded_events = Some(Vec::new());
+        }
+    }
+
+    /// Returns the events sent since the last call, in the order they arrived.
+    pub(crate) fn take_recorded_events(&mut self) -> Vec<RecordedEvent> {
+        self.recorded_events
+            .as_mut()
+            .map(|events| std::mem::replace(events, Vec::new()))
+            .unwrap_or_default()
+    }
+
+    /// Updates the input handler with the current time, firing the `ActionTrigger`s which
+    /// depend on the passing of time and moving ramping or smoothed axes toward their input.
+    ///
+    /// Presses and releases are timed with the last time sent. The `InputSystem` sends the
+    /// real time of the frame, after `send_frame_begin`.
+    pub fn send_time(&mut self, time: Duration, event_handler: &mut EventChannel<InputEvent<T>>) {
+        let elapsed = time.checked_sub(self.time).unwrap_or_default();
+        self.time = time;
+        self.update_filtered_axes(elapsed, event_handler);
+        let long_presses = self.touches.tick(time, &self.gesture_settings);
+        event_handler.iter_write(long_presses.into_iter().map(Gesture));
+        for (id, trigger) in self.active_triggers() {
+            let down_since = self.action_down_since(&trigger);
+            let fired = self
+                .trigger_states
+                .entry(id.clone())
+                .or_default()
+                .tick(&trigger, down_since, time);
+            if fired {
+                self.fire_trigger(id, event_handler);
+            }
+        }
+    }
+
+    /// Returns true if the action was pressed during the current frame.
+    ///
+    /// This is true even if the action was released again before the end of the frame.
+    pub fn action_just_pressed<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.just_pressed_actions
+            .iter()
+            .any(|a| Borrow::<A>::borrow(a) == action)
+    }
+
+    /// Returns true if the action was released during the current frame.
+    pub fn action_just_released<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.just_released_actions
+            .iter()
+            .any(|a| Borrow::<A>::borrow(a) == action)
+    }
+
+    /// Starts listening for the next input of the given kind, e.g. to let the player rebind an
+    /// action.
+    ///
+    /// Once an input is captured, `InputEvent::InputCaptured` is sent and the input can be
+    /// retrieved with `take_captured`. The input is still processed as usual, push a consuming
+    /// `BindingContext` while capturing to keep it from reaching the bound actions.
+    pub fn start_capture(&mut self, kind: CaptureKind) {
+        self.capture = Some(kind);
+        self.captured = None;
+    }
+
+    /// Stops listening for input without capturing any.
+    pub fn cancel_capture(&mut self) {
+        self.capture = None;
+    }
+
+    /// Returns whether the handler is listening for an input to capture.
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Returns the captured input, if any, and forgets it.
+    pub fn take_captured(&mut self) -> Option<CapturedInput> {
+        self.captured.take()
+    }
+
+    /// Returns true if the `ActionTrigger` of the triggered action fired during the current
+    /// frame.
+    pub fn action_triggered<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.triggered_actions
+            .iter()
+            .any(|a| Borrow::<A>::borrow(a) == action)
     }
 
     /// Returns an iterator over all keys that are down.
@@ -482,6 +647,64 @@ where
             .any(|ids| ids.0 == controller_id)
     }
 
+    /// Updates the information about a connected controller, like its name or battery level.
+    ///
+    /// Called by the SdlEventsSystem when using sdl_controller feature, after connecting the
+    /// controller and when its battery level changes. The information of disconnected
+    /// controllers is forgotten.
+    pub fn send_controller_info(&mut self, which: u32, info: ControllerInfo) {
+        if let Some(controller_id) = self.controller_idx_to_id(which) {
+            self.controller_infos.insert(controller_id, info);
+        }
+    }
+
+    /// Returns the information about a connected controller, if its backend sent any.
+    pub fn controller_info(&self, controller_id: u32) -> Option<&ControllerInfo> {
+        self.controller_infos.get(&controller_id)
+    }
+
+    /// Returns the name of a button of a connected controller, for prompts.
+    ///
+    /// Controllers of unknown kind use the names of Xbox controllers.
+    pub fn controller_button_name(
+        &self,
+        controller_id: u32,
+        button: ControllerButton,
+    ) -> &'static str {
+        self.controller_info(controller_id)
+            .map_or(ControllerKind::Unknown, |info| info.kind)
+            .button_name(button)
+    }
+
+    /// Requests a connected controller to rumble, returning false if it isn't connected.
+    ///
+    /// Rumbles are played by the controller backend, the SdlEventsSystem when using
+    /// sdl_controller feature, which drains them with `drain_rumbles`. A rumble which wasn't
+    /// played yet is replaced by the next one requested for the same controller.
+    pub fn rumble(&mut self, controller_id: u32, rumble: Rumble) -> bool {
+        let which = self
+            .connected_controllers
+            .iter()
+            .find(|ids| ids.0 == controller_id)
+            .map(|ids| ids.1);
+        match which {
+            Some(which) => {
+                self.rumbles.retain(|r| r.0 != which);
+                self.rumbles.push((which, rumble));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes and returns the requested rumbles, with the index of the controller, as sent with
+    /// `send_controller_event`, they were requested for.
+    ///
+    /// To be called by controller backends, or by tests checking the requested rumbles.
+    pub fn drain_rumbles(&mut self) -> impl Iterator<Item = (u32, Rumble)> + '_ {
+        self.rumbles.drain(..)
+    }
+
     /// Gets the current mouse position.
     ///
     /// this method can return None, either if no mouse is connected, or if no mouse events have
@@ -490,6 +713,73 @@ where
         self.mouse_position
     }
 
+    /// Returns an iterator over the touches on the screen, in the order they started.
+    pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches.touches.iter().map(|touch| &touch.point)
+    }
+
+    /// Returns the touch with the given id, if it is on the screen.
+    pub fn touch(&self, id: u64) -> Option<&TouchPoint> {
+        self.touches.get(id).map(|touch| &touch.point)
+    }
+
+    /// Returns the thresholds used to recognize gestures.
+    pub fn gesture_settings(&self) -> &GestureSettings {
+        &self.gesture_settings
+    }
+
+    /// Returns the thresholds used to recognize gestures, to change them.
+    pub fn gesture_settings_mut(&mut self) -> &mut GestureSettings {
+        &mut self.gesture_settings
+    }
+
+    /// Adds an on-screen button, bound with `Button::Touch(id)`.
+    ///
+    /// A touch starting on the button presses it until the touch ends, even if it moves off the
+    /// button. Touches holding a button or a stick are not used for gestures. This will replace
+    /// the button with the same id, if any, and return it.
+    pub fn insert_touch_button(&mut self, id: u32, button: TouchButton) -> Option<TouchButton> {
+        self.touch_buttons.insert(id, button)
+    }
+
+    /// Removes an on-screen button, returning it if successful.
+    pub fn remove_touch_button(&mut self, id: u32) -> Option<TouchButton> {
+        self.touch_buttons.remove(&id)
+    }
+
+    /// Returns whether a touch holds the on-screen button.
+    pub fn touch_button_is_down(&self, id: u32) -> bool {
+        self.touches.holds(TouchControl::Button(id))
+    }
+
+    /// Adds an on-screen stick, bound with `Axis::TouchStick` or an `Axis2D` of both directions.
+    ///
+    /// A touch starting on the stick holds it until the touch ends. This will replace the stick
+    /// with the same id, if any, and return it.
+    pub fn insert_touch_stick(&mut self, id: u32, stick: TouchStick) -> Option<TouchStick> {
+        self.touch_sticks.insert(id, stick)
+    }
+
+    /// Removes an on-screen stick, returning it if successful.
+    pub fn remove_touch_stick(&mut self, id: u32) -> Option<TouchStick> {
+        self.touch_sticks.remove(&id)
+    }
+
+    /// Returns the value of an on-screen stick, 0 if no touch holds it.
+    pub fn touch_stick_value(&self, id: u32) -> (f32, f32) {
+        let control = Some(TouchControl::Stick(id));
+        self.touches
+            .touches
+            .iter()
+            .find(|touch| touch.control == control)
+            .and_then(|touch| {
+                self.touch_sticks
+                    .get(&id)
+                    .map(|stick| stick.value(touch.point.position))
+            })
+            .unwrap_or((0.0, 0.0))
+    }
+
     /// Returns an iterator over all buttons that are down.
     pub fn buttons_that_are_down(&self) -> impl Iterator<Item = Button> + '_ {
         let mouse_buttons = self
@@ -502,7 +792,19 @@ where
             .iter()
             .map(|&gb| Button::Controller(gb.0, gb.1));
 
-        mouse_buttons.chain(keys).chain(controller_buttons)
+        let touch_buttons = self
+            .touches
+            .touches
+            .iter()
+            .filter_map(|touch| match touch.control {
+                Some(TouchControl::Button(id)) => Some(Button::Touch(id)),
+                _ => None,
+            });
+
+        mouse_buttons
+            .chain(keys)
+            .chain(controller_buttons)
+            .chain(touch_buttons)
     }
 
     /// Checks if a button is down.
@@ -512,29 +814,317 @@ where
             Button::Mouse(b) => self.mouse_button_is_down(b),
             Button::ScanCode(s) => self.scan_code_is_down(s),
             Button::Controller(g, b) => self.controller_button_is_down(g, b),
+            Button::Touch(id) => self.touch_button_is_down(id),
             _ => false,
         }
     }
 
     /// Returns the value of an axis by the id, if the id doesn't exist this returns None.
+    ///
+    /// The axis is looked up in the active binding contexts first, inputs bound by a higher
+    /// context are ignored. Axes of inactive contexts have a value of 0.
+    ///
+    /// The `AxisResponse` of the axis, if any, is applied to the value.
     pub fn axis_value<A>(&self, id: &A) -> Option<f32>
     where
         T::Axis: Borrow<A>,
         A: Hash + Eq + ?Sized,
     {
-        self.bindings.axes.get(id).map(|a| match *a {
-            Axis::Emulated { pos, neg, .. } => {
-                match (self.button_is_down(pos), self.button_is_down(neg)) {
-                    (true, false) => 1.0,
-                    (false, true) => -1.0,
-                    _ => 0.0,
+        self.unfiltered_axis_value(id)
+            .map(|(value, response)| match response {
+                Some(response) if response.is_filtered() => {
+                    self.filtered_axes.get(id).cloned().unwrap_or(0.0)
+                }
+                _ => value,
+            })
+    }
+
+    /// Returns the value of a 2D axis by the id, if the id doesn't exist this returns None.
+    ///
+    /// The 2D axis is looked up in the active binding contexts like `axis_value`, its radial
+    /// dead zone and response are applied to the value.
+    pub fn axis_2d_value<A>(&self, id: &A) -> Option<(f32, f32)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.unfiltered_axis_2d_value(id)
+            .map(|(value, axis)| match axis {
+                Some(axis) if axis.response.is_filtered() => {
+                    self.filtered_axes_2d.get(id).cloned().unwrap_or((0.0, 0.0))
                 }
+                _ => value,
+            })
+    }
+
+    /// Returns true if any of the actions bindings is down.
+    ///
+    /// If a binding represents a combination of buttons, all of them need to be down.
+    ///
+    /// The action is looked up in the active binding contexts first, combinations containing a
+    /// button bound by a higher context are ignored. Actions of inactive contexts are never down.
+    pub fn action_is_down<A>(&self, action: &A) -> Option<bool>
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let layers = self.layers();
+        let index = match layers
+            .iter()
+            .position(|layer| layer.actions.contains_key(action))
+        {
+            Some(index) => index,
+            None => {
+                return self
+                    .stacked_bindings()
+                    .find(|bindings| bindings.actions.contains_key(action))
+                    .map(|_| false)
             }
+        };
+        let above = &layers[..index];
+        layers[index].actions.get(action).map(|combinations| {
+            combinations.iter().any(|combination| {
+                combination.iter().all(|button| {
+                    self.button_is_down(*button)
+                        && !above.iter().any(|layer| layer.uses_button(*button))
+                })
+            })
+        })
+    }
+
+    /// Registers a binding context under the given name, replacing and returning the context
+    /// previously registered under it.
+    ///
+    /// Registering a context does not activate it, see `push_context`.
+    pub fn insert_context<N: Into<String>>(
+        &mut self,
+        name: N,
+        context: BindingContext<T>,
+    ) -> Option<BindingContext<T>> {
+        self.contexts.insert(name.into(), context)
+    }
+
+    /// Unregisters a binding context, removing it from the context stack.
+    pub fn remove_context(&mut self, name: &str) -> Option<BindingContext<T>> {
+        self.context_stack.retain(|n| n != name);
+        self.contexts.remove(name)
+    }
+
+    /// Returns a registered binding context.
+    pub fn context(&self, name: &str) -> Option<&BindingContext<T>> {
+        self.contexts.get(name)
+    }
+
+    /// Returns a registered binding context, to change its bindings.
+    pub fn context_mut(&mut self, name: &str) -> Option<&mut BindingContext<T>> {
+        self.contexts.get_mut(name)
+    }
+
+    /// Returns the names of the registered binding contexts.
+    pub fn contexts(&self) -> impl Iterator<Item = &str> {
+        self.contexts.keys().map(String::as_str)
+    }
+
+    /// Pushes a binding context on top of the context stack, activating its bindings over the
+    /// ones below.
+    ///
+    /// The `bindings` of the handler are always at the bottom of the stack. A context which is
+    /// not registered yet takes effect once it is inserted.
+    ///
+    /// Buttons held while the stack changes don't send action events for the change, only
+    /// `action_is_down` reflects it.
+    pub fn push_context<N: Into<String>>(&mut self, name: N) {
+        self.context_stack.push(name.into());
+    }
+
+    /// Pops the binding context on top of the context stack and returns its name.
+    pub fn pop_context(&mut self) -> Option<String> {
+        self.context_stack.pop()
+    }
+
+    /// Removes the topmost occurrence of the binding context from the context stack, wherever it
+    /// is in the stack.
+    ///
+    /// Returns whether the context was on the stack.
+    pub fn pop_context_named(&mut self, name: &str) -> bool {
+        match self.context_stack.iter().rposition(|n| n == name) {
+            Some(index) => {
+                self.context_stack.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the names of the contexts on the context stack, from the bottom to the top.
+    pub fn active_contexts(&self) -> impl Iterator<Item = &str> {
+        self.context_stack.iter().map(String::as_str)
+    }
+
+    /// Returns whether the context is on the context stack.
+    pub fn context_is_active(&self, name: &str) -> bool {
+        self.context_stack.iter().any(|n| n == name)
+    }
+
+    /// Returns the active binding layers, from the top of the context stack down to `bindings`.
+    ///
+    /// The layers below a consuming context are left out.
+    fn layers(&self) -> SmallVec<[&Bindings<T>; 4]> {
+        let mut layers = SmallVec::new();
+        for name in self.context_stack.iter().rev() {
+            if let Some(context) = self.contexts.get(name) {
+                layers.push(&context.bindings);
+                if context.mode == ContextMode::Consume {
+                    return layers;
+                }
+            }
+        }
+        layers.push(&self.bindings);
+        layers
+    }
+
+    /// Returns the bindings of every context on the stack, active or not.
+    fn stacked_bindings(&self) -> impl Iterator<Item = &Bindings<T>> {
+        let contexts = &self.contexts;
+        self.context_stack
+            .iter()
+            .filter_map(move |name| contexts.get(name))
+            .map(|context| &context.bindings)
+            .chain(Some(&self.bindings))
+    }
+
+    /// Returns the combinations of every active action, leaving out the ones shadowed by a
+    /// higher context.
+    fn active_combinations(&self) -> Vec<(&T::Action, &[Button])> {
+        let layers = self.layers();
+        let mut active = Vec::new();
+        for (index, &layer) in layers.iter().enumerate() {
+            let above = &layers[..index];
+            for (action, combinations) in layer.actions.iter() {
+                if above.iter().any(|l| l.actions.contains_key(action)) {
+                    continue;
+                }
+                active.extend(
+                    combinations
+                        .iter()
+                        .filter(|combination| {
+                            combination
+                                .iter()
+                                .all(|button| !above.iter().any(|l| l.uses_button(*button)))
+                        })
+                        .map(|combination| (action, combination.as_slice())),
+                );
+            }
+        }
+        active
+    }
+
+    /// Returns every active axis, leaving out the ones shadowed by a higher context.
+    fn active_axes(&self) -> Vec<(&T::Axis, &Axis)> {
+        let layers = self.layers();
+        let mut active = Vec::new();
+        for (index, &layer) in layers.iter().enumerate() {
+            let above = &layers[..index];
+            active.extend(
+                layer
+                    .axes
+                    .iter()
+                    .filter(|(axis, _)| !above.iter().any(|l| l.axes.contains_key(*axis))),
+            );
+        }
+        active
+    }
+
+    /// Returns the value of an axis before ramping and smoothing, with its response if any.
+    fn unfiltered_axis_value<A>(&self, id: &A) -> Option<(f32, Option<&AxisResponse>)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.unfiltered_axis_value_in(&self.layers(), id)
+    }
+
+    /// Returns the value of an axis in the given layers before smoothing, with its response.
+    fn unfiltered_axis_value_in<'b, A>(
+        &'b self,
+        layers: &[&'b Bindings<T>],
+        id: &A,
+    ) -> Option<(f32, Option<&'b AxisResponse>)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let index = match layers.iter().position(|layer| layer.axes.contains_key(id)) {
+            Some(index) => index,
+            None => {
+                return self
+                    .stacked_bindings()
+                    .find(|bindings| bindings.axes.contains_key(id))
+                    .map(|_| (0.0, None))
+            }
+        };
+        let layer: &Bindings<T> = layers[index];
+        let axis = layer.axes.get(id)?;
+        let value = self.raw_axis_value(axis, &layers[..index], true);
+        let response = layer.responses.get(id);
+        Some((response.map_or(value, |r| r.apply(value)), response))
+    }
+
+    /// Returns the value of a 2D axis before smoothing, with the 2D axis.
+    fn unfiltered_axis_2d_value<A>(&self, id: &A) -> Option<((f32, f32), Option<&Axis2D>)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.unfiltered_axis_2d_value_in(&self.layers(), id)
+    }
+
+    /// Returns the value of a 2D axis in the given layers before smoothing, with the 2D axis.
+    fn unfiltered_axis_2d_value_in<'b, A>(
+        &'b self,
+        layers: &[&'b Bindings<T>],
+        id: &A,
+    ) -> Option<((f32, f32), Option<&'b Axis2D>)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let index = match layers
+            .iter()
+            .position(|layer| layer.axes_2d.contains_key(id))
+        {
+            Some(index) => index,
+            None => {
+                return self
+                    .stacked_bindings()
+                    .find(|bindings| bindings.axes_2d.contains_key(id))
+                    .map(|_| ((0.0, 0.0), None))
+            }
+        };
+        let layer: &Bindings<T> = layers[index];
+        let axis = layer.axes_2d.get(id)?;
+        let above = &layers[..index];
+        let x = self.raw_axis_value(&axis.x, above, false);
+        let y = self.raw_axis_value(&axis.y, above, false);
+        Some((axis.apply(x, y), Some(axis)))
+    }
+
+    /// Returns the value of the input of an axis, ignoring the inputs bound in `above` layers.
+    fn raw_axis_value(&self, axis: &Axis, above: &[&Bindings<T>], dead_zone: bool) -> f32 {
+        let masked = |button: Button| above.iter().any(|layer| layer.uses_button(button));
+        let is_down = |button: Button| self.button_is_down(button) && !masked(button);
+        match *axis {
+            Axis::Emulated { pos, neg, .. } => match (is_down(pos), is_down(neg)) {
+                (true, false) => 1.0,
+                (false, true) => -1.0,
+                _ => 0.0,
+            },
+            _ if above.iter().any(|layer| layer.uses_axis_input(axis)) => 0.0,
             Axis::Controller {
                 controller_id,
                 axis,
                 invert,
-                dead_zone,
+                dead_zone: axis_dead_zone,
                 ..
             } => self
                 .controller_axes
@@ -542,7 +1132,11 @@ where
                 .find(|&&(id, a, _)| id == controller_id && a == axis)
                 .map(|&(_, _, val)| if invert { -val } else { val })
                 .map(|val| {
-                    let dead_zone = dead_zone as f32;
+                    let dead_zone = if dead_zone {
+                        axis_dead_zone as f32
+                    } else {
+                        0.0
+                    };
                     if val < -dead_zone {
                         (val + dead_zone) / (1.0 - dead_zone)
                     } else if val > dead_zone {
@@ -553,24 +1147,406 @@ where
                 })
                 .unwrap_or(0.0),
             Axis::MouseWheel { horizontal } => self.mouse_wheel_value(horizontal),
-        })
+            Axis::TouchStick { stick, vertical } => {
+                let (x, y) = self.touch_stick_value(stick);
+                if vertical {
+                    y
+                } else {
+                    x
+                }
+            }
+        }
+    }
+
+    /// Moves the values of ramping and smoothed axes toward their input.
+    fn update_filtered_axes(
+        &mut self,
+        elapsed: Duration,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        // Taken out of the handler so they can be updated while the bindings are borrowed.
+        let mut filtered = std::mem::take(&mut self.filtered_axes);
+        let mut filtered_2d = std::mem::take(&mut self.filtered_axes_2d);
+        {
+            let layers = self.layers();
+            let is_filtered = |id: &T::Axis| {
+                layers
+                    .iter()
+                    .any(|layer| layer.responses.get(id).map_or(false, |r| r.is_filtered()))
+            };
+            let is_filtered_2d = |id: &T::Axis| {
+                layers.iter().any(|layer| {
+                    layer
+                        .axes_2d
+                        .get(id)
+                        .map_or(false, |axis| axis.response.is_filtered())
+                })
+            };
+            filtered.retain(|id, _| is_filtered(id));
+            filtered_2d.retain(|id, _| is_filtered_2d(id));
+
+            for (index, layer) in layers.iter().enumerate() {
+                let above = &layers[..index];
+                for (id, response) in layer.responses.iter() {
+                    let seen = above
+                        .iter()
+                        .any(|l| l.responses.get(id).map_or(false, |r| r.is_filtered()));
+                    if !response.is_filtered() || seen {
+                        continue;
+                    }
+                    let current = filtered.get(id).cloned().unwrap_or(0.0);
+                    let value = match self.unfiltered_axis_value_in(&layers, id) {
+                        Some((target, Some(response))) => response.filter(current, target, elapsed),
+                        _ => 0.0,
+                    };
+                    if (value - current).abs() > std::f32::EPSILON {
+                        event_handler.single_write(AxisMoved {
+                            axis: id.clone(),
+                            value,
+                        });
+                    }
+                    match filtered.get_mut(id) {
+                        Some(filtered_value) => *filtered_value = value,
+                        None => {
+                            filtered.insert(id.clone(), value);
+                        }
+                    }
+                }
+                for (id, axis) in layer.axes_2d.iter() {
+                    let seen = above.iter().any(|l| {
+                        l.axes_2d
+                            .get(id)
+                            .map_or(false, |axis| axis.response.is_filtered())
+                    });
+                    if !axis.response.is_filtered() || seen {
+                        continue;
+                    }
+                    let (x, y) = filtered_2d.get(id).cloned().unwrap_or((0.0, 0.0));
+                    let value = match self.unfiltered_axis_2d_value_in(&layers, id) {
+                        Some(((target_x, target_y), Some(axis))) => (
+                            axis.response.filter(x, target_x, elapsed),
+                            axis.response.filter(y, target_y, elapsed),
+                        ),
+                        _ => (0.0, 0.0),
+                    };
+                    match filtered_2d.get_mut(id) {
+                        Some(filtered_value) => *filtered_value = value,
+                        None => {
+                            filtered_2d.insert(id.clone(), value);
+                        }
+                    }
+                }
+            }
+        }
+        self.filtered_axes = filtered;
+        self.filtered_axes_2d = filtered_2d;
+    }
+
+    /// Sends the value of an axis moved by an input.
+    ///
+    /// Ramping and smoothed axes are left out, `update_filtered_axes` sends their value as it
+    /// moves toward the input.
+    fn send_axis_moved(&self, axis: &T::Axis, event_handler: &mut EventChannel<InputEvent<T>>) {
+        match self.unfiltered_axis_value(axis) {
+            Some((_, Some(response))) if response.is_filtered() => {}
+            Some((value, _)) => event_handler.single_write(AxisMoved {
+                axis: axis.clone(),
+                value,
+            }),
+            None => {}
+        }
+    }
+
+    fn process_touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        position: (f32, f32),
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        match phase {
+            TouchPhase::Started => {
+                if self.touches.get(id).is_some() {
+                    return;
+                }
+                let control = self.touch_control_at(position);
+                self.touches.start(id, position, self.time, control);
+                event_handler.single_write(TouchStarted { id, position });
+                self.send_touch_control_events(control, true, event_handler);
+            }
+            TouchPhase::Moved => {
+                let gestures = self.touches.moved(id, position, &self.gesture_settings);
+                let control = match self.touches.get(id) {
+                    Some(touch) => touch.control,
+                    None => return,
+                };
+                event_handler.single_write(TouchMoved { id, position });
+                event_handler.iter_write(gestures.into_iter().map(Gesture));
+                if let Some(TouchControl::Stick(_)) = control {
+                    self.send_touch_control_events(control, true, event_handler);
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let (touch, gesture) =
+                    match self
+                        .touches
+                        .end(id, position, self.time, &self.gesture_settings)
+                    {
+                        Some(ended) => ended,
+                        None => return,
+                    };
+                event_handler.single_write(TouchEnded { id, position });
+                if phase == TouchPhase::Ended {
+                    event_handler.iter_write(gesture.map(Gesture));
+                }
+                self.send_touch_control_events(touch.control, false, event_handler);
+            }
+        }
+    }
+
+    /// Returns the free on-screen control at the position, sticks first.
+    fn touch_control_at(&self, position: (f32, f32)) -> Option<TouchControl> {
+        let sticks = self
+            .touch_sticks
+            .iter()
+            .filter(|(_, stick)| stick.contains(position))
+            .map(|(&id, _)| TouchControl::Stick(id));
+        let buttons = self
+            .touch_buttons
+            .iter()
+            .filter(|(_, button)| button.contains(position))
+            .map(|(&id, _)| TouchControl::Button(id));
+        sticks
+            .chain(buttons)
+            .find(|&control| !self.touches.holds(control))
+    }
+
+    /// Sends the events of an on-screen control pressed, moved or released by a touch.
+    fn send_touch_control_events(
+        &self,
+        control: Option<TouchControl>,
+        pressed: bool,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        match control {
+            Some(TouchControl::Button(id)) => {
+                let button = Button::Touch(id);
+                if pressed {
+                    event_handler.single_write(ButtonPressed(button));
+                } else {
+                    event_handler.single_write(ButtonReleased(button));
+                }
+                for (axis, input_axis) in self.active_axes() {
+                    if let Axis::Emulated { pos, neg } = *input_axis {
+                        if pos == button || neg == button {
+                            self.send_axis_moved(axis, event_handler);
+                        }
+                    }
+                }
+                for (action, combination) in self.active_combinations() {
+                    if combination.contains(&button)
+                        && combination
+                            .iter()
+                            .filter(|b| **b != button)
+                            .all(|b| self.button_is_down(*b))
+                    {
+                        if pressed {
+                            event_handler.single_write(ActionPressed(action.clone()));
+                        } else {
+                            event_handler.single_write(ActionReleased(action.clone()));
+                        }
+                    }
+                }
+            }
+            Some(TouchControl::Stick(id)) => {
+                for (axis, input_axis) in self.active_axes() {
+                    if let Axis::TouchStick { stick, .. } = *input_axis {
+                        if stick == id {
+                            self.send_axis_moved(axis, event_handler);
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Returns the buttons which are down, if buttons are being captured.
+    fn buttons_before_capture(&self) -> SmallVec<[Button; 8]> {
+        match self.capture {
+            Some(kind) if kind.accepts_buttons() => self.buttons_that_are_down().collect(),
+            _ => SmallVec::new(),
+        }
+    }
+
+    /// Captures the button pressed since `buttons` was taken, or the axis moved by the event.
+    fn capture_event(
+        &mut self,
+        event: &Event,
+        buttons: SmallVec<[Button; 8]>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let kind = match self.capture {
+            Some(kind) => kind,
+            None => return,
+        };
+        let captured = match *event {
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseWheel { delta },
+                ..
+            } => {
+                let (delta_x, delta_y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(LogicalPosition { x, y }) => (x as f32, y as f32),
+                };
+                CapturedInput::mouse_wheel(kind, delta_x, delta_y)
+            }
+            _ => self.pressed_button(kind, &buttons),
+        };
+        self.finish_capture(captured, event_handler);
+    }
+
+    fn capture_controller_event(
+        &mut self,
+        event: &ControllerEvent,
+        buttons: SmallVec<[Button; 8]>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let kind = match self.capture {
+            Some(kind) => kind,
+            None => return,
+        };
+        let captured = match *event {
+            ControllerEvent::ControllerAxisMoved { which, axis, value } if kind.accepts_axes() => {
+                self.controller_idx_to_id(which).and_then(|controller_id| {
+                    CapturedInput::controller_axis(controller_id, axis, value)
+                })
+            }
+            _ => self.pressed_button(kind, &buttons),
+        };
+        self.finish_capture(captured, event_handler);
+    }
+
+    /// Returns the first button which is down but was not in `buttons`.
+    fn pressed_button(&self, kind: CaptureKind, buttons: &[Button]) -> Option<CapturedInput> {
+        if !kind.accepts_buttons() {
+            return None;
+        }
+        self.buttons_that_are_down()
+            .find(|button| !buttons.contains(button))
+            .map(CapturedInput::Button)
+    }
+
+    fn finish_capture(
+        &mut self,
+        captured: Option<CapturedInput>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if let Some(captured) = captured {
+            event_handler.single_write(InputCaptured(captured.clone()));
+            self.capture = None;
+            self.captured = Some(captured);
+        }
     }
 
-    /// Returns true if any of the actions bindings is down.
-    ///
-    /// If a binding represents a combination of buttons, all of them need to be down.
-    pub fn action_is_down<A>(&self, action: &A) -> Option<bool>
-    where
-        T::Action: Borrow<A>,
-        A: Hash + Eq + ?Sized,
-    {
-        self.bindings.actions.get(action).map(|combinations| {
-            combinations.iter().any(|combination| {
-                combination
+    /// Returns the active actions which have a combination down.
+    fn actions_that_are_down(&self) -> SmallVec<[T::Action; 8]> {
+        let mut down = SmallVec::<[T::Action; 8]>::new();
+        for (action, combination) in self.active_combinations() {
+            if !down.contains(action)
+                && combination
                     .iter()
                     .all(|button| self.button_is_down(*button))
-            })
-        })
+            {
+                down.push(action.clone());
+            }
+        }
+        down
+    }
+
+    /// Records the actions pressed or released since `down` was taken, and fires the
+    /// `ActionTrigger`s depending on them.
+    fn send_action_edges(
+        &mut self,
+        down: SmallVec<[T::Action; 8]>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let now = self.time;
+        let down_after = self.actions_that_are_down();
+        let pressed = down_after
+            .iter()
+            .filter(|action| !down.contains(*action))
+            .cloned()
+            .collect::<SmallVec<[T::Action; 4]>>();
+        let released = down
+            .into_iter()
+            .filter(|action| !down_after.contains(action))
+            .collect::<SmallVec<[T::Action; 4]>>();
+        if pressed.is_empty() && released.is_empty() {
+            return;
+        }
+
+        let triggers = self.active_triggers();
+        for action in released {
+            let pressed_at = self.actions_down_since.remove(&action);
+            for (id, trigger) in triggers.iter().filter(|(_, t)| t.involves(&action)) {
+                let fired = self
+                    .trigger_states
+                    .entry(id.clone())
+                    .or_default()
+                    .release(trigger, pressed_at, now);
+                if fired {
+                    self.fire_trigger(id.clone(), event_handler);
+                }
+            }
+            self.just_released_actions.push(action);
+        }
+        for action in pressed {
+            self.actions_down_since.insert(action.clone(), now);
+            for (id, trigger) in triggers.iter().filter(|(_, t)| t.involves(&action)) {
+                let actions_down_since = &self.actions_down_since;
+                let fired = self.trigger_states.entry(id.clone()).or_default().press(
+                    trigger,
+                    &action,
+                    now,
+                    |a| actions_down_since.get(a).cloned(),
+                );
+                if fired {
+                    self.fire_trigger(id.clone(), event_handler);
+                }
+            }
+            self.just_pressed_actions.push(action);
+        }
+    }
+
+    /// Returns when the action of a hold trigger was pressed, if it is down.
+    fn action_down_since(&self, trigger: &ActionTrigger<T::Action>) -> Option<Duration> {
+        match trigger {
+            ActionTrigger::Hold { action, .. } => self.actions_down_since.get(action).cloned(),
+            _ => None,
+        }
+    }
+
+    fn fire_trigger(&mut self, id: T::Action, event_handler: &mut EventChannel<InputEvent<T>>) {
+        event_handler.single_write(ActionTriggered(id.clone()));
+        self.triggered_actions.push(id);
+    }
+
+    /// Returns every active trigger, leaving out the ones shadowed by a higher context.
+    fn active_triggers(&self) -> Vec<(T::Action, ActionTrigger<T::Action>)> {
+        let layers = self.layers();
+        let mut active = Vec::new();
+        for (index, &layer) in layers.iter().enumerate() {
+            let above = &layers[..index];
+            active.extend(
+                layer
+                    .triggers
+                    .iter()
+                    .filter(|(id, _)| !above.iter().any(|l| l.triggers.contains_key(*id)))
+                    .map(|(id, trigger)| (id.clone(), trigger.clone())),
+            );
+        }
+        active
     }
 
     /// Retrieve next free controller number to allocate new controller to
@@ -633,27 +1609,25 @@ where
         };
 
         // check for actions being bound to any invoked mouse wheel
-        for (action, combinations) in self.bindings.actions.iter() {
-            for combination in combinations {
-                if let Some(dir) = dir_x {
-                    if combination.contains(&Button::MouseWheel(dir))
-                        && combination
-                            .iter()
-                            .filter(|b| **b != Button::MouseWheel(dir))
-                            .all(|b| self.button_is_down(*b))
-                    {
-                        events.push(ActionWheelMoved(action.clone()));
-                    }
+        for (action, combination) in self.active_combinations() {
+            if let Some(dir) = dir_x {
+                if combination.contains(&Button::MouseWheel(dir))
+                    && combination
+                        .iter()
+                        .filter(|b| **b != Button::MouseWheel(dir))
+                        .all(|b| self.button_is_down(*b))
+                {
+                    events.push(ActionWheelMoved(action.clone()));
                 }
-                if let Some(dir) = dir_y {
-                    if combination.contains(&Button::MouseWheel(dir))
-                        && combination
-                            .iter()
-                            .filter(|b| **b != Button::MouseWheel(dir))
-                            .all(|b| self.button_is_down(*b))
-                    {
-                        events.push(ActionWheelMoved(action.clone()));
-                    }
+            }
+            if let Some(dir) = dir_y {
+                if combination.contains(&Button::MouseWheel(dir))
+                    && combination
+                        .iter()
+                        .filter(|b| **b != Button::MouseWheel(dir))
+                        .all(|b| self.button_is_down(*b))
+                {
+                    events.push(ActionWheelMoved(action.clone()));
                 }
             }
         }
@@ -668,39 +1642,24 @@ where
         key_code: VirtualKeyCode,
         scancode: u32,
     ) {
-        for (axis, input_axis) in self.bindings.axes.iter() {
+        for (axis, input_axis) in self.active_axes() {
             if let Axis::Emulated { pos, neg } = input_axis {
-                let value = self
-                    .axis_value(axis)
-                    .expect("Unreachable: `axis` is from bindings axes.");
                 match *pos {
                     Button::Key(key_code_pos) if key_code_pos == key_code => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+                        self.send_axis_moved(axis, event_handler);
                     }
                     Button::ScanCode(scancode_pos) if scancode_pos == scancode => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+                        self.send_axis_moved(axis, event_handler);
                     }
                     _ => {}
                 }
 
                 match *neg {
                     Button::Key(key_code_neg) if key_code_neg == key_code => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+                        self.send_axis_moved(axis, event_handler);
                     }
                     Button::ScanCode(scancode_neg) if scancode_neg == scancode => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+                        self.send_axis_moved(axis, event_handler);
                     }
                     _ => {}
                 }
@@ -713,27 +1672,18 @@ where
         event_handler: &mut EventChannel<InputEvent<T>>,
         mouse_button: MouseButton,
     ) {
-        for (axis, input_axis) in self.bindings.axes.iter() {
+        for (axis, input_axis) in self.active_axes() {
             if let Axis::Emulated { pos, neg } = input_axis {
-                let value = self
-                    .axis_value(axis)
-                    .expect("Unreachable: `axis` is from bindings axes.");
                 match *pos {
                     Button::Mouse(mouse_button_pos) if mouse_button_pos == mouse_button => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+                        self.send_axis_moved(axis, event_handler);
                     }
                     _ => {}
                 }
 
                 match *neg {
                     Button::Mouse(mouse_button_neg) if mouse_button_neg == mouse_button => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+                        self.send_axis_moved(axis, event_handler);
                     }
                     _ => {}
                 }
@@ -1201,6 +2151,499 @@ mod tests {
         assert_ulps_eq!(handler.mouse_wheel_value(true), -1.0);
     }
 
+    #[test]
+    fn shadowing_context_hides_shared_buttons() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("pause"),
+                [Button::Key(VirtualKeyCode::Escape)].iter().cloned(),
+            )
+            .unwrap();
+        let mut menu = Bindings::new();
+        menu.insert_action_binding(
+            String::from("select"),
+            [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+        )
+        .unwrap();
+        handler.insert_context("menu", BindingContext::shadowing(menu));
+        handler.push_context("menu");
+
+        handler.send_event(&key_press(57, VirtualKeyCode::Space), &mut events, HIDPI);
+        handler.send_event(&key_press(1, VirtualKeyCode::Escape), &mut events, HIDPI);
+        assert_eq!(handler.action_is_down("select"), Some(true));
+        assert_eq!(handler.action_is_down("jump"), Some(false));
+        assert_eq!(handler.action_is_down("pause"), Some(true));
+        let actions = events
+            .read(&mut reader)
+            .filter(|event| match event {
+                ActionPressed(_) => true,
+                _ => false,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        sets_are_equal(
+            &actions,
+            &[
+                ActionPressed(String::from("select")),
+                ActionPressed(String::from("pause")),
+            ],
+        );
+
+        assert_eq!(handler.pop_context(), Some(String::from("menu")));
+        assert_eq!(handler.action_is_down("select"), None);
+        assert_eq!(handler.action_is_down("jump"), Some(true));
+    }
+
+    #[test]
+    fn consuming_context_disables_lower_contexts() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        handler
+            .bindings
+            .insert_axis(
+                String::from("move"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::D),
+                    neg: Button::Key(VirtualKeyCode::A),
+                },
+            )
+            .unwrap();
+        let mut vehicle = Bindings::new();
+        vehicle
+            .insert_action_binding(
+                String::from("honk"),
+                [Button::Key(VirtualKeyCode::H)].iter().cloned(),
+            )
+            .unwrap();
+        handler.insert_context("vehicle", BindingContext::shadowing(vehicle));
+        handler.insert_context("text_entry", BindingContext::consuming(Bindings::new()));
+        handler.push_context("vehicle");
+        handler.push_context("text_entry");
+
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_event(&key_press(35, VirtualKeyCode::H), &mut events, HIDPI);
+        assert_eq!(handler.axis_value("move"), Some(0.0));
+        assert_eq!(handler.action_is_down("honk"), Some(false));
+        assert_eq!(
+            handler.active_contexts().collect::<Vec<_>>(),
+            vec!["vehicle", "text_entry"]
+        );
+
+        assert!(handler.pop_context_named("text_entry"));
+        assert_eq!(handler.axis_value("move"), Some(1.0));
+        assert_eq!(handler.action_is_down("honk"), Some(true));
+    }
+
+    #[test]
+    fn action_edges_last_one_frame() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+
+        handler.send_frame_begin();
+        handler.send_event(&key_press(57, VirtualKeyCode::Space), &mut events, HIDPI);
+        handler.send_event(&key_release(57, VirtualKeyCode::Space), &mut events, HIDPI);
+        assert!(handler.action_just_pressed("jump"));
+        assert!(handler.action_just_released("jump"));
+
+        handler.send_frame_begin();
+        handler.send_event(&key_press(57, VirtualKeyCode::Space), &mut events, HIDPI);
+        assert!(handler.action_just_pressed("jump"));
+        assert!(!handler.action_just_released("jump"));
+
+        handler.send_frame_begin();
+        assert!(!handler.action_just_pressed("jump"));
+        assert_eq!(handler.action_is_down("jump"), Some(true));
+    }
+
+    #[test]
+    fn hold_and_tap_triggers() {
+        use amethyst_core::shrev::ReaderId;
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("fire"),
+                [Button::Mouse(MouseButton::Left)].iter().cloned(),
+            )
+            .unwrap();
+        handler.bindings.insert_trigger(
+            String::from("charged_shot"),
+            ActionTrigger::Hold {
+                action: String::from("fire"),
+                ms: 500,
+            },
+        );
+        handler.bindings.insert_trigger(
+            String::from("quick_shot"),
+            ActionTrigger::Tap {
+                action: String::from("fire"),
+                ms: 150,
+            },
+        );
+        let triggered =
+            |events: &EventChannel<InputEvent<StringBindings>>,
+             reader: &mut ReaderId<InputEvent<StringBindings>>| {
+                events
+                    .read(reader)
+                    .filter(|event| match event {
+                        ActionTriggered(_) => true,
+                        _ => false,
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+
+        handler.send_time(Duration::from_millis(0), &mut events);
+        handler.send_event(&mouse_press(MouseButton::Left), &mut events, HIDPI);
+        handler.send_time(Duration::from_millis(100), &mut events);
+        handler.send_event(&mouse_release(MouseButton::Left), &mut events, HIDPI);
+        assert!(handler.action_triggered("quick_shot"));
+        assert_eq!(
+            triggered(&events, &mut reader),
+            vec![ActionTriggered(String::from("quick_shot"))]
+        );
+
+        handler.send_frame_begin();
+        handler.send_time(Duration::from_millis(1000), &mut events);
+        handler.send_event(&mouse_press(MouseButton::Left), &mut events, HIDPI);
+        handler.send_frame_begin();
+        handler.send_time(Duration::from_millis(1400), &mut events);
+        assert!(!handler.action_triggered("charged_shot"));
+        handler.send_frame_begin();
+        handler.send_time(Duration::from_millis(1600), &mut events);
+        assert!(handler.action_triggered("charged_shot"));
+        handler.send_event(&mouse_release(MouseButton::Left), &mut events, HIDPI);
+        assert_eq!(
+            triggered(&events, &mut reader),
+            vec![ActionTriggered(String::from("charged_shot"))]
+        );
+    }
+
+    #[test]
+    fn capture_next_pressed_button() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+
+        handler.send_event(&key_press(42, VirtualKeyCode::LShift), &mut events, HIDPI);
+        handler.start_capture(CaptureKind::Button);
+        assert!(handler.is_capturing());
+        handler.send_event(&key_release(42, VirtualKeyCode::LShift), &mut events, HIDPI);
+        handler.send_event(&mouse_wheel(0.0, 1.0), &mut events, HIDPI);
+        handler.send_event(&key_press(19, VirtualKeyCode::R), &mut events, HIDPI);
+        handler.send_event(&key_press(57, VirtualKeyCode::Space), &mut events, HIDPI);
+
+        let captured = Button::MouseWheel(ScrollDirection::ScrollUp);
+        assert!(!handler.is_capturing());
+        assert_eq!(
+            handler.take_captured(),
+            Some(CapturedInput::Button(captured))
+        );
+        assert_eq!(handler.take_captured(), None);
+        let captured_events = events
+            .read(&mut reader)
+            .filter(|event| match event {
+                InputCaptured(_) => true,
+                _ => false,
+            })
+            .count();
+        assert_eq!(captured_events, 1);
+
+        handler.start_capture(CaptureKind::Any);
+        handler.send_event(&key_press(19, VirtualKeyCode::R), &mut events, HIDPI);
+        assert_eq!(
+            handler.take_captured(),
+            Some(CapturedInput::Button(Button::Key(VirtualKeyCode::R)))
+        );
+    }
+
+    #[test]
+    fn capture_axis_ignores_buttons() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+
+        handler.start_capture(CaptureKind::Axis);
+        handler.send_event(&key_press(19, VirtualKeyCode::R), &mut events, HIDPI);
+        assert!(handler.is_capturing());
+        handler.send_event(&mouse_wheel(-1.0, 0.0), &mut events, HIDPI);
+        assert_eq!(
+            handler.take_captured(),
+            Some(CapturedInput::Axis(Axis::MouseWheel { horizontal: true }))
+        );
+
+        handler.start_capture(CaptureKind::Axis);
+        handler.cancel_capture();
+        handler.send_event(&mouse_wheel(0.0, 1.0), &mut events, HIDPI);
+        assert_eq!(handler.take_captured(), None);
+    }
+
+    #[test]
+    fn emulated_axis_ramps_up() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        handler
+            .bindings
+            .insert_axis(
+                String::from("leftright"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::D),
+                    neg: Button::Key(VirtualKeyCode::A),
+                },
+            )
+            .unwrap();
+        handler.bindings.insert_response(
+            String::from("leftright"),
+            AxisResponse {
+                ramp_ms: 200,
+                ..Default::default()
+            },
+        );
+
+        let mut reader = events.register_reader();
+        let mut axis_events = |events: &EventChannel<InputEvent<StringBindings>>| {
+            events
+                .read(&mut reader)
+                .filter_map(|event| match event {
+                    InputEvent::AxisMoved { value, .. } => Some(*value),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        handler.send_time(Duration::from_millis(0), &mut events);
+        handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+        assert_eq!(handler.axis_value("leftright"), Some(0.0));
+        // The value is only sent once it moved.
+        assert_eq!(axis_events(&events), vec![]);
+        handler.send_time(Duration::from_millis(100), &mut events);
+        assert_eq!(handler.axis_value("leftright"), Some(0.5));
+        assert_eq!(axis_events(&events), vec![0.5]);
+        handler.send_time(Duration::from_millis(300), &mut events);
+        assert_eq!(handler.axis_value("leftright"), Some(1.0));
+        handler.send_event(&key_release(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_time(Duration::from_millis(350), &mut events);
+        assert_eq!(handler.axis_value("leftright"), Some(0.75));
+    }
+
+    #[test]
+    fn axis_2d_radial_dead_zone() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let stick = |axis| Axis::Controller {
+            controller_id: 0,
+            axis,
+            invert: false,
+            dead_zone: 0.5,
+        };
+        handler.bindings.insert_axis_2d(
+            String::from("move"),
+            Axis2D {
+                dead_zone: 0.2,
+                ..Axis2D::new(stick(ControllerAxis::LeftX), stick(ControllerAxis::LeftY))
+            },
+        );
+        let move_stick = |handler: &mut InputHandler<StringBindings>,
+                          events: &mut EventChannel<InputEvent<StringBindings>>,
+                          axis,
+                          value| {
+            handler.send_controller_event(
+                &ControllerEvent::ControllerAxisMoved {
+                    which: 0,
+                    axis,
+                    value,
+                },
+                events,
+            );
+        };
+
+        handler.send_controller_event(
+            &ControllerEvent::ControllerConnected { which: 0 },
+            &mut events,
+        );
+        assert_eq!(handler.axis_2d_value("move"), Some((0.0, 0.0)));
+        move_stick(&mut handler, &mut events, ControllerAxis::LeftX, 0.1);
+        move_stick(&mut handler, &mut events, ControllerAxis::LeftY, 0.1);
+        assert_eq!(handler.axis_2d_value("move"), Some((0.0, 0.0)));
+
+        // The per-axis dead zone of the stick axes would hide this diagonal entirely.
+        move_stick(&mut handler, &mut events, ControllerAxis::LeftX, 0.36);
+        move_stick(&mut handler, &mut events, ControllerAxis::LeftY, 0.48);
+        let (x, y) = handler.axis_2d_value("move").unwrap();
+        assert!((x - 0.3).abs() < 1e-5);
+        assert!((y - 0.4).abs() < 1e-5);
+        assert_eq!(handler.axis_2d_value("look"), None);
+    }
+
+    #[test]
+    fn touch_controls_and_gestures() {
+        use crate::touch::Gesture as TouchGesture;
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        handler.insert_touch_button(
+            0,
+            TouchButton {
+                x: 900.0,
+                y: 500.0,
+                width: 100.0,
+                height: 100.0,
+            },
+        );
+        handler.insert_touch_stick(
+            0,
+            TouchStick {
+                center: (100.0, 500.0),
+                radius: 50.0,
+            },
+        );
+        handler
+            .bindings
+            .insert_action_binding(String::from("jump"), [Button::Touch(0)].iter().cloned())
+            .unwrap();
+        handler
+            .bindings
+            .insert_axis(
+                String::from("leftright"),
+                Axis::TouchStick {
+                    stick: 0,
+                    vertical: false,
+                },
+            )
+            .unwrap();
+
+        handler.send_event(
+            &touch(1, TouchPhase::Started, 950.0, 550.0),
+            &mut events,
+            HIDPI,
+        );
+        handler.send_event(
+            &touch(2, TouchPhase::Started, 100.0, 500.0),
+            &mut events,
+            HIDPI,
+        );
+        handler.send_event(
+            &touch(2, TouchPhase::Moved, 125.0, 500.0),
+            &mut events,
+            HIDPI,
+        );
+        assert_eq!(handler.action_is_down("jump"), Some(true));
+        assert_eq!(handler.axis_value("leftright"), Some(0.5));
+        assert_eq!(handler.touches().count(), 2);
+
+        handler.send_event(
+            &touch(1, TouchPhase::Ended, 950.0, 550.0),
+            &mut events,
+            HIDPI,
+        );
+        handler.send_event(
+            &touch(2, TouchPhase::Ended, 125.0, 500.0),
+            &mut events,
+            HIDPI,
+        );
+        assert_eq!(handler.action_is_down("jump"), Some(false));
+        assert_eq!(handler.axis_value("leftright"), Some(0.0));
+        let event_vec = events.read(&mut reader).cloned().collect::<Vec<_>>();
+        assert!(event_vec.contains(&ActionPressed(String::from("jump"))));
+        assert!(event_vec.contains(&ActionReleased(String::from("jump"))));
+        assert!(event_vec.iter().all(|event| match event {
+            Gesture(_) => false,
+            _ => true,
+        }));
+
+        handler.send_time(Duration::from_millis(1000), &mut events);
+        handler.send_event(
+            &touch(3, TouchPhase::Started, 500.0, 300.0),
+            &mut events,
+            HIDPI,
+        );
+        handler.send_time(Duration::from_millis(1100), &mut events);
+        handler.send_event(
+            &touch(3, TouchPhase::Ended, 502.0, 300.0),
+            &mut events,
+            HIDPI,
+        );
+        let gestures = events
+            .read(&mut reader)
+            .filter_map(|event| match event {
+                Gesture(gesture) => Some(gesture.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            gestures,
+            vec![TouchGesture::Tap {
+                position: (502.0, 300.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn controller_info_and_rumble() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let rumble = Rumble::new(0.5, Duration::from_millis(200));
+
+        assert!(!handler.rumble(0, rumble.clone()));
+        handler.send_controller_event(
+            &ControllerEvent::ControllerConnected { which: 7 },
+            &mut events,
+        );
+        handler.send_controller_info(7, ControllerInfo::new("PS4 Controller", BatteryLevel::Low));
+        let info = handler.controller_info(0).unwrap();
+        assert_eq!(info.kind, ControllerKind::PlayStation);
+        assert_eq!(info.battery, BatteryLevel::Low);
+        assert_eq!(
+            handler.controller_button_name(0, ControllerButton::Y),
+            "Triangle"
+        );
+
+        assert!(handler.rumble(0, rumble.clone()));
+        assert_eq!(
+            handler.drain_rumbles().collect::<Vec<_>>(),
+            vec![(7, rumble)]
+        );
+        assert_eq!(handler.drain_rumbles().next(), None);
+
+        // Rumbles requested before the frame began, e.g. by a state, are kept for the backend.
+        let stronger = Rumble::new(1.0, Duration::from_millis(100));
+        assert!(handler.rumble(0, rumble.clone()));
+        assert!(handler.rumble(0, stronger.clone()));
+        handler.send_frame_begin();
+        assert_eq!(
+            handler.drain_rumbles().collect::<Vec<_>>(),
+            vec![(7, stronger)]
+        );
+
+        handler.send_controller_event(
+            &ControllerEvent::ControllerDisconnected { which: 7 },
+            &mut events,
+        );
+        assert_eq!(handler.controller_info(0), None);
+        assert_eq!(handler.controller_button_name(0, ControllerButton::Y), "Y");
+    }
+
     /// Compares two sets for equality, but not the order
     fn sets_are_equal<T>(a: &[T], b: &[T])
     where
@@ -1286,6 +2729,18 @@ right: `{:?}`",
         }
     }
 
+    fn touch(id: u64, phase: TouchPhase, x: f64, y: f64) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::Touch(Touch {
+                device_id: unsafe { DeviceId::dummy() },
+                phase,
+                location: LogicalPosition { x, y },
+                id,
+            }),
+        }
+    }
+
     fn mouse_wheel(x: f32, y: f32) -> Event {
         Event::DeviceEvent {
             device_id: unsafe { DeviceId::dummy() },
@@ -12,15 +12,22 @@
 #[cfg(feature = "sdl_controller")]
 pub use self::sdl_events_system::SdlEventsSystem;
 pub use self::{
-    axis::Axis,
+    axis::{Axis, Axis2D},
     bindings::{BindingError, BindingTypes, Bindings, StringBindings},
     bundle::{BindingsFileError, InputBundle},
     button::Button,
+    capture::{CaptureKind, CapturedInput},
+    context::{BindingContext, ContextMode},
     controller::{ControllerAxis, ControllerButton, ControllerEvent},
+    controller_info::{BatteryLevel, ControllerInfo, ControllerKind, Rumble},
     event::InputEvent,
     input_handler::InputHandler,
+    recording::{InputPlayback, InputRecorder, InputRecording, RecordedEvent, RecordedFrame},
+    response::{AxisResponse, ResponseCurve},
     scroll_direction::ScrollDirection,
     system::{InputSystem, InputSystemDesc},
+    touch::{Gesture, GestureSettings, TouchButton, TouchPoint, TouchStick},
+    trigger::ActionTrigger,
     util::{
         get_input_axis_simple, get_key, get_mouse_button, is_close_requested, is_key_down,
         is_key_up, is_mouse_button_down,
@@ -36,11 +43,18 @@ mod axis;
 mod bindings;
 mod bundle;
 mod button;
+mod capture;
+mod context;
 mod controller;
+mod controller_info;
 mod event;
 mod input_handler;
+mod recording;
+mod response;
 mod scroll_direction;
 mod system;
+mod touch;
+mod trigger;
 mod util;
 
 #[cfg(feature = "sdl_controller")]
@@ -0,0 +1,469 @@
+//! Recording of input sessions, and their playback.
+
+use std::time::Duration;
+
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::LogicalPosition, DeviceEvent, DeviceId, ElementState, Event, KeyboardInput,
+    ModifiersState, MouseButton, MouseScrollDelta, Touch, TouchPhase, VirtualKeyCode, WindowEvent,
+    WindowId,
+};
+
+use amethyst_core::shrev::EventChannel;
+
+use super::{controller::ControllerEvent, event::InputEvent, BindingTypes, InputHandler};
+
+/// A raw input event, as sent to the `InputHandler`.
+///
+/// Only the events the `InputHandler` reacts to are recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    /// A `WindowEvent::KeyboardInput`.
+    Key {
+        /// Scancode of the key.
+        scancode: u32,
+        /// Whether the key was pressed or released.
+        state: ElementState,
+        /// Virtual key code of the key, if any.
+        key_code: Option<VirtualKeyCode>,
+        /// Modifiers held with the key.
+        modifiers: ModifiersState,
+    },
+    /// A `WindowEvent::MouseInput`.
+    MouseButton {
+        /// Whether the button was pressed or released.
+        state: ElementState,
+        /// The mouse button.
+        button: MouseButton,
+        /// Modifiers held with the button.
+        modifiers: ModifiersState,
+    },
+    /// A `WindowEvent::CursorMoved`.
+    CursorMoved {
+        /// The logical horizontal position of the cursor.
+        x: f64,
+        /// The logical vertical position of the cursor.
+        y: f64,
+        /// Modifiers held while moving.
+        modifiers: ModifiersState,
+    },
+    /// A `WindowEvent::Touch`.
+    Touch {
+        /// Identifies the touch until it ends.
+        id: u64,
+        /// Whether the touch started, moved or ended.
+        phase: TouchPhase,
+        /// The logical horizontal position of the touch.
+        x: f64,
+        /// The logical vertical position of the touch.
+        y: f64,
+    },
+    /// A `WindowEvent::ReceivedCharacter`.
+    Character(char),
+    /// A `WindowEvent::Focused`.
+    Focused(bool),
+    /// A `DeviceEvent::MouseMotion`.
+    MouseMotion {
+        /// The horizontal motion.
+        delta_x: f64,
+        /// The vertical motion.
+        delta_y: f64,
+    },
+    /// A `DeviceEvent::MouseWheel`.
+    MouseWheel(MouseScrollDelta),
+    /// A controller event, sent with `InputHandler::send_controller_event`.
+    Controller(ControllerEvent),
+}
+
+impl RecordedEvent {
+    /// Converts a winit event, returns `None` for events the `InputHandler` ignores.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match *event {
+            Event::WindowEvent { ref event, .. } => match *event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            scancode,
+                            state,
+                            virtual_keycode,
+                            modifiers,
+                        },
+                    ..
+                } => Some(RecordedEvent::Key {
+                    scancode,
+                    state,
+                    key_code: virtual_keycode,
+                    modifiers,
+                }),
+                WindowEvent::MouseInput {
+                    state,
+                    button,
+                    modifiers,
+                    ..
+                } => Some(RecordedEvent::MouseButton {
+                    state,
+                    button,
+                    modifiers,
+                }),
+                WindowEvent::CursorMoved {
+                    position: LogicalPosition { x, y },
+                    modifiers,
+                    ..
+                } => Some(RecordedEvent::CursorMoved { x, y, modifiers }),
+                WindowEvent::Touch(Touch {
+                    phase,
+                    location: LogicalPosition { x, y },
+                    id,
+                    ..
+                }) => Some(RecordedEvent::Touch { id, phase, x, y }),
+                WindowEvent::ReceivedCharacter(c) => Some(RecordedEvent::Character(c)),
+                WindowEvent::Focused(focused) => Some(RecordedEvent::Focused(focused)),
+                _ => None,
+            },
+            Event::DeviceEvent { ref event, .. } => match *event {
+                DeviceEvent::MouseMotion {
+                    delta: (delta_x, delta_y),
+                } => Some(RecordedEvent::MouseMotion { delta_x, delta_y }),
+                DeviceEvent::MouseWheel { delta } => Some(RecordedEvent::MouseWheel(delta)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Sends the event to the `InputHandler`, like the `InputSystem` or the `SdlEventsSystem`
+    /// would.
+    pub fn send_to<T: BindingTypes>(
+        &self,
+        handler: &mut InputHandler<T>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+        hidpi: f32,
+    ) {
+        // The `InputHandler` does not look at window and device ids.
+        let window_id = unsafe { WindowId::dummy() };
+        let device_id = unsafe { DeviceId::dummy() };
+        let window_event = |event| Event::WindowEvent { window_id, event };
+        let event = match *self {
+            RecordedEvent::Key {
+                scancode,
+                state,
+                key_code,
+                modifiers,
+            } => window_event(WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode,
+                    state,
+                    virtual_keycode: key_code,
+                    modifiers,
+                },
+            }),
+            RecordedEvent::MouseButton {
+                state,
+                button,
+                modifiers,
+            } => window_event(WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+                modifiers,
+            }),
+            RecordedEvent::CursorMoved { x, y, modifiers } => {
+                window_event(WindowEvent::CursorMoved {
+                    device_id,
+                    position: LogicalPosition { x, y },
+                    modifiers,
+                })
+            }
+            RecordedEvent::Touch { id, phase, x, y } => window_event(WindowEvent::Touch(Touch {
+                device_id,
+                phase,
+                location: LogicalPosition { x, y },
+                id,
+            })),
+            RecordedEvent::Character(c) => window_event(WindowEvent::ReceivedCharacter(c)),
+            RecordedEvent::Focused(focused) => window_event(WindowEvent::Focused(focused)),
+            RecordedEvent::MouseMotion { delta_x, delta_y } => Event::DeviceEvent {
+                device_id,
+                event: DeviceEvent::MouseMotion {
+                    delta: (delta_x, delta_y),
+                },
+            },
+            RecordedEvent::MouseWheel(delta) => Event::DeviceEvent {
+                device_id,
+                event: DeviceEvent::MouseWheel { delta },
+            },
+            RecordedEvent::Controller(ref event) => {
+                handler.send_controller_event(event, event_handler);
+                return;
+            }
+        };
+        handler.send_event(&event, event_handler, hidpi);
+    }
+}
+
+/// The input of one frame of a recorded session.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Clone(bound = ""), PartialEq(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct RecordedFrame<T: BindingTypes> {
+    /// The `Time::frame_number` of the frame when it was recorded.
+    pub frame_number: u64,
+    /// The real time of the frame, since the recording started.
+    pub time: Duration,
+    /// The raw events sent to the `InputHandler` during the frame.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<RecordedEvent>,
+    /// The `InputEvent`s sent during the frame, used to detect desyncs when playing back.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_events: Vec<InputEvent<T>>,
+}
+
+/// A recorded input session, one entry per frame.
+///
+/// Recordings are saved and loaded as RON files through `amethyst_config::Config`, e.g.
+/// `recording.write("bug_1234.ron")` and `InputRecording::load_no_fallback("bug_1234.ron")`.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(
+    Debug(bound = ""),
+    Default(bound = ""),
+    Clone(bound = ""),
+    PartialEq(bound = "")
+)]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct InputRecording<T: BindingTypes> {
+    /// The hidpi factor of the window during the recording.
+    #[derivative(Default(value = "1.0"))]
+    pub hidpi: f32,
+    /// The recorded frames, in order.
+    pub frames: Vec<RecordedFrame<T>>,
+}
+
+/// Records the input processed by the `InputSystem`.
+///
+/// Start a recording with `start`, and retrieve it with `stop` to save it.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct InputRecorder<T: BindingTypes> {
+    recording: Option<InputRecording<T>>,
+    start: Option<Duration>,
+}
+
+impl<T: BindingTypes> InputRecorder<T> {
+    /// Starts a new recording from the next frame on, dropping the current one.
+    pub fn start(&mut self) {
+        self.recording = Some(InputRecording::default());
+        self.start = None;
+    }
+
+    /// Stops recording and returns the recording, if any.
+    pub fn stop(&mut self) -> Option<InputRecording<T>> {
+        self.start = None;
+        self.recording.take()
+    }
+
+    /// Returns whether input is being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Returns the current recording, if any.
+    pub fn recording(&self) -> Option<&InputRecording<T>> {
+        self.recording.as_ref()
+    }
+
+    pub(crate) fn record_frame(
+        &mut self,
+        frame_number: u64,
+        time: Duration,
+        hidpi: f32,
+        events: Vec<RecordedEvent>,
+        input_events: Vec<InputEvent<T>>,
+    ) {
+        if let Some(recording) = self.recording.as_mut() {
+            let start = *self.start.get_or_insert(time);
+            if recording.frames.is_empty() {
+                recording.hidpi = hidpi;
+            }
+            recording.frames.push(RecordedFrame {
+                frame_number,
+                time: time.checked_sub(start).unwrap_or_default(),
+                events,
+                input_events,
+            });
+        }
+    }
+}
+
+/// Plays a recorded input session back through the `InputSystem`, one recorded frame per frame.
+///
+/// While a recording plays, window events and controller events are not sent to the
+/// `InputHandler`. Insert it with a recording in an `amethyst_test` application to replay a
+/// session as a regression test, then check `desynced_frames`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct InputPlayback<T: BindingTypes> {
+    recording: Option<InputRecording<T>>,
+    next_frame: usize,
+    desynced_frames: Vec<u64>,
+}
+
+impl<T: BindingTypes> InputPlayback<T> {
+    /// Creates a playback of the given recording, starting on the next frame.
+    pub fn new(recording: InputRecording<T>) -> Self {
+        let mut playback = InputPlayback::default();
+        playback.play(recording);
+        playback
+    }
+
+    /// Plays the given recording from the next frame on, replacing the current one.
+    pub fn play(&mut self, recording: InputRecording<T>) {
+        self.recording = Some(recording);
+        self.next_frame = 0;
+        self.desynced_frames.clear();
+    }
+
+    /// Stops playing.
+    pub fn stop(&mut self) {
+        self.recording = None;
+    }
+
+    /// Returns whether a recording is playing.
+    pub fn is_playing(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Returns the amount of frames played so far.
+    pub fn frames_played(&self) -> usize {
+        self.next_frame
+    }
+
+    /// Returns the recorded frame numbers of the frames which did not send the same
+    /// `InputEvent`s as when they were recorded.
+    pub fn desynced_frames(&self) -> &[u64] {
+        &self.desynced_frames
+    }
+
+    /// Returns the next frame to play and the hidpi factor of the recording, stopping once the
+    /// recording is over.
+    pub(crate) fn next_frame(&mut self) -> Option<(RecordedFrame<T>, f32)> {
+        let recording = self.recording.as_ref()?;
+        match recording.frames.get(self.next_frame) {
+            Some(frame) => {
+                self.next_frame += 1;
+                Some((frame.clone(), recording.hidpi))
+            }
+            None => {
+                self.recording = None;
+                None
+            }
+        }
+    }
+
+    /// Compares the events sent while playing a frame with the recorded ones.
+    pub(crate) fn check(&mut self, frame: &RecordedFrame<T>, input_events: &[InputEvent<T>]) {
+        if !same_events(&frame.input_events, input_events) {
+            self.desynced_frames.push(frame.frame_number);
+        }
+    }
+}
+
+/// Compares events regardless of their order, since systems may send them in any order.
+fn same_events<T: BindingTypes>(a: &[InputEvent<T>], b: &[InputEvent<T>]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut remaining = b.iter().collect::<Vec<_>>();
+    a.iter()
+        .all(|event| match remaining.iter().position(|e| *e == event) {
+            Some(i) => {
+                remaining.swap_remove(i);
+                true
+            }
+            None => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Button, StringBindings};
+
+    fn key(state: ElementState) -> RecordedEvent {
+        RecordedEvent::Key {
+            scancode: 57,
+            state,
+            key_code: Some(VirtualKeyCode::Space),
+            modifiers: ModifiersState {
+                shift: false,
+                ctrl: false,
+                alt: false,
+                logo: false,
+            },
+        }
+    }
+
+    #[test]
+    fn recorded_events_round_trip() {
+        let event = key(ElementState::Pressed);
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut channel = EventChannel::new();
+        let mut reader = channel.register_reader();
+
+        event.send_to(&mut handler, &mut channel, 1.0);
+        assert!(handler.key_is_down(VirtualKeyCode::Space));
+        assert!(channel
+            .read(&mut reader)
+            .any(|e| *e == InputEvent::ButtonPressed(Button::Key(VirtualKeyCode::Space))));
+    }
+
+    #[test]
+    fn recorder_times_frames_from_the_start() {
+        let mut recorder = InputRecorder::<StringBindings>::default();
+        recorder.record_frame(1, Duration::from_secs(1), 1.0, Vec::new(), Vec::new());
+        assert!(recorder.recording().is_none());
+
+        recorder.start();
+        recorder.record_frame(7, Duration::from_secs(5), 2.0, Vec::new(), Vec::new());
+        let events = vec![key(ElementState::Pressed)];
+        recorder.record_frame(8, Duration::from_secs(6), 2.0, events.clone(), Vec::new());
+        let recording = recorder.stop().unwrap();
+
+        assert_eq!(recording.hidpi, 2.0);
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(recording.frames[1].frame_number, 8);
+        assert_eq!(recording.frames[1].time, Duration::from_secs(1));
+        assert_eq!(recording.frames[1].events, events);
+    }
+
+    #[test]
+    fn playback_detects_desyncs() {
+        let frame = RecordedFrame::<StringBindings> {
+            frame_number: 3,
+            time: Duration::from_secs(0),
+            events: Vec::new(),
+            input_events: vec![InputEvent::KeyTyped('a')],
+        };
+        let mut playback = InputPlayback::new(InputRecording {
+            hidpi: 1.0,
+            frames: vec![frame.clone(), frame],
+        });
+
+        let (frame, _) = playback.next_frame().unwrap();
+        playback.check(&frame, &[InputEvent::KeyTyped('a')]);
+        let (frame, _) = playback.next_frame().unwrap();
+        playback.check(&frame, &[]);
+        assert!(playback.next_frame().is_none());
+
+        assert!(!playback.is_playing());
+        assert_eq!(playback.frames_played(), 2);
+        assert_eq!(playback.desynced_frames(), &[3]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Processing of analogue axis values: response curves, sensitivity, ramping and smoothing.
+
+use std::time::Duration;
+
+use amethyst_core::timing::duration_to_secs;
+use serde::{Deserialize, Serialize};
+
+/// Maps the magnitude of an axis, from 0 to 1, to the magnitude of its value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    /// The value is the axis input.
+    Linear,
+    /// The value is the square of the axis input, for finer control of small movements.
+    Quadratic,
+    /// The value is `cubic * x³ + quadratic * x² + linear * x` of the axis input `x`.
+    ///
+    /// The coefficients should add up to 1, so a fully moved axis still reaches 1.
+    Cubic {
+        /// Coefficient of the cubed input.
+        cubic: f32,
+        /// Coefficient of the squared input.
+        quadratic: f32,
+        /// Coefficient of the input.
+        linear: f32,
+    },
+}
+
+impl Default for ResponseCurve {
+    fn default() -> Self {
+        ResponseCurve::Linear
+    }
+}
+
+impl ResponseCurve {
+    /// Applies the curve to a magnitude from 0 to 1.
+    pub fn apply(&self, magnitude: f32) -> f32 {
+        let x = magnitude.max(0.0).min(1.0);
+        match *self {
+            ResponseCurve::Linear => x,
+            ResponseCurve::Quadratic => x * x,
+            ResponseCurve::Cubic {
+                cubic,
+                quadratic,
+                linear,
+            } => ((cubic * x + quadratic) * x + linear) * x,
+        }
+    }
+}
+
+/// How the value of an axis responds to its input.
+///
+/// Responses are part of the `Bindings`, keyed by the axis they apply to. Durations are in
+/// milliseconds. Example Ron config:
+/// ```ron
+/// (
+///     axes: {
+///         "throttle": Controller(controller_id: 0, axis: RightTrigger, invert: false, dead_zone: 0.05),
+///         "leftright": Emulated(pos: Key(D), neg: Key(A)),
+///     },
+///     actions: {},
+///     responses: {
+///         "throttle": (curve: Quadratic, sensitivity: 0.8, smoothing_ms: 50),
+///         "leftright": (ramp_ms: 150),
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisResponse {
+    /// The curve applied to the magnitude of the axis.
+    pub curve: ResponseCurve,
+    /// Scale applied to the value after the curve.
+    pub sensitivity: f32,
+    /// How long the value takes to move from 0 to 1, instead of jumping to its input.
+    ///
+    /// Mostly useful for `Axis::Emulated`, the value restarts from 0 when the direction reverses.
+    pub ramp_ms: u64,
+    /// Time constant of the exponential smoothing of the value, 0 to disable smoothing.
+    pub smoothing_ms: u64,
+}
+
+impl Default for AxisResponse {
+    fn default() -> Self {
+        AxisResponse {
+            curve: ResponseCurve::Linear,
+            sensitivity: 1.0,
+            ramp_ms: 0,
+            smoothing_ms: 0,
+        }
+    }
+}
+
+impl AxisResponse {
+    /// Applies the curve and the sensitivity to an axis input from -1 to 1.
+    pub fn apply(&self, input: f32) -> f32 {
+        self.curve.apply(input.abs()).copysign(input) * self.sensitivity
+    }
+
+    /// Returns whether the value changes over time, rather than as soon as the input does.
+    pub fn is_filtered(&self) -> bool {
+        self.ramp_ms > 0 || self.smoothing_ms > 0
+    }
+
+    /// Moves the `current` value toward the `target` value over `elapsed` time.
+    pub fn filter(&self, current: f32, target: f32, elapsed: Duration) -> f32 {
+        let ms = duration_to_secs(elapsed) * 1000.0;
+        let ramped = if self.ramp_ms == 0 {
+            target
+        } else {
+            let current = if current * target < 0.0 { 0.0 } else { current };
+            let step = ms / self.ramp_ms as f32 * self.sensitivity.abs();
+            if target > current {
+                (current + step).min(target)
+            } else {
+                (current - step).max(target)
+            }
+        };
+        if self.smoothing_ms == 0 {
+            ramped
+        } else {
+            ramped + (current - ramped) * (-ms / self.smoothing_ms as f32).exp()
+        }
+    }
+}
+
+/// Applies a radial dead zone to a 2D input, rescaling the remaining range so the value still
+/// goes from 0 to 1 in every direction.
+///
+/// A dead zone of 1 or more hides every input.
+pub(crate) fn radial_dead_zone(x: f32, y: f32, dead_zone: f32) -> (f32, f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= dead_zone || dead_zone >= 1.0 {
+        return (0.0, 0.0, 0.0);
+    }
+    let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    (x / magnitude, y / magnitude, scaled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn curves_keep_the_sign_and_range() {
+        let quadratic = AxisResponse {
+            curve: ResponseCurve::Quadratic,
+            ..Default::default()
+        };
+        assert_eq!(quadratic.apply(-0.5), -0.25);
+        assert_eq!(quadratic.apply(1.0), 1.0);
+
+        let cubic = AxisResponse {
+            curve: ResponseCurve::Cubic {
+                cubic: 0.5,
+                quadratic: 0.0,
+                linear: 0.5,
+            },
+            sensitivity: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(cubic.apply(0.5), 2.0 * (0.0625 + 0.25));
+        assert_eq!(cubic.apply(-1.0), -2.0);
+    }
+
+    #[test]
+    fn ramp_restarts_from_zero_when_reversing() {
+        let response = AxisResponse {
+            ramp_ms: 200,
+            ..Default::default()
+        };
+        let value = response.filter(0.0, 1.0, ms(100));
+        assert_eq!(value, 0.5);
+        let value = response.filter(value, 1.0, ms(200));
+        assert_eq!(value, 1.0);
+        assert_eq!(response.filter(value, -1.0, ms(50)), -0.25);
+    }
+
+    #[test]
+    fn radial_dead_zone_rescales_magnitude() {
+        assert_eq!(radial_dead_zone(0.1, 0.1, 0.2), (0.0, 0.0, 0.0));
+        let (x, y, magnitude) = radial_dead_zone(0.0, -0.6, 0.2);
+        assert_eq!((x, y), (0.0, -1.0));
+        assert!((magnitude - 0.5).abs() < 1e-6);
+        assert_eq!(radial_dead_zone(1.0, 1.0, 1.0), (0.0, 0.0, 0.0));
+        assert_eq!(radial_dead_zone(1.0, 1.0, 2.0), (0.0, 0.0, 0.0));
+    }
+}
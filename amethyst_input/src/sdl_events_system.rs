@@ -1,4 +1,4 @@
-use std::{fmt, marker::PhantomData, path::PathBuf};
+use std::{convert::TryFrom, fmt, marker::PhantomData, path::PathBuf};
 
 use derivative::Derivative;
 use derive_new::new;
@@ -6,18 +6,20 @@ use sdl2::{
     self,
     controller::{AddMappingError, Axis, Button, GameController},
     event::Event,
-    EventPump, GameControllerSubsystem, Sdl,
+    haptic::Haptic,
+    joystick::{Joystick, PowerLevel},
+    EventPump, GameControllerSubsystem, HapticSubsystem, JoystickSubsystem, Sdl,
 };
 
 use amethyst_core::{
-    ecs::prelude::{System, SystemData, World, Write},
+    ecs::prelude::{Read, System, SystemData, World, Write},
     shrev::EventChannel,
     SystemDesc,
 };
 
 use super::{
     controller::{ControllerAxis, ControllerButton, ControllerEvent},
-    BindingTypes, InputEvent, InputHandler,
+    BatteryLevel, BindingTypes, ControllerInfo, InputEvent, InputHandler, InputPlayback, Rumble,
 };
 
 /// A collection of errors that can occur in the SDL system.
@@ -27,6 +29,8 @@ pub enum SdlSystemError {
     ContextInit(String),
     /// Failure initializing SDL controller subsystem
     ControllerSubsystemInit(String),
+    /// Failure initializing SDL joystick subsystem
+    JoystickSubsystemInit(String),
     /// Failure adding a controller mapping
     AddMappingError(AddMappingError),
 }
@@ -38,6 +42,9 @@ impl fmt::Display for SdlSystemError {
             SdlSystemError::ControllerSubsystemInit(ref msg) => {
                 write!(f, "Failed to initialize SDL controller subsystem: {}", msg)
             }
+            SdlSystemError::JoystickSubsystemInit(ref msg) => {
+                write!(f, "Failed to initialize SDL joystick subsystem: {}", msg)
+            }
             SdlSystemError::AddMappingError(ref err) => {
                 write!(f, "Failed to load controller mappings: {}", err)
             }
@@ -84,29 +91,45 @@ pub struct SdlEventsSystem<T: BindingTypes> {
     sdl_context: Sdl,
     event_pump: Option<EventPump>,
     controller_subsystem: GameControllerSubsystem,
+    joystick_subsystem: JoystickSubsystem,
+    /// Plays rumbles, if haptic feedback is supported.
+    haptic_subsystem: Option<HapticSubsystem>,
     /// Vector of opened controllers and their corresponding joystick indices
     opened_controllers: Vec<(u32, GameController)>,
+    /// Joysticks of the opened controllers, to read their battery level, by instance id.
+    opened_joysticks: Vec<(u32, Joystick, BatteryLevel)>,
+    /// Haptic devices of the opened controllers which can rumble, by instance id.
+    opened_haptics: Vec<(u32, Haptic)>,
     marker: PhantomData<T>,
 }
 
 type SdlEventsData<'a, T> = (
     Write<'a, InputHandler<T>>,
     Write<'a, EventChannel<InputEvent<T>>>,
+    Read<'a, InputPlayback<T>>,
 );
 
 impl<'a, T: BindingTypes> System<'a> for SdlEventsSystem<T> {
     type SystemData = SdlEventsData<'a, T>;
 
-    fn run(&mut self, (mut handler, mut output): Self::SystemData) {
+    fn run(&mut self, (mut handler, mut output, playback): Self::SystemData) {
         let mut event_pump = self
             .event_pump
             .take()
             .expect("Unreachable: `event_pump` is always reinserted after `take`");
         for event in event_pump.poll_iter() {
+            // Recorded controller events are replayed by the `InputSystem`.
+            if playback.is_playing() {
+                continue;
+            }
             // handle appropriate events locally
             self.handle_sdl_event(&event, &mut handler, &mut output);
         }
         self.event_pump = Some(event_pump);
+        self.update_batteries(&mut handler);
+        for (which, rumble) in handler.drain_rumbles() {
+            self.play_rumble(which, &rumble);
+        }
     }
 }
 
@@ -124,6 +147,11 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
         let controller_subsystem = sdl_context
             .game_controller()
             .map_err(SdlSystemError::ControllerSubsystemInit)?;
+        let joystick_subsystem = sdl_context
+            .joystick()
+            .map_err(SdlSystemError::JoystickSubsystemInit)?;
+        // Controllers work without rumble.
+        let haptic_subsystem = sdl_context.haptic().ok();
 
         match mappings {
             Some(ControllerMappings::FromPath(p)) => {
@@ -144,10 +172,14 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             sdl_context,
             event_pump: Some(event_pump),
             controller_subsystem,
+            joystick_subsystem,
+            haptic_subsystem,
             opened_controllers: vec![],
+            opened_joysticks: vec![],
+            opened_haptics: vec![],
             marker: PhantomData,
         };
-        let (mut handler, mut output) = SdlEventsData::fetch(world);
+        let (mut handler, mut output, _) = SdlEventsData::<T>::fetch(world);
         sys.initialize_controllers(&mut handler, &mut output);
         Ok(sys)
     }
@@ -207,6 +239,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             Event::ControllerDeviceAdded { which, .. } => {
                 if let Some(idx) = self.open_controller(which) {
                     handler.send_controller_event(&ControllerConnected { which: idx }, output);
+                    self.send_controller_info(idx, handler);
                 }
             }
             _ => {}
@@ -218,6 +251,17 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             self.controller_subsystem.open(which).ok().map(|c| {
                 let id = c.instance_id() as u32;
                 self.opened_controllers.push((which, c));
+                if let Ok(joystick) = self.joystick_subsystem.open(which) {
+                    let battery = battery_level(&joystick);
+                    self.opened_joysticks.push((id, joystick, battery));
+                }
+                let haptic = self
+                    .haptic_subsystem
+                    .as_ref()
+                    .and_then(|haptic| haptic.open_from_joystick_id(which).ok());
+                if let Some(haptic) = haptic {
+                    self.opened_haptics.push((id, haptic));
+                }
                 id
             })
         } else {
@@ -233,6 +277,57 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
         if let Some(i) = index {
             self.opened_controllers.swap_remove(i);
         }
+        self.opened_joysticks.retain(|(id, _, _)| *id != which);
+        self.opened_haptics.retain(|(id, _)| *id != which);
+    }
+
+    /// Sends the name and battery level of an opened controller to the handler.
+    fn send_controller_info(&self, which: u32, handler: &mut InputHandler<T>) {
+        let controller = self
+            .opened_controllers
+            .iter()
+            .find(|(_, c)| c.instance_id() as u32 == which);
+        if let Some((_, controller)) = controller {
+            let battery = self
+                .opened_joysticks
+                .iter()
+                .find(|(id, _, _)| *id == which)
+                .map_or(BatteryLevel::Unknown, |(_, _, battery)| *battery);
+            handler.send_controller_info(which, ControllerInfo::new(controller.name(), battery));
+        }
+    }
+
+    /// Sends the information of the controllers whose battery level changed.
+    fn update_batteries(&mut self, handler: &mut InputHandler<T>) {
+        let mut changed = Vec::new();
+        for (id, joystick, battery) in &mut self.opened_joysticks {
+            let level = battery_level(joystick);
+            if level != *battery {
+                *battery = level;
+                changed.push(*id);
+            }
+        }
+        for which in changed {
+            self.send_controller_info(which, handler);
+        }
+    }
+
+    /// Rumbles a controller, SDL's haptic rumble playing both motors with the same intensity.
+    fn play_rumble(&mut self, which: u32, rumble: &Rumble) {
+        let intensity = rumble
+            .low_frequency
+            .max(rumble.high_frequency)
+            .max(0.0)
+            .min(1.0);
+        let duration = u32::try_from(rumble.duration.as_millis()).unwrap_or(u32::max_value());
+        let haptic = self.opened_haptics.iter_mut().find(|(id, _)| *id == which);
+        if let Some((_, haptic)) = haptic {
+            if intensity > 0.0 && duration > 0 {
+                haptic.rumble_play(intensity, duration);
+            } else {
+                haptic.rumble_stop();
+            }
+        }
     }
 
     fn initialize_controllers(
@@ -246,6 +341,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             for id in 0..available {
                 if let Some(idx) = self.open_controller(id) {
                     handler.send_controller_event(&ControllerConnected { which: idx }, output);
+                    self.send_controller_info(idx, handler);
                 }
             }
         }
@@ -274,6 +370,17 @@ impl From<Button> for ControllerButton {
     }
 }
 
+fn battery_level(joystick: &Joystick) -> BatteryLevel {
+    match joystick.power_level() {
+        Ok(PowerLevel::Empty) => BatteryLevel::Empty,
+        Ok(PowerLevel::Low) => BatteryLevel::Low,
+        Ok(PowerLevel::Medium) => BatteryLevel::Medium,
+        Ok(PowerLevel::Full) => BatteryLevel::Full,
+        Ok(PowerLevel::Wired) => BatteryLevel::Wired,
+        Ok(PowerLevel::Unknown) | Err(_) => BatteryLevel::Unknown,
+    }
+}
+
 impl From<Axis> for ControllerAxis {
     fn from(axis: Axis) -> Self {
         match axis {
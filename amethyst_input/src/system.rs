@@ -2,13 +2,16 @@
 use derive_new::new;
 use winit::Event;
 
-use crate::{BindingTypes, Bindings, InputEvent, InputHandler};
+use crate::{
+    BindingContext, BindingTypes, Bindings, InputEvent, InputHandler, InputPlayback, InputRecorder,
+};
 use amethyst_core::{
     ecs::{
         prelude::{Read, ReadExpect, System, World, Write},
         SystemData,
     },
     shrev::{EventChannel, ReaderId},
+    timing::Time,
     SystemDesc,
 };
 use amethyst_window::ScreenDimensions;
@@ -23,6 +26,19 @@ where
     T: BindingTypes,
 {
     bindings: Option<Bindings<T>>,
+    #[new(default)]
+    contexts: Vec<(String, BindingContext<T>)>,
+}
+
+impl<T> InputSystemDesc<T>
+where
+    T: BindingTypes,
+{
+    /// Registers a binding context with the `InputHandler`, see `InputHandler::insert_context`.
+    pub fn with_context<N: Into<String>>(mut self, name: N, context: BindingContext<T>) -> Self {
+        self.contexts.push((name.into(), context));
+        self
+    }
 }
 
 impl<'a, 'b, T> SystemDesc<'a, 'b, InputSystem<T>> for InputSystemDesc<T>
@@ -36,6 +52,12 @@ where
         if let Some(bindings) = self.bindings.as_ref() {
             world.fetch_mut::<InputHandler<T>>().bindings = bindings.clone();
         }
+        {
+            let mut handler = world.fetch_mut::<InputHandler<T>>();
+            for (name, context) in self.contexts {
+                handler.insert_context(name, context);
+            }
+        }
 
         InputSystem::new(reader, self.bindings)
     }
@@ -52,12 +74,18 @@ where
 {
     reader: ReaderId<Event>,
     bindings: Option<Bindings<T>>,
+    /// Reads the sent `InputEvent`s while recording or playing back.
+    input_event_reader: Option<ReaderId<InputEvent<T>>>,
 }
 
 impl<T: BindingTypes> InputSystem<T> {
     /// Create a new input system. Needs a reader id for `EventHandler<winit::Event>`.
     pub fn new(reader: ReaderId<Event>, bindings: Option<Bindings<T>>) -> Self {
-        InputSystem { reader, bindings }
+        InputSystem {
+            reader,
+            bindings,
+            input_event_reader: None,
+        }
     }
 
     fn process_event(
@@ -76,20 +104,211 @@ impl<'a, T: BindingTypes> System<'a> for InputSystem<T> {
         Write<'a, InputHandler<T>>,
         Write<'a, EventChannel<InputEvent<T>>>,
         ReadExpect<'a, ScreenDimensions>,
+        Read<'a, Time>,
+        Write<'a, InputRecorder<T>>,
+        Write<'a, InputPlayback<T>>,
     );
 
-    fn run(&mut self, (input, mut handler, mut output, screen_dimensions): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("input_system");
 
+        let (input, mut handler, mut output, screen_dimensions, time, mut recorder, mut playback) =
+            data;
+
+        // Controller events sent by other systems since the last frame are kept by the handler
+        // too, so the events are recorded in the order they arrived.
+        handler.record_events(recorder.is_recording());
         handler.send_frame_begin();
-        for event in input.read(&mut self.reader) {
-            Self::process_event(
-                event,
-                &mut *handler,
-                &mut *output,
-                screen_dimensions.hidpi_factor() as f32,
+        let hidpi = screen_dimensions.hidpi_factor() as f32;
+        let played = playback.next_frame();
+        match played {
+            Some((ref frame, recorded_hidpi)) => {
+                // Window events are dropped while a recording plays.
+                for _ in input.read(&mut self.reader) {}
+                handler.send_time(frame.time, &mut output);
+                for event in &frame.events {
+                    event.send_to(&mut handler, &mut output, recorded_hidpi);
+                }
+            }
+            None => {
+                handler.send_time(time.absolute_real_time(), &mut output);
+                for event in input.read(&mut self.reader) {
+                    Self::process_event(event, &mut *handler, &mut *output, hidpi);
+                }
+            }
+        }
+
+        let reader = self
+            .input_event_reader
+            .as_mut()
+            .expect("`InputSystem::setup` was not called before `InputSystem::run`");
+        if played.is_none() && !recorder.is_recording() {
+            for _ in output.read(reader) {}
+            return;
+        }
+        let input_events = output.read(reader).cloned().collect::<Vec<_>>();
+        if let Some((frame, _)) = played {
+            playback.check(&frame, &input_events);
+        }
+        if recorder.is_recording() {
+            recorder.record_frame(
+                time.frame_number(),
+                time.absolute_real_time(),
+                hidpi,
+                handler.take_recorded_events(),
+                input_events,
             );
         }
     }
+
+    fn setup(&mut self, world: &mut World) {
+        <Self::SystemData as SystemData<'_>>::setup(world);
+        self.input_event_reader = Some(
+            world
+                .fetch_mut::<EventChannel<InputEvent<T>>>()
+                .register_reader(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{
+        DeviceId, ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent,
+        WindowId,
+    };
+
+    use amethyst_core::ecs::RunNow;
+
+    use super::*;
+    use crate::{
+        Button, ControllerButton, ControllerEvent, InputRecording, RecordedEvent, StringBindings,
+    };
+
+    fn shift(state: ElementState) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::KeyboardInput {
+                device_id: unsafe { DeviceId::dummy() },
+                input: KeyboardInput {
+                    scancode: 42,
+                    state,
+                    virtual_keycode: Some(VirtualKeyCode::LShift),
+                    modifiers: ModifiersState::default(),
+                },
+            },
+        }
+    }
+
+    fn button_a(pressed: bool) -> ControllerEvent {
+        let button = ControllerButton::A;
+        if pressed {
+            ControllerEvent::ControllerButtonPressed { which: 0, button }
+        } else {
+            ControllerEvent::ControllerButtonReleased { which: 0, button }
+        }
+    }
+
+    /// Sends a controller event like the `SdlEventsSystem` does, outside of the `InputSystem`.
+    fn send_controller_event(world: &World, event: ControllerEvent) {
+        let mut output = world.fetch_mut::<EventChannel<InputEvent<StringBindings>>>();
+        world
+            .fetch_mut::<InputHandler<StringBindings>>()
+            .send_controller_event(&event, &mut output);
+    }
+
+    fn setup() -> (World, InputSystem<StringBindings>) {
+        let mut world = World::new();
+        world.insert(ScreenDimensions::new(640, 480, 1.0));
+        let mut bindings = Bindings::new();
+        bindings
+            .insert_action_binding(
+                "chord".to_string(),
+                vec![
+                    Button::Key(VirtualKeyCode::LShift),
+                    Button::Controller(0, ControllerButton::A),
+                ],
+            )
+            .unwrap();
+        let mut system = InputSystemDesc::new(Some(bindings)).build(&mut world);
+        System::setup(&mut system, &mut world);
+        send_controller_event(&world, ControllerEvent::ControllerConnected { which: 0 });
+        (world, system)
+    }
+
+    fn chord_is_down(world: &World) -> bool {
+        world
+            .fetch::<InputHandler<StringBindings>>()
+            .action_is_down("chord")
+            .unwrap()
+    }
+
+    fn record_chord() -> InputRecording<StringBindings> {
+        let (world, mut system) = setup();
+        world.fetch_mut::<InputRecorder<StringBindings>>().start();
+        RunNow::run_now(&mut system, &world);
+
+        send_controller_event(&world, button_a(true));
+        world
+            .fetch_mut::<EventChannel<Event>>()
+            .single_write(shift(ElementState::Pressed));
+        RunNow::run_now(&mut system, &world);
+        assert!(chord_is_down(&world));
+
+        world
+            .fetch_mut::<EventChannel<Event>>()
+            .single_write(shift(ElementState::Released));
+        RunNow::run_now(&mut system, &world);
+        send_controller_event(&world, button_a(false));
+        RunNow::run_now(&mut system, &world);
+        assert!(!chord_is_down(&world));
+
+        world
+            .fetch_mut::<InputRecorder<StringBindings>>()
+            .stop()
+            .unwrap()
+    }
+
+    #[test]
+    fn records_events_in_arrival_order() {
+        let recording = record_chord();
+        let events = recording
+            .frames
+            .iter()
+            .map(|frame| frame.events.clone())
+            .collect::<Vec<_>>();
+
+        let key = |state| RecordedEvent::from_event(&shift(state)).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                vec![],
+                vec![
+                    RecordedEvent::Controller(button_a(true)),
+                    key(ElementState::Pressed),
+                ],
+                vec![key(ElementState::Released)],
+                vec![RecordedEvent::Controller(button_a(false))],
+            ]
+        );
+    }
+
+    #[test]
+    fn replays_keyboard_and_controller_chord() {
+        let recording = record_chord();
+        let (mut world, mut system) = setup();
+        world.insert(InputPlayback::new(recording));
+
+        RunNow::run_now(&mut system, &world);
+        RunNow::run_now(&mut system, &world);
+        assert!(chord_is_down(&world));
+        RunNow::run_now(&mut system, &world);
+        RunNow::run_now(&mut system, &world);
+        assert!(!chord_is_down(&world));
+
+        let playback = world.fetch::<InputPlayback<StringBindings>>();
+        assert_eq!(playback.frames_played(), 4);
+        assert!(playback.desynced_frames().is_empty());
+    }
 }
@@ -0,0 +1,382 @@
+//! Touch points, gestures and on-screen touch controls.
+
+use std::{f32::consts::PI, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+/// A finger touching the screen, tracked by the `InputHandler`.
+///
+/// Positions are in physical pixels, like `InputHandler::mouse_position`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TouchPoint {
+    /// Identifies the touch until it ends, winit reuses ids of ended touches.
+    pub id: u64,
+    /// The current position of the touch.
+    pub position: (f32, f32),
+    /// Where the touch started.
+    pub start_position: (f32, f32),
+    /// When the touch started, as sent with `InputHandler::send_time`.
+    pub started: Duration,
+}
+
+/// A gesture recognized from touches which don't hold an on-screen control.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Gesture {
+    /// A single touch was released quickly without moving.
+    Tap {
+        /// Where the touch was released.
+        position: (f32, f32),
+    },
+    /// A single touch was held without moving.
+    LongPress {
+        /// Where the touch is held.
+        position: (f32, f32),
+    },
+    /// A single touch moved quickly over a distance before being released.
+    Swipe {
+        /// Where the touch started.
+        start: (f32, f32),
+        /// Where the touch was released.
+        end: (f32, f32),
+    },
+    /// Two touches moved apart or together.
+    Pinch {
+        /// The ratio of the distance between both touches to their previous distance.
+        scale: f32,
+    },
+    /// Two touches turned around each other.
+    Rotate {
+        /// The angle turned since the previous rotation, in radians, clockwise on screen.
+        angle: f32,
+    },
+}
+
+/// Thresholds used to recognize `Gesture`s. Durations are in milliseconds, distances in
+/// physical pixels.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GestureSettings {
+    /// How long a tap can be held at most.
+    pub tap_ms: u64,
+    /// How long a touch has to be held to be a long press.
+    pub long_press_ms: u64,
+    /// How far a touch can move and still be a tap or a long press.
+    pub tap_distance: f32,
+    /// How far a touch has to move to be a swipe.
+    pub swipe_distance: f32,
+    /// How long a swipe can take at most.
+    pub swipe_ms: u64,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        GestureSettings {
+            tap_ms: 250,
+            long_press_ms: 500,
+            tap_distance: 10.0,
+            swipe_distance: 50.0,
+            swipe_ms: 500,
+        }
+    }
+}
+
+/// A rectangular on-screen button, bound with `Button::Touch`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TouchButton {
+    /// The left edge of the button, in physical pixels.
+    pub x: f32,
+    /// The top edge of the button, in physical pixels.
+    pub y: f32,
+    /// The width of the button.
+    pub width: f32,
+    /// The height of the button.
+    pub height: f32,
+}
+
+impl TouchButton {
+    /// Returns whether the position is on the button.
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// A round on-screen stick, bound with `Axis::TouchStick`.
+///
+/// The value of the stick is the offset of the touch holding it from its center, divided by
+/// its radius. Like the sticks of controllers, the vertical value is positive downward.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TouchStick {
+    /// The center of the stick, in physical pixels.
+    pub center: (f32, f32),
+    /// The radius of the stick, touches starting within it hold the stick.
+    pub radius: f32,
+}
+
+impl TouchStick {
+    /// Returns whether the position is on the stick.
+    pub fn contains(&self, position: (f32, f32)) -> bool {
+        distance(self.center, position) <= self.radius
+    }
+
+    /// Returns the value of the stick held at the position, with a length of at most 1.
+    pub fn value(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        let length = distance(self.center, (x, y)).max(self.radius);
+        if length <= 0.0 {
+            (0.0, 0.0)
+        } else {
+            (dx / length, dy / length)
+        }
+    }
+}
+
+/// The on-screen control held by a touch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TouchControl {
+    Button(u32),
+    Stick(u32),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TrackedTouch {
+    pub point: TouchPoint,
+    pub control: Option<TouchControl>,
+    /// Whether the touch moved too far to be a tap or a long press.
+    moved: bool,
+    /// Whether another gesture touch was down at the same time.
+    multi: bool,
+    long_pressed: bool,
+}
+
+/// Tracks touches and recognizes gestures, kept by the `InputHandler`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TouchTracker {
+    pub touches: SmallVec<[TrackedTouch; 4]>,
+}
+
+impl TouchTracker {
+    pub fn get(&self, id: u64) -> Option<&TrackedTouch> {
+        self.touches.iter().find(|touch| touch.point.id == id)
+    }
+
+    /// Returns whether a touch holds the control.
+    pub fn holds(&self, control: TouchControl) -> bool {
+        self.touches
+            .iter()
+            .any(|touch| touch.control == Some(control))
+    }
+
+    pub fn start(
+        &mut self,
+        id: u64,
+        position: (f32, f32),
+        now: Duration,
+        control: Option<TouchControl>,
+    ) {
+        let mut multi = false;
+        if control.is_none() {
+            for touch in self.gesture_touches_mut() {
+                touch.multi = true;
+                multi = true;
+            }
+        }
+        self.touches.push(TrackedTouch {
+            point: TouchPoint {
+                id,
+                position,
+                start_position: position,
+                started: now,
+            },
+            control,
+            moved: false,
+            multi,
+            long_pressed: false,
+        });
+    }
+
+    /// Moves a touch, returning the pinch and rotation of two gesture touches.
+    pub fn moved(
+        &mut self,
+        id: u64,
+        position: (f32, f32),
+        settings: &GestureSettings,
+    ) -> SmallVec<[Gesture; 2]> {
+        let mut gestures = SmallVec::new();
+        let before = self.pair();
+        match self.touches.iter_mut().find(|touch| touch.point.id == id) {
+            Some(touch) => {
+                touch.point.position = position;
+                if distance(touch.point.start_position, position) > settings.tap_distance {
+                    touch.moved = true;
+                }
+            }
+            None => return gestures,
+        }
+        if let (Some((a, b)), Some((c, d))) = (before, self.pair()) {
+            let (old, new) = (distance(a, b), distance(c, d));
+            if old > 0.0 && new > 0.0 && (new - old).abs() > std::f32::EPSILON {
+                gestures.push(Gesture::Pinch { scale: new / old });
+            }
+            let mut angle = angle(c, d) - angle(a, b);
+            if angle > PI {
+                angle -= 2.0 * PI;
+            } else if angle < -PI {
+                angle += 2.0 * PI;
+            }
+            if angle.abs() > std::f32::EPSILON {
+                gestures.push(Gesture::Rotate { angle });
+            }
+        }
+        gestures
+    }
+
+    /// Ends a touch, returning it and the tap or swipe it made, if any.
+    pub fn end(
+        &mut self,
+        id: u64,
+        position: (f32, f32),
+        now: Duration,
+        settings: &GestureSettings,
+    ) -> Option<(TrackedTouch, Option<Gesture>)> {
+        let index = self.touches.iter().position(|touch| touch.point.id == id)?;
+        let mut touch = self.touches.remove(index);
+        touch.point.position = position;
+        if touch.control.is_some() || touch.multi || touch.long_pressed {
+            return Some((touch, None));
+        }
+        let held = now.checked_sub(touch.point.started).unwrap_or_default();
+        let start = touch.point.start_position;
+        let gesture = if !touch.moved && held <= Duration::from_millis(settings.tap_ms) {
+            Some(Gesture::Tap { position })
+        } else if distance(start, position) >= settings.swipe_distance
+            && held <= Duration::from_millis(settings.swipe_ms)
+        {
+            Some(Gesture::Swipe {
+                start,
+                end: position,
+            })
+        } else {
+            None
+        };
+        Some((touch, gesture))
+    }
+
+    /// Returns the long presses of the touches held long enough.
+    pub fn tick(&mut self, now: Duration, settings: &GestureSettings) -> SmallVec<[Gesture; 2]> {
+        let long_press = Duration::from_millis(settings.long_press_ms);
+        self.gesture_touches_mut()
+            .filter(|touch| !touch.moved && !touch.multi && !touch.long_pressed)
+            .filter(|touch| now.checked_sub(touch.point.started).unwrap_or_default() >= long_press)
+            .map(|touch| {
+                touch.long_pressed = true;
+                Gesture::LongPress {
+                    position: touch.point.position,
+                }
+            })
+            .collect()
+    }
+
+    fn gesture_touches_mut(&mut self) -> impl Iterator<Item = &mut TrackedTouch> {
+        self.touches
+            .iter_mut()
+            .filter(|touch| touch.control.is_none())
+    }
+
+    /// Returns the positions of the gesture touches, if there are exactly two.
+    fn pair(&self) -> Option<((f32, f32), (f32, f32))> {
+        let mut touches = self
+            .touches
+            .iter()
+            .filter(|touch| touch.control.is_none())
+            .map(|touch| touch.point.position);
+        match (touches.next(), touches.next(), touches.next()) {
+            (Some(a), Some(b), None) => Some((a, b)),
+            _ => None,
+        }
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+fn angle(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (b.1 - a.1).atan2(b.0 - a.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn tap_swipe_and_long_press() {
+        let settings = GestureSettings::default();
+        let mut tracker = TouchTracker::default();
+
+        tracker.start(0, (100.0, 100.0), ms(0), None);
+        tracker.moved(0, (104.0, 100.0), &settings);
+        let (_, gesture) = tracker.end(0, (104.0, 100.0), ms(100), &settings).unwrap();
+        assert_eq!(
+            gesture,
+            Some(Gesture::Tap {
+                position: (104.0, 100.0)
+            })
+        );
+
+        tracker.start(1, (100.0, 100.0), ms(1000), None);
+        tracker.moved(1, (200.0, 100.0), &settings);
+        let (_, gesture) = tracker.end(1, (200.0, 100.0), ms(1200), &settings).unwrap();
+        assert_eq!(
+            gesture,
+            Some(Gesture::Swipe {
+                start: (100.0, 100.0),
+                end: (200.0, 100.0),
+            })
+        );
+
+        tracker.start(2, (100.0, 100.0), ms(2000), None);
+        assert!(tracker.tick(ms(2400), &settings).is_empty());
+        assert_eq!(tracker.tick(ms(2500), &settings).len(), 1);
+        assert!(tracker.tick(ms(2600), &settings).is_empty());
+        let (_, gesture) = tracker.end(2, (100.0, 100.0), ms(2700), &settings).unwrap();
+        assert_eq!(gesture, None);
+    }
+
+    #[test]
+    fn two_touches_pinch_and_rotate() {
+        let settings = GestureSettings::default();
+        let mut tracker = TouchTracker::default();
+        tracker.start(0, (0.0, 0.0), ms(0), None);
+        tracker.start(1, (100.0, 0.0), ms(0), None);
+
+        let gestures = tracker.moved(1, (200.0, 0.0), &settings);
+        assert_eq!(gestures.as_slice(), &[Gesture::Pinch { scale: 2.0 }]);
+
+        let gestures = tracker.moved(1, (0.0, 200.0), &settings);
+        assert_eq!(
+            gestures.as_slice(),
+            &[Gesture::Rotate {
+                angle: std::f32::consts::FRAC_PI_2
+            }]
+        );
+
+        let (_, gesture) = tracker.end(0, (0.0, 0.0), ms(100), &settings).unwrap();
+        assert_eq!(gesture, None);
+    }
+
+    #[test]
+    fn stick_value_is_clamped() {
+        let stick = TouchStick {
+            center: (100.0, 100.0),
+            radius: 50.0,
+        };
+        assert!(stick.contains((120.0, 120.0)));
+        assert_eq!(stick.value((125.0, 100.0)), (0.5, 0.0));
+        assert_eq!(stick.value((100.0, 0.0)), (0.0, -1.0));
+    }
+}
@@ -0,0 +1,263 @@
+//! Triggers firing on timed patterns of actions.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// A pattern of action presses and releases which fires a triggered action.
+///
+/// Triggers are part of the `Bindings`, keyed by the action they fire. When a trigger fires,
+/// an `InputEvent::ActionTriggered` is sent and `InputHandler::action_triggered` returns true
+/// for the rest of the frame.
+///
+/// Durations are in milliseconds. Example Ron config:
+/// ```ron
+/// (
+///     axes: {},
+///     actions: {
+///         "fire": [ [Mouse(Left)] ],
+///         "left": [ [Key(A)] ],
+///         "down": [ [Key(S)] ],
+///         "punch": [ [Key(J)] ],
+///     },
+///     triggers: {
+///         "charged_shot": Hold(action: "fire", ms: 500),
+///         "quick_shot": Tap(action: "fire", ms: 150),
+///         "dodge": DoubleTap(action: "left", ms: 250),
+///         "slide": Chord(actions: ["left", "down"], ms: 50),
+///         "uppercut": Sequence(actions: ["down", "punch"], ms: 300),
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionTrigger<A> {
+    /// Fires once the action was held down for `ms`.
+    Hold {
+        /// The held action.
+        action: A,
+        /// How long the action has to be held.
+        ms: u64,
+    },
+    /// Fires when the action is released at most `ms` after being pressed.
+    Tap {
+        /// The tapped action.
+        action: A,
+        /// How long the action can be held at most.
+        ms: u64,
+    },
+    /// Fires when the action is pressed a second time at most `ms` after the first press.
+    DoubleTap {
+        /// The tapped action.
+        action: A,
+        /// The longest delay between both presses.
+        ms: u64,
+    },
+    /// Fires when all actions are down, after being pressed at most `ms` apart.
+    Chord {
+        /// The actions of the chord, in any order.
+        actions: Vec<A>,
+        /// The longest delay between the first and the last press.
+        ms: u64,
+    },
+    /// Fires when the actions are pressed in order, each at most `ms` after the previous one.
+    ///
+    /// Pressing another action of the sequence out of order starts it over.
+    Sequence {
+        /// The actions of the sequence, in order.
+        actions: Vec<A>,
+        /// The longest delay between two presses.
+        ms: u64,
+    },
+}
+
+impl<A: PartialEq> ActionTrigger<A> {
+    /// Returns whether the action is part of the pattern.
+    pub fn involves(&self, action: &A) -> bool {
+        match self {
+            ActionTrigger::Hold { action: a, .. }
+            | ActionTrigger::Tap { action: a, .. }
+            | ActionTrigger::DoubleTap { action: a, .. } => a == action,
+            ActionTrigger::Chord { actions, .. } | ActionTrigger::Sequence { actions, .. } => {
+                actions.contains(action)
+            }
+        }
+    }
+
+    /// Returns the time window of the pattern.
+    pub fn window(&self) -> Duration {
+        match *self {
+            ActionTrigger::Hold { ms, .. }
+            | ActionTrigger::Tap { ms, .. }
+            | ActionTrigger::DoubleTap { ms, .. }
+            | ActionTrigger::Chord { ms, .. }
+            | ActionTrigger::Sequence { ms, .. } => Duration::from_millis(ms),
+        }
+    }
+}
+
+/// Progress of an `ActionTrigger`, kept by the `InputHandler`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TriggerState {
+    /// Whether a hold fired since its action was last pressed.
+    pub fired: bool,
+    /// How many actions of a sequence were pressed.
+    pub progress: usize,
+    /// When the last counted press happened.
+    pub last_press: Option<Duration>,
+}
+
+impl TriggerState {
+    /// Updates the state with a press of `action`, returning whether the trigger fires.
+    pub fn press<A: PartialEq>(
+        &mut self,
+        trigger: &ActionTrigger<A>,
+        action: &A,
+        now: Duration,
+        down_since: impl Fn(&A) -> Option<Duration>,
+    ) -> bool {
+        let window = trigger.window();
+        let within =
+            |last: Option<Duration>| last.map_or(false, |last| elapsed(now, last) <= window);
+        match trigger {
+            ActionTrigger::Hold { .. } => {
+                self.fired = false;
+                false
+            }
+            ActionTrigger::Tap { .. } => false,
+            ActionTrigger::DoubleTap { .. } => {
+                if within(self.last_press) {
+                    self.last_press = None;
+                    true
+                } else {
+                    self.last_press = Some(now);
+                    false
+                }
+            }
+            ActionTrigger::Chord { actions, .. } => {
+                let presses = actions.iter().map(down_since).collect::<Option<Vec<_>>>();
+                match presses {
+                    Some(presses) if !presses.is_empty() => {
+                        let first = *presses.iter().min().unwrap();
+                        let last = *presses.iter().max().unwrap();
+                        last - first <= window
+                    }
+                    _ => false,
+                }
+            }
+            ActionTrigger::Sequence { actions, .. } if actions.is_empty() => false,
+            ActionTrigger::Sequence { actions, .. } => {
+                if self.progress > 0
+                    && actions.get(self.progress) == Some(action)
+                    && within(self.last_press)
+                {
+                    self.progress += 1;
+                } else if actions.first() == Some(action) {
+                    self.progress = 1;
+                } else {
+                    self.progress = 0;
+                }
+                self.last_press = Some(now);
+                if self.progress == actions.len() {
+                    self.progress = 0;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Updates the state with a release of an action pressed at `pressed`, returning whether
+    /// the trigger fires.
+    pub fn release<A>(
+        &mut self,
+        trigger: &ActionTrigger<A>,
+        pressed: Option<Duration>,
+        now: Duration,
+    ) -> bool {
+        match trigger {
+            ActionTrigger::Tap { .. } => {
+                pressed.map_or(false, |pressed| elapsed(now, pressed) <= trigger.window())
+            }
+            _ => false,
+        }
+    }
+
+    /// Updates the state with the passing of time, returning whether the trigger fires.
+    pub fn tick<A>(
+        &mut self,
+        trigger: &ActionTrigger<A>,
+        down_since: Option<Duration>,
+        now: Duration,
+    ) -> bool {
+        match trigger {
+            ActionTrigger::Hold { .. } if !self.fired => {
+                let held =
+                    down_since.map_or(false, |since| elapsed(now, since) >= trigger.window());
+                self.fired = held;
+                held
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the time since `since`, or zero if the clock was reset in between.
+fn elapsed(now: Duration, since: Duration) -> Duration {
+    now.checked_sub(since).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn double_tap_fires_once_within_window() {
+        let trigger = ActionTrigger::DoubleTap {
+            action: "left",
+            ms: 250,
+        };
+        let mut state = TriggerState::default();
+        let none = |_: &&str| None;
+
+        assert!(!state.press(&trigger, &"left", ms(0), none));
+        assert!(!state.press(&trigger, &"left", ms(400), none));
+        assert!(state.press(&trigger, &"left", ms(500), none));
+        assert!(!state.press(&trigger, &"left", ms(600), none));
+    }
+
+    #[test]
+    fn sequence_restarts_when_out_of_order() {
+        let trigger = ActionTrigger::Sequence {
+            actions: vec!["down", "forward", "punch"],
+            ms: 300,
+        };
+        let mut state = TriggerState::default();
+        let none = |_: &&str| None;
+
+        assert!(!state.press(&trigger, &"down", ms(0), none));
+        assert!(!state.press(&trigger, &"punch", ms(100), none));
+        assert!(!state.press(&trigger, &"forward", ms(200), none));
+        assert!(!state.press(&trigger, &"down", ms(1000), none));
+        assert!(!state.press(&trigger, &"forward", ms(1200), none));
+        assert!(state.press(&trigger, &"punch", ms(1400), none));
+    }
+
+    #[test]
+    fn hold_fires_after_duration() {
+        let trigger = ActionTrigger::Hold {
+            action: "fire",
+            ms: 500,
+        };
+        let mut state = TriggerState::default();
+        state.press(&trigger, &"fire", ms(0), |_| None);
+
+        assert!(!state.tick(&trigger, Some(ms(0)), ms(400)));
+        assert!(state.tick(&trigger, Some(ms(0)), ms(500)));
+        assert!(!state.tick(&trigger, Some(ms(0)), ms(600)));
+    }
+}
@@ -0,0 +1,62 @@
+//! A local lobby server, meant to be run next to clients under test.
+//!
+//! Usage: `lobby_server [ADDRESS] [NAME] [MIN_PLAYERS]`
+//!
+//! The server listens on `ADDRESS` (by default `0.0.0.0:3456`, every interface), announces itself
+//! on the local network as `NAME`, with the address of the interface the announcements go out on,
+//! and starts the match as soon as `MIN_PLAYERS` players are ready.
+
+use std::{env, net::SocketAddr, process, thread::sleep, time::Duration};
+
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{World, WorldExt},
+    shred::DispatcherBuilder,
+};
+use amethyst_network::{DiscoveryConfig, LobbyConfig, LobbyServerBundle, ServerInfo};
+
+const FRAME_DURATION: Duration = Duration::from_millis(16);
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let addr: SocketAddr = args
+        .next()
+        .unwrap_or_else(|| "0.0.0.0:3456".to_string())
+        .parse()
+        .unwrap_or_else(|e| exit(&format!("Invalid address: {}", e)));
+    let name = args.next().unwrap_or_else(|| "Local lobby".to_string());
+    let min_players = args
+        .next()
+        .map(|n| {
+            n.parse()
+                .unwrap_or_else(|e| exit(&format!("Invalid player count: {}", e)))
+        })
+        .unwrap_or(2);
+
+    let mut world = World::new();
+    let mut builder = DispatcherBuilder::new();
+    LobbyServerBundle::new(addr)
+        .with_lobby_config(LobbyConfig {
+            min_players,
+            auto_start: true,
+            ..Default::default()
+        })
+        .with_discovery(ServerInfo::new(name, addr), DiscoveryConfig::default())
+        .build(&mut world, &mut builder)
+        .unwrap_or_else(|e| exit(&format!("Failed to start the lobby server: {}", e)));
+
+    let mut dispatcher = builder.build();
+    dispatcher.setup(&mut world);
+
+    println!("Lobby server listening on {}", addr);
+    loop {
+        dispatcher.dispatch(&world);
+        world.maintain();
+        sleep(FRAME_DURATION);
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,490 @@
+//! LAN server discovery.
+//!
+//! A server periodically broadcasts a `ServerInfo` over UDP using the `DiscoveryBroadcastSystem`,
+//! and clients collect those announcements into the `AvailableServers` resource using the
+//! `DiscoveryListenSystem`.
+//!
+//! Discovery runs on its own plain UDP socket and does not go through laminar, since broadcast
+//! packets are connectionless by nature.
+
+use std::{
+    collections::{hash_map, HashMap},
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bincode::{deserialize, serialize};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{ReadExpect, System, World, Write},
+    shred::DispatcherBuilder,
+};
+use amethyst_error::{Error, ResultExt};
+
+use crate::error::Result;
+
+/// Magic bytes prepended to every discovery announcement so unrelated broadcast traffic on the
+/// same port is ignored.
+const DISCOVERY_MAGIC: [u8; 4] = *b"AMDS";
+
+/// The maximal size of a single discovery announcement.
+const MAX_ANNOUNCEMENT_SIZE: usize = 1024;
+
+/// Information a server advertises to clients on the local network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    /// Human readable name of the server.
+    pub name: String,
+    /// The address clients should connect to.
+    ///
+    /// When the ip is unspecified (e.g. `0.0.0.0`), the `DiscoveryBroadcastSystem` announces the
+    /// address of the interface the broadcast goes out on instead. Should that be unknown,
+    /// clients use the address the announcement was received from.
+    pub game_addr: SocketAddr,
+    /// The amount of players currently connected.
+    pub player_count: u16,
+    /// The maximal amount of players the server accepts.
+    pub max_players: u16,
+    /// Game specific information, like the current map or game mode.
+    pub metadata: HashMap<String, String>,
+}
+
+impl ServerInfo {
+    /// Creates a new `ServerInfo` for a server accepting game connections on `game_addr`.
+    pub fn new<N: Into<String>>(name: N, game_addr: SocketAddr) -> Self {
+        ServerInfo {
+            name: name.into(),
+            game_addr,
+            player_count: 0,
+            max_players: 0,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Sets the maximal amount of players.
+    pub fn with_max_players(mut self, max_players: u16) -> Self {
+        self.max_players = max_players;
+        self
+    }
+
+    /// Adds a custom metadata entry.
+    pub fn with_metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl Default for ServerInfo {
+    fn default() -> Self {
+        ServerInfo::new("Amethyst server", "0.0.0.0:0".parse().unwrap())
+    }
+}
+
+/// The configuration used for LAN discovery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveryConfig {
+    /// Address announcements are sent to. Clients listen on the port of this address.
+    /// This is by default `255.255.255.255:21100`.
+    pub broadcast_addr: SocketAddr,
+    /// How often a server sends out its announcement.
+    /// This is by default one second.
+    pub interval: Duration,
+    /// After how long without an announcement a server is removed from `AvailableServers`.
+    /// This is by default five seconds.
+    pub server_timeout: Duration,
+}
+
+impl DiscoveryConfig {
+    /// Returns the address the discovery listener binds to.
+    pub fn listen_addr(&self) -> SocketAddr {
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            self.broadcast_addr.port(),
+        )
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            broadcast_addr: "255.255.255.255:21100".parse().unwrap(),
+            interval: Duration::from_secs(1),
+            server_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A server which was found on the local network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredServer {
+    /// The latest information the server announced.
+    pub info: ServerInfo,
+    /// The address the announcement was sent from.
+    pub source: SocketAddr,
+    /// When the latest announcement was received.
+    pub last_seen: Instant,
+}
+
+impl DiscoveredServer {
+    /// Returns the address clients should connect to.
+    pub fn game_addr(&self) -> SocketAddr {
+        if self.info.game_addr.ip().is_unspecified() {
+            SocketAddr::new(self.source.ip(), self.info.game_addr.port())
+        } else {
+            self.info.game_addr
+        }
+    }
+}
+
+/// Resource containing all servers currently announcing themselves on the local network.
+///
+/// Servers are keyed by the address clients should connect to.
+#[derive(Debug, Default)]
+pub struct AvailableServers {
+    servers: HashMap<SocketAddr, DiscoveredServer>,
+}
+
+impl AvailableServers {
+    /// Returns an iterator over all known servers.
+    pub fn iter(&self) -> hash_map::Values<'_, SocketAddr, DiscoveredServer> {
+        self.servers.values()
+    }
+
+    /// Returns the server reachable on the given game address, if known.
+    pub fn get(&self, game_addr: SocketAddr) -> Option<&DiscoveredServer> {
+        self.servers.get(&game_addr)
+    }
+
+    /// Returns the amount of known servers.
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// Returns whether no servers are known.
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Removes all known servers.
+    pub fn clear(&mut self) {
+        self.servers.clear();
+    }
+
+    /// Inserts or refreshes a server from an announcement received from `source` at `now`.
+    pub(crate) fn announce(&mut self, info: ServerInfo, source: SocketAddr, now: Instant) {
+        let server = DiscoveredServer {
+            info,
+            source,
+            last_seen: now,
+        };
+        self.servers.insert(server.game_addr(), server);
+    }
+
+    /// Removes all servers which have not been seen for longer than `timeout`.
+    pub(crate) fn prune(&mut self, now: Instant, timeout: Duration) {
+        self.servers
+            .retain(|_, server| now.duration_since(server.last_seen) <= timeout);
+    }
+}
+
+/// Serializes a `ServerInfo` into a discovery announcement.
+pub(crate) fn encode_announcement(info: &ServerInfo) -> Result<Vec<u8>> {
+    let mut payload = DISCOVERY_MAGIC.to_vec();
+    payload.extend(serialize(info)?);
+    Ok(payload)
+}
+
+/// Deserializes a discovery announcement, returns `None` if the data is not an announcement.
+pub(crate) fn decode_announcement(data: &[u8]) -> Option<ServerInfo> {
+    if data.len() < DISCOVERY_MAGIC.len() || data[..DISCOVERY_MAGIC.len()] != DISCOVERY_MAGIC {
+        return None;
+    }
+    deserialize(&data[DISCOVERY_MAGIC.len()..]).ok()
+}
+
+/// Returns the address announced for `game_addr`: with an unspecified ip, the ip of the interface
+/// the broadcast goes out on, if known.
+pub(crate) fn announced_addr(game_addr: SocketAddr, interface: Option<IpAddr>) -> SocketAddr {
+    match interface {
+        Some(ip) if game_addr.ip().is_unspecified() => SocketAddr::new(ip, game_addr.port()),
+        _ => game_addr,
+    }
+}
+
+/// Returns the ip of the interface packets to `broadcast_addr` go out on.
+///
+/// Connecting a UDP socket sends nothing, it only picks the route to the address.
+fn interface_ip(broadcast_addr: SocketAddr) -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.set_broadcast(true).ok()?;
+    socket.connect(broadcast_addr).ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+/// Periodically broadcasts the `ServerInfo` resource on the local network.
+///
+/// A `ServerInfo` with an unspecified ip announces the ip of the interface the broadcast goes out
+/// on, so a server bound to `0.0.0.0` is reachable by its clients.
+#[allow(missing_debug_implementations)]
+pub struct DiscoveryBroadcastSystem {
+    socket: UdpSocket,
+    config: DiscoveryConfig,
+    interface: Option<IpAddr>,
+    last_sent: Option<Instant>,
+}
+
+impl DiscoveryBroadcastSystem {
+    /// Creates a `DiscoveryBroadcastSystem` which binds to an arbitrary local port.
+    pub fn new(config: DiscoveryConfig) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        let interface = interface_ip(config.broadcast_addr);
+        if interface.is_none() {
+            warn!(
+                "Cannot find the interface to {}, clients will use the address announcements \
+                 are received from.",
+                config.broadcast_addr
+            );
+        }
+
+        Ok(DiscoveryBroadcastSystem {
+            socket,
+            config,
+            interface,
+            last_sent: None,
+        })
+    }
+}
+
+impl<'a> System<'a> for DiscoveryBroadcastSystem {
+    type SystemData = ReadExpect<'a, ServerInfo>;
+
+    fn run(&mut self, info: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("discovery_broadcast_system");
+
+        let now = Instant::now();
+        if let Some(last_sent) = self.last_sent {
+            if now.duration_since(last_sent) < self.config.interval {
+                return;
+            }
+        }
+        self.last_sent = Some(now);
+
+        let game_addr = announced_addr(info.game_addr, self.interface);
+        let announcement = if game_addr == info.game_addr {
+            encode_announcement(&info)
+        } else {
+            encode_announcement(&ServerInfo {
+                game_addr,
+                ..info.clone()
+            })
+        };
+        match announcement {
+            Ok(payload) => {
+                if let Err(e) = self.socket.send_to(&payload, self.config.broadcast_addr) {
+                    warn!("Failed to broadcast server announcement: {}", e);
+                }
+            }
+            Err(e) => error!("Cannot serialize server announcement. Reason: {}", e),
+        }
+    }
+}
+
+/// Listens for server announcements and keeps the `AvailableServers` resource up to date.
+#[allow(missing_debug_implementations)]
+pub struct DiscoveryListenSystem {
+    socket: UdpSocket,
+    config: DiscoveryConfig,
+    buffer: Vec<u8>,
+}
+
+impl DiscoveryListenSystem {
+    /// Creates a `DiscoveryListenSystem` which binds to the port of `config.broadcast_addr`.
+    pub fn new(config: DiscoveryConfig) -> Result<Self> {
+        let socket = UdpSocket::bind(config.listen_addr())?;
+        socket.set_nonblocking(true)?;
+
+        Ok(DiscoveryListenSystem {
+            socket,
+            config,
+            buffer: vec![0; MAX_ANNOUNCEMENT_SIZE],
+        })
+    }
+}
+
+impl<'a> System<'a> for DiscoveryListenSystem {
+    type SystemData = Write<'a, AvailableServers>;
+
+    fn run(&mut self, mut servers: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("discovery_listen_system");
+
+        let now = Instant::now();
+        loop {
+            match self.socket.recv_from(&mut self.buffer) {
+                Ok((len, source)) => {
+                    if let Some(info) = decode_announcement(&self.buffer[..len]) {
+                        servers.announce(info, source, now);
+                    }
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    error!("Failed to receive server announcement: {}", e);
+                    break;
+                }
+            }
+        }
+        servers.prune(now, self.config.server_timeout);
+    }
+}
+
+/// Adds the `DiscoveryBroadcastSystem` and inserts the given `ServerInfo` into the world.
+///
+/// Update the `ServerInfo` resource to change what is announced.
+#[derive(Debug)]
+pub struct DiscoveryServerBundle {
+    info: ServerInfo,
+    config: DiscoveryConfig,
+}
+
+impl DiscoveryServerBundle {
+    /// Creates a new `DiscoveryServerBundle` announcing `info`.
+    pub fn new(info: ServerInfo) -> Self {
+        DiscoveryServerBundle {
+            info,
+            config: DiscoveryConfig::default(),
+        }
+    }
+
+    /// Sets the discovery configuration.
+    pub fn with_config(mut self, config: DiscoveryConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for DiscoveryServerBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> std::result::Result<(), Error> {
+        world.insert(self.info);
+        let system = DiscoveryBroadcastSystem::new(self.config)
+            .with_context(|_| Error::from_string("Failed to open discovery broadcast socket."))?;
+        builder.add(system, "discovery_broadcast", &[]);
+        Ok(())
+    }
+}
+
+/// Adds the `DiscoveryListenSystem`, which fills the `AvailableServers` resource.
+#[derive(Debug, Default)]
+pub struct DiscoveryClientBundle {
+    config: DiscoveryConfig,
+}
+
+impl DiscoveryClientBundle {
+    /// Creates a new `DiscoveryClientBundle` with the default configuration.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the discovery configuration.
+    pub fn with_config(mut self, config: DiscoveryConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for DiscoveryClientBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> std::result::Result<(), Error> {
+        world.insert(AvailableServers::default());
+        let system = DiscoveryListenSystem::new(self.config)
+            .with_context(|_| Error::from_string("Failed to open discovery listen socket."))?;
+        builder.add(system, "discovery_listen", &[]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn announcement_roundtrip() {
+        let info = ServerInfo::new("test", "127.0.0.1:3455".parse().unwrap())
+            .with_max_players(8)
+            .with_metadata("map", "dust");
+
+        let payload = encode_announcement(&info).unwrap();
+
+        assert_eq!(decode_announcement(&payload), Some(info));
+    }
+
+    #[test]
+    fn foreign_packets_are_ignored() {
+        assert_eq!(decode_announcement(b"AM"), None);
+        assert_eq!(decode_announcement(b"not an announcement"), None);
+    }
+
+    #[test]
+    fn unspecified_game_addr_uses_source_ip() {
+        let mut servers = AvailableServers::default();
+        let info = ServerInfo::new("test", "0.0.0.0:3455".parse().unwrap());
+
+        servers.announce(info, "192.168.1.20:40000".parse().unwrap(), Instant::now());
+
+        assert!(servers.get("192.168.1.20:3455".parse().unwrap()).is_some());
+    }
+
+    #[test]
+    fn unspecified_game_addr_announces_the_interface_ip() {
+        let interface = Some("192.168.1.20".parse().unwrap());
+
+        assert_eq!(
+            announced_addr("0.0.0.0:3456".parse().unwrap(), interface),
+            "192.168.1.20:3456".parse().unwrap()
+        );
+        assert_eq!(
+            announced_addr("10.0.0.5:3456".parse().unwrap(), interface),
+            "10.0.0.5:3456".parse().unwrap()
+        );
+        assert_eq!(
+            announced_addr("0.0.0.0:3456".parse().unwrap(), None),
+            "0.0.0.0:3456".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn silent_servers_are_pruned() {
+        let mut servers = AvailableServers::default();
+        let start = Instant::now();
+        servers.announce(
+            ServerInfo::new("old", "127.0.0.1:1000".parse().unwrap()),
+            "127.0.0.1:50000".parse().unwrap(),
+            start,
+        );
+        servers.announce(
+            ServerInfo::new("new", "127.0.0.1:2000".parse().unwrap()),
+            "127.0.0.1:50001".parse().unwrap(),
+            start + Duration::from_secs(4),
+        );
+
+        servers.prune(start + Duration::from_secs(6), Duration::from_secs(5));
+
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers.iter().next().unwrap().info.name, "new");
+    }
+}
@@ -12,7 +12,15 @@
 pub use crate::{
     bundle::NetworkBundle,
     connection::{ConnectionState, NetConnection, NetIdentity},
+    discovery::{
+        AvailableServers, DiscoveredServer, DiscoveryBroadcastSystem, DiscoveryClientBundle,
+        DiscoveryConfig, DiscoveryListenSystem, DiscoveryServerBundle, ServerInfo,
+    },
     error::Result,
+    lobby::{
+        Lobby, LobbyClient, LobbyClientBundle, LobbyClientSystem, LobbyConfig, LobbyEvent,
+        LobbyMessage, LobbyPlayer, LobbyServerBundle, LobbyServerSystem, LobbyState, PlayerId,
+    },
     net_event::{NetEvent, NetPacket},
     network_socket::NetSocketSystem,
     server::{Host, ServerConfig},
@@ -26,7 +34,9 @@ use serde::{de::DeserializeOwned, Serialize};
 
 mod bundle;
 mod connection;
+mod discovery;
 mod error;
+mod lobby;
 mod net_event;
 mod network_socket;
 mod server;
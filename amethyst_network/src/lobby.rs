@@ -0,0 +1,786 @@
+//! A minimal lobby protocol for joining a server, readying up and starting a match.
+//!
+//! The lobby is built on the regular `NetEvent` plumbing: lobby traffic is sent as
+//! `NetEvent::Packet(NetPacket<LobbyMessage>)` over a `NetConnection<LobbyMessage>`.
+//!
+//! - On the server, the `LobbyServerSystem` reads requests from every connection, updates the
+//!   `Lobby` resource and answers the clients. Lobby changes are published as `LobbyEvent`s.
+//! - On the client, the `LobbyClientSystem` sends the requests queued on the `LobbyClient`
+//!   resource and keeps it in sync with what the server reports.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use shrev::{EventChannel, ReaderId};
+
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{Entities, Entity, Join, System, World, Write, WriteStorage},
+    shred::DispatcherBuilder,
+};
+use amethyst_error::Error;
+
+use crate::{
+    discovery::{DiscoveryConfig, DiscoveryServerBundle, ServerInfo},
+    NetConnection, NetEvent, NetPacket, NetworkBundle, ServerConfig,
+};
+
+/// How long a client waits for an answer before sending its join request again.
+const JOIN_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Identifier the server assigns to a player when it joins the lobby.
+pub type PlayerId = u32;
+
+/// The messages exchanged between lobby clients and the lobby server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LobbyMessage {
+    /// Client request to join the lobby under the given name.
+    Join {
+        /// The name the player wants to be displayed with.
+        name: String,
+    },
+    /// Client request to change its ready state.
+    SetReady(bool),
+    /// Client request to start the match. Only the host, the player who joined first, may start.
+    RequestStart,
+    /// Client notification that it leaves the lobby.
+    Leave,
+    /// Server answer to a successful `Join`.
+    Welcome {
+        /// The id assigned to the joining player.
+        player_id: PlayerId,
+    },
+    /// Server answer to a `Join` or `RequestStart` which was refused.
+    Rejected {
+        /// Why the request was refused.
+        reason: String,
+    },
+    /// Server notification containing all players currently in the lobby.
+    Roster(Vec<LobbyPlayer>),
+    /// Server notification that the match starts.
+    MatchStarting,
+}
+
+impl LobbyMessage {
+    /// Wraps this message in a reliable ordered `NetEvent`, ready to be queued on a `NetConnection`.
+    pub fn into_net_event(self) -> NetEvent<LobbyMessage> {
+        NetEvent::Packet(NetPacket::reliable_ordered(self, None))
+    }
+}
+
+/// A player in the lobby.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LobbyPlayer {
+    /// The id assigned by the server.
+    pub id: PlayerId,
+    /// The display name of the player.
+    pub name: String,
+    /// Whether the player is ready to start.
+    pub ready: bool,
+}
+
+/// The state of the lobby.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LobbyState {
+    /// Players can join and ready up.
+    Waiting,
+    /// The match was started, new players are rejected.
+    Started,
+}
+
+/// Events published by the `LobbyServerSystem` whenever the lobby changes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LobbyEvent {
+    /// A player joined the lobby.
+    PlayerJoined(PlayerId),
+    /// A player left the lobby or was disconnected.
+    PlayerLeft(PlayerId),
+    /// A player changed its ready state.
+    PlayerReady(PlayerId, bool),
+    /// The match was started.
+    MatchStarted,
+}
+
+/// The configuration of the lobby server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LobbyConfig {
+    /// The maximal amount of players. This is by default 8.
+    pub max_players: usize,
+    /// The minimal amount of players needed to start. This is by default 2.
+    pub min_players: usize,
+    /// Start automatically as soon as enough players joined and all of them are ready.
+    /// This is disabled by default, in which case the host has to send `RequestStart`.
+    pub auto_start: bool,
+}
+
+impl Default for LobbyConfig {
+    fn default() -> Self {
+        LobbyConfig {
+            max_players: 8,
+            min_players: 2,
+            auto_start: false,
+        }
+    }
+}
+
+/// Server side lobby resource.
+///
+/// Requests are processed with `handle_message`, the answers are collected and sent out by the
+/// `LobbyServerSystem`.
+#[derive(Debug)]
+pub struct Lobby {
+    config: LobbyConfig,
+    state: LobbyState,
+    players: Vec<(SocketAddr, LobbyPlayer)>,
+    next_id: PlayerId,
+    outbox: Vec<(Option<SocketAddr>, LobbyMessage)>,
+    events: Vec<LobbyEvent>,
+}
+
+impl Lobby {
+    /// Creates an empty lobby.
+    pub fn new(config: LobbyConfig) -> Self {
+        Lobby {
+            config,
+            state: LobbyState::Waiting,
+            players: Vec::new(),
+            next_id: 0,
+            outbox: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the current state of the lobby.
+    pub fn state(&self) -> LobbyState {
+        self.state
+    }
+
+    /// Returns the lobby configuration.
+    pub fn config(&self) -> &LobbyConfig {
+        &self.config
+    }
+
+    /// Returns an iterator over all players in join order.
+    pub fn players(&self) -> impl Iterator<Item = &LobbyPlayer> {
+        self.players.iter().map(|(_, player)| player)
+    }
+
+    /// Returns the address of the given player.
+    pub fn player_addr(&self, id: PlayerId) -> Option<SocketAddr> {
+        self.players
+            .iter()
+            .find(|(_, player)| player.id == id)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Returns whether the match can be started right now.
+    pub fn can_start(&self) -> bool {
+        self.state == LobbyState::Waiting
+            && self.players.len() >= self.config.min_players
+            && self.players.iter().all(|(_, player)| player.ready)
+    }
+
+    /// Starts the match regardless of who asked for it, if `can_start` allows it.
+    ///
+    /// Returns whether the match was started.
+    pub fn start(&mut self) -> bool {
+        if !self.can_start() {
+            return false;
+        }
+        self.state = LobbyState::Started;
+        self.outbox.push((None, LobbyMessage::MatchStarting));
+        self.events.push(LobbyEvent::MatchStarted);
+        true
+    }
+
+    /// Processes a message received from `from`.
+    pub fn handle_message(&mut self, from: SocketAddr, message: &LobbyMessage) {
+        match message {
+            LobbyMessage::Join { name } => self.join(from, name),
+            LobbyMessage::SetReady(ready) => {
+                if let Some((_, player)) = self.players.iter_mut().find(|(a, _)| *a == from) {
+                    if player.ready != *ready {
+                        player.ready = *ready;
+                        self.events.push(LobbyEvent::PlayerReady(player.id, *ready));
+                        self.push_roster();
+                        if self.config.auto_start {
+                            self.start();
+                        }
+                    }
+                }
+            }
+            LobbyMessage::RequestStart => {
+                let is_host = self.players.first().map(|(addr, _)| *addr) == Some(from);
+                if !is_host {
+                    self.reject(from, "only the host can start the match");
+                } else if !self.start() {
+                    self.reject(from, "not all players are ready");
+                }
+            }
+            LobbyMessage::Leave => self.disconnect(from),
+            _ => {}
+        }
+    }
+
+    /// Removes the player connected from `addr`, if any.
+    pub fn disconnect(&mut self, addr: SocketAddr) {
+        if let Some(index) = self.players.iter().position(|(a, _)| *a == addr) {
+            let (_, player) = self.players.remove(index);
+            self.events.push(LobbyEvent::PlayerLeft(player.id));
+            self.push_roster();
+        }
+    }
+
+    fn join(&mut self, from: SocketAddr, name: &str) {
+        if let Some((_, player)) = self.players.iter().find(|(a, _)| *a == from) {
+            // The welcome message got lost, the client is asking again.
+            let player_id = player.id;
+            self.outbox
+                .push((Some(from), LobbyMessage::Welcome { player_id }));
+            self.push_roster();
+            return;
+        }
+        if self.state != LobbyState::Waiting {
+            self.reject(from, "the match has already started");
+            return;
+        }
+        if self.players.len() >= self.config.max_players {
+            self.reject(from, "the lobby is full");
+            return;
+        }
+
+        let player_id = self.next_id;
+        self.next_id += 1;
+        self.players.push((
+            from,
+            LobbyPlayer {
+                id: player_id,
+                name: name.to_string(),
+                ready: false,
+            },
+        ));
+        self.outbox
+            .push((Some(from), LobbyMessage::Welcome { player_id }));
+        self.events.push(LobbyEvent::PlayerJoined(player_id));
+        self.push_roster();
+    }
+
+    fn reject(&mut self, to: SocketAddr, reason: &str) {
+        self.outbox.push((
+            Some(to),
+            LobbyMessage::Rejected {
+                reason: reason.to_string(),
+            },
+        ));
+    }
+
+    fn push_roster(&mut self) {
+        let roster = self.players().cloned().collect();
+        self.outbox.push((None, LobbyMessage::Roster(roster)));
+    }
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Lobby::new(LobbyConfig::default())
+    }
+}
+
+/// Runs the server side of the lobby.
+///
+/// Reads the lobby requests from every `NetConnection<LobbyMessage>`, answers them and
+/// publishes the resulting `LobbyEvent`s. If a `ServerInfo` resource is present its player count
+/// is kept up to date, so LAN discovery announces the lobby occupancy.
+#[derive(Default)]
+#[allow(missing_debug_implementations)]
+pub struct LobbyServerSystem {
+    readers: HashMap<Entity, ReaderId<NetEvent<LobbyMessage>>>,
+}
+
+impl LobbyServerSystem {
+    /// Creates a new `LobbyServerSystem`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a> System<'a> for LobbyServerSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, NetConnection<LobbyMessage>>,
+        Write<'a, Lobby>,
+        Write<'a, EventChannel<LobbyEvent>>,
+        Option<Write<'a, ServerInfo>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut connections, mut lobby, mut lobby_events, server_info): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("lobby_server_system");
+
+        for (entity, connection) in (&entities, &mut connections).join() {
+            let reader = self
+                .readers
+                .entry(entity)
+                .or_insert_with(|| connection.register_reader());
+
+            for event in connection.received_events(reader) {
+                match event {
+                    NetEvent::Packet(packet) => {
+                        lobby.handle_message(connection.target_addr, packet.content())
+                    }
+                    NetEvent::Disconnected(addr) => lobby.disconnect(*addr),
+                    _ => {}
+                }
+            }
+        }
+        self.readers.retain(|entity, _| entities.is_alive(*entity));
+
+        let outbox: Vec<_> = lobby.outbox.drain(..).collect();
+        for (to, message) in outbox {
+            for connection in (&mut connections).join() {
+                let addr = connection.target_addr;
+                let recipient = match to {
+                    Some(to) => to == addr,
+                    None => lobby.players.iter().any(|(a, _)| *a == addr),
+                };
+                if recipient {
+                    connection.queue(message.clone().into_net_event());
+                }
+            }
+        }
+        lobby_events.drain_vec_write(&mut lobby.events);
+
+        if let Some(mut info) = server_info {
+            info.player_count = lobby.players.len() as u16;
+            info.max_players = lobby.config.max_players as u16;
+        }
+    }
+}
+
+/// Client side lobby resource.
+///
+/// Requests are queued with `join`, `set_ready`, `request_start` and `leave`, and sent by the
+/// `LobbyClientSystem` over the `NetConnection<LobbyMessage>` targeting the server.
+#[derive(Debug, Clone)]
+pub struct LobbyClient {
+    server: SocketAddr,
+    name: String,
+    player_id: Option<PlayerId>,
+    roster: Vec<LobbyPlayer>,
+    rejection: Option<String>,
+    started: bool,
+    left: bool,
+    last_join: Option<Instant>,
+    outbox: Vec<LobbyMessage>,
+}
+
+impl LobbyClient {
+    /// Creates a client which will join the lobby on `server` as `name`.
+    pub fn new<N: Into<String>>(server: SocketAddr, name: N) -> Self {
+        LobbyClient {
+            server,
+            name: name.into(),
+            player_id: None,
+            roster: Vec::new(),
+            rejection: None,
+            started: false,
+            left: false,
+            last_join: None,
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Returns the address of the lobby server.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Returns the id the server assigned to this client, once it joined.
+    pub fn player_id(&self) -> Option<PlayerId> {
+        self.player_id
+    }
+
+    /// Returns the players in the lobby, as last reported by the server.
+    pub fn roster(&self) -> &[LobbyPlayer] {
+        &self.roster
+    }
+
+    /// Returns the reason of the last refused request, if any.
+    pub fn rejection(&self) -> Option<&str> {
+        self.rejection.as_deref()
+    }
+
+    /// Returns whether the server started the match.
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// Queues a change of the ready state.
+    pub fn set_ready(&mut self, ready: bool) {
+        self.outbox.push(LobbyMessage::SetReady(ready));
+    }
+
+    /// Queues a request to start the match.
+    pub fn request_start(&mut self) {
+        self.outbox.push(LobbyMessage::RequestStart);
+    }
+
+    /// Joins the lobby again after `leave`, or retries after a rejection.
+    ///
+    /// A new client joins on its own, without calling this.
+    pub fn join(&mut self) {
+        self.left = false;
+        self.rejection = None;
+        self.last_join = None;
+    }
+
+    /// Queues leaving the lobby. The client stays out of the lobby until `join` is called.
+    pub fn leave(&mut self) {
+        self.outbox.push(LobbyMessage::Leave);
+        self.player_id = None;
+        self.roster.clear();
+        self.left = true;
+    }
+
+    /// Applies a message received from the server.
+    pub fn handle_message(&mut self, message: &LobbyMessage) {
+        match message {
+            LobbyMessage::Welcome { player_id } => {
+                self.player_id = Some(*player_id);
+                self.rejection = None;
+            }
+            LobbyMessage::Rejected { reason } => self.rejection = Some(reason.clone()),
+            LobbyMessage::Roster(roster) => self.roster = roster.clone(),
+            LobbyMessage::MatchStarting => self.started = true,
+            _ => {}
+        }
+    }
+
+    fn should_join(&mut self, now: Instant) -> bool {
+        if self.player_id.is_some() || self.rejection.is_some() || self.started || self.left {
+            return false;
+        }
+        match self.last_join {
+            Some(last_join) if now.duration_since(last_join) < JOIN_RETRY_INTERVAL => false,
+            _ => {
+                self.last_join = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// Runs the client side of the lobby.
+///
+/// Requires an entity with a `NetConnection<LobbyMessage>` targeting `LobbyClient::server`.
+/// The join request is sent, and resent until answered, as soon as that connection exists.
+#[derive(Default)]
+#[allow(missing_debug_implementations)]
+pub struct LobbyClientSystem {
+    reader: Option<(Entity, ReaderId<NetEvent<LobbyMessage>>)>,
+}
+
+impl LobbyClientSystem {
+    /// Creates a new `LobbyClientSystem`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a> System<'a> for LobbyClientSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, NetConnection<LobbyMessage>>,
+        Option<Write<'a, LobbyClient>>,
+    );
+
+    fn run(&mut self, (entities, mut connections, client): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("lobby_client_system");
+
+        let mut client = match client {
+            Some(client) => client,
+            None => return,
+        };
+
+        for (entity, connection) in (&entities, &mut connections).join() {
+            if connection.target_addr != client.server {
+                continue;
+            }
+
+            if self.reader.as_ref().map(|(e, _)| *e) != Some(entity) {
+                self.reader = Some((entity, connection.register_reader()));
+            }
+            if let Some((_, reader)) = self.reader.as_mut() {
+                for event in connection.received_events(reader) {
+                    if let NetEvent::Packet(packet) = event {
+                        client.handle_message(packet.content());
+                    }
+                }
+            }
+
+            if client.should_join(Instant::now()) {
+                let name = client.name.clone();
+                connection.queue(LobbyMessage::Join { name }.into_net_event());
+            }
+            for message in client.outbox.drain(..) {
+                connection.queue(message.into_net_event());
+            }
+            break;
+        }
+    }
+}
+
+/// Sets up a lobby server: the network socket, the `LobbyServerSystem` and, optionally,
+/// LAN discovery announcing the lobby.
+#[allow(missing_debug_implementations)]
+pub struct LobbyServerBundle {
+    server_config: ServerConfig,
+    lobby_config: LobbyConfig,
+    discovery: Option<(ServerInfo, DiscoveryConfig)>,
+}
+
+impl LobbyServerBundle {
+    /// Creates a lobby server listening on the given address.
+    pub fn new(udp_socket_addr: SocketAddr) -> Self {
+        LobbyServerBundle {
+            server_config: ServerConfig {
+                udp_socket_addr,
+                ..Default::default()
+            },
+            lobby_config: LobbyConfig::default(),
+            discovery: None,
+        }
+    }
+
+    /// Sets the lobby configuration.
+    pub fn with_lobby_config(mut self, lobby_config: LobbyConfig) -> Self {
+        self.lobby_config = lobby_config;
+        self
+    }
+
+    /// Announces the lobby on the local network.
+    pub fn with_discovery(mut self, info: ServerInfo, config: DiscoveryConfig) -> Self {
+        self.discovery = Some((info, config));
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for LobbyServerBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.insert(Lobby::new(self.lobby_config));
+
+        NetworkBundle::<LobbyMessage>::from_config(self.server_config).build(world, builder)?;
+        builder.add(LobbyServerSystem::new(), "lobby_server", &["net_socket"]);
+
+        if let Some((info, config)) = self.discovery {
+            DiscoveryServerBundle::new(info)
+                .with_config(config)
+                .build(world, builder)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets up a lobby client: the network socket and the `LobbyClientSystem`.
+///
+/// Insert a `LobbyClient` resource and create a `NetConnection<LobbyMessage>` to the server
+/// to join a lobby.
+#[allow(missing_debug_implementations)]
+pub struct LobbyClientBundle {
+    server_config: ServerConfig,
+}
+
+impl LobbyClientBundle {
+    /// Creates a lobby client whose socket is bound to the given address.
+    pub fn new(udp_socket_addr: SocketAddr) -> Self {
+        LobbyClientBundle {
+            server_config: ServerConfig {
+                udp_socket_addr,
+                create_net_connection_on_connect: false,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for LobbyClientBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        NetworkBundle::<LobbyMessage>::from_config(self.server_config).build(world, builder)?;
+        builder.add(LobbyClientSystem::new(), "lobby_client", &["net_socket"]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use amethyst_core::ecs::{Builder, RunNow, WorldExt};
+
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn join(lobby: &mut Lobby, port: u16, name: &str) {
+        lobby.handle_message(
+            addr(port),
+            &LobbyMessage::Join {
+                name: name.to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn players_are_welcomed_and_listed() {
+        let mut lobby = Lobby::default();
+        join(&mut lobby, 1000, "alice");
+        join(&mut lobby, 1001, "bob");
+
+        assert_eq!(lobby.players().count(), 2);
+        assert!(lobby
+            .outbox
+            .contains(&(Some(addr(1001)), LobbyMessage::Welcome { player_id: 1 })));
+        assert_eq!(
+            lobby.events,
+            vec![LobbyEvent::PlayerJoined(0), LobbyEvent::PlayerJoined(1)]
+        );
+    }
+
+    #[test]
+    fn full_lobby_rejects_players() {
+        let mut lobby = Lobby::new(LobbyConfig {
+            max_players: 1,
+            ..Default::default()
+        });
+        join(&mut lobby, 1000, "alice");
+        join(&mut lobby, 1001, "bob");
+
+        assert_eq!(lobby.players().count(), 1);
+        assert!(lobby.outbox.contains(&(
+            Some(addr(1001)),
+            LobbyMessage::Rejected {
+                reason: "the lobby is full".to_string()
+            }
+        )));
+    }
+
+    #[test]
+    fn only_host_starts_when_everyone_is_ready() {
+        let mut lobby = Lobby::default();
+        join(&mut lobby, 1000, "alice");
+        join(&mut lobby, 1001, "bob");
+        lobby.handle_message(addr(1000), &LobbyMessage::SetReady(true));
+
+        lobby.handle_message(addr(1000), &LobbyMessage::RequestStart);
+        assert_eq!(lobby.state(), LobbyState::Waiting);
+
+        lobby.handle_message(addr(1001), &LobbyMessage::SetReady(true));
+        lobby.handle_message(addr(1001), &LobbyMessage::RequestStart);
+        assert_eq!(lobby.state(), LobbyState::Waiting);
+
+        lobby.handle_message(addr(1000), &LobbyMessage::RequestStart);
+        assert_eq!(lobby.state(), LobbyState::Started);
+        assert!(lobby.events.contains(&LobbyEvent::MatchStarted));
+    }
+
+    #[test]
+    fn auto_start_when_everyone_is_ready() {
+        let mut lobby = Lobby::new(LobbyConfig {
+            auto_start: true,
+            ..Default::default()
+        });
+        join(&mut lobby, 1000, "alice");
+        join(&mut lobby, 1001, "bob");
+        lobby.handle_message(addr(1000), &LobbyMessage::SetReady(true));
+        lobby.handle_message(addr(1001), &LobbyMessage::SetReady(true));
+
+        assert_eq!(lobby.state(), LobbyState::Started);
+    }
+
+    #[test]
+    fn leaving_players_are_removed() {
+        let mut lobby = Lobby::default();
+        join(&mut lobby, 1000, "alice");
+        lobby.handle_message(addr(1000), &LobbyMessage::Leave);
+
+        assert_eq!(lobby.players().count(), 0);
+        assert_eq!(lobby.events.last(), Some(&LobbyEvent::PlayerLeft(0)));
+    }
+
+    #[test]
+    fn client_tracks_server_messages() {
+        let mut client = LobbyClient::new(addr(1000), "alice");
+        let now = Instant::now();
+        assert!(client.should_join(now));
+        assert!(!client.should_join(now));
+
+        client.handle_message(&LobbyMessage::Welcome { player_id: 3 });
+        client.handle_message(&LobbyMessage::MatchStarting);
+
+        assert_eq!(client.player_id(), Some(3));
+        assert!(client.is_started());
+        assert!(!client.should_join(now + JOIN_RETRY_INTERVAL));
+    }
+
+    fn sent_messages(connection: &mut NetConnection<LobbyMessage>) -> Vec<LobbyMessage> {
+        connection
+            .send_buffer_early_read()
+            .filter_map(|event| match event {
+                NetEvent::Packet(packet) => Some(packet.content().clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn client_stays_out_of_the_lobby_after_leaving() {
+        let mut world = World::new();
+        world.register::<NetConnection<LobbyMessage>>();
+        let entity = world
+            .create_entity()
+            .with(NetConnection::<LobbyMessage>::new(addr(1000)))
+            .build();
+
+        let mut client = LobbyClient::new(addr(1000), "alice");
+        client.handle_message(&LobbyMessage::Welcome { player_id: 3 });
+        client.last_join = Some(Instant::now() - JOIN_RETRY_INTERVAL * 2);
+        client.leave();
+        world.insert(client);
+
+        let mut system = LobbyClientSystem::new();
+        system.run_now(&world);
+        let mut connections = world.write_storage::<NetConnection<LobbyMessage>>();
+        let connection = connections.get_mut(entity).unwrap();
+        assert_eq!(sent_messages(connection), vec![LobbyMessage::Leave]);
+        drop(connections);
+
+        world.write_resource::<LobbyClient>().join();
+        system.run_now(&world);
+        let mut connections = world.write_storage::<NetConnection<LobbyMessage>>();
+        let connection = connections.get_mut(entity).unwrap();
+        assert_eq!(
+            sent_messages(connection),
+            vec![LobbyMessage::Join {
+                name: "alice".to_string()
+            }]
+        );
+    }
+}
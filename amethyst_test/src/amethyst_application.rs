@@ -2,7 +2,8 @@ use std::{any::Any, marker::PhantomData, panic, path::PathBuf, sync::Mutex};
 
 use amethyst::{
     self,
-    core::{transform::TransformBundle, EventReader, RunNowDesc, SystemBundle, SystemDesc},
+    audio::{output::Output, AudioBundle},
+    core::{transform::TransformBundle, EventReader, RunNowDesc, SystemBundle, SystemDesc, Time},
     ecs::prelude::*,
     error::Error,
     input::{BindingTypes, InputBundle},
@@ -91,6 +92,8 @@ where
     /// States to run, in user specified order.
     #[derivative(Debug = "ignore")]
     state_fns: Vec<FnState<T, E>>,
+    /// Whether frames run on a virtual clock, see `with_headless`.
+    headless: bool,
     /// Game data and event type.
     state_data: PhantomData<(T, E, R)>,
 }
@@ -104,6 +107,7 @@ impl AmethystApplication<GameData<'static, 'static>, StateEvent, StateEventReade
             resource_add_fns: Vec::new(),
             setup_fns: Vec::new(),
             state_fns: Vec::new(),
+            headless: false,
             state_data: PhantomData,
         }
     }
@@ -120,6 +124,17 @@ impl AmethystApplication<GameData<'static, 'static>, StateEvent, StateEventReade
             .with_resource(ScreenDimensions::new(SCREEN_WIDTH, SCREEN_HEIGHT, HIDPI))
     }
 
+    /// Returns an application with the Transform and Audio bundles, playing to a null `Output`.
+    ///
+    /// This does not need an audio device. What was played can be inspected through
+    /// `Output::null_output`.
+    pub fn audio_base(
+    ) -> AmethystApplication<GameData<'static, 'static>, StateEvent, StateEventReader> {
+        AmethystApplication::blank()
+            .with_bundle(TransformBundle::new())
+            .with_bundle_fn(|| AudioBundle::new(Output::null()))
+    }
+
     /// Returns a `PathBuf` to `<crate_dir>/assets`.
     pub fn assets_dir() -> Result<PathBuf, Error> {
         Ok(application_root_dir()?.join("assets"))
@@ -144,6 +159,7 @@ where
             self.resource_add_fns,
             self.setup_fns,
             self.state_fns,
+            self.headless,
         );
         Self::build_internal(params)
     }
@@ -157,11 +173,12 @@ where
     // parameters which causes a compilation failure.
     #[allow(unknown_lints, clippy::type_complexity)]
     fn build_internal(
-        (bundle_add_fns, resource_add_fns, setup_fns, state_fns): (
+        (bundle_add_fns, resource_add_fns, setup_fns, state_fns, headless): (
             Vec<BundleAddFn>,
             Vec<FnResourceAdd>,
             Vec<FnSetup>,
             Vec<FnState<GameData<'static, 'static>, E>>,
+            bool,
         ),
     ) -> Result<CoreApplication<'static, GameData<'static, 'static>, E, R>, Error>
     where
@@ -184,6 +201,7 @@ where
             game_data,
             resource_add_fns,
             setup_fns,
+            headless,
         )
     }
 
@@ -192,6 +210,7 @@ where
         game_data: GameDataBuilder<'static, 'static>,
         resource_add_fns: Vec<FnResourceAdd>,
         setup_fns: Vec<FnSetup>,
+        headless: bool,
     ) -> Result<CoreApplication<'static, GameData<'static, 'static>, E, R>, Error>
     where
         S: State<GameData<'static, 'static>, E> + 'static,
@@ -200,6 +219,13 @@ where
         let assets_dir =
             AmethystApplication::assets_dir().expect("Failed to get default assets dir.");
         let mut application_builder = CoreApplication::build(assets_dir, first_state)?;
+        if headless {
+            let frame_length = application_builder
+                .world
+                .read_resource::<Time>()
+                .fixed_time();
+            application_builder = application_builder.headless(frame_length);
+        }
         {
             let world = &mut application_builder.world;
             for mut function in resource_add_fns {
@@ -222,6 +248,7 @@ where
             self.resource_add_fns,
             self.setup_fns,
             self.state_fns,
+            self.headless,
         );
 
         // `CoreApplication` is `!UnwindSafe`, but wrapping it in a `Mutex` allows us to
@@ -308,10 +335,20 @@ where
             resource_add_fns: self.resource_add_fns,
             setup_fns: self.setup_fns,
             state_fns: Vec::new(),
+            headless: self.headless,
             state_data: PhantomData,
         }
     }
 
+    /// Runs the application headless, advancing every frame by the fixed step length.
+    ///
+    /// Frames don't wait for the frame limiter, and every run sees the same `Time`, see
+    /// `ApplicationBuilder::headless`.
+    pub fn with_headless(mut self) -> Self {
+        self.headless = true;
+        self
+    }
+
     /// Adds a bundle to the list of bundles.
     ///
     /// # Parameters
@@ -1004,6 +1041,140 @@ mod test {
         }
     }
 
+    mod null_audio_test {
+        use std::{fs::File, io::Read};
+
+        use amethyst::{
+            audio::{output::Output, Source},
+            error::Error,
+            utils::application_root_dir,
+        };
+
+        use super::AmethystApplication;
+
+        #[test]
+        fn audio_base_records_played_sounds() -> Result<(), Error> {
+            AmethystApplication::audio_base()
+                .with_effect(|world| {
+                    let mut bytes = Vec::new();
+                    let path = application_root_dir()
+                        .unwrap()
+                        .join("../amethyst_audio/tests/sound_test.wav");
+                    File::open(path).unwrap().read_to_end(&mut bytes).unwrap();
+                    let source = Source::from_bytes(bytes);
+                    world.read_resource::<Output>().play_once(&source, 1.0);
+                    world.insert(source);
+                })
+                .with_assertion(|world| {
+                    let source = world.read_resource::<Source>();
+                    let output = world.read_resource::<Output>();
+                    let records = output.null_output().unwrap().records_of(&source);
+
+                    assert_eq!(records.len(), 1);
+                    assert_eq!(records[0].volume, 1.0);
+                })
+                .run()
+        }
+    }
+
+    mod headless_test {
+        use amethyst::{core::Time, error::Error};
+
+        use super::AmethystApplication;
+
+        #[test]
+        fn time_advances_by_the_fixed_step_each_frame() -> Result<(), Error> {
+            AmethystApplication::blank()
+                .with_headless()
+                .with_effect(|_| {})
+                .with_assertion(|world| {
+                    let time = world.read_resource::<Time>();
+                    let frames = time.frame_number() as u32;
+                    assert!(frames > 0);
+                    assert_eq!(time.delta_time(), time.fixed_time());
+                    assert_eq!(time.absolute_time(), time.fixed_time() * frames);
+                })
+                .run()
+        }
+
+        #[test]
+        fn step_stops_when_the_last_state_stops() -> Result<(), Error> {
+            let mut application = AmethystApplication::blank()
+                .with_headless()
+                .with_assertion(|_| {})
+                .build()?;
+
+            assert!(application.step(1));
+            assert!(!application.step(10));
+            assert!(!application.is_running());
+            Ok(())
+        }
+    }
+
+    mod input_playback_test {
+        use std::time::Duration;
+
+        use amethyst::{
+            error::Error,
+            input::{
+                Button, InputHandler, InputPlayback, InputRecording, RecordedEvent, RecordedFrame,
+                StringBindings,
+            },
+            winit::{ElementState, ModifiersState, VirtualKeyCode},
+        };
+
+        use super::AmethystApplication;
+
+        #[test]
+        fn input_playback_replays_recorded_frames() -> Result<(), Error> {
+            let press = RecordedEvent::Key {
+                scancode: 57,
+                state: ElementState::Pressed,
+                key_code: Some(VirtualKeyCode::Space),
+                modifiers: ModifiersState {
+                    shift: false,
+                    ctrl: false,
+                    alt: false,
+                    logo: false,
+                },
+            };
+            let recording = InputRecording::<StringBindings> {
+                hidpi: 1.0,
+                frames: vec![RecordedFrame {
+                    frame_number: 0,
+                    time: Duration::from_secs(0),
+                    events: vec![press],
+                    input_events: Vec::new(),
+                }],
+            };
+
+            AmethystApplication::ui_base::<StringBindings>()
+                .with_effect(move |world| {
+                    world
+                        .write_resource::<InputHandler<StringBindings>>()
+                        .bindings
+                        .insert_action_binding(
+                            String::from("jump"),
+                            [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+                        )
+                        .unwrap();
+                    world
+                        .write_resource::<InputPlayback<StringBindings>>()
+                        .play(recording.clone());
+                })
+                .with_assertion(|world| {
+                    let handler = world.read_resource::<InputHandler<StringBindings>>();
+                    let playback = world.read_resource::<InputPlayback<StringBindings>>();
+
+                    assert_eq!(handler.action_is_down("jump"), Some(true));
+                    assert_eq!(playback.frames_played(), 1);
+                    // The recording expected no events, but the replay sent the key press.
+                    assert_eq!(playback.desynced_frames(), &[0]);
+                })
+                .run()
+        }
+    }
+
     // === Resources === //
     #[derive(Debug, Default)]
     struct ApplicationResource;
@@ -2,6 +2,7 @@
 
 use amethyst_core::{
     ecs::prelude::{DispatcherBuilder, Read, System, World, Write},
+    frame_limiter::{FrameLimiter, FrameTimeStats},
     timing::{duration_to_nanos, Time},
     SystemBundle,
 };
@@ -25,6 +26,9 @@ use thread_profiler::profile_scope;
 /// sampled_fls will return the averaged framerate. This gives a better approximation of the "felt"
 /// framerate by the user.
 ///
+/// The FpsCounterSystem also copies the frame pacing statistics of the `FrameLimiter`, read with
+/// `frame_time_stats` and `is_throttled`.
+///
 /// # Example
 /// ```rust
 /// # use amethyst_utils::fps_counter::FpsCounter;
@@ -39,6 +43,8 @@ use thread_profiler::profile_scope;
 pub struct FpsCounter {
     buf: CircularBuffer<u64>,
     sum: u64,
+    frame_time_stats: Option<FrameTimeStats>,
+    throttled: bool,
 }
 
 impl Default for FpsCounter {
@@ -53,6 +59,8 @@ impl FpsCounter {
         FpsCounter {
             buf: CircularBuffer::<u64>::new(samplesize),
             sum: 0,
+            frame_time_stats: None,
+            throttled: false,
         }
     }
 
@@ -79,6 +87,22 @@ impl FpsCounter {
         }
         1.0e9 * self.buf.queue().len() as f32 / self.sum as f32
     }
+
+    ///Get the min, mean, 99th percentile and max frame times recorded by the `FrameLimiter`.
+    pub fn frame_time_stats(&self) -> Option<FrameTimeStats> {
+        self.frame_time_stats
+    }
+
+    ///Get whether the `FrameLimiter` lowered the frame rate because the window is unfocused.
+    pub fn is_throttled(&self) -> bool {
+        self.throttled
+    }
+
+    ///Copy the frame pacing statistics of the `FrameLimiter`.
+    pub fn update_frame_pacing(&mut self, limiter: &FrameLimiter) {
+        self.frame_time_stats = limiter.frame_times().stats();
+        self.throttled = limiter.is_throttled();
+    }
 }
 
 /// Add this system to your game to automatically push FPS values
@@ -87,12 +111,19 @@ impl FpsCounter {
 pub struct FpsCounterSystem;
 
 impl<'a> System<'a> for FpsCounterSystem {
-    type SystemData = (Read<'a, Time>, Write<'a, FpsCounter>);
-    fn run(&mut self, (time, mut counter): Self::SystemData) {
+    type SystemData = (
+        Read<'a, Time>,
+        Option<Read<'a, FrameLimiter>>,
+        Write<'a, FpsCounter>,
+    );
+    fn run(&mut self, (time, limiter, mut counter): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("fps_counter_system");
 
         counter.push(duration_to_nanos(time.delta_real_time()));
+        if let Some(limiter) = limiter {
+            counter.update_frame_pacing(&limiter);
+        }
         //Enable this to debug performance engine wide.
         log::debug!(
             "Cur FPS: {}, Sampled: {}",
@@ -3,9 +3,17 @@ use amethyst_config::Config;
 use amethyst_core::{
     ecs::{ReadExpect, RunNow, System, SystemData, World, Write, WriteExpect},
     shrev::EventChannel,
+    EventPump, Idle, Wakeup,
 };
-use std::path::Path;
-use winit::{Event, EventsLoop, Window};
+use std::{
+    cell::RefCell,
+    path::Path,
+    rc::Rc,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread,
+    time::Instant,
+};
+use winit::{ControlFlow, Event, EventsLoop, EventsLoopProxy, Window};
 
 /// System for opening and managing the window.
 #[derive(Debug)]
@@ -87,34 +95,141 @@ impl<'a> System<'a> for WindowSystem {
 ///
 /// This system must be active for any `GameState` to receive
 /// any `StateEvent::Window` event into it's `handle_event` method.
+///
+/// Its events loop is the event pump of event driven applications: while the application is
+/// idle, it blocks until a window event arrives or the `Wakeup` resource is woken, see
+/// `Wakeup::set_event_pump`.
 #[derive(Debug)]
 pub struct EventsLoopSystem {
-    events_loop: EventsLoop,
-    events: Vec<Event>,
+    pump: Rc<RefCell<WindowEventPump>>,
 }
 
 impl EventsLoopSystem {
     /// Creates a new `EventsLoopSystem` using the provided `EventsLoop`
     pub fn new(events_loop: EventsLoop) -> Self {
         Self {
-            events_loop,
-            events: Vec::with_capacity(128),
+            pump: Rc::new(RefCell::new(WindowEventPump {
+                events_loop,
+                events: Vec::with_capacity(128),
+                alarm: None,
+            })),
         }
     }
 }
 
-impl<'a> RunNow<'a> for EventsLoopSystem {
-    fn run_now(&mut self, world: &'a World) {
-        let mut event_handler = <Write<'a, EventChannel<Event>>>::fetch(world);
+/// The events loop of the `EventsLoopSystem`, shared with the application through the `Wakeup`
+/// resource.
+#[derive(Debug)]
+struct WindowEventPump {
+    events_loop: EventsLoop,
+    events: Vec<Event>,
+    alarm: Option<Sender<Option<Instant>>>,
+}
 
+impl WindowEventPump {
+    /// Writes the window events which arrived to the `World`, returning whether there were any.
+    fn poll(&mut self, world: &World) -> bool {
         let events = &mut self.events;
         self.events_loop.poll_events(|event| {
             events.push(event);
         });
-        event_handler.drain_vec_write(events);
+        let polled = !events.is_empty();
+        <Write<'_, EventChannel<Event>>>::fetch(world).drain_vec_write(events);
+        polled
+    }
+
+    /// Blocks until a window event arrives, the events loop is woken up or the deadline of
+    /// `idle` is reached.
+    fn wait(&mut self, idle: Idle) {
+        if let Idle::Until(deadline) = idle {
+            if deadline <= Instant::now() {
+                return;
+            }
+            self.set_alarm(Some(deadline));
+        }
+
+        let events = &mut self.events;
+        self.events_loop.run_forever(|event| {
+            events.push(event);
+            ControlFlow::Break
+        });
+
+        if let Idle::Until(_) = idle {
+            self.set_alarm(None);
+        }
+    }
+
+    /// Sets or cancels the deadline at which the alarm thread wakes the events loop up.
+    fn set_alarm(&mut self, deadline: Option<Instant>) {
+        if self.alarm.is_none() {
+            self.alarm = Some(spawn_alarm(self.events_loop.create_proxy()));
+        }
+        if let Some(alarm) = &self.alarm {
+            // The alarm thread only stops once the events loop is dropped.
+            let _ = alarm.send(deadline);
+        }
+    }
+}
+
+/// Spawns a thread waking the events loop up at the last deadline it received.
+fn spawn_alarm(proxy: EventsLoopProxy) -> Sender<Option<Instant>> {
+    let (sender, receiver) = mpsc::channel::<Option<Instant>>();
+    thread::spawn(move || {
+        let mut deadline = None;
+        loop {
+            let received = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        let _ = proxy.wakeup();
+                        Ok(None)
+                    } else {
+                        receiver.recv_timeout(deadline - now)
+                    }
+                }
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(next) => deadline = next,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+    sender
+}
+
+/// The `EventPump` of an `EventsLoopSystem`, kept by the `Wakeup` resource.
+struct SharedEventPump(Rc<RefCell<WindowEventPump>>);
+
+impl EventPump for SharedEventPump {
+    fn wait(&mut self, idle: Idle, world: &World) {
+        let mut pump = self.0.borrow_mut();
+        pump.wait(idle);
+        pump.poll(world);
+    }
+}
+
+impl<'a> RunNow<'a> for EventsLoopSystem {
+    fn run_now(&mut self, world: &'a World) {
+        if self.pump.borrow_mut().poll(world) {
+            // Let the states handle the events during the next frame.
+            if let Some(mut wakeup) = world.try_fetch_mut::<Wakeup>() {
+                if wakeup.is_event_driven() {
+                    wakeup.request_redraw();
+                }
+            }
+        }
     }
 
     fn setup(&mut self, world: &mut World) {
         <Write<'a, EventChannel<Event>>>::setup(world);
+        let proxy = self.pump.borrow().events_loop.create_proxy();
+        world
+            .entry::<Wakeup>()
+            .or_insert_with(Wakeup::default)
+            .set_event_pump(SharedEventPump(self.pump.clone()), move || {
+                let _ = proxy.wakeup();
+            });
     }
 }
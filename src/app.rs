@@ -1,6 +1,12 @@
 //! The core engine framework.
 
-use std::{env, marker::PhantomData, path::Path, sync::Arc, time::Duration};
+use std::{
+    env,
+    marker::PhantomData,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::shred::Resource;
 use derivative::Derivative;
@@ -20,12 +26,12 @@ use crate::{
         frame_limiter::{FrameLimiter, FrameRateLimitConfig, FrameRateLimitStrategy},
         shrev::{EventChannel, ReaderId},
         timing::{Stopwatch, Time},
-        ArcThreadPool, EventReader, Named,
+        ArcThreadPool, EventReader, FrameProfiler, Idle, Named, Timers, Wakeup,
     },
     ecs::prelude::{Component, Read, World, WorldExt, Write},
     error::Error,
     game_data::{DataDispose, DataInit},
-    state::{State, StateData, StateMachine, TransEvent},
+    state::{State, StateData, StateMachine, StateStack, StateTransitionEvent, Trans, TransEvent},
     state_event::{StateEvent, StateEventReader},
     ui::UiEvent,
 };
@@ -58,11 +64,24 @@ where
     event_reader_id: ReaderId<Event>,
     #[derivative(Debug = "ignore")]
     trans_reader_id: ReaderId<TransEvent<T, E>>,
+    #[derivative(Debug = "ignore")]
+    pending_trans: Vec<Trans<T, E>>,
     states: StateMachine<'a, T, E>,
     ignore_window_close: bool,
+    virtual_frame_length: Option<Duration>,
+    lifecycle: Lifecycle,
     data: T,
 }
 
+/// Whether the application was started and shut down, as `step` can start it and shut it down
+/// over several calls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Lifecycle {
+    Created,
+    Running,
+    ShutDown,
+}
+
 /// An Application is the root object of the game engine. It binds the OS
 /// event loop, state machines, timers and other core components in a central place.
 ///
@@ -236,7 +255,13 @@ where
             None
         };
 
-        self.initialize();
+        if self.virtual_frame_length.is_some() {
+            while self.step(1) {}
+            return;
+        }
+
+        let event_driven = self.world.read_resource::<Wakeup>().is_event_driven();
+        self.start();
         self.world.write_resource::<Stopwatch>().start();
         while self.states.is_running() {
             self.advance_frame();
@@ -245,18 +270,184 @@ where
                 profile_scope!("frame_limiter wait");
                 self.world.write_resource::<FrameLimiter>().wait();
             }
+            let elapsed = self.world.read_resource::<Stopwatch>().elapsed();
+            self.finish_frame(elapsed);
+            if event_driven {
+                #[cfg(feature = "profiler")]
+                profile_scope!("wait_for_wakeup");
+                self.wait_for_wakeup();
+            }
+            // After `wait_for_wakeup`, so the next frame doesn't include the time slept.
             {
-                let elapsed = self.world.read_resource::<Stopwatch>().elapsed();
-                let mut time = self.world.write_resource::<Time>();
-                time.increment_frame_number();
-                time.set_delta_time(elapsed);
+                let mut stopwatch = self.world.write_resource::<Stopwatch>();
+                stopwatch.stop();
+                stopwatch.restart();
             }
-            let mut stopwatch = self.world.write_resource::<Stopwatch>();
-            stopwatch.stop();
-            stopwatch.restart();
         }
 
-        self.shutdown();
+        self.stop();
+    }
+
+    /// Runs the given number of frames on the virtual clock, without waiting for the
+    /// `FrameLimiter`, and returns whether the game is still running.
+    ///
+    /// Each frame lasts the length given to `ApplicationBuilder::headless`, or the fixed step
+    /// length if the application isn't headless. The application starts on the first step and
+    /// shuts down on the step where its last state stops, later steps do nothing.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use std::time::Duration;
+    ///
+    /// use amethyst::prelude::*;
+    ///
+    /// struct NullState;
+    /// impl EmptyState for NullState {}
+    ///
+    /// # fn main() -> amethyst::Result<()> {
+    /// let mut server = Application::build("assets/", NullState)?
+    ///     .headless(Duration::from_millis(50))
+    ///     .build(())?;
+    ///
+    /// // Simulates one minute of the game, as fast as possible.
+    /// server.step_for(Duration::from_secs(60));
+    /// // Simulates 20 more frames.
+    /// server.step(20);
+    /// #     Ok(())
+    /// # }
+    /// ~~~
+    pub fn step(&mut self, frames: u64) -> bool
+    where
+        for<'b> R: EventReader<'b, Event = E>,
+    {
+        if self.lifecycle == Lifecycle::ShutDown {
+            return false;
+        }
+        self.start();
+        let frame_length = self.frame_length();
+        for _ in 0..frames {
+            if !self.states.is_running() {
+                break;
+            }
+            self.advance_frame();
+            self.finish_frame(frame_length);
+        }
+        if self.states.is_running() {
+            true
+        } else {
+            self.stop();
+            false
+        }
+    }
+
+    /// Runs frames on the virtual clock until at least `duration` passed, see `step`.
+    pub fn step_for(&mut self, duration: Duration) -> bool
+    where
+        for<'b> R: EventReader<'b, Event = E>,
+    {
+        let frame_length = self.frame_length().as_nanos();
+        let frames = (duration.as_nanos() + frame_length - 1) / frame_length;
+        self.step(frames as u64)
+    }
+
+    /// Returns whether the game is still running, which is `false` once its last state stopped.
+    pub fn is_running(&self) -> bool {
+        self.lifecycle != Lifecycle::ShutDown
+    }
+
+    /// Returns the state machine, e.g. to inspect the state stack.
+    pub fn states(&self) -> &StateMachine<'a, T, E> {
+        &self.states
+    }
+
+    /// Returns the `World` of the application, e.g. to check its state between steps.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Returns the `World` of the application mutably.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// The length of a frame on the virtual clock.
+    fn frame_length(&self) -> Duration {
+        self.virtual_frame_length
+            .unwrap_or_else(|| self.world.read_resource::<Time>().fixed_time())
+    }
+
+    /// Starts the application, unless it was already started.
+    fn start(&mut self) {
+        if self.lifecycle == Lifecycle::Created {
+            self.lifecycle = Lifecycle::Running;
+            self.initialize();
+        }
+    }
+
+    /// Shuts the application down, unless it was already shut down.
+    fn stop(&mut self) {
+        if self.lifecycle != Lifecycle::ShutDown {
+            self.lifecycle = Lifecycle::ShutDown;
+            self.shutdown();
+        }
+    }
+
+    /// Advances `Time` to the next frame, which took `elapsed`.
+    fn finish_frame(&mut self, elapsed: Duration) {
+        let mut time = self.world.write_resource::<Time>();
+        time.increment_frame_number();
+        time.set_delta_time(elapsed);
+    }
+
+    /// Sleeps until something wakes an event driven application, unless a redraw was requested
+    /// or transitions or callbacks are pending.
+    ///
+    /// This runs between frames, and the window event pump writes the events which woke the
+    /// application before the next frame starts. The time slept only counts for the `Timers`.
+    fn wait_for_wakeup(&mut self) {
+        self.queue_trans_events();
+        let callbacks_pending = !self
+            .world
+            .read_resource::<CallbackQueue>()
+            .receiver
+            .is_empty();
+        let next_timer = self
+            .world
+            .read_resource::<Timers>()
+            .next_deadline(&self.world.read_resource::<Time>());
+
+        let redraw_requested = self.world.write_resource::<Wakeup>().take_redraw_request();
+        if redraw_requested
+            || callbacks_pending
+            || !self.pending_trans.is_empty()
+            || !self.states.is_running()
+        {
+            return;
+        }
+        let start = Instant::now();
+        let idle = match next_timer {
+            Some(remaining) => Idle::Until(start + remaining),
+            None => Idle::UntilWoken,
+        };
+        self.world
+            .write_resource::<Wakeup>()
+            .idle(idle, &self.world);
+        self.world
+            .write_resource::<Timers>()
+            .add_idle_time(start.elapsed());
+        self.world.write_resource::<FrameLimiter>().start();
+    }
+
+    /// Moves the transitions pushed to the `TransEvent` channel to the pending transitions.
+    fn queue_trans_events(&mut self) {
+        let reader = &mut self.trans_reader_id;
+        self.pending_trans.extend(
+            self.world
+                .read_resource::<EventChannel<TransEvent<T, E>>>()
+                .read(reader)
+                .map(|e| e()),
+        );
     }
 
     /// Sets up the application.
@@ -268,38 +459,32 @@ where
             .expect("Tried to start state machine without any states present");
     }
 
-    // React to window close events
+    // React to window close events, and tell the `FrameLimiter` when the window focus changes
     fn should_close(&mut self) -> bool {
-        if self.ignore_window_close {
-            false
-        } else {
-            use crate::winit::WindowEvent;
-            let world = &mut self.world;
-            let reader_id = &mut self.event_reader_id;
-            world.exec(|ev: Read<'_, EventChannel<Event>>| {
-                ev.read(reader_id).any(|e| {
-                    if cfg!(target_os = "ios") {
-                        if let Event::WindowEvent {
-                            event: WindowEvent::Destroyed,
-                            ..
-                        } = e
-                        {
-                            true
-                        } else {
-                            false
+        use crate::winit::WindowEvent;
+        let world = &mut self.world;
+        let reader_id = &mut self.event_reader_id;
+        let (close_requested, focused) = world.exec(|ev: Read<'_, EventChannel<Event>>| {
+            let mut close_requested = false;
+            let mut focused = None;
+            for e in ev.read(reader_id) {
+                if let Event::WindowEvent { event, .. } = e {
+                    match event {
+                        WindowEvent::Focused(focus) => focused = Some(*focus),
+                        WindowEvent::Destroyed if cfg!(target_os = "ios") => close_requested = true,
+                        WindowEvent::CloseRequested if !cfg!(target_os = "ios") => {
+                            close_requested = true
                         }
-                    } else if let Event::WindowEvent {
-                        event: WindowEvent::CloseRequested,
-                        ..
-                    } = e
-                    {
-                        true
-                    } else {
-                        false
+                        _ => {}
                     }
-                })
-            })
+                }
+            }
+            (close_requested, focused)
+        });
+        if let Some(focused) = focused {
+            world.write_resource::<FrameLimiter>().set_focused(focused);
         }
+        close_requested && !self.ignore_window_close
     }
 
     /// Advances the game world by one tick.
@@ -308,24 +493,19 @@ where
         for<'b> R: EventReader<'b, Event = E>,
     {
         trace!("Advancing frame (`Application::advance_frame`)");
+        self.world.write_resource::<FrameProfiler>().begin_frame();
         if self.should_close() {
             let world = &mut self.world;
             let states = &mut self.states;
-            states.stop(StateData::new(world, &mut self.data));
+            states.transition(Trans::Quit, StateData::new(world, &mut self.data));
         }
 
         // Read the Trans queue and apply changes.
         {
+            self.queue_trans_events();
             let mut world = &mut self.world;
             let states = &mut self.states;
-            let reader = &mut self.trans_reader_id;
-
-            let trans = world
-                .read_resource::<EventChannel<TransEvent<T, E>>>()
-                .read(reader)
-                .map(|e| e())
-                .collect::<Vec<_>>();
-            for tr in trans {
+            for tr in self.pending_trans.drain(..) {
                 states.transition(tr, StateData::new(&mut world, &mut self.data));
             }
         }
@@ -340,6 +520,12 @@ where
             }
         }
 
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("run_timers");
+            Timers::run(&mut self.world);
+        }
+
         {
             #[cfg(feature = "profiler")]
             profile_scope!("handle_event");
@@ -379,9 +565,16 @@ where
                 .update(StateData::new(&mut self.world, &mut self.data));
         }
 
-        #[cfg(feature = "profiler")]
-        profile_scope!("maintain");
-        self.world.maintain();
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("maintain");
+            self.world.maintain();
+        }
+
+        let frame = self.world.read_resource::<Time>().frame_number();
+        self.world
+            .write_resource::<FrameProfiler>()
+            .end_frame(frame);
     }
 
     /// Cleans up after the quit signal is received.
@@ -417,6 +610,7 @@ pub struct ApplicationBuilder<S, T, E, R> {
     /// Used by bundles to access the world directly
     pub world: World,
     ignore_window_close: bool,
+    virtual_frame_length: Option<Duration>,
     phantom: PhantomData<(T, E, R)>,
 }
 
@@ -546,10 +740,15 @@ where
         world.insert(EventChannel::<Event>::with_capacity(2000));
         world.insert(EventChannel::<UiEvent>::with_capacity(40));
         world.insert(EventChannel::<TransEvent<T, StateEvent>>::with_capacity(2));
+        world.insert(EventChannel::<StateTransitionEvent>::with_capacity(8));
+        world.insert(StateStack::default());
         world.insert(FrameLimiter::default());
         world.insert(Stopwatch::default());
         world.insert(Time::default());
         world.insert(CallbackQueue::default());
+        world.insert(Timers::default());
+        world.insert(FrameProfiler::default());
+        world.insert(Wakeup::default());
 
         world.register::<Named>();
 
@@ -557,6 +756,7 @@ where
             initial_state,
             world,
             ignore_window_close: false,
+            virtual_frame_length: None,
             phantom: PhantomData,
         })
     }
@@ -830,10 +1030,18 @@ where
     ///
     /// `duration`: The duration between fixed updates.
     ///
+    /// # Panics
+    ///
+    /// Panics if `duration` is zero.
+    ///
     /// # Returns
     ///
     /// This function returns the ApplicationBuilder after modifying it.
     pub fn with_fixed_step_length(self, duration: Duration) -> Self {
+        assert!(
+            duration > Duration::from_secs(0),
+            "The fixed step length must not be zero."
+        );
         self.world.write_resource::<Time>().set_fixed_time(duration);
         self
     }
@@ -854,6 +1062,57 @@ where
         self
     }
 
+    /// Runs the application headless, with frames driven by a virtual clock rather than the
+    /// `Stopwatch` and `FrameLimiter`.
+    ///
+    /// Every frame advances `Time` by exactly `frame_length`, and frames run back to back, so
+    /// simulations, dedicated servers and tests run as fast as possible and reproducibly. Such
+    /// applications shouldn't add a window. Besides `run`, they can be driven frame by frame with
+    /// `CoreApplication::step` and `CoreApplication::step_for`.
+    ///
+    /// # Parameters
+    ///
+    /// `frame_length`: The virtual duration of each frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame_length` is zero.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn headless(mut self, frame_length: Duration) -> Self {
+        assert!(
+            frame_length > Duration::from_secs(0),
+            "The frame length of a headless application must not be zero."
+        );
+        self.virtual_frame_length = Some(frame_length);
+        self
+    }
+
+    /// Runs the application event driven: after every frame, it sleeps until a window event, a
+    /// `TransEvent`, a `CallbackQueue` callback, a timer or a redraw request wakes it, so idle
+    /// tools and editors use next to no CPU.
+    ///
+    /// Game code requests more frames with `Wakeup::request_redraw`, e.g. while something
+    /// animates, and other threads wake the application with the `Waker` of `Wakeup::waker`.
+    /// Headless applications ignore this.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn event_driven(self) -> Self {
+        let waker = {
+            let mut wakeup = self.world.write_resource::<Wakeup>();
+            wakeup.set_event_driven(true);
+            wakeup.waker()
+        };
+        self.world
+            .write_resource::<CallbackQueue>()
+            .wake_on_send(waker);
+        self
+    }
+
     /// Build an `Application` object using the `ApplicationBuilder` as configured.
     ///
     /// # Returns
@@ -906,9 +1165,152 @@ where
             reader,
             events: Vec::new(),
             ignore_window_close: self.ignore_window_close,
+            virtual_frame_length: self.virtual_frame_length,
+            lifecycle: Lifecycle::Created,
             data,
             event_reader_id,
             trans_reader_id,
+            pending_trans: Vec::new(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::{core::Timer, EmptyState};
+
+    struct Empty;
+
+    impl EmptyState for Empty {}
+
+    /// A started event driven application, woken after a few seconds should it sleep too long.
+    fn application() -> Application<'static, ()> {
+        let mut application = Application::build("assets", Empty)
+            .unwrap()
+            .event_driven()
+            .build(())
+            .unwrap();
+        application.start();
+        let waker = application.world.read_resource::<Wakeup>().waker();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(5));
+            waker.wake();
+        });
+        application
+    }
+
+    fn wait_for_wakeup(application: &mut Application<'static, ()>) -> Duration {
+        let start = Instant::now();
+        application.wait_for_wakeup();
+        start.elapsed()
+    }
+
+    #[test]
+    fn pending_transitions_keep_the_application_awake() {
+        let mut application = application();
+        application
+            .world
+            .write_resource::<EventChannel<TransEvent<(), StateEvent>>>()
+            .single_write(Box::new(|| Trans::None));
+
+        assert!(wait_for_wakeup(&mut application) < Duration::from_secs(1));
+        assert_eq!(application.pending_trans.len(), 1);
+    }
+
+    #[test]
+    fn redraw_requests_keep_the_application_awake() {
+        let mut application = application();
+        application
+            .world
+            .write_resource::<Wakeup>()
+            .request_redraw();
+
+        assert!(wait_for_wakeup(&mut application) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn callbacks_wake_the_application() {
+        let mut application = application();
+        let sender = application
+            .world
+            .read_resource::<CallbackQueue>()
+            .send_handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let callback: Callback = Box::new(|world| world.insert(1u32));
+            sender.send(callback).unwrap();
+        });
+
+        let slept = wait_for_wakeup(&mut application);
+        assert!(slept >= Duration::from_millis(50));
+        assert!(slept < Duration::from_secs(1));
+
+        // The callback is still pending, so the application stays awake until it runs.
+        assert!(wait_for_wakeup(&mut application) < Duration::from_secs(1));
+        let callback = application
+            .world
+            .read_resource::<CallbackQueue>()
+            .receiver
+            .try_recv()
+            .unwrap();
+        callback(&mut application.world);
+        assert_eq!(*application.world.read_resource::<u32>(), 1);
+    }
+
+    #[test]
+    fn timers_wake_the_application_and_count_the_time_slept() {
+        let mut application = application();
+        application.world.insert(0u32);
+        application.world.write_resource::<Timers>().start(
+            Timer::once(Duration::from_millis(100))
+                .real_time()
+                .calling(|world| *world.write_resource::<u32>() += 1),
+        );
+
+        let slept = wait_for_wakeup(&mut application);
+        assert!(slept >= Duration::from_millis(100));
+        assert!(slept < Duration::from_secs(1));
+
+        // The next frame only lasts as long as it takes, but the timer counted the time slept.
+        Timers::run(&mut application.world);
+        assert_eq!(*application.world.read_resource::<u32>(), 1);
+        assert_eq!(
+            application.world.read_resource::<Time>().delta_real_time(),
+            Duration::from_secs(0)
+        );
+    }
+
+    #[test]
+    fn timers_due_during_a_frame_wake_the_application_on_time() {
+        let mut application = application();
+        application.world.insert(0u32);
+        application.world.write_resource::<Timers>().start(
+            Timer::once(Duration::from_millis(200))
+                .real_time()
+                .calling(|world| *world.write_resource::<u32>() += 1),
+        );
+
+        // The timers count this frame on the next tick, only the rest of the timer is slept.
+        application.finish_frame(Duration::from_millis(150));
+        let slept = wait_for_wakeup(&mut application);
+        assert!(slept >= Duration::from_millis(50));
+        assert!(slept < Duration::from_millis(200));
+
+        Timers::run(&mut application.world);
+        assert_eq!(*application.world.read_resource::<u32>(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The fixed step length must not be zero.")]
+    fn fixed_step_length_must_not_be_zero() {
+        let _ = Application::build("assets", Empty)
+            .unwrap()
+            .with_fixed_step_length(Duration::from_secs(0));
+    }
+}
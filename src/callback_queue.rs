@@ -1,4 +1,6 @@
-use crate::core::ecs::World;
+use std::thread;
+
+use crate::core::{ecs::World, Waker};
 use crossbeam_channel::{Receiver, Sender};
 
 /// The type of a callback.
@@ -37,6 +39,21 @@ impl CallbackQueue {
     pub fn send_handle(&self) -> Sender<Callback> {
         self.sender.clone()
     }
+
+    /// Relays the sent `Callback`s through a thread which wakes an event driven application for
+    /// each of them.
+    pub(crate) fn wake_on_send(&mut self, waker: Waker) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let sent = std::mem::replace(&mut self.receiver, receiver);
+        thread::spawn(move || {
+            for callback in sent.iter() {
+                if sender.send(callback).is_err() {
+                    break;
+                }
+                waker.wake();
+            }
+        });
+    }
 }
 
 impl Default for CallbackQueue {
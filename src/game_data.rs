@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Instant};
 
 use crate::{
     core::{
@@ -7,9 +7,10 @@ use crate::{
             DispatcherOperation,
         },
         ecs::prelude::{Dispatcher, DispatcherBuilder, RunNow, System, World, WorldExt},
-        ArcThreadPool, RunNowDesc, SystemBundle, SystemDesc,
+        ArcThreadPool, FrameProfiler, RunNowDesc, SystemBundle, SystemDesc,
     },
     error::Error,
+    state::StateStack,
 };
 
 /// Initialise trait for game data
@@ -28,9 +29,37 @@ pub trait DataDispose {
 ///
 /// The lifetimes are for the systems inside and can be `'static` unless a system has a borrowed
 /// field.
+///
+/// Besides the dispatcher run once per frame by `update`, the game data can have a fixed
+/// dispatcher run once per fixed time step by `fixed_update`. `SimpleState`s run both
+/// dispatchers, other states have to call `update` and `fixed_update` themselves.
+///
+/// `update` also runs the systems attached to states, see `GameDataBuilder::with_state_system`,
+/// after the systems of the dispatcher but before its thread local systems.
+///
+/// Every stage of the dispatchers is recorded in the `FrameProfiler`, including the systems added
+/// by bundles, as `game_data::dispatcher`, `game_data::state::<state>`,
+/// `game_data::thread_local` and `game_data::fixed_dispatcher`.
 #[allow(missing_debug_implementations)]
 pub struct GameData<'a, 'b> {
     dispatcher: Option<Dispatcher<'a, 'b>>,
+    fixed_dispatcher: Option<Dispatcher<'a, 'b>>,
+    state_dispatchers: Vec<StateDispatcher<'a, 'b>>,
+}
+
+/// The systems attached to a state, with their own dispatcher.
+struct StateDispatcher<'a, 'b> {
+    state: String,
+    stage: String,
+    while_paused: bool,
+    dispatcher: Dispatcher<'a, 'b>,
+}
+
+/// The dispatcher operations of the systems attached to a state, see `StateDispatcher`.
+struct StateSystems<'a, 'b> {
+    state: String,
+    while_paused: bool,
+    dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
 }
 
 impl<'a, 'b> GameData<'a, 'b> {
@@ -38,21 +67,109 @@ impl<'a, 'b> GameData<'a, 'b> {
     pub fn new(dispatcher: Dispatcher<'a, 'b>) -> Self {
         GameData {
             dispatcher: Some(dispatcher),
+            fixed_dispatcher: None,
+            state_dispatchers: Vec::new(),
         }
     }
 
+    /// Adds a dispatcher run once per fixed time step, see `fixed_update`.
+    pub fn with_fixed_dispatcher(mut self, fixed_dispatcher: Dispatcher<'a, 'b>) -> Self {
+        self.fixed_dispatcher = Some(fixed_dispatcher);
+        self
+    }
+
+    /// Adds a dispatcher run by `update` while the state with the given name is active, or
+    /// while it is on the state stack if `while_paused` is true.
+    pub fn with_state_dispatcher<N: Into<String>>(
+        mut self,
+        state: N,
+        while_paused: bool,
+        dispatcher: Dispatcher<'a, 'b>,
+    ) -> Self {
+        let state: String = state.into();
+        self.state_dispatchers.push(StateDispatcher {
+            stage: format!("game_data::state::{}", state),
+            state,
+            while_paused,
+            dispatcher,
+        });
+        self
+    }
+
     /// Update game data
+    ///
+    /// Runs the systems of the dispatcher, then the systems attached to the current states, and
+    /// last the thread local systems of the dispatcher, like rendering, which thereby see the
+    /// changes of the state systems in the same frame.
     pub fn update(&mut self, world: &World) {
         if let Some(dispatcher) = &mut self.dispatcher {
-            dispatcher.dispatch(&world);
+            profile_stage(world, "game_data::dispatcher", || {
+                #[cfg(not(no_threading))]
+                dispatcher.dispatch_par(&world);
+                #[cfg(no_threading)]
+                dispatcher.dispatch_seq(&world);
+            });
+        }
+        for state_dispatcher in &mut self.state_dispatchers {
+            let enabled = world.try_fetch::<StateStack>().map_or(false, |stack| {
+                if state_dispatcher.while_paused {
+                    stack.contains(&state_dispatcher.state)
+                } else {
+                    stack.is_active(&state_dispatcher.state)
+                }
+            });
+            if enabled {
+                let dispatcher = &mut state_dispatcher.dispatcher;
+                profile_stage(world, &state_dispatcher.stage, || {
+                    dispatcher.dispatch(&world)
+                });
+            }
+        }
+        if let Some(dispatcher) = &mut self.dispatcher {
+            profile_stage(world, "game_data::thread_local", || {
+                dispatcher.dispatch_thread_local(&world)
+            });
+        }
+    }
+
+    /// Runs the fixed dispatcher, if any, for one fixed time step.
+    ///
+    /// Called from `State::fixed_update`, once per step of `Time::step_fixed_update`.
+    pub fn fixed_update(&mut self, world: &World) {
+        if let Some(dispatcher) = &mut self.fixed_dispatcher {
+            profile_stage(world, "game_data::fixed_dispatcher", || {
+                dispatcher.dispatch(&world)
+            });
         }
     }
 
-    /// Dispose game data, dropping the dispatcher
+    /// Dispose game data, dropping the dispatchers
     pub fn dispose(&mut self, mut world: &mut World) {
         if let Some(dispatcher) = self.dispatcher.take() {
             dispatcher.dispose(&mut world);
         }
+        if let Some(dispatcher) = self.fixed_dispatcher.take() {
+            dispatcher.dispose(&mut world);
+        }
+        for state_dispatcher in self.state_dispatchers.drain(..) {
+            state_dispatcher.dispatcher.dispose(&mut world);
+        }
+    }
+}
+
+/// Runs a stage of a dispatcher, recording it in the `FrameProfiler` if it is enabled.
+fn profile_stage<F: FnOnce()>(world: &World, name: &str, stage: F) {
+    let enabled = world
+        .try_fetch::<FrameProfiler>()
+        .map_or(false, |profiler| profiler.is_enabled());
+    if !enabled {
+        stage();
+        return;
+    }
+    let start = Instant::now();
+    stage();
+    if let Some(profiler) = world.try_fetch::<FrameProfiler>() {
+        profiler.record_stage(name, start);
     }
 }
 
@@ -70,6 +187,8 @@ impl DataDispose for GameData<'_, '_> {
 #[allow(missing_debug_implementations)]
 pub struct GameDataBuilder<'a, 'b> {
     dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
+    fixed_dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
+    state_systems: Vec<StateSystems<'a, 'b>>,
     disp_builder: DispatcherBuilder<'a, 'b>,
 }
 
@@ -84,6 +203,8 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     pub fn new() -> Self {
         GameDataBuilder {
             dispatcher_operations: Vec::new(),
+            fixed_dispatcher_operations: Vec::new(),
+            state_systems: Vec::new(),
             disp_builder: DispatcherBuilder::new(),
         }
     }
@@ -421,6 +542,259 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         Ok(self)
     }
 
+    /// Adds a given system to the fixed dispatcher, which runs once per fixed time step.
+    ///
+    /// Use this for systems which have to run at a stable rate, like physics or netcode. They
+    /// should use `Time::fixed_seconds` as their time step. Systems of the fixed dispatcher can
+    /// only depend on other systems of the fixed dispatcher.
+    ///
+    /// The fixed dispatcher is run by `GameData::fixed_update`, which `SimpleState`s call from
+    /// `State::fixed_update`. States implementing `State<GameData, E>` directly have to call it
+    /// themselves, as they do for `GameData::update`, or the fixed systems never run.
+    ///
+    /// See [`with`] for the parameters and panics.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::core::SystemDesc;
+    /// use amethyst::derive::SystemDesc;
+    /// use amethyst::prelude::*;
+    /// use amethyst::ecs::prelude::{System, SystemData, World};
+    ///
+    /// #[derive(SystemDesc)]
+    /// struct NopSystem;
+    /// impl<'a> System<'a> for NopSystem {
+    ///     type SystemData = ();
+    ///     fn run(&mut self, _: Self::SystemData) {}
+    /// }
+    ///
+    /// GameDataBuilder::default()
+    ///     // "input" runs once per frame.
+    ///     .with(NopSystem, "input", &[])
+    ///     // "physics" and "collisions" run once per fixed time step.
+    ///     .with_fixed(NopSystem, "physics", &[])
+    ///     .with_fixed(NopSystem, "collisions", &["physics"]);
+    /// ~~~
+    ///
+    /// [`with`]: #method.with
+    pub fn with_fixed<S, N>(mut self, system: S, name: N, dependencies: &[N]) -> Self
+    where
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let name = Into::<String>::into(name);
+        let dependencies = dependencies
+            .iter()
+            .map(Clone::clone)
+            .map(Into::<String>::into)
+            .collect::<Vec<String>>();
+        let dispatcher_operation = Box::new(AddSystem {
+            system,
+            name,
+            dependencies,
+        }) as Box<dyn DispatcherOperation<'a, 'b> + 'static>;
+        self.fixed_dispatcher_operations.push(dispatcher_operation);
+        self
+    }
+
+    /// Adds a system descriptor to the fixed dispatcher, see [`with_fixed`] and
+    /// [`with_system_desc`].
+    ///
+    /// [`with_fixed`]: #method.with_fixed
+    /// [`with_system_desc`]: #method.with_system_desc
+    pub fn with_fixed_system_desc<SD, S, N>(
+        mut self,
+        system_desc: SD,
+        name: N,
+        dependencies: &[N],
+    ) -> Self
+    where
+        SD: SystemDesc<'a, 'b, S> + 'static,
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let name = Into::<String>::into(name);
+        let dependencies = dependencies
+            .iter()
+            .map(Clone::clone)
+            .map(Into::<String>::into)
+            .collect::<Vec<String>>();
+        let dispatcher_operation = Box::new(AddSystemDesc {
+            system_desc,
+            name,
+            dependencies,
+            marker: PhantomData::<S>,
+        }) as Box<dyn DispatcherOperation<'a, 'b> + 'static>;
+        self.fixed_dispatcher_operations.push(dispatcher_operation);
+        self
+    }
+
+    /// Inserts a barrier in the fixed dispatcher, see [`with_barrier`].
+    ///
+    /// [`with_barrier`]: #method.with_barrier
+    pub fn with_fixed_barrier(mut self) -> Self {
+        self.fixed_dispatcher_operations.push(Box::new(AddBarrier));
+        self
+    }
+
+    /// Add a given ECS bundle to the fixed dispatcher, see [`with_fixed`] and [`with_bundle`].
+    ///
+    /// The resources of the bundle are added to the `World` like for any bundle, its systems
+    /// run once per fixed time step.
+    ///
+    /// [`with_fixed`]: #method.with_fixed
+    /// [`with_bundle`]: #method.with_bundle
+    pub fn with_fixed_bundle<B>(mut self, bundle: B) -> Result<Self, Error>
+    where
+        B: SystemBundle<'a, 'b> + 'static,
+    {
+        self.fixed_dispatcher_operations
+            .push(Box::new(AddBundle { bundle }));
+        Ok(self)
+    }
+
+    /// Attaches a given system to the state with the given name, see `State::name`.
+    ///
+    /// The systems of a state run after the other systems, in their own dispatcher, but before
+    /// the thread local systems added with [`with_thread_local`]. They are enabled when the
+    /// state starts or resumes, and disabled when it is paused or stops, unless
+    /// [`with_state_systems_while_paused`] keeps them running while the state is paused. They
+    /// can still be made `pausable`, and then only run when both the state and the resource
+    /// value allow it. Systems attached to a state can only depend on systems attached to the
+    /// same state.
+    ///
+    /// See [`with`] for the other parameters and panics.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::core::SystemDesc;
+    /// use amethyst::derive::SystemDesc;
+    /// use amethyst::prelude::*;
+    /// use amethyst::ecs::prelude::{System, SystemData, World};
+    ///
+    /// #[derive(SystemDesc)]
+    /// struct NopSystem;
+    /// impl<'a> System<'a> for NopSystem {
+    ///     type SystemData = ();
+    ///     fn run(&mut self, _: Self::SystemData) {}
+    /// }
+    ///
+    /// GameDataBuilder::default()
+    ///     // Runs whatever the active state is.
+    ///     .with(NopSystem, "input", &[])
+    ///     // Runs while the "game" state is active.
+    ///     .with_state_system("game", NopSystem, "movement", &[])
+    ///     // Runs while the "menu" state is active.
+    ///     .with_state_system("menu", NopSystem, "menu_navigation", &[]);
+    /// ~~~
+    ///
+    /// [`with`]: #method.with
+    /// [`with_thread_local`]: #method.with_thread_local
+    /// [`with_state_systems_while_paused`]: #method.with_state_systems_while_paused
+    pub fn with_state_system<S, N>(
+        mut self,
+        state: &str,
+        system: S,
+        name: N,
+        dependencies: &[N],
+    ) -> Self
+    where
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let name = Into::<String>::into(name);
+        let dependencies = dependencies
+            .iter()
+            .map(Clone::clone)
+            .map(Into::<String>::into)
+            .collect::<Vec<String>>();
+        let dispatcher_operation = Box::new(AddSystem {
+            system,
+            name,
+            dependencies,
+        }) as Box<dyn DispatcherOperation<'a, 'b> + 'static>;
+        self.state_systems_mut(state)
+            .dispatcher_operations
+            .push(dispatcher_operation);
+        self
+    }
+
+    /// Attaches a system descriptor to the state with the given name, see
+    /// [`with_state_system`] and [`with_system_desc`].
+    ///
+    /// [`with_state_system`]: #method.with_state_system
+    /// [`with_system_desc`]: #method.with_system_desc
+    pub fn with_state_system_desc<SD, S, N>(
+        mut self,
+        state: &str,
+        system_desc: SD,
+        name: N,
+        dependencies: &[N],
+    ) -> Self
+    where
+        SD: SystemDesc<'a, 'b, S> + 'static,
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+    {
+        let name = Into::<String>::into(name);
+        let dependencies = dependencies
+            .iter()
+            .map(Clone::clone)
+            .map(Into::<String>::into)
+            .collect::<Vec<String>>();
+        let dispatcher_operation = Box::new(AddSystemDesc {
+            system_desc,
+            name,
+            dependencies,
+            marker: PhantomData::<S>,
+        }) as Box<dyn DispatcherOperation<'a, 'b> + 'static>;
+        self.state_systems_mut(state)
+            .dispatcher_operations
+            .push(dispatcher_operation);
+        self
+    }
+
+    /// Attaches the systems of a given ECS bundle to the state with the given name, see
+    /// [`with_state_system`] and [`with_bundle`].
+    ///
+    /// [`with_state_system`]: #method.with_state_system
+    /// [`with_bundle`]: #method.with_bundle
+    pub fn with_state_bundle<B>(mut self, state: &str, bundle: B) -> Result<Self, Error>
+    where
+        B: SystemBundle<'a, 'b> + 'static,
+    {
+        self.state_systems_mut(state)
+            .dispatcher_operations
+            .push(Box::new(AddBundle { bundle }));
+        Ok(self)
+    }
+
+    /// Keeps the systems attached to the state with the given name running while the state is
+    /// paused beneath another one, see [`with_state_system`].
+    ///
+    /// [`with_state_system`]: #method.with_state_system
+    pub fn with_state_systems_while_paused(mut self, state: &str) -> Self {
+        self.state_systems_mut(state).while_paused = true;
+        self
+    }
+
+    fn state_systems_mut(&mut self, state: &str) -> &mut StateSystems<'a, 'b> {
+        let index = match self.state_systems.iter().position(|s| s.state == state) {
+            Some(index) => index,
+            None => {
+                self.state_systems.push(StateSystems {
+                    state: state.to_string(),
+                    while_paused: false,
+                    dispatcher_operations: Vec::new(),
+                });
+                self.state_systems.len() - 1
+            }
+        };
+        &mut self.state_systems[index]
+    }
+
     // /// Create a basic renderer with a single given `Pass`, and optional support for the `DrawUi` pass.
     // ///
     // /// Will set the clear color to black.
@@ -461,28 +835,179 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
 }
 
 impl<'a, 'b> DataInit<GameData<'a, 'b>> for GameDataBuilder<'a, 'b> {
-    fn build(self, mut world: &mut World) -> GameData<'a, 'b> {
-        #[cfg(not(no_threading))]
-        let pool = (*world.read_resource::<ArcThreadPool>()).clone();
+    fn build(self, world: &mut World) -> GameData<'a, 'b> {
+        let dispatcher = build_dispatcher(world, self.disp_builder, self.dispatcher_operations);
+        let mut game_data = GameData::new(dispatcher);
+        if !self.fixed_dispatcher_operations.is_empty() {
+            let fixed_dispatcher = build_dispatcher(
+                world,
+                DispatcherBuilder::new(),
+                self.fixed_dispatcher_operations,
+            );
+            game_data = game_data.with_fixed_dispatcher(fixed_dispatcher);
+        }
+        for state_systems in self.state_systems {
+            let dispatcher = build_dispatcher(
+                world,
+                DispatcherBuilder::new(),
+                state_systems.dispatcher_operations,
+            );
+            game_data = game_data.with_state_dispatcher(
+                state_systems.state,
+                state_systems.while_paused,
+                dispatcher,
+            );
+        }
+        game_data
+    }
+}
 
-        let mut dispatcher_builder = self.disp_builder;
+fn build_dispatcher<'a, 'b>(
+    mut world: &mut World,
+    mut dispatcher_builder: DispatcherBuilder<'a, 'b>,
+    dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
+) -> Dispatcher<'a, 'b> {
+    #[cfg(not(no_threading))]
+    let pool = (*world.read_resource::<ArcThreadPool>()).clone();
 
-        self.dispatcher_operations
-            .into_iter()
-            .try_for_each(|dispatcher_operation| {
-                dispatcher_operation.exec(world, &mut dispatcher_builder)
-            })
-            .unwrap_or_else(|e| panic!("Failed to set up dispatcher: {}", e));
-
-        #[cfg(not(no_threading))]
-        let mut dispatcher = dispatcher_builder.with_pool(pool).build();
-        #[cfg(no_threading)]
-        let mut dispatcher = dispatcher_builder.build();
-        dispatcher.setup(&mut world);
-        GameData::new(dispatcher)
-    }
+    dispatcher_operations
+        .into_iter()
+        .try_for_each(|dispatcher_operation| {
+            dispatcher_operation.exec(world, &mut dispatcher_builder)
+        })
+        .unwrap_or_else(|e| panic!("Failed to set up dispatcher: {}", e));
+
+    #[cfg(not(no_threading))]
+    let mut dispatcher = dispatcher_builder.with_pool(pool).build();
+    #[cfg(no_threading)]
+    let mut dispatcher = dispatcher_builder.build();
+    dispatcher.setup(&mut world);
+    dispatcher
 }
 
 impl DataInit<()> for () {
     fn build(self, _: &mut World) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rayon::ThreadPoolBuilder;
+
+    use super::*;
+    use crate::{
+        core::{ecs::prelude::Write, timing::Time},
+        SimpleState, StateData, StateMachine, Trans,
+    };
+
+    /// The names of the systems which ran, in order.
+    #[derive(Default)]
+    struct Runs(Vec<&'static str>);
+
+    /// Records its name in `Runs` every time it runs.
+    struct Record(&'static str);
+
+    impl<'a> System<'a> for Record {
+        type SystemData = Write<'a, Runs>;
+
+        fn run(&mut self, mut runs: Self::SystemData) {
+            runs.0.push(self.0);
+        }
+    }
+
+    struct Game;
+
+    impl SimpleState for Game {}
+
+    struct Named(&'static str);
+
+    impl SimpleState for Named {
+        fn name(&self) -> Option<&str> {
+            Some(self.0)
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        world.insert::<ArcThreadPool>(Arc::new(pool));
+        world.insert(Time::default());
+        world
+    }
+
+    #[test]
+    fn fixed_systems_run_once_per_fixed_step() {
+        let mut world = world();
+        let mut game_data = GameDataBuilder::default()
+            .with(Record("update"), "update", &[])
+            .with_fixed(Record("fixed"), "fixed", &[])
+            .build(&mut world);
+        let mut states = StateMachine::new(Game);
+        states
+            .start(StateData::new(&mut world, &mut game_data))
+            .unwrap();
+
+        {
+            let mut time = world.write_resource::<Time>();
+            time.set_fixed_seconds(0.1);
+            time.set_delta_seconds(0.35);
+            time.start_fixed_update();
+        }
+        while world.write_resource::<Time>().step_fixed_update() {
+            states.fixed_update(StateData::new(&mut world, &mut game_data));
+        }
+        states.update(StateData::new(&mut world, &mut game_data));
+
+        assert_eq!(
+            world.read_resource::<Runs>().0,
+            vec!["fixed", "fixed", "fixed", "update"]
+        );
+    }
+
+    #[test]
+    fn state_systems_run_while_their_state_is_active_or_on_the_stack() {
+        let mut world = world();
+        world.insert(StateStack::default());
+        let mut game_data = GameDataBuilder::default()
+            .with(Record("main"), "main", &[])
+            .with_thread_local(Record("render"))
+            .with_state_system("game", Record("game"), "game", &[])
+            .with_state_system("paused", Record("paused"), "paused", &[])
+            .with_state_systems_while_paused("paused")
+            .with_state_system("menu", Record("menu"), "menu", &[])
+            .build(&mut world);
+        let mut states = StateMachine::new(Named("game"));
+        states
+            .start(StateData::new(&mut world, &mut game_data))
+            .unwrap();
+
+        states.update(StateData::new(&mut world, &mut game_data));
+        assert_eq!(
+            world.write_resource::<Runs>().0.split_off(0),
+            vec!["main", "game", "render"]
+        );
+
+        states.transition(
+            Trans::Push(Box::new(Named("paused"))),
+            StateData::new(&mut world, &mut game_data),
+        );
+        states.transition(
+            Trans::Push(Box::new(Named("menu"))),
+            StateData::new(&mut world, &mut game_data),
+        );
+        states.update(StateData::new(&mut world, &mut game_data));
+        assert_eq!(
+            world.write_resource::<Runs>().0.split_off(0),
+            vec!["main", "paused", "menu", "render"]
+        );
+
+        states.transition(Trans::Pop, StateData::new(&mut world, &mut game_data));
+        states.transition(Trans::Pop, StateData::new(&mut world, &mut game_data));
+        states.update(StateData::new(&mut world, &mut game_data));
+        assert_eq!(
+            world.write_resource::<Runs>().0.split_off(0),
+            vec!["main", "game", "render"]
+        );
+    }
+}
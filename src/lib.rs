@@ -99,8 +99,8 @@ pub use self::{
     game_data::{DataDispose, DataInit, GameData, GameDataBuilder},
     logger::{start_logger, LevelFilter as LogLevelFilter, Logger, LoggerConfig, StdoutLog},
     state::{
-        EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, StateMachine, Trans,
-        TransEvent,
+        EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, StateMachine,
+        StateStack, StateTransitionEvent, Trans, TransEvent, TransitionKind,
     },
     state_event::{StateEvent, StateEventReader},
 };
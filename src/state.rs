@@ -3,8 +3,9 @@
 use amethyst_input::is_close_requested;
 
 use derivative::Derivative;
+use log::warn;
 
-use crate::{ecs::World, GameData, StateEvent};
+use crate::{ecs::World, shrev::EventChannel, GameData, StateEvent};
 
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 
@@ -60,6 +61,14 @@ pub enum Trans<T, E> {
     Push(Box<dyn State<T, E>>),
     /// Remove the current state on the stack and insert a different one.
     Switch(Box<dyn State<T, E>>),
+    /// Stop and remove all states, then start the given state as the only one on the stack.
+    Replace(Box<dyn State<T, E>>),
+    /// Stop and remove the states above the topmost state with the given name, see
+    /// `State::name`, and resume it. Does nothing if no state on the stack has the name.
+    PopTo(String),
+    /// Perform the transitions one after the other, e.g. to replace the stack with a main menu
+    /// and push an options menu over it.
+    Sequence(Vec<Trans<T, E>>),
     /// Stop and remove all states and shut down the engine.
     Quit,
 }
@@ -70,11 +79,49 @@ impl<T, E> Debug for Trans<T, E> {
             Trans::Pop => f.write_str("Pop"),
             Trans::Push(_) => f.write_str("Push"),
             Trans::Switch(_) => f.write_str("Switch"),
+            Trans::Replace(_) => f.write_str("Replace"),
+            Trans::PopTo(ref name) => f.debug_tuple("PopTo").field(name).finish(),
+            Trans::Sequence(ref transitions) => {
+                f.debug_tuple("Sequence").field(transitions).finish()
+            }
             Trans::Quit => f.write_str("Quit"),
         }
     }
 }
 
+/// The kind of a `StateTransitionEvent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// The state machine started its initial state.
+    Start,
+    /// See `Trans::Pop`.
+    Pop,
+    /// See `Trans::Push`.
+    Push,
+    /// See `Trans::Switch`.
+    Switch,
+    /// See `Trans::Replace`.
+    Replace,
+    /// See `Trans::PopTo`.
+    PopTo,
+    /// See `Trans::Quit`.
+    Quit,
+}
+
+/// Sent to the `EventChannel<StateTransitionEvent>` after every change of the state stack,
+/// e.g. for analytics or debug overlays. A `Trans::Sequence` sends one event per transition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateTransitionEvent {
+    /// The transition which was performed.
+    pub kind: TransitionKind,
+    /// The name of the state active before the transition, if any.
+    pub from: Option<String>,
+    /// The name of the state active after the transition, if any.
+    pub to: Option<String>,
+    /// The names of the states on the stack after the transition, from the bottom up.
+    pub stack: Vec<Option<String>>,
+}
+
 /// Event queue to trigger state `Trans` from other places than a `State`'s methods.
 /// # Example:
 /// ```rust, ignore
@@ -93,6 +140,11 @@ pub type SimpleTrans = Trans<GameData<'static, 'static>, StateEvent>;
 
 /// A trait which defines game states that can be used by the state machine.
 pub trait State<T, E: Send + Sync + 'static> {
+    /// Names the state, for `Trans::PopTo` and the introspection of the `StateMachine`.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, _data: StateData<'_, T>) {}
 
@@ -137,6 +189,11 @@ pub trait State<T, E: Send + Sync + 'static> {
 
 /// An empty `State` trait. It contains no `StateData` or custom `StateEvent`.
 pub trait EmptyState {
+    /// Names the state, for `Trans::PopTo` and the introspection of the `StateMachine`.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, _data: StateData<'_, ()>) {}
 
@@ -186,6 +243,11 @@ pub trait EmptyState {
 }
 
 impl<T: EmptyState> State<(), StateEvent> for T {
+    /// Names the state, for `Trans::PopTo` and the introspection of the `StateMachine`.
+    fn name(&self) -> Option<&str> {
+        self.name()
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, data: StateData<'_, ()>) {
         self.on_start(data)
@@ -240,6 +302,11 @@ impl<T: EmptyState> State<(), StateEvent> for T {
 
 /// A simple `State` trait. It contains `GameData` as its `StateData` and no custom `StateEvent`.
 pub trait SimpleState {
+    /// Names the state, for `Trans::PopTo` and the introspection of the `StateMachine`.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {}
 
@@ -295,6 +362,11 @@ pub trait SimpleState {
 impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     //pub trait SimpleState<'a,'b>: State<GameData<'a,'b>,()> {
 
+    /// Names the state, for `Trans::PopTo` and the introspection of the `StateMachine`.
+    fn name(&self) -> Option<&str> {
+        self.name()
+    }
+
     /// Executed when the game state begins.
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         self.on_start(data)
@@ -327,7 +399,10 @@ impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     /// Executed repeatedly at stable, predictable intervals (1/60th of a second
     /// by default).
     fn fixed_update(&mut self, data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        self.fixed_update(data)
+        let StateData { world, data } = data;
+        let r = self.fixed_update(StateData::new(world, data));
+        data.fixed_update(&world);
+        r
     }
 
     /// Executed on every frame immediately, as fast as the engine will allow (taking into account the frame rate limit).
@@ -353,7 +428,45 @@ impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     }
 }
 
+/// The names of the states on the stack of the `StateMachine`, from the bottom up.
+///
+/// The state machine keeps this resource up to date, if it is in the `World`. `GameData` uses it
+/// to run the systems attached to states.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateStack {
+    names: Vec<Option<String>>,
+}
+
+impl StateStack {
+    /// Returns the names of the states on the stack, from the bottom up.
+    pub fn names(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    /// Returns the name of the active state, if it has one.
+    pub fn active(&self) -> Option<&str> {
+        self.names
+            .last()
+            .and_then(|name| name.as_ref().map(String::as_str))
+    }
+
+    /// Returns whether the state with the given name is active.
+    pub fn is_active(&self, name: &str) -> bool {
+        self.active() == Some(name)
+    }
+
+    /// Returns whether a state with the given name is on the stack, active or paused.
+    pub fn contains(&self, name: &str) -> bool {
+        self.names
+            .iter()
+            .any(|state| state.as_ref().map(String::as_str) == Some(name))
+    }
+}
+
 /// A simple stack-based state machine (pushdown automaton).
+///
+/// Every change of the stack updates the `StateStack` resource and sends a
+/// `StateTransitionEvent`, if the `World` has them.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct StateMachine<'a, T, E> {
@@ -376,15 +489,32 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         self.running
     }
 
+    /// Returns the number of states on the stack.
+    pub fn depth(&self) -> usize {
+        self.state_stack.len()
+    }
+
+    /// Returns the names of the states on the stack, from the bottom up, see `State::name`.
+    pub fn state_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.state_stack.iter().map(|state| state.name())
+    }
+
+    /// Returns the name of the active state, if it has one.
+    pub fn active_state_name(&self) -> Option<&str> {
+        self.state_stack.last().and_then(|state| state.name())
+    }
+
     /// Initializes the state machine.
     pub fn start(&mut self, data: StateData<'_, T>) -> Result<(), StateError> {
         if !self.running {
+            let StateData { world, data } = data;
             let state = self
                 .state_stack
                 .last_mut()
                 .ok_or(StateError::NoStatesPresent)?;
-            state.on_start(data);
+            state.on_start(StateData { world, data });
             self.running = true;
+            self.send_transition_event(TransitionKind::Start, None, world);
         }
         Ok(())
     }
@@ -460,13 +590,66 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     /// sequentially in the order of insertion.
     pub fn transition(&mut self, request: Trans<T, E>, data: StateData<'_, T>) {
         if self.running {
-            match request {
-                Trans::None => (),
-                Trans::Pop => self.pop(data),
-                Trans::Push(state) => self.push(state, data),
-                Trans::Switch(state) => self.switch(state, data),
-                Trans::Quit => self.stop(data),
-            }
+            let StateData { world, data } = data;
+            let from = self.active_state_name().map(String::from);
+            let kind = match request {
+                Trans::None => return,
+                Trans::Pop => {
+                    self.pop(StateData { world, data });
+                    TransitionKind::Pop
+                }
+                Trans::Push(state) => {
+                    self.push(state, StateData { world, data });
+                    TransitionKind::Push
+                }
+                Trans::Switch(state) => {
+                    self.switch(state, StateData { world, data });
+                    TransitionKind::Switch
+                }
+                Trans::Replace(state) => {
+                    self.replace(state, StateData { world, data });
+                    TransitionKind::Replace
+                }
+                Trans::PopTo(name) => {
+                    if !self.pop_to(&name, StateData { world, data }) {
+                        warn!(
+                            "No state named {:?} to pop to, ignoring `Trans::PopTo`",
+                            name
+                        );
+                        return;
+                    }
+                    TransitionKind::PopTo
+                }
+                Trans::Sequence(requests) => {
+                    for request in requests {
+                        self.transition(request, StateData { world, data });
+                    }
+                    return;
+                }
+                Trans::Quit => {
+                    self.stop(StateData { world, data });
+                    TransitionKind::Quit
+                }
+            };
+            self.send_transition_event(kind, from, world);
+        }
+    }
+
+    fn send_transition_event(&self, kind: TransitionKind, from: Option<String>, world: &World) {
+        let stack = self
+            .state_names()
+            .map(|name| name.map(String::from))
+            .collect::<Vec<_>>();
+        if let Some(mut state_stack) = world.try_fetch_mut::<StateStack>() {
+            state_stack.names = stack.clone();
+        }
+        if let Some(mut channel) = world.try_fetch_mut::<EventChannel<StateTransitionEvent>>() {
+            channel.single_write(StateTransitionEvent {
+                kind,
+                from,
+                to: self.active_state_name().map(String::from),
+                stack,
+            });
         }
     }
 
@@ -519,6 +702,48 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         }
     }
 
+    /// Stops and removes all states, then starts the given state.
+    fn replace(&mut self, state: Box<dyn State<T, E>>, data: StateData<'_, T>) {
+        if self.running {
+            let StateData { world, data } = data;
+            while let Some(mut state) = self.state_stack.pop() {
+                state.on_stop(StateData { world, data });
+            }
+
+            self.state_stack.push(state);
+
+            //State was just pushed, thus pop will always succeed
+            let new_state = self.state_stack.last_mut().unwrap();
+            new_state.on_start(StateData { world, data });
+        }
+    }
+
+    /// Stops and removes the states above the topmost state with the given name and resumes
+    /// it. Returns whether a state has the name.
+    fn pop_to(&mut self, name: &str, data: StateData<'_, T>) -> bool {
+        let index = match self
+            .state_stack
+            .iter()
+            .rposition(|state| state.name() == Some(name))
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        if self.running && index + 1 < self.state_stack.len() {
+            let StateData { world, data } = data;
+            while self.state_stack.len() > index + 1 {
+                if let Some(mut state) = self.state_stack.pop() {
+                    state.on_stop(StateData { world, data });
+                }
+            }
+
+            //The named state was below the popped ones, thus last will always succeed
+            let state = self.state_stack.last_mut().unwrap();
+            state.on_resume(StateData { world, data });
+        }
+        true
+    }
+
     /// Shuts the state machine down.
     pub(crate) fn stop(&mut self, data: StateData<'_, T>) {
         if self.running {
@@ -574,4 +799,94 @@ mod tests {
         sm.update(StateData::new(&mut world, &mut ()));
         assert!(!sm.is_running());
     }
+
+    struct Named(&'static str);
+
+    impl State<(), ()> for Named {
+        fn name(&self) -> Option<&str> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn sequence_replace_and_pop_to() {
+        use crate::ecs::prelude::{World, WorldExt};
+
+        let mut world = World::new();
+        let mut channel = EventChannel::<StateTransitionEvent>::new();
+        let mut reader = channel.register_reader();
+        world.insert(channel);
+
+        let mut sm = StateMachine::new(Named("intro"));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.transition(
+            Trans::Sequence(vec![
+                Trans::Replace(Box::new(Named("main"))),
+                Trans::Push(Box::new(Named("options"))),
+                Trans::Push(Box::new(State2)),
+            ]),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(sm.depth(), 3);
+        assert_eq!(
+            sm.state_names().collect::<Vec<_>>(),
+            vec![Some("main"), Some("options"), None]
+        );
+
+        sm.transition(
+            Trans::PopTo(String::from("main")),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(sm.depth(), 1);
+        assert_eq!(sm.active_state_name(), Some("main"));
+
+        // Popping to a missing state does nothing.
+        sm.transition(
+            Trans::PopTo(String::from("credits")),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(sm.depth(), 1);
+
+        let channel = world.read_resource::<EventChannel<StateTransitionEvent>>();
+        let kinds = channel
+            .read(&mut reader)
+            .map(|event| event.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                TransitionKind::Start,
+                TransitionKind::Replace,
+                TransitionKind::Push,
+                TransitionKind::Push,
+                TransitionKind::PopTo,
+            ]
+        );
+    }
+
+    #[test]
+    fn state_stack_follows_transitions() {
+        use crate::ecs::prelude::{World, WorldExt};
+
+        let mut world = World::new();
+        world.insert(StateStack::default());
+
+        let mut sm = StateMachine::new(Named("game"));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        assert!(world.read_resource::<StateStack>().is_active("game"));
+
+        sm.transition(
+            Trans::Push(Box::new(Named("pause"))),
+            StateData::new(&mut world, &mut ()),
+        );
+        {
+            let stack = world.read_resource::<StateStack>();
+            assert!(!stack.is_active("game"));
+            assert!(stack.contains("game"));
+            assert_eq!(stack.active(), Some("pause"));
+        }
+
+        sm.transition(Trans::Quit, StateData::new(&mut world, &mut ()));
+        assert!(world.read_resource::<StateStack>().names().is_empty());
+    }
 }